@@ -0,0 +1,35 @@
+fn main() {
+    // Helpers shared by days go unused when only some of them are built.
+    println!("cargo::rustc-check-cfg=cfg(all_days)");
+    if (1..=25).all(|day| std::env::var_os(format!("CARGO_FEATURE_DAY{day}")).is_some()) {
+        println!("cargo::rustc-cfg=all_days");
+    }
+
+    // The gRPC service is described in Rust rather than compiled from `proto/aoc.proto`, so that building it doesn't
+    // require `protoc`. Both must be kept in sync.
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route_name: &str, output_type: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route_name)
+                .input_type("crate::grpc::SolveRequest")
+                .output_type(output_type)
+                .codec_path("tonic::codec::ProstCodec")
+        };
+        let service = Service::builder()
+            .name("Solver")
+            .package("aoc")
+            .method(method("solve", "Solve", "crate::grpc::SolveResponse").build())
+            .method(
+                method("visualize", "Visualize", "crate::grpc::Frame")
+                    .server_streaming()
+                    .build(),
+            )
+            .build();
+
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
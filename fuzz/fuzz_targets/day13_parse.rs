@@ -0,0 +1,10 @@
+#![no_main]
+
+use advent_of_code_2024::day13::SolverImpl;
+use advent_of_code_2024::input::Parse;
+use libfuzzer_sys::fuzz_target;
+
+// Parsing the claw machine blocks must fail with an error, never panic.
+fuzz_target!(|file: &str| {
+    let _ = SolverImpl::parse(file);
+});
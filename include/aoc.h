@@ -0,0 +1,33 @@
+/* C interface to the solvers, exported by the library when built with the `ffi` feature:
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * which produces `target/release/libadvent_of_code_2024.so` (`.dylib` on macOS, `.dll` on Windows). */
+
+#ifndef AOC_H
+#define AOC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Solves the `part` (1 or 2) of a `day` (between 1 and 25) for the UTF-8 input of `input_len` bytes at `input`, and
+ * returns the text of the answer found by the solver, a sentence holding it, e.g. "Total is 11". Returns NULL if it
+ * failed instead, in which case `aoc_last_error` tells why. The returned string must be released with `aoc_free`. */
+char *aoc_solve(uint8_t day, uint8_t part, const uint8_t *input, size_t input_len);
+
+/* Why the last call to `aoc_solve` from the current thread failed, or NULL if it succeeded or there was none. The
+ * returned string must be released with `aoc_free`. */
+char *aoc_last_error(void);
+
+/* Releases a string returned by this library. Does nothing if `string` is NULL. */
+void aoc_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
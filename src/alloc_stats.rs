@@ -0,0 +1,146 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+// Set by `--alloc-stats`, so that allocations are only counted when they're reported.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static NUM_ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Allocator counting allocations made through it, on top of the system allocator. It must be registered as the
+/// global allocator of the binary for `measure` to see anything, and only counts once `enable` is called, so that it
+/// costs next to nothing otherwise.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn record_allocation(size: usize) {
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+        let live_bytes = LIVE_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+        PEAK_LIVE_BYTES.fetch_max(live_bytes, Ordering::Relaxed);
+    }
+
+    fn record_deallocation(size: usize) {
+        // Memory allocated before counting started may be freed after, which mustn't wrap around.
+        let _ = LIVE_BYTES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live_bytes| {
+            Some(live_bytes.saturating_sub(size as u64))
+        });
+    }
+}
+
+// SAFETY: all allocations are delegated to the system allocator, only their sizes are looked at.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if is_enabled() {
+            Self::record_allocation(layout.size());
+        }
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if is_enabled() {
+            Self::record_allocation(layout.size());
+        }
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc_zeroed`.
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if is_enabled() {
+            Self::record_deallocation(layout.size());
+        }
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    // Growing or shrinking counts as a new allocation of the new size, replacing the old one.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if is_enabled() {
+            Self::record_deallocation(layout.size());
+            Self::record_allocation(new_size);
+        }
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::realloc`.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Starts counting allocations made through the `CountingAllocator`.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Allocations made while running something, across all threads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Number of allocations, including reallocations.
+    pub num_allocations: u64,
+    /// Bytes requested by all allocations, whether or not they were freed since.
+    pub allocated_bytes: u64,
+    /// Most bytes allocated at once, on top of what was already live beforehand.
+    pub peak_live_bytes: u64,
+}
+
+impl fmt::Display for AllocStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations, {} bytes allocated, {} bytes live at peak",
+            self.num_allocations, self.allocated_bytes, self.peak_live_bytes
+        )
+    }
+}
+
+/// Runs `f`, and counts the allocations it made. Counting must be enabled, and measurements mustn't overlap, as they
+/// share the same counters: allocations of other threads running meanwhile are counted too.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let num_allocations = NUM_ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_LIVE_BYTES.store(live_bytes, Ordering::Relaxed);
+
+    let result = f();
+
+    let stats = AllocStats {
+        num_allocations: NUM_ALLOCATIONS.load(Ordering::Relaxed) - num_allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        peak_live_bytes: PEAK_LIVE_BYTES
+            .load(Ordering::Relaxed)
+            .saturating_sub(live_bytes),
+    };
+    (result, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn allocations_are_counted() {
+        enable();
+
+        // Other tests may allocate meanwhile, so only lower bounds are known.
+        let (sum, stats) = measure(|| {
+            let first = vec![1u64; 1000];
+            let second = vec![2u64; 1000];
+            drop(first);
+            let third = vec![3u64; 500];
+            second.iter().chain(&third).sum::<u64>()
+        });
+        assert_eq!(sum, 3500);
+        assert!(stats.num_allocations >= 3);
+        assert!(stats.allocated_bytes >= 20000);
+        assert!(stats.peak_live_bytes >= 16000);
+        assert!(stats.peak_live_bytes <= stats.allocated_bytes);
+    }
+}
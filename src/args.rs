@@ -1,34 +1,242 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use seq_macro::seq;
 
+use crate::output::Format;
+use crate::registry::{DayCommand, DynCommand};
+
 #[derive(Parser, Debug)]
 #[command(author, version)]
 pub struct Args {
     #[command(subcommand)]
-    pub day: Day,
+    pub command: Command,
+
+    #[arg(long, global = true)]
+    /// Never colour grids printed on the terminal. Colours are otherwise used when printing to a terminal.
+    pub no_color: bool,
+
+    #[arg(long, global = true)]
+    /// Print how long each phase of the solver took on stderr, e.g. parsing, solving, and each shard run concurrently.
+    pub trace: bool,
+
+    #[arg(long, global = true)]
+    /// Write the spans of the solver to this path as a Chrome trace, which can be opened with `chrome://tracing` or
+    /// Perfetto.
+    pub trace_chrome: Option<std::path::PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Solve everything on a single thread, in a stable order, so that outputs and timings are reproducible when
+    /// debugging. Days sharding their work otherwise solve each shard concurrently.
+    pub sequential: bool,
+
+    #[arg(long, global = true)]
+    /// Print on stderr how many allocations solving each part made, how many bytes they allocated, and the most bytes
+    /// live at once, along with how long it took.
+    pub alloc_stats: bool,
+
+    #[arg(long, global = true)]
+    /// Measure how long solving each part took, apart from parsing its input, and write it on stderr in this format,
+    /// e.g. to track performance across commits. Days parsing their input as they solve it have no parse time.
+    pub time: Option<Format>,
+
+    #[arg(long, global = true, requires = "time")]
+    /// Write the timings measured with `--time` to this file rather than on stderr. The file is replaced on each run.
+    pub time_out: Option<std::path::PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Memory-map the input file rather than reading it, which saves copying large generated inputs into memory.
+    pub mmap: bool,
+
+    #[arg(long, global = true)]
+    /// Append a JSON line recording each solved part to this file: when it was solved, the day and part, a hash of
+    /// the input, the answer, how long each phase took, and the git revision. Useful to track timings across commits.
+    pub history: Option<std::path::PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Write how long solved parts took, along with their answers, to this file as Prometheus gauges, in the text
+    /// format scraped by the node exporter's textfile collector. The file is replaced on each run.
+    pub metrics_out: Option<std::path::PathBuf>,
+
+    #[arg(long, global = true, value_parser = parse_seconds)]
+    /// Give up solving after this many seconds, e.g. `2.5`, and exit with code 7. Solving runs on a worker thread, which
+    /// solvers sharding their work stop cooperatively.
+    pub timeout: Option<std::time::Duration>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Day(Day),
+    /// Solve both parts of every day, and print their answers along with how long they took.
+    All {
+        #[arg(short, long, default_value = "input")]
+        /// Name of the input file of each day, like the `--input` of days, e.g. `example`.
+        input: String,
+    },
+    /// Solve both parts of every day, and check their answers against the expected ones: those in `answers.toml` for
+    /// the real inputs, and in each day's `expected.toml` for the examples. Fails with code 8 if any answer is wrong.
+    Verify {
+        #[arg(short, long, default_value = "input")]
+        /// Name of the input file of each day, like the `--input` of days, e.g. `example`.
+        input: String,
+    },
+    /// Rewrite a day's input into a shareable one, with the same structure but different values, and print it.
+    Scrub {
+        #[arg(value_parser = parse_day)]
+        /// Day whose input is scrubbed, e.g. `day23` or `23`.
+        day: u8,
+
+        #[arg(short, long, default_value = "input")]
+        /// Name of the input file in the day's directory, without its `.txt` extension, the path of any other file,
+        /// told apart by its directory or extension, or `-` to read the input from stdin.
+        input: String,
+
+        #[arg(short, long)]
+        /// Seed picking the new values, to scrub reproducibly. Defaults to a random one.
+        seed: Option<u64>,
+    },
+    /// Start a prompt solving days one command after the other, e.g. `16 2`, `viz 15`, or `time 22 2`. Inputs are
+    /// kept in memory between commands. Type `help` at the prompt for all commands.
+    Repl,
+    /// Serve the solvers over gRPC, with the `Solver` service of `proto/aoc.proto`.
+    #[cfg(feature = "grpc")]
+    Serve {
+        #[arg(short, long, default_value = "[::1]:50051")]
+        /// Address to listen on.
+        address: std::net::SocketAddr,
+    },
+    /// Show a private leaderboard of the 2024 edition, with the points and stars of its members. Leaderboards are
+    /// fetched at most every 15 minutes, as asked by Advent of Code, with the session cookie set in `AOC_SESSION`.
+    Leaderboard {
+        /// Id of the leaderboard, found at the end of its URL.
+        id: u64,
+    },
+    /// Download the inputs of all days which aren't there yet, with the session cookie set in `AOC_SESSION`.
+    /// Requests are spaced out, so that adventofcode.com isn't hammered.
+    FetchAll {
+        #[arg(short, long, default_value = "src")]
+        /// Directory holding the inputs, each saved as `dayN/input.txt` like the ones days read.
+        dir: std::path::PathBuf,
+
+        #[arg(long, value_parser = parse_seconds, default_value = "5")]
+        /// Seconds to wait between two requests, e.g. `2.5`.
+        delay: std::time::Duration,
+    },
+    /// Generate a large valid input for a day, and print it.
+    Gen {
+        #[arg(value_parser = parse_day)]
+        /// Day whose input is generated, e.g. `day16` or `16`.
+        day: u8,
+
+        #[arg(short = 'n', long, default_value_t = 1000)]
+        /// Size of the input: the number of reports (day 2), of characters (day 3), of buyers (day 22), of
+        /// schematics (day 25), or the width of the grid (days 16 and 18).
+        size: usize,
+
+        #[arg(short, long)]
+        /// Seed picking the values of the input, to generate it reproducibly. Defaults to a random one.
+        seed: Option<u64>,
+    },
+}
+
+fn parse_seconds(s: &str) -> Result<std::time::Duration, String> {
+    s.parse()
+        .ok()
+        .and_then(|seconds| std::time::Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("expected a number of seconds, got {s:?}"))
+}
+
+fn parse_day(s: &str) -> Result<u8, String> {
+    match s.strip_prefix("day").unwrap_or(s).parse() {
+        Ok(day @ 1..=25) => Ok(day),
+        _ => Err(format!("expected a day between 1 and 25, got {s:?}")),
+    }
 }
 
 seq!(N in 1..=25 {
     #[derive(Subcommand, Debug)]
     pub enum Day {
         #(
-            #[command(about = format!("Solve day {}'s problem.", N))]
+            #[command(about = format!("Solve day {}'s problem. Both parts are solved unless one is picked.", N))]
             Day~N {
                 #[command(subcommand)]
-                part: Part,
+                command: Option<<crate::day~N::SolverImpl as crate::solver::Solver>::Command>,
 
-                #[arg(short, long, default_value = "input", global=true)]
-                /// Name of the input file. Do not include the path nor the file extension (must be `.txt`).
-                input: String,
+                #[command(flatten)]
+                options: DayOptions,
             },
         )*
     }
+
+    impl Day {
+        /// Number of the day, its command if one was picked, and its options. Commands are parsed into a type of
+        /// their day, which is hidden behind `DynCommand`.
+        pub fn split(self) -> (u8, Option<Box<dyn DynCommand>>, DayOptions) {
+            match self {
+                #(
+                    Day::Day~N { command, options } => (
+                        N,
+                        command.map(|command| {
+                            Box::new(DayCommand::<crate::day~N::SolverImpl>(command)) as Box<dyn DynCommand>
+                        }),
+                        options,
+                    ),
+                )*
+            }
+        }
+    }
 });
 
-#[derive(Subcommand, Debug)]
+/// Options shared by the commands of all days.
+#[derive(clap::Args, Debug)]
+pub struct DayOptions {
+    #[arg(short, long, default_value = "input", global = true)]
+    /// Name of the input file in the day's directory, without its `.txt` extension, e.g. `example2`. Paths of
+    /// other files are read as is, told apart by their directory or extension, e.g. `~/aoc/day16.txt`, and
+    /// `-` reads the input from stdin.
+    pub input: String,
+
+    #[arg(short, long, global = true, conflicts_with = "input")]
+    /// Use the example input of the problem, i.e. `--input example`.
+    pub example: bool,
+
+    #[arg(long, global = true)]
+    /// Show how the problem gets solved on the terminal, for days solved on a grid.
+    pub visualize: bool,
+
+    #[arg(long, global = true)]
+    /// Write the final frame of the visualization to this path, as an SVG image.
+    pub visualize_out: Option<std::path::PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Play back how the search solving the problem expands, for days solved by searching a grid. Implies
+    /// `--visualize`, unless `--visualize-out` is given.
+    pub frontier: bool,
+
+    #[arg(long, global = true)]
+    /// Drive the simulation solving the problem, for days solved step by step.
+    pub simulate: Option<SimulationMode>,
+
+    #[arg(long, global = true)]
+    /// Solve the part with every implementation of this day, and fail if their answers disagree.
+    pub cross_check: bool,
+
+    #[arg(long, global=true, num_args = 1.., conflicts_with_all = ["input", "example"])]
+    /// Solve each of these inputs, named like `--input`, and print their answers and timings side by side.
+    pub inputs: Vec<String>,
+}
+
+#[derive(Subcommand, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Part {
     /// Solve the 1st part of the problem.
     Part1,
     /// Solve the 2nd part of the problem.
     Part2,
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SimulationMode {
+    /// Step through the simulation with commands read from stdin.
+    Interactive,
+    /// Run the simulation until it's done, and show its final state.
+    Complete,
+}
@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use itertools::Itertools;
+
+use crate::utils::{FxHashMap, FxHashSet};
+
+/// Directed acyclic graph, stored as the successors of each node. An edge goes from a node to one that must come after
+/// it, e.g. from a page to the pages printed after it, or from a wire to the wires computed from it.
+/// Cycles aren't rejected when adding edges, but fail the queries which rely on the graph being acyclic.
+#[derive(Clone, Debug)]
+pub struct Dag<T> {
+    successors: FxHashMap<T, Vec<T>>,
+}
+
+impl<T> Dag<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Adds a node without any edge, if it isn't in the graph already.
+    pub fn add_node(&mut self, node: T) {
+        self.successors.entry(node).or_default();
+    }
+
+    /// Adds an edge from `from` to `to`, along with both nodes.
+    pub fn add_edge(&mut self, from: T, to: T) {
+        self.successors.entry(from).or_default().push(to);
+        self.add_node(to);
+    }
+
+    /// All nodes of the graph, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = T> + '_ {
+        self.successors.keys().copied()
+    }
+
+    /// Nodes that `node` has an edge to.
+    pub fn successors(&self, node: T) -> &[T] {
+        self.successors.get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    /// Orders all nodes so that each comes after the nodes with an edge to it. Fails if the graph has a cycle.
+    pub fn topological_order(&self) -> Result<Vec<T>, CycleError<T>> {
+        let mut num_unsorted_predecessors: FxHashMap<T, usize> =
+            self.nodes().map(|node| (node, 0)).collect();
+        for successor in self.successors.values().flatten() {
+            *num_unsorted_predecessors.get_mut(successor).unwrap() += 1;
+        }
+
+        // Kahn's algorithm: repeatedly sort a node that has no unsorted predecessors left.
+        let mut sortable_nodes: VecDeque<_> = num_unsorted_predecessors
+            .iter()
+            .filter(|(_, num)| **num == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut sorted_nodes = Vec::with_capacity(num_unsorted_predecessors.len());
+        while let Some(node) = sortable_nodes.pop_front() {
+            sorted_nodes.push(node);
+
+            for successor in self.successors(node) {
+                let num = num_unsorted_predecessors.get_mut(successor).unwrap();
+                *num -= 1;
+                if *num == 0 {
+                    sortable_nodes.push_back(*successor);
+                }
+            }
+        }
+
+        if sorted_nodes.len() == num_unsorted_predecessors.len() {
+            return Ok(sorted_nodes);
+        }
+
+        // Every node left unsorted has at least one unsorted predecessor, hence is reached by one. Following edges
+        // between unsorted nodes backwards must then eventually loop.
+        let unsorted_nodes: FxHashSet<_> = num_unsorted_predecessors
+            .into_iter()
+            .filter(|(_, num)| *num > 0)
+            .map(|(node, _)| node)
+            .collect();
+        let mut predecessors: FxHashMap<T, T> = FxHashMap::default();
+        for (node, successors) in &self.successors {
+            if unsorted_nodes.contains(node) {
+                for successor in successors {
+                    predecessors.insert(*successor, *node);
+                }
+            }
+        }
+
+        let mut path = vec![*unsorted_nodes.iter().next().unwrap()];
+        loop {
+            let previous = predecessors[path.last().unwrap()];
+            if let Some(cycle_start) = path.iter().position(|node| *node == previous) {
+                let mut cycle = path.split_off(cycle_start);
+                cycle.push(previous);
+                // The path was followed backwards.
+                cycle.reverse();
+                return Err(CycleError(cycle));
+            }
+            path.push(previous);
+        }
+    }
+
+    /// Finds a path with the most edges, as the nodes along it. If several paths share the maximum length, any of
+    /// them may be returned. Fails if the graph has a cycle.
+    pub fn longest_path(&self) -> Result<Vec<T>, CycleError<T>> {
+        // Longest path starting from each node, as its length and the next node along it. Nodes are visited in
+        // reverse topological order, so that the paths from their successors are known by then.
+        let mut longest_paths: FxHashMap<T, (usize, Option<T>)> = FxHashMap::default();
+        for node in self.topological_order()?.into_iter().rev() {
+            let longest_path = self
+                .successors(node)
+                .iter()
+                .map(|successor| (longest_paths[successor].0 + 1, Some(*successor)))
+                .max_by_key(|(length, _)| *length)
+                .unwrap_or((0, None));
+            longest_paths.insert(node, longest_path);
+        }
+
+        let Some((&start, _)) = longest_paths.iter().max_by_key(|(_, (length, _))| *length) else {
+            return Ok(Vec::new());
+        };
+        let mut path = vec![start];
+        while let (_, Some(next)) = longest_paths[path.last().unwrap()] {
+            path.push(next);
+        }
+
+        Ok(path)
+    }
+
+    /// All nodes reached by following edges from `node`. It is only part of them if it is on a cycle.
+    pub fn reachable_from(&self, node: T) -> FxHashSet<T> {
+        let mut reached = FxHashSet::default();
+        let mut to_visit = self.successors(node).to_vec();
+        while let Some(node) = to_visit.pop() {
+            if reached.insert(node) {
+                to_visit.extend_from_slice(self.successors(node));
+            }
+        }
+
+        reached
+    }
+
+    /// Whether following edges from `from` leads to `to`, stopping as soon as it does.
+    pub fn can_reach(&self, from: T, to: T) -> bool {
+        let mut visited = FxHashSet::default();
+        let mut to_visit = self.successors(from).to_vec();
+        while let Some(node) = to_visit.pop() {
+            if node == to {
+                return true;
+            }
+            if visited.insert(node) {
+                to_visit.extend_from_slice(self.successors(node));
+            }
+        }
+
+        false
+    }
+}
+
+impl<T> Default for Dag<T> {
+    fn default() -> Self {
+        Self {
+            successors: FxHashMap::default(),
+        }
+    }
+}
+
+impl<T> FromIterator<(T, T)> for Dag<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Builds the graph from a list of edges, each from a node to one that must come after it.
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut dag = Dag::default();
+
+        for (from, to) in iter {
+            dag.add_edge(from, to);
+        }
+
+        dag
+    }
+}
+
+/// Error returned when nodes can't be sorted topologically, holding one of the cycles that prevents it.
+/// The cycle starts and ends with the same node.
+#[derive(Debug)]
+pub struct CycleError<T>(pub Vec<T>);
+
+impl<T> Display for CycleError<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "found a dependency cycle: {}",
+            self.0.iter().join(" -> ")
+        )
+    }
+}
+
+impl<T> std::error::Error for CycleError<T> where T: Debug + Display {}
+
+/// Sorts topologically all nodes found in `dependencies`, which maps nodes to the nodes they depend on.
+/// Each node is placed after all of its dependencies, including nodes that only appear as a dependency.
+/// Fails if the dependencies contain a cycle, in which case each node of the cycle depends on the next one.
+pub fn topological_sort<T>(dependencies: &FxHashMap<T, Vec<T>>) -> Result<Vec<T>, CycleError<T>>
+where
+    T: Copy + Eq + Hash,
+{
+    let mut dag = Dag::default();
+    for (node, node_dependencies) in dependencies {
+        dag.add_node(*node);
+        for dependency in node_dependencies {
+            dag.add_edge(*dependency, *node);
+        }
+    }
+
+    dag.topological_order().map_err(|CycleError(mut cycle)| {
+        // Edges go from dependencies to their dependents.
+        cycle.reverse();
+        CycleError(cycle)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nodes_come_after_their_predecessors() {
+        let dag: Dag<_> = [(1, 2), (1, 3), (3, 2), (2, 4)].into_iter().collect();
+
+        assert_eq!(dag.topological_order().unwrap(), [1, 3, 2, 4]);
+        assert_eq!(dag.longest_path().unwrap(), [1, 3, 2, 4]);
+        assert_eq!(dag.reachable_from(3), FxHashSet::from_iter([2, 4]));
+        assert!(dag.can_reach(1, 4));
+        assert!(!dag.can_reach(4, 1));
+        assert!(Dag::<u8>::default().longest_path().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let dag: Dag<_> = [(0, 1), (1, 2), (2, 3), (3, 1)].into_iter().collect();
+
+        let CycleError(cycle) = dag.topological_order().unwrap_err();
+        assert_eq!(cycle.len(), 4);
+        assert_eq!(cycle.first(), cycle.last());
+        for (from, to) in cycle.iter().tuple_windows() {
+            assert!(dag.successors(*from).contains(to));
+        }
+    }
+
+    #[test]
+    fn dependencies_are_sorted_first() {
+        let dependencies = FxHashMap::from_iter([('c', vec!['a', 'b']), ('b', vec!['a'])]);
+
+        assert_eq!(topological_sort(&dependencies).unwrap(), ['a', 'b', 'c']);
+    }
+}
@@ -1,55 +1,48 @@
-use std::collections::HashMap;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 
-use crate::{solver::Solver, utils::generate_benchmark};
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Solver},
+    utils::{FxHashMap, generate_benchmark},
+    viz::Visualize,
+};
 
-pub struct SolverImpl {}
+#[derive(Clone, Debug)]
+pub struct LocationLists {
+    location_ids_a: Vec<i32>,
+    location_ids_b: Vec<i32>,
+}
 
-impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let (mut location_ids_a, mut location_ids_b): (Vec<_>, Vec<_>) = file
-            .lines()
-            .map(|line| {
-                let (location_id_a, location_id_b) = line.split_once("   ").unwrap();
-                (
-                    location_id_a.parse::<i32>().unwrap(),
-                    location_id_b.parse::<i32>().unwrap(),
-                )
-            })
-            .unzip();
+impl LocationLists {
+    fn total_distance(&self) -> i32 {
+        let mut location_ids_a = self.location_ids_a.clone();
+        let mut location_ids_b = self.location_ids_b.clone();
         location_ids_a.sort();
         location_ids_b.sort();
 
         let mut total = 0;
-        for (location_id_a, location_id_b) in
-            location_ids_a.into_iter().zip(location_ids_b.into_iter())
-        {
+        for (location_id_a, location_id_b) in location_ids_a.into_iter().zip(location_ids_b) {
             total += (location_id_b - location_id_a).abs();
         }
 
-        println!("Total is {total}");
+        total
     }
 
-    fn solve_part2(file: &str) {
-        let (location_ids_a, location_ids_b): (Vec<_>, Vec<_>) = file
-            .lines()
-            .map(|line| {
-                let (location_id_a, location_id_b) = line.split_once("   ").unwrap();
-                (
-                    location_id_a.parse::<i32>().unwrap(),
-                    location_id_b.parse::<i32>().unwrap(),
-                )
-            })
-            .unzip();
-        let mut location_ids_and_count_a: HashMap<i32, i32> = HashMap::new();
-        for location_id_a in location_ids_a {
+    fn similarity_score(&self) -> i32 {
+        let mut location_ids_and_count_a: FxHashMap<i32, i32> = FxHashMap::default();
+        for &location_id_a in &self.location_ids_a {
             location_ids_and_count_a
                 .entry(location_id_a)
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
         }
 
-        let mut location_ids_and_count_b: HashMap<i32, i32> = HashMap::new();
-        for location_id_b in location_ids_b {
+        let mut location_ids_and_count_b: FxHashMap<i32, i32> = FxHashMap::default();
+        for &location_id_b in &self.location_ids_b {
             location_ids_and_count_b
                 .entry(location_id_b)
                 .and_modify(|count| *count += 1)
@@ -63,8 +56,132 @@ impl Solver for SolverImpl {
             total += count * (location_id * location_id_b_count);
         }
 
-        println!("Total is {total}");
+        total
+    }
+}
+
+pub struct SolverImpl {}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = LocationLists;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let (location_ids_a, location_ids_b) = Lines::new(file)
+            .map(|line| {
+                // Each line holds one location ID of each list, so a line with a missing or extra location ID would
+                // leave the lists with different lengths.
+                let mut location_ids = line.text.split_whitespace();
+                let (Some(location_id_a), Some(location_id_b)) =
+                    (location_ids.next(), location_ids.next())
+                else {
+                    return Err(line.error(
+                        "expected a location ID from each list, the lists have different lengths",
+                    ));
+                };
+                if let Some(extra_location_id) = location_ids.next() {
+                    return Err(line.error_at(
+                        extra_location_id,
+                        "expected only two location IDs, the lists have different lengths",
+                    ));
+                }
+
+                Ok((line.parse(location_id_a)?, line.parse(location_id_b)?))
+            })
+            .collect::<Result<Vec<(i32, i32)>, ParseError>>()?
+            .into_iter()
+            .unzip();
+
+        Ok(LocationLists {
+            location_ids_a,
+            location_ids_b,
+        })
+    }
+}
+
+impl Solver for SolverImpl {
+    type Command = Part;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.total_distance();
+        Ok(Answer::new(format!("Total is {answer}"), answer))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.similarity_score();
+        Ok(Answer::new(format!("Total is {answer}"), answer))
+    }
+
+    // Shuffles each list independently, and shifts all location IDs by the same amount. The total distance is
+    // preserved, as are the location IDs found in both lists.
+    fn scrub(file: &str, rng: &mut StdRng) -> Result<String, AocError> {
+        let LocationLists {
+            mut location_ids_a,
+            mut location_ids_b,
+        } = Self::parse(file)?;
+        location_ids_a.shuffle(rng);
+        location_ids_b.shuffle(rng);
+
+        let all_location_ids = || location_ids_a.iter().chain(&location_ids_b);
+        let min = all_location_ids().min().copied().unwrap_or(0);
+        let max = all_location_ids().max().copied().unwrap_or(0);
+        // Location IDs stay positive, and no longer than in the original input.
+        let offset = rng.random_range(-min..=(99999 - max).max(-min));
+
+        Ok(location_ids_a
+            .iter()
+            .zip(&location_ids_b)
+            .map(|(location_id_a, location_id_b)| {
+                format!("{}   {}\n", location_id_a + offset, location_id_b + offset)
+            })
+            .collect())
     }
 }
 
 generate_benchmark!(day1);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_lists_in_order() {
+        let lists = SolverImpl::parse("3   4\n1   3\n").unwrap();
+
+        assert_eq!(lists.location_ids_a, [3, 1]);
+        assert_eq!(lists.location_ids_b, [4, 3]);
+    }
+
+    #[test]
+    fn a_missing_location_id_fails_to_parse() {
+        let error = SolverImpl::parse("3   4\n1\n").unwrap_err();
+
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("different lengths"));
+    }
+
+    #[test]
+    fn extra_or_malformed_location_ids_fail_to_parse() {
+        let error = SolverImpl::parse("3   4   5\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, Some(9)));
+
+        let error = SolverImpl::parse("3   4\n\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = SolverImpl::parse("3   x\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, Some(5)));
+    }
+
+    #[test]
+    fn pairs_location_ids_by_rank() {
+        let lists = LocationLists {
+            location_ids_a: vec![3, 1, 2],
+            location_ids_b: vec![1, 5, 3],
+        };
+
+        assert_eq!(lists.total_distance(), 1 + 2);
+        assert_eq!(lists.similarity_score(), 3 + 1);
+    }
+}
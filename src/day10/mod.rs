@@ -1,45 +1,35 @@
-use hashbrown::HashSet;
+use clap::Subcommand;
 
-use crate::solver::Solver;
-use crate::utils::{Position, generate_benchmark, pos};
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{FxHashSet, Position, generate_benchmark, pos};
+use crate::viz::Visualize;
 
 const TRAIL_START: i8 = 0;
 const TRAIL_END: i8 = 9;
 const TRAIL_OUT_OF_BOUNDS: i8 = -1;
 
-struct TopographicMap {
+/// Statistics of all the hiking trails starting at a trailhead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrailheadStats {
+    pub trailhead: Position,
+    /// Number of distinct peaks reachable from the trailhead, i.e. its score.
+    pub num_reachable_peaks: usize,
+    /// Number of distinct trails from the trailhead to any peak, i.e. its rating.
+    pub num_trails: usize,
+    /// Minimum, maximum, and mean number of steps of the trails, if there is any trail.
+    pub trail_lengths: Option<(usize, usize, f64)>,
+}
+
+#[derive(Clone)]
+pub struct TopographicMap {
     topographic_map: Vec<Vec<i8>>,
     trailheads: Vec<Position>,
 }
 
 impl TopographicMap {
-    fn new(file: &str) -> Self {
-        let mut topographic_map = Vec::new();
-        let mut trailheads = Vec::new();
-        // Note that the map is padded with a layer of out_of_bounds locations.
-        let topographic_map_size = file.lines().next().unwrap().len() + 2;
-
-        topographic_map.push(vec![TRAIL_OUT_OF_BOUNDS; topographic_map_size]);
-        for (row, line) in file.lines().enumerate() {
-            let mut topographic_row = vec![TRAIL_OUT_OF_BOUNDS; 1];
-            for (col, height) in line.char_indices() {
-                let height = height.to_digit(10).unwrap() as i8;
-                topographic_row.push(height);
-                if height == TRAIL_START {
-                    trailheads.push(pos!(row + 1, col + 1));
-                }
-            }
-            topographic_row.push(TRAIL_OUT_OF_BOUNDS);
-            topographic_map.push(topographic_row);
-        }
-        topographic_map.push(vec![TRAIL_OUT_OF_BOUNDS; topographic_map_size]);
-
-        Self {
-            topographic_map,
-            trailheads,
-        }
-    }
-
     fn at(&self, position: Position) -> i8 {
         self.topographic_map[position.row][position.col]
     }
@@ -58,7 +48,7 @@ impl TopographicMap {
         topographic_map: &Self,
         skip_duplicate_trailheads: bool,
     ) -> usize {
-        let mut visited_positions = HashSet::new();
+        let mut visited_positions = FxHashSet::default();
         let mut positions_to_visit = vec![trailhead];
 
         let mut trailheads_count = 0;
@@ -83,21 +73,189 @@ impl TopographicMap {
 
         trailheads_count
     }
+
+    /// Statistics of the trails of each trailhead, in the order they appear on the map.
+    pub fn trailheads_stats(&self) -> Vec<TrailheadStats> {
+        self.trailheads
+            .iter()
+            .map(|&trailhead| self.trailhead_stats(trailhead))
+            .collect()
+    }
+
+    // Same search as `compute_trailhead_score` without skipping duplicates, but keeping track of the length of each
+    // trail and of the peaks they end at.
+    fn trailhead_stats(&self, trailhead: Position) -> TrailheadStats {
+        let mut reachable_peaks = FxHashSet::default();
+        let mut trail_lengths = Vec::new();
+        let mut positions_to_visit = vec![(trailhead, 0)];
+
+        while let Some((current_position, num_steps)) = positions_to_visit.pop() {
+            let current_height = self.at(current_position);
+            if current_height == TRAIL_END {
+                reachable_peaks.insert(current_position);
+                trail_lengths.push(num_steps);
+                continue;
+            }
+
+            for next_position in current_position.surroundings() {
+                if self.at(next_position) == current_height + 1 {
+                    positions_to_visit.push((next_position, num_steps + 1));
+                }
+            }
+        }
+
+        TrailheadStats {
+            trailhead,
+            num_reachable_peaks: reachable_peaks.len(),
+            num_trails: trail_lengths.len(),
+            trail_lengths: trail_lengths.iter().min().map(|&min_length| {
+                (
+                    min_length,
+                    *trail_lengths.iter().max().unwrap(),
+                    trail_lengths.iter().sum::<usize>() as f64 / trail_lengths.len() as f64,
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report, per trailhead, the peaks it reaches, its number of trails, and how long they are.
+    Trailheads,
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Trailheads => {
+                let topographic_map = SolverImpl::parse(file)?;
+                for stats in topographic_map.trailheads_stats() {
+                    let Position { row, col } = stats.trailhead;
+                    print!(
+                        "The trailhead at line {row}, column {col} reaches {} peaks through {} trails",
+                        stats.num_reachable_peaks, stats.num_trails
+                    );
+                    match stats.trail_lengths {
+                        Some((min_length, max_length, mean_length)) => println!(
+                            " of {min_length} to {max_length} steps ({mean_length:.2} on average)."
+                        ),
+                        None => println!("."),
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Trailheads => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
 }
+
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = TopographicMap;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let lines = Lines::new(file);
+        let mut topographic_map = Vec::new();
+        let mut trailheads = Vec::new();
+        // Note that the map is padded with a layer of out_of_bounds locations.
+        let topographic_map_size = lines.clone().next_line()?.text.len() + 2;
+
+        topographic_map.push(vec![TRAIL_OUT_OF_BOUNDS; topographic_map_size]);
+        for line in lines {
+            let mut topographic_row = vec![TRAIL_OUT_OF_BOUNDS; 1];
+            for (col, height) in line.parse_digits()?.into_iter().enumerate() {
+                let height = height as i8;
+                topographic_row.push(height);
+                if height == TRAIL_START {
+                    // The padding row above makes line numbers match rows.
+                    trailheads.push(pos!(line.number, col + 1));
+                }
+            }
+            topographic_row.push(TRAIL_OUT_OF_BOUNDS);
+            topographic_map.push(topographic_row);
+        }
+        topographic_map.push(vec![TRAIL_OUT_OF_BOUNDS; topographic_map_size]);
+
+        Ok(TopographicMap {
+            topographic_map,
+            trailheads,
+        })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let topographic_map = TopographicMap::new(file);
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let topographic_map = Self::parse_shared(file)?;
         let trailheads_scores = topographic_map.compute_trailheads_score(true);
-        println!("The trailheads score is {}", trailheads_scores);
+        Ok(Answer::new(
+            format!("The trailheads score is {trailheads_scores}"),
+            trailheads_scores,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let topographic_map = TopographicMap::new(file);
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let topographic_map = Self::parse_shared(file)?;
         let trailheads_rating = topographic_map.compute_trailheads_score(false);
-        println!("The trailheads rating is {}", trailheads_rating);
+        Ok(Answer::new(
+            format!("The trailheads rating is {trailheads_rating}"),
+            trailheads_rating,
+        ))
     }
 }
 
 generate_benchmark!(day10);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn trailhead_stats_match_scores_and_ratings() {
+        let file = "0123\n1234\n8765\n9876\n";
+        let topographic_map = SolverImpl::parse(file).unwrap();
+
+        assert_eq!(
+            topographic_map.trailheads_stats(),
+            [TrailheadStats {
+                trailhead: pos!(1, 1),
+                num_reachable_peaks: 1,
+                num_trails: 16,
+                trail_lengths: Some((9, 9, 9.0)),
+            }]
+        );
+    }
+
+    #[test]
+    fn trailheads_without_trails_have_no_lengths() {
+        let topographic_map = SolverImpl::parse("0129\n").unwrap();
+
+        let [stats] = topographic_map.trailheads_stats().try_into().unwrap();
+        assert_eq!(stats.num_reachable_peaks, 0);
+        assert_eq!(stats.num_trails, 0);
+        assert_eq!(stats.trail_lengths, None);
+    }
+}
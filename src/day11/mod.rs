@@ -1,42 +1,37 @@
-use std::num::ParseIntError;
-use std::str::FromStr;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
 
-use hashbrown::HashMap;
+use rustc_hash::FxBuildHasher;
 
-use crate::solver::Solver;
-use crate::utils::generate_benchmark;
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Lines, Parse, ParseError};
+use crate::solver::{Answer, Solver, Variant};
+use crate::utils::math::{OverflowError, checked_sum, mul_add_unsigned, num_digits, pow10};
+use crate::utils::{FxHashMap, generate_benchmark, shard_and_solve_concurrently};
+use crate::viz::Visualize;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+// Number of locks guarding the shared stones history. Threads looking up different stones rarely wait on each other.
+const NUM_HISTORY_SHARDS: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Stone {
     value: u64,
     generation: u8,
 }
-impl FromStr for Stone {
-    type Err = ParseIntError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            value: s.parse()?,
-            generation: 0,
-        })
-    }
-}
 impl Stone {
-    fn next(&self) -> Vec<Self> {
+    // Stones this one turns into on the next blink. Fails if a stone's engraving no longer fits in 64 bits.
+    fn next(&self) -> Result<Vec<Self>, OverflowError> {
         let generation = self.generation + 1;
-        let num_digits = if self.value == 0 {
-            1
-        } else {
-            self.value.ilog10() + 1
-        };
+        let num_digits = num_digits(self.value);
 
-        if self.value == 0 {
+        Ok(if self.value == 0 {
             vec![Self {
                 value: 1,
                 generation,
             }]
-        } else if num_digits % 2 == 0 {
-            let exponent = 10_u64.pow(num_digits >> 1);
+        } else if num_digits.is_multiple_of(2) {
+            let exponent = pow10(num_digits >> 1)?;
             let left_number = self.value / exponent;
             let right_number = self.value % exponent;
             vec![
@@ -51,71 +46,200 @@ impl Stone {
             ]
         } else {
             vec![Self {
-                value: self.value * 2024,
+                value: mul_add_unsigned(self.value, 2024, 0)?,
                 generation,
             }]
-        }
+        })
     }
 }
 
-struct Blinker {
-    stones: Vec<Stone>,
+// Memo of how many stones a stone turns into by the final generation.
+trait StonesHistory {
+    fn get(&mut self, stone: &Stone) -> Option<u64>;
+    fn insert(&mut self, stone: Stone, num_stones: u64);
 }
-impl Blinker {
-    fn new(file: &str) -> Self {
+
+impl StonesHistory for FxHashMap<Stone, u64> {
+    fn get(&mut self, stone: &Stone) -> Option<u64> {
+        FxHashMap::get(self, stone).copied()
+    }
+
+    fn insert(&mut self, stone: Stone, num_stones: u64) {
+        FxHashMap::insert(self, stone, num_stones);
+    }
+}
+
+// Stones history shared by threads blinking concurrently, split in shards each behind their own lock. Locks are only
+// held while looking up or inserting a stone, never while blinking it.
+struct SharedStonesHistory {
+    shards: [Mutex<FxHashMap<Stone, u64>>; NUM_HISTORY_SHARDS],
+}
+
+impl SharedStonesHistory {
+    fn new() -> Self {
         Self {
-            stones: file
-                .split_whitespace()
-                .map(Stone::from_str)
-                .collect::<Result<_, _>>()
-                .unwrap(),
+            shards: std::array::from_fn(|_| Mutex::default()),
         }
     }
 
-    fn blink(self, final_generation: u8) -> u64 {
-        let mut stones_history = HashMap::default();
+    fn shard(&self, stone: &Stone) -> &Mutex<FxHashMap<Stone, u64>> {
+        &self.shards[FxBuildHasher.hash_one(stone) as usize % NUM_HISTORY_SHARDS]
+    }
+}
+
+impl StonesHistory for &SharedStonesHistory {
+    fn get(&mut self, stone: &Stone) -> Option<u64> {
+        self.shard(stone).lock().unwrap().get(stone).copied()
+    }
+
+    fn insert(&mut self, stone: Stone, num_stones: u64) {
+        self.shard(&stone).lock().unwrap().insert(stone, num_stones);
+    }
+}
+
+#[derive(Clone)]
+pub struct Blinker {
+    stones: Vec<Stone>,
+}
+impl Blinker {
+    fn blink(self, final_generation: u8) -> Result<u64, OverflowError> {
+        let mut stones_history = FxHashMap::default();
+
+        checked_sum(
+            self.stones
+                .into_iter()
+                .map(|stone| Self::blink_rec(stone, &mut stones_history, final_generation)),
+        )
+    }
 
-        self.stones
-            .into_iter()
-            .map(|stone| Self::blink_rec(stone, &mut stones_history, final_generation))
-            .sum()
+    // Same as `blink`, but initial stones are sharded across threads, which share what they learn about stones.
+    fn blink_concurrently(self, final_generation: u8) -> Result<u64, OverflowError> {
+        checked_sum(shard_and_solve_concurrently(
+            self.stones,
+            Arc::new(SharedStonesHistory::new()),
+            move |stones, stones_history| {
+                let mut stones_history = stones_history.as_ref();
+                checked_sum(
+                    stones
+                        .into_iter()
+                        .map(|stone| Self::blink_rec(stone, &mut stones_history, final_generation)),
+                )
+            },
+        ))
     }
 
     fn blink_rec(
         stone: Stone,
-        stones_history: &mut HashMap<Stone, u64>,
+        stones_history: &mut impl StonesHistory,
         final_generation: u8,
-    ) -> u64 {
+    ) -> Result<u64, OverflowError> {
         if stone.generation == final_generation {
-            return 1;
+            return Ok(1);
         }
 
         if let Some(num_stones) = stones_history.get(&stone) {
-            return *num_stones;
+            return Ok(num_stones);
         }
 
-        let num_stones = stone
-            .next()
-            .into_iter()
-            .map(|next_stone| Self::blink_rec(next_stone, stones_history, final_generation))
-            .sum();
+        let num_stones = checked_sum(
+            stone
+                .next()?
+                .into_iter()
+                .map(|next_stone| Self::blink_rec(next_stone, stones_history, final_generation)),
+        )?;
 
         stones_history.insert(stone, num_stones);
-        return num_stones;
+        Ok(num_stones)
     }
 }
+
 pub struct SolverImpl {}
 
+impl SolverImpl {
+    const PART1_VARIANTS: &[Variant] = &[
+        Variant::new("single-threaded", |file| {
+            Ok(Self::parse(file)?.blink(25)?.to_string())
+        }),
+        Variant::new("shared-memo", |file| {
+            Ok(Self::parse(file)?.blink_concurrently(25)?.to_string())
+        }),
+    ];
+
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("single-threaded", |file| {
+            Ok(Self::parse(file)?.blink(75)?.to_string())
+        }),
+        Variant::new("shared-memo", |file| {
+            Ok(Self::parse(file)?.blink_concurrently(75)?.to_string())
+        }),
+    ];
+}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Blinker;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let line = Lines::new(file).next_line()?;
+        let stones = line
+            .text
+            .split_whitespace()
+            .map(|value| {
+                Ok(Stone {
+                    value: line.parse(value)?,
+                    generation: 0,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Blinker { stones })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let blinker = Blinker::new(file);
-        println!("We have {} stones", blinker.blink(25));
+    type Command = Part;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let blinker = Self::parse_shared(file)?;
+        let answer = blinker.blink(25)?;
+        Ok(Answer::new(format!("We have {answer} stones"), answer))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let blinker = Self::parse_shared(file)?;
+        let answer = blinker.blink_concurrently(75)?;
+        Ok(Answer::new(format!("We have {answer} stones"), answer))
     }
 
-    fn solve_part2(file: &str) {
-        let blinker = Blinker::new(file);
-        println!("We have {} stones", blinker.blink(75));
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => Self::PART1_VARIANTS,
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
     }
 }
 
 generate_benchmark!(day11);
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod concurrency_benches {
+    use test::Bencher;
+
+    use super::*;
+
+    #[bench]
+    fn bench_day11_blink_single_threaded(b: &mut Bencher) {
+        let file = crate::utils::read_benchmark_input("day11");
+
+        b.iter(|| SolverImpl::parse(&file).unwrap().blink(75));
+    }
+
+    #[bench]
+    fn bench_day11_blink_shared_memo(b: &mut Bencher) {
+        let file = crate::utils::read_benchmark_input("day11");
+
+        b.iter(|| SolverImpl::parse(&file).unwrap().blink_concurrently(75));
+    }
+}
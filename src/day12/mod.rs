@@ -1,10 +1,27 @@
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+
 use crate::{
-    solver::Solver,
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    output::{Format, write_records},
+    solver::{Answer, Run, Solver, Variant},
     utils::{Position, generate_benchmark, pos},
+    viz::Visualize,
 };
 
 const OUT_OF_BOUNDS_PLANT: char = '?';
 
+/// Which plots touching each other are part of the same region, if they have the same plant.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only plots sharing an edge, like in the puzzle.
+    Orthogonal,
+    /// Plots sharing an edge or a corner.
+    Diagonal,
+}
+
 #[derive(Clone, Debug)]
 struct GardenPlot {
     position: Position,
@@ -36,8 +53,9 @@ impl GardenPlot {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Area {
+    plant: char,
     garden_plot_positions: Vec<Position>,
 }
 
@@ -46,38 +64,37 @@ impl Area {
         self.garden_plot_positions.len() as u32
     }
 
-    fn perimeter(&self, garden_plots: &Vec<Vec<Option<GardenPlot>>>) -> u32 {
+    fn perimeter(&self, garden_plots: &[Vec<Option<GardenPlot>>]) -> u32 {
         self.garden_plot_positions
             .iter()
             .map(|Position { row, col }| garden_plots[*row][*col].as_ref().unwrap().num_sides())
             .sum()
     }
 
-    fn perimeter_based_price(&self, garden_plots: &Vec<Vec<Option<GardenPlot>>>) -> u32 {
+    fn perimeter_based_price(&self, garden_plots: &[Vec<Option<GardenPlot>>]) -> u32 {
         self.area() * self.perimeter(garden_plots)
     }
 }
 
-#[derive(Debug)]
-struct Arrangement {
+#[derive(Clone, Debug)]
+pub struct Arrangement {
     areas: Vec<Area>,
     garden_plots: Vec<Vec<Option<GardenPlot>>>,
 }
 
 impl Arrangement {
-    fn new(file: &str) -> Self {
-        // Note that we pad the grid with an out-of-bounds layer.
-        let grid_size = file.lines().next().unwrap().len() + 2;
-        // Intermediate representation. Stores a garden plot plant, and whether it's been added
-        // to an area yet.
-        let mut plant_and_part_of_existing_areas =
-            vec![vec![(OUT_OF_BOUNDS_PLANT, true); grid_size]; grid_size];
-
-        for (row, line) in file.lines().enumerate() {
-            for (col, plant) in line.char_indices() {
-                plant_and_part_of_existing_areas[row + 1][col + 1] = (plant, false);
-            }
-        }
+    // Groups the plants of the garden into areas. `plant_and_part_of_existing_areas` is the intermediate representation
+    // of the garden, padded with an out-of-bounds layer. It stores each garden plot plant, and whether it's been added
+    // to an area yet.
+    //
+    // Note that the connectivity only decides which plots are in the same area. Sides are fences between a plot and
+    // a plot orthogonal to it, and those are in the same area under both connectivities if they have the same plant.
+    // Hence the sides of plots, and the sweeps and corners built on them, work the same for both.
+    fn new(
+        mut plant_and_part_of_existing_areas: Vec<Vec<(char, bool)>>,
+        connectivity: Connectivity,
+    ) -> Self {
+        let grid_size = plant_and_part_of_existing_areas.len();
 
         // Visit every garden plot to decide a new area must be defined. Build a new grid of fully built garden plots.
         // This grid is again padded with an out-of-bounds layer, represented with `None`.
@@ -96,9 +113,11 @@ impl Arrangement {
                     areas.len(),
                     pos!(row, col),
                     &mut plant_and_part_of_existing_areas,
+                    connectivity,
                 );
 
                 areas.push(Area {
+                    plant,
                     garden_plot_positions: area_garden_plots
                         .iter()
                         .map(|garden_plot| garden_plot.position)
@@ -122,7 +141,8 @@ impl Arrangement {
         plant: char,
         area_id: usize,
         position: Position,
-        plant_and_part_of_existing_areas: &mut Vec<Vec<(char, bool)>>,
+        plant_and_part_of_existing_areas: &mut [Vec<(char, bool)>],
+        connectivity: Connectivity,
     ) -> Vec<GardenPlot> {
         let mut plots_to_explore = vec![position];
         let mut garden_plots = Vec::new();
@@ -169,6 +189,22 @@ impl Arrangement {
                 }
             }
 
+            if connectivity == Connectivity::Diagonal {
+                // Diagonal plots never share a side with this plot, they only extend the area.
+                for diagonal_plot in [
+                    pos!(row - 1, col + 1),
+                    pos!(row + 1, col + 1),
+                    pos!(row + 1, col - 1),
+                    pos!(row - 1, col - 1),
+                ] {
+                    let (diagonal_plant, diagonal_plant_part_of_existing_area) =
+                        plant_and_part_of_existing_areas[diagonal_plot.row][diagonal_plot.col];
+                    if diagonal_plant == plant && !diagonal_plant_part_of_existing_area {
+                        plots_to_explore.push(diagonal_plot);
+                    }
+                }
+            }
+
             garden_plots.push(GardenPlot {
                 position: plot_to_explore,
                 area_id,
@@ -189,7 +225,30 @@ impl Arrangement {
             .sum()
     }
 
-    fn num_of_sides_based_price(&self) -> u32 {
+    fn num_of_sides_based_price(&self, num_sides_per_area: &[u32]) -> u32 {
+        self.areas
+            .iter()
+            .zip(num_sides_per_area)
+            .map(|(area, num_sides)| area.area() * num_sides)
+            .sum()
+    }
+
+    fn region_reports(&self) -> Vec<RegionReport> {
+        self.areas
+            .iter()
+            .zip(self.num_sides_per_area())
+            .map(|(area, num_sides)| RegionReport {
+                plant: area.plant,
+                area: area.area(),
+                perimeter: area.perimeter(&self.garden_plots),
+                sides: num_sides,
+                perimeter_based_price: area.perimeter_based_price(&self.garden_plots),
+                sides_based_price: area.area() * num_sides,
+            })
+            .collect()
+    }
+
+    fn num_sides_per_area(&self) -> Vec<u32> {
         let mut num_sides_per_area = vec![0; self.areas.len()];
 
         // Visit every garden plot left to right, row by row, whilst ignoring out-of-bounds plots.
@@ -270,32 +329,255 @@ impl Arrangement {
             }
         }
 
-        self.areas
-            .iter()
-            .zip(num_sides_per_area)
-            .map(|(area, num_sides)| area.area() * num_sides)
-            .sum()
+        num_sides_per_area
+    }
+
+    // Same as `num_sides_per_area`, but counts the corners of each area instead: going around an area, each corner
+    // ends one side and starts another, so an area has as many sides as corners.
+    fn num_sides_per_area_by_corners(&self) -> Vec<u32> {
+        let mut num_corners_per_area = vec![0; self.areas.len()];
+        let area_id_at = |Position { row, col }: Position| {
+            self.garden_plots[row][col]
+                .as_ref()
+                .map(|garden_plot| garden_plot.area_id)
+        };
+
+        // Out-of-bounds plots are `None`, and are skipped.
+        for garden_plot in self.garden_plots.iter().flatten().flatten() {
+            let Position { row, col } = garden_plot.position;
+            // Each corner of a plot is between two of its sides, next to a diagonal plot.
+            let corners = [
+                (
+                    garden_plot.side_up,
+                    garden_plot.side_right,
+                    pos!(row - 1, col + 1),
+                ),
+                (
+                    garden_plot.side_right,
+                    garden_plot.side_down,
+                    pos!(row + 1, col + 1),
+                ),
+                (
+                    garden_plot.side_down,
+                    garden_plot.side_left,
+                    pos!(row + 1, col - 1),
+                ),
+                (
+                    garden_plot.side_left,
+                    garden_plot.side_up,
+                    pos!(row - 1, col - 1),
+                ),
+            ];
+
+            for (side_a, side_b, diagonal) in corners {
+                // Outer corners have both sides, whereas inner corners have neither, but the diagonal plot is
+                // from another area.
+                let is_outer_corner = side_a && side_b;
+                let is_inner_corner =
+                    !side_a && !side_b && area_id_at(diagonal) != Some(garden_plot.area_id);
+                if is_outer_corner || is_inner_corner {
+                    num_corners_per_area[garden_plot.area_id] += 1;
+                }
+            }
+        }
+
+        num_corners_per_area
+    }
+}
+
+/// Measurements of a region of the garden, and the prices of its fence in each part.
+#[derive(Debug, Serialize)]
+pub struct RegionReport {
+    pub plant: char,
+    pub area: u32,
+    pub perimeter: u32,
+    pub sides: u32,
+    pub perimeter_based_price: u32,
+    pub sides_based_price: u32,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report the measurements and fence prices of every region, in the order they're found.
+    Regions {
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        #[arg(short, long, value_enum, default_value_t = Connectivity::Orthogonal)]
+        connectivity: Connectivity,
+    },
+    /// Compute the price of fencing the garden in both parts, with the given connectivity of regions.
+    Price {
+        #[arg(short, long, value_enum, default_value_t = Connectivity::Orthogonal)]
+        connectivity: Connectivity,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Regions {
+                format,
+                connectivity,
+            } => write_records(
+                &SolverImpl::parse_with_connectivity(file, connectivity)?.region_reports(),
+                format,
+            )?,
+            Self::Price { connectivity } => {
+                let arrangement = SolverImpl::parse_with_connectivity(file, connectivity)?;
+                println!(
+                    "The price for fencing this arrangement is {} by perimeter, and {} by number of sides",
+                    arrangement.perimeter_based_price(),
+                    arrangement.num_of_sides_based_price(&arrangement.num_sides_per_area())
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
     }
 }
 
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Arrangement;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Self::parse_with_connectivity(file, Connectivity::Orthogonal)
+    }
+}
+
+impl SolverImpl {
+    fn parse_with_connectivity(
+        file: &str,
+        connectivity: Connectivity,
+    ) -> Result<Arrangement, ParseError> {
+        let lines = Lines::new(file);
+        let garden_size = lines.clone().next_line()?.text.chars().count();
+        // Note that we pad the grid with an out-of-bounds layer.
+        let grid_size = garden_size + 2;
+        let mut plant_and_part_of_existing_areas =
+            vec![vec![(OUT_OF_BOUNDS_PLANT, true); grid_size]; grid_size];
+
+        for line in lines {
+            if line.number > garden_size || line.text.chars().count() != garden_size {
+                return Err(line.error(format!(
+                    "the garden must be a {garden_size}x{garden_size} square"
+                )));
+            }
+
+            // The padding row above makes line numbers match rows.
+            for (col, plant) in line.text.chars().enumerate() {
+                plant_and_part_of_existing_areas[line.number][col + 1] = (plant, false);
+            }
+        }
+
+        Ok(Arrangement::new(
+            plant_and_part_of_existing_areas,
+            connectivity,
+        ))
+    }
+
+    // Sides are either found by sweeping the garden, or by counting corners.
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("sweep", |file| {
+            let arrangement = Self::parse(file)?;
+            Ok(arrangement
+                .num_of_sides_based_price(&arrangement.num_sides_per_area())
+                .to_string())
+        }),
+        Variant::new("corners", |file| {
+            let arrangement = Self::parse(file)?;
+            Ok(arrangement
+                .num_of_sides_based_price(&arrangement.num_sides_per_area_by_corners())
+                .to_string())
+        }),
+    ];
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let arrangement = Arrangement::new(file);
-        println!(
-            "The price for fencing this arrangement is {}",
-            arrangement.perimeter_based_price()
-        );
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let arrangement = Self::parse_shared(file)?;
+        let answer = arrangement.perimeter_based_price();
+        Ok(Answer::new(
+            format!("The price for fencing this arrangement is {answer}"),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let arrangement = Arrangement::new(file);
-        println!(
-            "The price for fencing this arrangement is {}",
-            arrangement.num_of_sides_based_price()
-        );
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let arrangement = Self::parse_shared(file)?;
+        let answer = arrangement.num_of_sides_based_price(&arrangement.num_sides_per_area());
+        Ok(Answer::new(
+            format!("The price for fencing this arrangement is {answer}"),
+            answer,
+        ))
+    }
+
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => &[],
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
     }
 }
 
 generate_benchmark!(day12);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn diagonal_plots_join_regions() {
+        let file = "AB\nBA\n";
+
+        let orthogonal =
+            SolverImpl::parse_with_connectivity(file, Connectivity::Orthogonal).unwrap();
+        assert_eq!(orthogonal.areas.len(), 4);
+        assert_eq!(orthogonal.perimeter_based_price(), 16);
+
+        let diagonal = SolverImpl::parse_with_connectivity(file, Connectivity::Diagonal).unwrap();
+        assert_eq!(diagonal.areas.len(), 2);
+        assert_eq!(diagonal.perimeter_based_price(), 32);
+        assert_eq!(diagonal.num_sides_per_area(), [8, 8]);
+        assert_eq!(diagonal.num_sides_per_area_by_corners(), [8, 8]);
+    }
+
+    #[test]
+    fn sweeps_and_corners_agree_with_diagonal_regions() {
+        for file in [
+            include_str!("example.txt"),
+            include_str!("example2.txt"),
+            include_str!("example3.txt"),
+        ] {
+            let arrangement =
+                SolverImpl::parse_with_connectivity(file, Connectivity::Diagonal).unwrap();
+            assert_eq!(
+                arrangement.num_sides_per_area(),
+                arrangement.num_sides_per_area_by_corners()
+            );
+        }
+    }
+}
@@ -1,14 +1,30 @@
-use itertools::Itertools;
+use std::path::{Path, PathBuf};
 
-use crate::{solver::Solver, utils::generate_benchmark};
+use clap::Subcommand;
+use rand::Rng;
+use rand::rngs::StdRng;
+use serde::Serialize;
+
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    output::{Format, write_records},
+    solver::{Answer, Run, Solver},
+    utils::generate_benchmark,
+    viz::Visualize,
+};
 
 const EPSILON: f64 = 0.0001;
 const NUM_TOKENS_PER_A_PRESS: u64 = 3;
 const NUM_TOKENS_PER_B_PRESS: u64 = 1;
+const PART2_PRIZE_POSITION_OFFSET: f64 = 10000000000000.0;
 
 pub struct SolverImpl {}
 
-#[derive(Debug)]
+impl Visualize for SolverImpl {}
+
+#[derive(Clone, Debug)]
 struct ClawMachine {
     xa: f64,
     xb: f64,
@@ -33,10 +49,8 @@ impl ClawMachine {
             if b_presses_approx - b_presses > EPSILON {
                 return 0;
             }
-        } else if b_presses > b_presses_approx {
-            if b_presses - b_presses_approx > EPSILON {
-                return 0;
-            }
+        } else if b_presses > b_presses_approx && b_presses - b_presses_approx > EPSILON {
+            return 0;
         }
 
         if b_presses_approx < 0.0 {
@@ -50,10 +64,8 @@ impl ClawMachine {
             if a_presses_approx - a_presses > EPSILON {
                 return 0;
             }
-        } else if a_presses > a_presses_approx {
-            if a_presses - a_presses_approx > EPSILON {
-                return 0;
-            }
+        } else if a_presses > a_presses_approx && a_presses - a_presses_approx > EPSILON {
+            return 0;
         }
 
         if a_presses_approx < 0.0 {
@@ -63,36 +75,58 @@ impl ClawMachine {
         NUM_TOKENS_PER_B_PRESS * b_presses_approx as u64
             + NUM_TOKENS_PER_A_PRESS * a_presses_approx as u64
     }
+
+    // Whether the prize can be reached by pressing each button a whole number of times. Unlike
+    // `find_num_tokens_spent`, this uses exact integer arithmetic, so it's only meant for the small part 1 prizes.
+    fn is_solvable(&self) -> bool {
+        let [xa, xb, xf, ya, yb, yf] =
+            [self.xa, self.xb, self.xf, self.ya, self.yb, self.yf].map(|v| v as i64);
+        let determinant = xa * yb - xb * ya;
+        if determinant == 0 {
+            return false;
+        }
+
+        let a_presses = xf * yb - xb * yf;
+        let b_presses = xa * yf - xf * ya;
+        a_presses % determinant == 0
+            && b_presses % determinant == 0
+            && a_presses / determinant >= 0
+            && b_presses / determinant >= 0
+    }
 }
 
-#[derive(Debug)]
-struct ClawMachineSimulation {
-    claw_machines: Vec<ClawMachine>,
+impl std::fmt::Display for ClawMachine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Button A: X+{}, Y+{}", self.xa, self.ya)?;
+        writeln!(f, "Button B: X+{}, Y+{}", self.xb, self.yb)?;
+        writeln!(f, "Prize: X={}, Y={}", self.xf, self.yf)
+    }
 }
 
-impl ClawMachineSimulation {
-    fn new(file: &str, prize_position_offset: f64) -> Self {
-        let mut claw_machines = Vec::new();
+#[derive(Clone, Debug)]
+pub struct ClawMachineSimulation {
+    claw_machines: Vec<ClawMachine>,
+}
 
-        for mut simulation in &file.lines().chunks(4) {
-            let a_simulation = simulation.next().unwrap();
-            let b_simulation = simulation.next().unwrap();
-            let prize_simulation = simulation.next().unwrap();
+// Parses the "X+1, Y+2" or "X=1, Y=2" coordinates at the end of a line, after the given `prefix`.
+fn parse_coordinates(line: Line, prefix: &str, separator: &str) -> Result<(f64, f64), ParseError> {
+    let coordinates = line.strip_prefix(prefix)?;
+    let (x, y) = line.split_once(coordinates, separator)?;
+    Ok((line.parse(x)?, line.parse(y)?))
+}
 
-            let (_, a_simulation) = a_simulation.split_once("X+").unwrap();
-            let (xa, ya) = a_simulation.split_once(", Y+").unwrap();
-            let (xa, ya) = (xa.parse().unwrap(), ya.parse().unwrap());
+impl Parse for SolverImpl {
+    type Input = ClawMachineSimulation;
 
-            let (_, b_simulation) = b_simulation.split_once("X+").unwrap();
-            let (xb, yb) = b_simulation.split_once(", Y+").unwrap();
-            let (xb, yb) = (xb.parse().unwrap(), yb.parse().unwrap());
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut claw_machines = Vec::new();
+        let mut lines = Lines::new(file);
 
-            let (_, prize_simulation) = prize_simulation.split_once("X=").unwrap();
-            let (xf, yf) = prize_simulation.split_once(", Y=").unwrap();
-            let (xf, yf) = (
-                xf.parse::<f64>().unwrap() + prize_position_offset,
-                yf.parse::<f64>().unwrap() + prize_position_offset,
-            );
+        while let Some(a_line) = lines.next() {
+            let (xa, ya) = parse_coordinates(a_line, "Button A: X+", ", Y+")?;
+            let (xb, yb) = parse_coordinates(lines.next_line()?, "Button B: X+", ", Y+")?;
+            let (xf, yf) = parse_coordinates(lines.next_line()?, "Prize: X=", ", Y=")?;
 
             claw_machines.push(ClawMachine {
                 xa,
@@ -102,9 +136,27 @@ impl ClawMachineSimulation {
                 yb,
                 yf,
             });
+
+            // Claw machines are separated by an empty line.
+            if let Some(line) = lines.next()
+                && !line.text.is_empty()
+            {
+                return Err(line.error("expected an empty line between claw machines"));
+            }
+        }
+
+        Ok(ClawMachineSimulation { claw_machines })
+    }
+}
+
+impl ClawMachineSimulation {
+    fn with_prize_position_offset(mut self, prize_position_offset: f64) -> Self {
+        for claw_machine in &mut self.claw_machines {
+            claw_machine.xf += prize_position_offset;
+            claw_machine.yf += prize_position_offset;
         }
 
-        Self { claw_machines }
+        self
     }
 
     fn find_num_tokens_spent(&self) -> u64 {
@@ -115,21 +167,168 @@ impl ClawMachineSimulation {
     }
 }
 
+/// Tokens spent on the claw machines of one file of a batch, in each part. A file which can't be parsed has no tokens,
+/// but an error instead.
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub file: String,
+    pub num_claw_machines: Option<usize>,
+    pub part1_tokens: Option<u64>,
+    pub part2_tokens: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl BatchReport {
+    fn new(path: &Path) -> Result<Self, AocError> {
+        let file = std::fs::read_to_string(path).map_err(|e| AocError::io(path, e))?;
+        let mut report = Self {
+            file: path.display().to_string(),
+            num_claw_machines: None,
+            part1_tokens: None,
+            part2_tokens: None,
+            error: None,
+        };
+
+        match SolverImpl::parse(&file) {
+            Ok(claw_machine_simulation) => {
+                report.num_claw_machines = Some(claw_machine_simulation.claw_machines.len());
+                report.part1_tokens = Some(claw_machine_simulation.find_num_tokens_spent());
+                report.part2_tokens = Some(
+                    claw_machine_simulation
+                        .with_prize_position_offset(PART2_PRIZE_POSITION_OFFSET)
+                        .find_num_tokens_spent(),
+                );
+            }
+            Err(e) => report.error = Some(e.to_string()),
+        }
+
+        Ok(report)
+    }
+}
+
+// Files to solve in a batch: the given files, and the files directly inside the given directories, sorted by name.
+fn batch_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, AocError> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        let mut directory_files = std::fs::read_dir(path)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|e| AocError::io(path, e))?;
+        directory_files.retain(|file| file.is_file());
+        directory_files.sort();
+        files.extend(directory_files);
+    }
+
+    Ok(files)
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Solve both parts for each claw machine file given, including the files in the given directories, and report
+    /// the tokens spent on each. Files which can't be parsed are reported with their error.
+    Batch {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Batch { paths, format } => {
+                let reports = batch_files(&paths)?
+                    .iter()
+                    .map(|path| BatchReport::new(path))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                write_records(&reports, format)
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Batch { .. } => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn reads_input(&self) -> bool {
+        !matches!(self, Self::Batch { .. })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let claw_machine_simulation = ClawMachineSimulation::new(file, 0.0);
-        println!(
-            "Number of tokens spent: {}",
-            claw_machine_simulation.find_num_tokens_spent()
-        );
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let claw_machine_simulation = Self::parse_shared(file)?;
+        let answer = claw_machine_simulation.find_num_tokens_spent();
+        Ok(Answer::new(
+            format!("Number of tokens spent: {answer}"),
+            answer,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let claw_machine_simulation =
+            Self::parse_shared(file)?.with_prize_position_offset(PART2_PRIZE_POSITION_OFFSET);
+        let answer = claw_machine_simulation.find_num_tokens_spent();
+        Ok(Answer::new(
+            format!("Number of tokens spent: {answer}"),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let claw_machine_simulation = ClawMachineSimulation::new(file, 10000000000000.0);
-        println!(
-            "Number of tokens spent: {}",
-            claw_machine_simulation.find_num_tokens_spent()
-        );
+    // Moves every prize, while keeping it reachable only if it was reachable in the original input.
+    fn scrub(file: &str, rng: &mut StdRng) -> Result<String, AocError> {
+        let mut claw_machine_simulation = Self::parse(file)?;
+        for claw_machine in &mut claw_machine_simulation.claw_machines {
+            if claw_machine.is_solvable() {
+                // Presses are limited to 100 per button in the first part of the problem.
+                let (a_presses, b_presses) = (rng.random_range(1..=100), rng.random_range(1..=100));
+                claw_machine.xf =
+                    a_presses as f64 * claw_machine.xa + b_presses as f64 * claw_machine.xb;
+                claw_machine.yf =
+                    a_presses as f64 * claw_machine.ya + b_presses as f64 * claw_machine.yb;
+            } else {
+                // Most prizes can't be reached, so this quickly finds one that can't.
+                loop {
+                    claw_machine.xf = rng.random_range(1000..20000) as f64;
+                    claw_machine.yf = rng.random_range(1000..20000) as f64;
+                    if !claw_machine.is_solvable() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(claw_machine_simulation
+            .claw_machines
+            .iter()
+            .map(ClawMachine::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"))
     }
 }
 
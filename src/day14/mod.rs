@@ -1,10 +1,14 @@
-use std::str::FromStr;
+use clap::Subcommand;
 
 use crate::{
-    solver::Solver,
-    utils::{Position, generate_benchmark, pos, shard_and_solve_concurrently},
+    args::Part,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver},
+    utils::{Position, Simulation, generate_benchmark, pos, shard_and_solve_concurrently},
+    viz::{Frame, LegendEntry, Style, Visualize, simulation_frames},
 };
-use anyhow::anyhow;
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -12,6 +16,8 @@ lazy_static! {
     static ref ROBOT: Regex = Regex::new(r"p=(\d+),(\d+) v=(-?\d+),(-?\d+)").unwrap();
 }
 
+const PART1_NUM_GENERATIONS: usize = 100;
+
 #[derive(Clone, Copy, Debug)]
 struct Velocity {
     horizontal: i32,
@@ -24,10 +30,8 @@ struct Robot {
     velocity: Velocity,
 }
 
-impl FromStr for Robot {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Robot {
+    fn parse(line: Line) -> Result<Self, ParseError> {
         let (
             _,
             [
@@ -37,16 +41,16 @@ impl FromStr for Robot {
                 vertical_velocity,
             ],
         ) = ROBOT
-            .captures(s)
-            .ok_or(anyhow!("capture failed"))?
+            .captures(line.text)
+            .ok_or_else(|| line.error("expected a robot, like \"p=0,4 v=3,-3\""))?
             .extract();
 
-        let horizontal_position = horizontal_position.parse()?;
-        let vertical_position = vertical_position.parse()?;
+        let horizontal_position = line.parse(horizontal_position)?;
+        let vertical_position = line.parse(vertical_position)?;
         let position = pos!(vertical_position, horizontal_position);
 
-        let horizontal_velocity = horizontal_velocity.parse()?;
-        let vertical_velocity = vertical_velocity.parse()?;
+        let horizontal_velocity = line.parse(horizontal_velocity)?;
+        let vertical_velocity = line.parse(vertical_velocity)?;
         let velocity = Velocity {
             horizontal: horizontal_velocity,
             vertical: vertical_velocity,
@@ -54,9 +58,7 @@ impl FromStr for Robot {
 
         Ok(Self { position, velocity })
     }
-}
 
-impl Robot {
     // Note: this is safe to call as long as `num_generations`, `num_horizontal_tiles`, and
     // `num_vertical_tiles` can all safely be represented as an i32.
     fn run(
@@ -84,28 +86,45 @@ impl Robot {
     }
 }
 
+// Index of the cell containing the tile at `index`, when `num_tiles` tiles are split into `num_cells` cells of the
+// same size. Scaled by `num_cells`, tile `index` spans from `index * num_cells` to `(index + 1) * num_cells`, and
+// cells are all `num_tiles` long. The tile belongs to no cell if the end of its cell is within that span.
+fn cell_index(index: usize, num_tiles: usize, num_cells: usize) -> Option<usize> {
+    let tile_start = index * num_cells;
+    let cell = tile_start / num_tiles;
+
+    ((cell + 1) * num_tiles >= tile_start + num_cells).then_some(cell)
+}
+
+/// Number of robots in each cell of the bathroom, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridPartition {
+    pub num_robots_per_cell: Vec<Vec<usize>>,
+}
+
+impl GridPartition {
+    /// Product of the number of robots in each cell, which is the safety factor for quadrants.
+    pub fn product(&self) -> usize {
+        self.num_robots_per_cell.iter().flatten().product()
+    }
+}
+
 #[derive(Clone, Debug)]
-struct Simulation {
+pub struct RobotSimulation {
     robots: Vec<Robot>,
     num_horizontal_tiles: usize,
     num_vertical_tiles: usize,
     generation: usize,
+    // Generation at which the simulation is done, when driven step by step.
+    final_generation: usize,
 }
 
-impl Simulation {
-    fn new(file: &str) -> Result<Self, anyhow::Error> {
-        let mut lines = file.lines();
-        let dimensions = lines.next().unwrap().split_once(',').unwrap();
-        let (num_horizontal_tiles, num_vertical_tiles) =
-            (dimensions.0.parse().unwrap(), dimensions.1.parse().unwrap());
-
-        let robots = lines.map(Robot::from_str).collect::<Result<_, _>>()?;
-        Ok(Self {
-            robots,
-            num_horizontal_tiles,
-            num_vertical_tiles,
-            generation: 0,
-        })
+impl RobotSimulation {
+    fn until(self, final_generation: usize) -> Self {
+        Self {
+            final_generation,
+            ..self
+        }
     }
 
     fn run(&self, num_generations: usize) -> Self {
@@ -122,75 +141,43 @@ impl Simulation {
             num_horizontal_tiles,
             num_vertical_tiles,
             generation: self.generation + num_generations,
+            final_generation: self.final_generation,
         }
     }
 
+    // Product of the number of robots in each quadrant.
     fn calculate_safety_factor(&self) -> usize {
-        let median_row = self.num_vertical_tiles / 2;
-        let median_col = self.num_horizontal_tiles / 2;
+        self.partition(2).product()
+    }
 
-        let mut num_robots_top_left_quadrant = 0;
-        let mut num_robots_top_right_quadrant = 0;
-        let mut num_robots_bottom_left_quadrant = 0;
-        let mut num_robots_bottom_right_quadrant = 0;
+    /// Counts the robots in each cell of the bathroom split into `num_cells` by `num_cells` cells of the same size.
+    /// Tiles straddling the border between two cells, like the median row and column of the quadrants, are not part
+    /// of any cell.
+    fn partition(&self, num_cells: usize) -> GridPartition {
+        let mut num_robots_per_cell = vec![vec![0; num_cells]; num_cells];
         for robot in &self.robots {
-            match robot.position {
-                Position { row, col } if row < median_row && col < median_col => {
-                    num_robots_top_left_quadrant += 1
-                }
-                Position { row, col } if row < median_row && col > median_col => {
-                    num_robots_top_right_quadrant += 1
-                }
-                Position { row, col } if row > median_row && col < median_col => {
-                    num_robots_bottom_left_quadrant += 1
-                }
-                Position { row, col } if row > median_row && col > median_col => {
-                    num_robots_bottom_right_quadrant += 1
-                }
-                // A robot on the median row or median column is not part of any quadrants.
-                _ => {}
+            if let (Some(cell_row), Some(cell_col)) = (
+                cell_index(robot.position.row, self.num_vertical_tiles, num_cells),
+                cell_index(robot.position.col, self.num_horizontal_tiles, num_cells),
+            ) {
+                num_robots_per_cell[cell_row][cell_col] += 1;
             }
         }
 
-        num_robots_top_left_quadrant
-            * num_robots_top_right_quadrant
-            * num_robots_bottom_left_quadrant
-            * num_robots_bottom_right_quadrant
-    }
-
-    fn display_grid(&self) -> String {
-        let mut grid = vec![vec![' '; self.num_horizontal_tiles]; self.num_vertical_tiles];
-
-        for robot in &self.robots {
-            grid[robot.position.row][robot.position.col] = 'X';
+        GridPartition {
+            num_robots_per_cell,
         }
-
-        grid.iter()
-            .map(String::from_iter)
-            .collect::<Vec<_>>()
-            .join("\n")
-    }
-}
-
-pub struct SolverImpl {}
-
-impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let simulation = Simulation::new(file).unwrap().run(100);
-        let safety_factor = simulation.calculate_safety_factor();
-        println!("The safety factor is {safety_factor}.");
     }
 
-    fn solve_part2(file: &str) {
-        let simulation = Simulation::new(file).unwrap();
-
+    // Finds the generation displaying a Christmas tree, along with its safety factor.
+    fn find_christmas_tree(&self) -> (usize, usize) {
         // Find the generation with the minimum safety score and secondly minimum generation.
         // This is a clue that this image has less entropy, meaning a lot of robots are
         // concentrated in one quadrant. The grid with the minimum entropy indeed happens to
         // be the the one displaying a Christmas tree.
         let (min_safety_factor, min_generation) = shard_and_solve_concurrently(
             1..10000, // Ten thousand generations seems to be enough.
-            simulation.clone(),
+            self.clone(),
             |generations, simulation| {
                 let mut min_safety_factor = usize::MAX;
                 let mut min_simulation = None;
@@ -204,18 +191,230 @@ impl Solver for SolverImpl {
                     }
                 }
 
-                return (min_safety_factor, min_simulation.unwrap().generation);
+                (min_safety_factor, min_simulation.unwrap().generation)
             },
         )
         .min()
         .unwrap();
 
-        println!(
-            "Safety factor: {min_safety_factor}\tGeneration: {}\n{}\n",
-            min_generation,
-            simulation.run(min_generation).display_grid()
+        (min_generation, min_safety_factor)
+    }
+
+    fn frame(&self) -> Frame {
+        let mut grid = vec![vec![' '; self.num_horizontal_tiles]; self.num_vertical_tiles];
+
+        for robot in &self.robots {
+            grid[robot.position.row][robot.position.col] = 'X';
+        }
+
+        Frame {
+            grid,
+            caption: format!("Generation: {}", self.generation),
+        }
+    }
+}
+
+impl Parse for SolverImpl {
+    type Input = RobotSimulation;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        // The first line holds the dimensions of the bathroom, e.g. "101,103".
+        let dimensions = lines.next_line()?;
+        let (num_horizontal_tiles, num_vertical_tiles) =
+            dimensions.split_once(dimensions.text, ",")?;
+        let (num_horizontal_tiles, num_vertical_tiles) = (
+            dimensions.parse(num_horizontal_tiles)?,
+            dimensions.parse(num_vertical_tiles)?,
         );
+
+        let robots = lines.map(Robot::parse).collect::<Result<_, _>>()?;
+        Ok(RobotSimulation {
+            robots,
+            num_horizontal_tiles,
+            num_vertical_tiles,
+            generation: 0,
+            final_generation: 0,
+        })
+    }
+}
+
+impl Simulation for RobotSimulation {
+    fn step(&mut self) {
+        *self = self.run(1);
+    }
+
+    fn is_done(&self) -> bool {
+        self.generation >= self.final_generation
+    }
+
+    fn render(&self) -> Frame {
+        self.frame()
+    }
+
+    // Robots move linearly, so they can jump straight to the final generation.
+    fn run_to_completion(&mut self) {
+        if !self.is_done() {
+            *self = self.run(self.final_generation - self.generation);
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Split the bathroom into cells, like the quadrants of part 1, and count the robots in each.
+    Partition {
+        #[arg(short, long, default_value_t = 2)]
+        /// Number of cells along each side of the bathroom.
+        cells: usize,
+        #[arg(short, long, default_value_t = PART1_NUM_GENERATIONS)]
+        /// Number of seconds the robots move for before they're counted.
+        generations: usize,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Partition { cells, generations } => {
+                if cells == 0 {
+                    return Err(AocError::Unsupported(
+                        "the bathroom must be split into at least one cell".to_string(),
+                    ));
+                }
+
+                let partition = SolverImpl::parse(file)?.run(generations).partition(cells);
+                for num_robots_per_cell in &partition.num_robots_per_cell {
+                    println!("{}", num_robots_per_cell.iter().join("\t"));
+                }
+                println!(
+                    "The product of the number of robots is {}.",
+                    partition.product()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
+pub struct SolverImpl {}
+
+impl Solver for SolverImpl {
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let mut simulation = Self::parse_shared(file)?.until(PART1_NUM_GENERATIONS);
+        simulation.run_to_completion();
+        let safety_factor = simulation.calculate_safety_factor();
+        Ok(Answer::new(
+            format!("The safety factor is {safety_factor}."),
+            safety_factor,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let simulation = Self::parse_shared(file)?;
+        let (min_generation, min_safety_factor) = simulation.find_christmas_tree();
+        let mut simulation = simulation.until(min_generation);
+        simulation.run_to_completion();
+
+        Ok(Answer::new(
+            format!(
+                "Safety factor: {min_safety_factor}\tGeneration: {min_generation}\n{}",
+                simulation.render().render(Self::LEGEND)
+            ),
+            min_generation,
+        ))
+    }
+}
+
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[LegendEntry::new('X', "robot", Style::Actor)];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let Some(mut simulation) = Self::simulation(part, file)? else {
+            return Ok(None);
+        };
+        Ok(Some(match part {
+            // Show the robots moving, until the safety factor is calculated.
+            Part::Part1 => simulation_frames(simulation, 1),
+            Part::Part2 => {
+                simulation.run_to_completion();
+                Box::new(std::iter::once(simulation.render()))
+            }
+        }))
+    }
+
+    fn simulation(part: Part, file: &str) -> Result<Option<Box<dyn Simulation + '_>>, ParseError> {
+        let simulation = Self::parse(file)?;
+        let final_generation = match part {
+            Part::Part1 => PART1_NUM_GENERATIONS,
+            Part::Part2 => simulation.find_christmas_tree().0,
+        };
+
+        Ok(Some(Box::new(simulation.until(final_generation))))
     }
 }
 
 generate_benchmark!(day14);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn tiles_straddling_cells_are_left_out() {
+        // Quadrants of 7 tiles leave out the median tile.
+        assert_eq!(
+            (0..7).map(|i| cell_index(i, 7, 2)).collect::<Vec<_>>(),
+            [Some(0), Some(0), Some(0), None, Some(1), Some(1), Some(1)]
+        );
+        // Splitting 6 tiles in thirds leaves none out.
+        assert_eq!(
+            (0..6).map(|i| cell_index(i, 6, 3)).collect::<Vec<_>>(),
+            [Some(0), Some(0), Some(1), Some(1), Some(2), Some(2)]
+        );
+        // Splitting 7 tiles in thirds leaves out the tiles straddling 7/3 and 14/3.
+        assert_eq!(
+            (0..7).map(|i| cell_index(i, 7, 3)).collect::<Vec<_>>(),
+            [Some(0), Some(0), None, Some(1), None, Some(2), Some(2)]
+        );
+    }
+
+    #[test]
+    fn quadrants_give_the_safety_factor() {
+        let simulation = SolverImpl::parse(include_str!("example.txt"))
+            .unwrap()
+            .run(PART1_NUM_GENERATIONS);
+
+        let quadrants = simulation.partition(2);
+        assert_eq!(quadrants.num_robots_per_cell, [[1, 3], [4, 1]]);
+        assert_eq!(quadrants.product(), 12);
+        assert_eq!(
+            simulation.partition(1).num_robots_per_cell,
+            [[simulation.robots.len()]]
+        );
+    }
+}
@@ -1,13 +1,20 @@
 use std::collections::VecDeque;
+use std::path::PathBuf;
 
-use hashbrown::HashSet;
-use itertools::Itertools;
+use clap::Subcommand;
 
 use crate::{
-    solver::Solver,
-    utils::{Direction, Position, generate_benchmark, pos},
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver},
+    utils::{Direction, FxHashSet, Position, Simulation, generate_benchmark, pos},
+    viz::{Frame, LegendEntry, Style, Visualize, simulation_frames},
 };
 
+// Maximum number of frames shown when visualizing the robot, as inputs have thousands of moves.
+const MAX_NUM_FRAMES: usize = 500;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Tile {
     Wall,
@@ -44,6 +51,20 @@ impl Tile {
     }
 }
 
+impl TryFrom<char> for Tile {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            '#' => Ok(Self::Wall),
+            'O' => Ok(Self::Box),
+            '@' => Ok(Self::Robot),
+            '.' => Ok(Self::Empty),
+            _ => Err(value),
+        }
+    }
+}
+
 impl From<&Tile> for char {
     fn from(value: &Tile) -> Self {
         match value {
@@ -57,74 +78,63 @@ impl From<&Tile> for char {
     }
 }
 
-#[derive(Debug)]
-struct Robot {
+// Warehouse as described by the input, before the robot moves.
+#[derive(Clone, Debug)]
+pub struct Warehouse {
     map: Vec<Vec<Tile>>,
-    position: Position,
     directions: VecDeque<Direction>,
 }
 
-impl Robot {
-    fn new(file: &str, wide: bool) -> Self {
-        let mut lines = file.lines();
-        let mut map: Vec<Vec<_>> = Vec::new();
-
-        // Build the map first.
-        for line in &mut lines {
-            if line.is_empty() {
-                break;
-            }
-
-            let mut map_row = Vec::new();
-            for tile in line.chars() {
-                match tile {
-                    '#' => {
-                        map_row.push(Tile::Wall);
-                        if wide {
-                            map_row.push(Tile::Wall);
-                        }
-                    }
-                    'O' if wide => {
-                        map_row.push(Tile::LeftBox);
-                        map_row.push(Tile::RightBox);
-                    }
-                    'O' => {
-                        map_row.push(Tile::Box);
-                    }
-                    '.' => {
-                        map_row.push(Tile::Empty);
-                        if wide {
-                            map_row.push(Tile::Empty);
+impl Warehouse {
+    // Makes everything twice as wide, except the robot.
+    fn widen(self) -> Self {
+        let map = self
+            .map
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .flat_map(|tile| match tile {
+                        Tile::Wall => [Tile::Wall, Tile::Wall],
+                        Tile::Box => [Tile::LeftBox, Tile::RightBox],
+                        Tile::Robot => [Tile::Robot, Tile::Empty],
+                        Tile::Empty => [Tile::Empty, Tile::Empty],
+                        Tile::LeftBox | Tile::RightBox => {
+                            unreachable!("boxes are only wide once widened")
                         }
-                    }
-                    '@' => {
-                        map_row.push(Tile::Robot);
-                        if wide {
-                            map_row.push(Tile::Empty);
-                        }
-                    }
-                    _ => unreachable!(),
-                }
-            }
+                    })
+                    .collect()
+            })
+            .collect();
 
-            map.push(map_row);
+        Self {
+            map,
+            directions: self.directions,
         }
+    }
+}
 
-        let position = Self::find_robot(&map);
+#[derive(Debug)]
+struct Robot {
+    map: Vec<Vec<Tile>>,
+    position: Position,
+    directions: VecDeque<Direction>,
+    num_moves_done: usize,
+}
 
-        let directions = lines
-            .map(|line| line.chars().map(Direction::from))
-            .flatten()
-            .collect();
+impl Robot {
+    fn new(warehouse: Warehouse, wide: bool) -> Self {
+        let Warehouse { map, directions } = if wide { warehouse.widen() } else { warehouse };
+        let position = Self::find_robot(&map);
 
         Self {
             map,
             position,
             directions,
+            num_moves_done: 0,
         }
     }
 
-    fn find_robot(map: &Vec<Vec<Tile>>) -> Position {
+    fn find_robot(map: &[Vec<Tile>]) -> Position {
         for (i, row) in map.iter().enumerate() {
             for (j, tile) in row.iter().enumerate() {
                 if tile.is_robot() {
@@ -136,12 +146,6 @@ impl Robot {
         unreachable!("there must be one robot on the map")
     }
 
-    fn consume_directions_sequence(&mut self) {
-        while let Some(direction) = self.directions.pop_front() {
-            self.go(direction);
-        }
-    }
-
     fn at(&self, position: Position) -> Tile {
         self.map[position.row][position.col]
     }
@@ -162,7 +166,7 @@ impl Robot {
         // moved will be moved into an empty tile, not onto another box.
         let mut boxes_to_move = Vec::new();
         // Prevent inspecting the same tile twice, which could lead to corruption of moves.
-        let mut inspected_tiles = HashSet::new();
+        let mut inspected_tiles = FxHashSet::default();
         // We use a deque to apply a BFS. This is important, because inspected box tiles
         // are eventually added to `boxes_to_move`, and a mismatch in order could make moving
         // boxes around corrupt wide boxes.
@@ -235,10 +239,9 @@ impl Robot {
                 Tile::LeftBox => Some(next_tile_to_inspect.right(1)),
                 Tile::RightBox => Some(next_tile_to_inspect.left(1)),
                 _ => unreachable!("next_tile_to_inspect has to be a box in this branch"),
-            } {
-                if inspected_tiles.insert(attached_next_tile_to_inspect) {
-                    tiles_to_inspect.push_back(attached_next_tile_to_inspect);
-                }
+            } && inspected_tiles.insert(attached_next_tile_to_inspect)
+            {
+                tiles_to_inspect.push_back(attached_next_tile_to_inspect);
             }
         }
 
@@ -257,16 +260,8 @@ impl Robot {
         self.position = self.position.go(direction);
     }
 
-    fn _display_map(&self) -> String {
-        self.map
-            .iter()
-            .map(|tiles| {
-                tiles
-                    .iter()
-                    .map(|tile| char::from(tile))
-                    .collect::<String>()
-            })
-            .join("\n")
+    fn frame(&self, caption: impl Into<String>) -> Frame {
+        Frame::new(&self.map, caption)
     }
 
     fn sum_box_gps_coordinates(&self) -> usize {
@@ -288,26 +283,347 @@ impl Robot {
     }
 }
 
+impl Simulation for Robot {
+    fn step(&mut self) {
+        let direction = self.directions.pop_front().unwrap();
+        self.go(direction);
+        self.num_moves_done += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        self.directions.is_empty()
+    }
+
+    fn render(&self) -> Frame {
+        let num_moves = self.num_moves_done + self.directions.len();
+        let caption = if self.is_done() {
+            format!(
+                "Move {}/{num_moves}, sum of the box GPS coordinates: {}",
+                self.num_moves_done,
+                self.sum_box_gps_coordinates()
+            )
+        } else {
+            format!("Move {}/{num_moves}", self.num_moves_done)
+        };
+
+        self.frame(caption)
+    }
+}
+
+impl Parse for SolverImpl {
+    type Input = Warehouse;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        // The map comes first, and is separated from the moves by an empty line.
+        let map = parse_map(&mut lines)?;
+        let directions = parse_directions(lines)?;
+
+        Ok(Warehouse { map, directions })
+    }
+}
+
+// Parses the map, up to the empty line ending it if there is one.
+fn parse_map(lines: &mut Lines) -> Result<Vec<Vec<Tile>>, ParseError> {
+    let mut map = Vec::new();
+    let mut num_robots = 0;
+
+    for line in lines.by_ref() {
+        if line.text.is_empty() {
+            break;
+        }
+
+        let map_row: Vec<Tile> = line.parse_chars()?;
+        num_robots += map_row.iter().filter(|tile| tile.is_robot()).count();
+        if num_robots > 1 {
+            return Err(line.error("there must be a single robot"));
+        }
+        map.push(map_row);
+    }
+    if num_robots == 0 {
+        return Err(lines.error_at_end("the robot is missing"));
+    }
+
+    Ok(map)
+}
+
+// Parses moves, which may span several lines.
+fn parse_directions(lines: Lines) -> Result<VecDeque<Direction>, ParseError> {
+    let mut directions = VecDeque::new();
+    for line in lines {
+        for (i, direction) in line.text.char_indices() {
+            if !matches!(direction, '^' | '>' | 'v' | '<') {
+                return Err(line.error_at(
+                    &line.text[i..i + direction.len_utf8()],
+                    format!("unexpected move {direction:?}"),
+                ));
+            }
+            directions.push_back(Direction::from(direction));
+        }
+    }
+
+    Ok(directions)
+}
+
+// Parses moves typed on the keyboard, either as arrow keys or as in the input, ignoring whitespace. Arrow keys are
+// escape sequences, e.g. "\x1b[A" for up. Fails with the first key which isn't a move.
+fn parse_keys(keys: &str) -> Result<Vec<Direction>, String> {
+    let mut directions = Vec::new();
+    let mut keys = keys.chars();
+
+    while let Some(key) = keys.next() {
+        directions.push(match key {
+            '^' | '>' | 'v' | '<' => Direction::from(key),
+            '\x1b' => match (keys.next(), keys.next()) {
+                (Some('['), Some('A')) => Direction::Up,
+                (Some('['), Some('B')) => Direction::Down,
+                (Some('['), Some('C')) => Direction::Right,
+                (Some('['), Some('D')) => Direction::Left,
+                _ => return Err(String::from("an unknown escape sequence")),
+            },
+            _ if key.is_whitespace() => continue,
+            _ => return Err(format!("{key:?}")),
+        });
+    }
+
+    Ok(directions)
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Move the robot with the moves of another file, or with moves typed on the keyboard, instead of the moves of
+    /// the input. Only the map of the input is used.
+    Control {
+        /// File with the moves of the robot, in the same format as in the input.
+        #[arg(short, long, conflicts_with = "interactive")]
+        moves: Option<PathBuf>,
+        /// Type moves with the arrow keys, or as in the input, and see the warehouse after each line of moves.
+        #[arg(long)]
+        interactive: bool,
+        /// Make the warehouse twice as wide first, like in part 2.
+        #[arg(short, long)]
+        wide: bool,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Control {
+                moves,
+                interactive,
+                wide,
+            } => {
+                let mut warehouse = SolverImpl::parse(file)?;
+                if let Some(moves) = moves {
+                    let moves =
+                        std::fs::read_to_string(&moves).map_err(|e| AocError::io(&moves, e))?;
+                    warehouse.directions = parse_directions(Lines::new(&moves))?;
+                } else if interactive {
+                    warehouse.directions.clear();
+                }
+
+                let mut robot = Robot::new(warehouse, wide);
+                if interactive {
+                    control_interactively(&mut robot);
+                } else {
+                    robot.run_to_completion();
+                }
+                println!(
+                    "Sum of the box GPS coordinates: {}",
+                    robot.sum_box_gps_coordinates()
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Control { .. } => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
+// Moves the robot with the moves typed on each line of stdin, until it ends. Lines are only read once entered, as the
+// terminal is left as is, so arrow keys show up as they're typed, and take effect when pressing enter.
+fn control_interactively(robot: &mut Robot) {
+    let print_warehouse = |robot: &Robot| {
+        let frame = robot.frame(format!(
+            "Move {}, sum of the box GPS coordinates: {}",
+            robot.num_moves_done,
+            robot.sum_box_gps_coordinates()
+        ));
+        println!("{}{}", frame.render(SolverImpl::LEGEND), frame.caption);
+    };
+
+    print_warehouse(robot);
+    println!("Type moves with the arrow keys or ^>v<, then press enter. End the input to stop.");
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        match parse_keys(&line) {
+            Ok(directions) => {
+                robot.directions.extend(directions);
+                robot.run_to_completion();
+                print_warehouse(robot);
+            }
+            Err(key) => println!("Unexpected key {key}, expected arrow keys or ^>v<."),
+        }
+    }
+}
+
 pub struct SolverImpl {}
 
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let mut robot = Robot::new(file, false);
-        robot.consume_directions_sequence();
-        println!(
-            "Sum of the box GPS coordinates: {}",
-            robot.sum_box_gps_coordinates()
-        );
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let mut robot = Robot::new(Self::parse_shared(file)?, false);
+        robot.run_to_completion();
+        let answer = robot.sum_box_gps_coordinates();
+        Ok(Answer::new(
+            format!("Sum of the box GPS coordinates: {answer}"),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let mut robot = Robot::new(file, true);
-        robot.consume_directions_sequence();
-        println!(
-            "Sum of the box GPS coordinates: {}",
-            robot.sum_box_gps_coordinates()
-        );
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let mut robot = Robot::new(Self::parse_shared(file)?, true);
+        robot.run_to_completion();
+        let answer = robot.sum_box_gps_coordinates();
+        Ok(Answer::new(
+            format!("Sum of the box GPS coordinates: {answer}"),
+            answer,
+        ))
+    }
+}
+
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[
+        LegendEntry::new('#', "wall", Style::Wall),
+        LegendEntry::new('O', "box", Style::Object),
+        LegendEntry::new('[', "left side of a box", Style::Object),
+        LegendEntry::new(']', "right side of a box", Style::Object),
+        LegendEntry::new('@', "robot", Style::Actor),
+    ];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let robot = Robot::new(Self::parse(file)?, part == Part::Part2);
+        // Only show some of the moves, evenly spread.
+        let moves_per_frame = robot.directions.len().div_ceil(MAX_NUM_FRAMES).max(1);
+
+        Ok(Some(simulation_frames(Box::new(robot), moves_per_frame)))
+    }
+
+    fn simulation(part: Part, file: &str) -> Result<Option<Box<dyn Simulation + '_>>, ParseError> {
+        Ok(Some(Box::new(Robot::new(
+            Self::parse(file)?,
+            part == Part::Part2,
+        ))))
     }
 }
 
 generate_benchmark!(day15);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn widening_duplicates_walls_and_boxes_but_not_the_robot() {
+        let warehouse = SolverImpl::parse("#####\n#O@.#\n#####\n\n<").unwrap();
+        let map: Vec<String> = warehouse
+            .widen()
+            .map
+            .iter()
+            .map(|row| row.iter().map(char::from).collect())
+            .collect();
+
+        assert_eq!(
+            map,
+            ["##########", "##[]@...##", "##########"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn moves_can_span_multiple_lines() {
+        let warehouse = SolverImpl::parse("@.\n\n<^\nv>").unwrap();
+
+        assert_eq!(
+            warehouse.directions,
+            [
+                Direction::Left,
+                Direction::Up,
+                Direction::Down,
+                Direction::Right
+            ]
+        );
+    }
+
+    #[test]
+    fn a_second_robot_fails_to_parse() {
+        let error = SolverImpl::parse("@.\n.@\n\n<").unwrap_err();
+
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn pushing_boxes_into_a_wall_does_nothing() {
+        let mut robot = Robot::new(SolverImpl::parse("#.OO@\n\n<<<").unwrap(), false);
+        robot.run_to_completion();
+
+        assert_eq!(robot.position, pos!(0, 3));
+        assert_eq!(robot.sum_box_gps_coordinates(), 1 + 2);
+    }
+
+    #[test]
+    fn arrow_keys_and_input_moves_can_be_mixed() {
+        assert_eq!(
+            parse_keys("\x1b[A\x1b[C v <\x1b[B\x1b[D"),
+            Ok(vec![
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+                Direction::Down,
+                Direction::Left
+            ])
+        );
+        assert_eq!(parse_keys("^x"), Err(String::from("'x'")));
+        assert!(parse_keys("\x1b[Z").is_err());
+    }
+
+    #[test]
+    fn moves_are_parsed_separately_from_the_map() {
+        let mut lines = Lines::new("#@.#\n\n>>");
+        let map = parse_map(&mut lines).unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            parse_directions(lines).unwrap(),
+            [Direction::Right, Direction::Right]
+        );
+
+        // A map without moves leaves the robot still.
+        let warehouse = SolverImpl::parse("#@.#").unwrap();
+        assert!(warehouse.directions.is_empty());
+    }
+}
@@ -1,11 +1,26 @@
-use std::{cmp::Reverse, collections::BinaryHeap, usize};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+
+use clap::Subcommand;
 
 use crate::{
-    solver::Solver,
-    utils::{Direction, Position, generate_benchmark, pos},
+    args::Part,
+    error::AocError,
+    grid::Grid,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver, Variant},
+    utils::{Direction, FxHashSet, Position, generate_benchmark, pos},
+    viz::{
+        EXPLORED_SYMBOL, Frame, LegendEntry, SearchObserver, SearchRecording, Style, Visualize,
+        play_back_search,
+    },
 };
-use hashbrown::HashSet;
-use itertools::Itertools;
 
 const COST_MOVE: usize = 1;
 const COST_TURN: usize = 1000;
@@ -16,7 +31,7 @@ enum Tile {
     Wall,
     Start,
     End,
-    _Visited,
+    Visited,
 }
 
 impl Tile {
@@ -33,14 +48,16 @@ impl Tile {
     }
 }
 
-impl From<char> for Tile {
-    fn from(value: char) -> Self {
+impl TryFrom<char> for Tile {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
         match value {
-            '.' => Self::Empty,
-            '#' => Self::Wall,
-            'S' => Self::Start,
-            'E' => Self::End,
-            _ => unreachable!(),
+            '.' => Ok(Self::Empty),
+            '#' => Ok(Self::Wall),
+            'S' => Ok(Self::Start),
+            'E' => Ok(Self::End),
+            _ => Err(value),
         }
     }
 }
@@ -52,53 +69,116 @@ impl From<&Tile> for char {
             Tile::Wall => '#',
             Tile::Start => 'S',
             Tile::End => 'E',
-            Tile::_Visited => 'O',
+            Tile::Visited => 'O',
         }
     }
 }
 
-struct MinScoresPerTileDirection(Vec<Vec<MinScorePerDirection>>);
+struct MinScoresPerTileDirection(Grid<MinScorePerDirection>);
 
 impl MinScoresPerTileDirection {
-    fn new(maze: &Vec<Vec<Tile>>) -> Self {
-        Self(vec![
-            vec![MinScorePerDirection::default(); maze[0].len()];
-            maze.len()
-        ])
-    }
-
-    fn update_min_score_if_not_greater(&mut self, action: &Action) -> bool {
-        if self.0[action.position.row][action.position.col].min_score(action.direction)
-            < action.score
-        {
-            false
-        } else {
-            *self.0[action.position.row][action.position.col].min_score_mut(action.direction) =
-                action.score;
-            true
+    fn new(maze: &Grid<Tile>) -> Self {
+        Self(Grid::filled(
+            maze.num_rows(),
+            maze.num_cols(),
+            MinScorePerDirection::default(),
+        ))
+    }
+
+    fn min_score(&self, position: Position, direction: Direction) -> &MinScore {
+        self.0[position].min_score(direction)
+    }
+
+    // Records the action if its score is the lowest one so far for its tile and direction, or ties with it. Returns
+    // true iff its score is strictly lower, which is when the action is worth exploring: ties already are.
+    fn update_min_score_if_lower(&mut self, action: &Action) -> bool {
+        let min_score = self.0[action.position].min_score_mut(action.direction);
+        let optimal_move = action.last_move.map_or(0, Move::bit);
+        match action.score.cmp(&min_score.score) {
+            Ordering::Greater => false,
+            Ordering::Equal => {
+                min_score.optimal_moves |= optimal_move;
+                false
+            }
+            Ordering::Less => {
+                *min_score = MinScore {
+                    score: action.score,
+                    optimal_moves: optimal_move,
+                };
+                true
+            }
         }
     }
 }
 
-#[derive(Clone)]
+// How an action follows the previous one.
+#[derive(Clone, Copy, Debug)]
+enum Move {
+    Forward,
+    TurnClockwise,
+    TurnCounterClockwise,
+}
+
+impl Move {
+    const ALL: [Self; 3] = [
+        Self::Forward,
+        Self::TurnClockwise,
+        Self::TurnCounterClockwise,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    // Tile and direction of the action preceding the one at `position` facing `direction`, with this move.
+    fn undo(self, position: Position, direction: Direction) -> (Position, Direction) {
+        match self {
+            Self::Forward => (
+                position.go(direction.turn_clockwise().turn_clockwise()),
+                direction,
+            ),
+            Self::TurnClockwise => (position, direction.turn_counter_clockwise()),
+            Self::TurnCounterClockwise => (position, direction.turn_clockwise()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MinScore {
+    score: usize,
+    // Set of the moves reaching a tile and direction with its lowest score, as bits. Following them backwards leads
+    // to all best paths from the start.
+    optimal_moves: u8,
+}
+
+impl Default for MinScore {
+    fn default() -> Self {
+        Self {
+            score: usize::MAX,
+            optimal_moves: 0,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 struct MinScorePerDirection {
-    up: usize,
-    right: usize,
-    down: usize,
-    left: usize,
+    up: MinScore,
+    right: MinScore,
+    down: MinScore,
+    left: MinScore,
 }
 
 impl MinScorePerDirection {
-    fn min_score(&self, direction: Direction) -> usize {
+    fn min_score(&self, direction: Direction) -> &MinScore {
         match direction {
-            Direction::Up => self.up,
-            Direction::Right => self.right,
-            Direction::Down => self.down,
-            Direction::Left => self.left,
+            Direction::Up => &self.up,
+            Direction::Right => &self.right,
+            Direction::Down => &self.down,
+            Direction::Left => &self.left,
         }
     }
 
-    fn min_score_mut(&mut self, direction: Direction) -> &mut usize {
+    fn min_score_mut(&mut self, direction: Direction) -> &mut MinScore {
         match direction {
             Direction::Up => &mut self.up,
             Direction::Right => &mut self.right,
@@ -108,26 +188,22 @@ impl MinScorePerDirection {
     }
 }
 
-impl Default for MinScorePerDirection {
-    fn default() -> Self {
-        Self {
-            up: usize::MAX,
-            right: usize::MAX,
-            down: usize::MAX,
-            left: usize::MAX,
-        }
-    }
-}
-
-struct ActionHistory {
-    position: Position,
-    previous_action_history_index: Option<usize>,
+// How the maze is searched for its best paths.
+#[derive(Clone, Copy, Debug)]
+enum Search {
+    // Explores actions by increasing score.
+    Dijkstra,
+    // Explores actions by increasing score plus the distance left to the end. That distance never overestimates the
+    // score left, and never decreases by more than the cost of a move, so best paths are still found first.
+    AStar,
 }
 
 #[derive(Debug)]
 struct BestPaths {
     score: usize,
-    unique_tiles: HashSet<Position>,
+    unique_tiles: FxHashSet<Position>,
+    // Start and end of the best paths, sorted.
+    start_end_pairs: Vec<(Position, Position)>,
 }
 
 #[derive(Clone, Debug)]
@@ -135,16 +211,18 @@ struct Action {
     position: Position,
     direction: Direction,
     score: usize,
-    history_index: usize,
-    previous_action_history_index: Option<usize>,
+    // Score, plus the estimated score left to reach the end, if any.
+    priority: usize,
+    // Move leading to this action, if it isn't the start action.
+    last_move: Option<Move>,
 }
 
-// We order Actions strictly based on the score. This is necessary
+// We order Actions strictly based on the priority. This is necessary
 // to pop Actions from the min-heap of Actions, so that we always
-// work with the Actions with the best score.
+// work with the Actions with the most promising score.
 impl PartialEq for Action {
     fn eq(&self, other: &Self) -> bool {
-        self.score == other.score
+        self.priority == other.priority
     }
 }
 
@@ -158,147 +236,162 @@ impl PartialOrd for Action {
 
 impl Ord for Action {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.score.cmp(&other.score)
+        self.priority.cmp(&other.priority)
     }
 }
 
-struct ReindeerMaze {
-    maze: Vec<Vec<Tile>>,
-    start_position: Position,
-    end_position: Position,
+#[derive(Clone)]
+pub struct ReindeerMaze {
+    maze: Grid<Tile>,
+    start_positions: Vec<Position>,
+    end_positions: Vec<Position>,
 }
 
 impl ReindeerMaze {
-    fn new(file: &str) -> Self {
-        let maze: Vec<Vec<_>> = file
-            .lines()
-            .map(|line| line.chars().map(Tile::from).collect())
-            .collect();
-
-        let mut start_position = None;
-        let mut end_position = None;
-        for (i, row) in maze.iter().enumerate() {
-            for (j, tile) in row.iter().enumerate() {
-                if tile.is_start() {
-                    start_position = Some(pos!(i, j));
-                } else if tile.is_end() {
-                    end_position = Some(pos!(i, j));
-                }
-            }
+    // Shows the maze, with `visited_tiles` marked as visited.
+    fn frame(&self, visited_tiles: &FxHashSet<Position>, caption: impl Into<String>) -> Frame {
+        let mut maze = self.maze.clone();
+        for &position in visited_tiles {
+            maze[position] = Tile::Visited;
         }
 
-        let start_position = start_position.unwrap();
-        let end_position = end_position.unwrap();
+        Frame::new(maze.rows(), caption)
+    }
 
-        Self {
-            maze,
-            start_position,
-            end_position,
-        }
+    fn is_end_action(&self, action: &Action) -> bool {
+        self.end_positions.contains(&action.position)
     }
 
-    fn _display_map(&self) -> String {
-        self.maze
-            .iter()
-            .map(|tiles| {
-                tiles
+    // Lower bound of the score left to reach the closest end from `position`, used to prioritize actions.
+    fn estimate_score_left(&self, position: Position, search: Search) -> usize {
+        match search {
+            Search::Dijkstra => 0,
+            Search::AStar => {
+                self.end_positions
                     .iter()
-                    .map(|tile| char::from(tile))
-                    .collect::<String>()
-            })
-            .join("\n")
+                    .map(|end_position| {
+                        position.row.abs_diff(end_position.row)
+                            + position.col.abs_diff(end_position.col)
+                    })
+                    .min()
+                    .unwrap()
+                    * COST_MOVE
+            }
+        }
     }
 
-    fn _display_map_with_visited_tiles(&self, visited_tiles: &HashSet<Position>) -> String {
-        let mut maze = self.maze.clone();
-        for (i, row) in maze.iter_mut().enumerate() {
-            for (j, tile) in row.iter_mut().enumerate() {
-                if visited_tiles.contains(&pos!(i, j)) {
-                    *tile = Tile::_Visited;
+    fn at(&self, position: Position) -> Tile {
+        self.maze[position]
+    }
+
+    // Walks the optimal moves backwards, from `end_position` faced in every direction reached with the best score, back
+    // to the starts. Along the way, this goes through the tiles of all best paths ending there. Also returns the
+    // starts of these paths, which are the only actions without a move leading to them.
+    fn walk_back_best_paths(
+        &self,
+        min_scores_per_tile_direction: &MinScoresPerTileDirection,
+        end_position: Position,
+        best_paths_score: usize,
+    ) -> (FxHashSet<Position>, Vec<Position>) {
+        let mut actions_to_undo: Vec<_> = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ]
+        .into_iter()
+        .filter(|direction| {
+            min_scores_per_tile_direction
+                .min_score(end_position, *direction)
+                .score
+                == best_paths_score
+        })
+        .map(|direction| (end_position, direction))
+        .collect();
+        // Best paths often share actions, which only need to be undone once.
+        let mut undone_actions: FxHashSet<_> = actions_to_undo.iter().copied().collect();
+        let mut best_paths_unique_tiles = FxHashSet::default();
+        let mut start_positions = Vec::new();
+
+        while let Some((position, direction)) = actions_to_undo.pop() {
+            best_paths_unique_tiles.insert(position);
+
+            let optimal_moves = min_scores_per_tile_direction
+                .min_score(position, direction)
+                .optimal_moves;
+            if optimal_moves == 0 {
+                start_positions.push(position);
+            }
+            for optimal_move in Move::ALL {
+                if optimal_moves & optimal_move.bit() == 0 {
+                    continue;
+                }
+
+                let previous_action = optimal_move.undo(position, direction);
+                if undone_actions.insert(previous_action) {
+                    actions_to_undo.push(previous_action);
                 }
             }
         }
 
-        maze.iter()
-            .map(|tiles| {
-                tiles
-                    .iter()
-                    .map(|tile| char::from(tile))
-                    .collect::<String>()
-            })
-            .join("\n")
+        (best_paths_unique_tiles, start_positions)
     }
 
-    fn is_end_action(&self, action: &Action) -> bool {
-        self.end_position == action.position
+    fn find_best_paths(&self, search: Search) -> Result<BestPaths, AocError> {
+        self.find_best_paths_observed(search, &mut ())
     }
 
-    fn at(&self, position: Position) -> Tile {
-        self.maze[position.row][position.col]
-    }
-
-    fn record_best_paths_unique_tiles(
-        end_action: &Action,
-        actions_history: &[ActionHistory],
-        best_paths_unique_tiles: &mut HashSet<Position>,
-    ) {
-        best_paths_unique_tiles.insert(end_action.position);
-
-        // Walk backwards through the given best path, until we reach the start position.
-        let mut previous_action_history_index = end_action.previous_action_history_index;
-        while let Some(index) = previous_action_history_index {
-            let previous_action_history = &actions_history[index];
-            best_paths_unique_tiles.insert(previous_action_history.position);
-            previous_action_history_index = previous_action_history.previous_action_history_index;
-        }
-    }
-
-    fn find_best_paths(&self) -> BestPaths {
-        // Min-heap of potential actions, which will prioritize fetching the action with the lowest score.
-        // If we repeat this process, we can guarantee via Dijkstra to generate the shortest path.
+    // Same as `find_best_paths`, reporting each tile expanded to the `observer`.
+    fn find_best_paths_observed(
+        &self,
+        search: Search,
+        observer: &mut impl SearchObserver,
+    ) -> Result<BestPaths, AocError> {
+        // Min-heap of potential actions, which will prioritize fetching the action with the lowest priority.
+        // If we repeat this process, we can guarantee via Dijkstra (or A*) to generate the shortest path.
         let mut potential_actions = BinaryHeap::new();
-        // Keep track of all actions that are generated throughout this search. Each item has a pointer
-        // to the previous action that led to the current action.
-        // This is crucial to generate the path taken once a best path to the end is found.
-        let mut actions_history = Vec::new();
-        // Records all unique tiles visited across all known best paths. Each of these best paths will share
-        // the same `best_paths_score`.
-        let mut best_paths_unique_tiles = HashSet::new();
         let mut best_paths_score = None;
         // Crucial pruning mechanism: we keep track for each tile the minimum score that has reached this
         // point for each direction. That way, if we make it to a tile in a given direction that already
         // has been visited with a lower score, then necessarily the given path is not worth pursuing.
+        // Along with the minimum score, we keep track of the moves that reached it, so that all best paths
+        // can be walked back once the end is reached.
         let mut min_scores_per_tile_direction = MinScoresPerTileDirection::new(&self.maze);
 
-        // We start with the start tile, which we are told we are facing East (right).
-        let start_action = Action {
-            position: self.start_position,
-            direction: Direction::Right,
-            score: 0, // Start position incurred no cost so far.
-            history_index: 0,
-            previous_action_history_index: None, // Start action has no previous action.
-        };
-        actions_history.push(ActionHistory {
-            position: start_action.position,
-            previous_action_history_index: start_action.previous_action_history_index,
-        });
-        potential_actions.push(Reverse(start_action));
+        // We start with the start tiles, which we are told we are facing East (right). Searching from all of them at
+        // once finds the best paths from any of them.
+        for &start_position in &self.start_positions {
+            let start_action = Action {
+                position: start_position,
+                direction: Direction::Right,
+                score: 0, // Start position incurred no cost so far.
+                priority: self.estimate_score_left(start_position, search),
+                last_move: None, // Start action has no previous action.
+            };
+            min_scores_per_tile_direction.update_min_score_if_lower(&start_action);
+            potential_actions.push(Reverse(start_action));
+        }
 
         // Iterative Dijkstra.
         while let Some(Reverse(action)) = potential_actions.pop() {
-            // If we have found a best path, and the current action has a score that is larger than
+            // If we have found a best path, and the current action has a priority that is larger than
             // that best score, than we can stop completely. That path will surely not be a best path,
-            // and all remaining actions fetched from this min-heap will not have a smaller score,
+            // and all remaining actions fetched from this min-heap will not have a smaller priority,
             // so there is no point in pursuing.
-            if best_paths_score.unwrap_or(usize::MAX) < action.score {
+            if best_paths_score.unwrap_or(usize::MAX) < action.priority {
                 break;
             }
 
-            // If the action has a score that is larger than what is historically recorded for that tile
-            // and direction, then that path is not worth pursuing.
-            if !min_scores_per_tile_direction.update_min_score_if_not_greater(&action) {
+            // If a lower score was recorded for that tile and direction since the action was pushed, then
+            // that path is not worth pursuing.
+            if min_scores_per_tile_direction
+                .min_score(action.position, action.direction)
+                .score
+                < action.score
+            {
                 continue;
             }
+            observer.expand(action.position);
 
             if self.is_end_action(&action) {
                 if best_paths_score.is_none() {
@@ -309,12 +402,6 @@ impl ReindeerMaze {
                     unreachable!("Dijkstra guarantees finding the shortest path first");
                 }
 
-                Self::record_best_paths_unique_tiles(
-                    &action,
-                    &actions_history,
-                    &mut best_paths_unique_tiles,
-                );
-
                 // We don't search further on this path if we have reached the end.
                 continue;
             }
@@ -327,24 +414,25 @@ impl ReindeerMaze {
                     position: forward_position,
                     direction: action.direction,
                     score: action.score + COST_MOVE,
-                    history_index: actions_history.len(),
-                    previous_action_history_index: Some(action.history_index),
+                    priority: action.score
+                        + COST_MOVE
+                        + self.estimate_score_left(forward_position, search),
+                    last_move: Some(Move::Forward),
                 };
 
-                // Crucial pruning: don't explore the path forward if the score of that path is
-                // higher than what is recorded historically.
-                if min_scores_per_tile_direction.update_min_score_if_not_greater(&forward_action) {
-                    actions_history.push(ActionHistory {
-                        position: forward_action.position,
-                        previous_action_history_index: forward_action.previous_action_history_index,
-                    });
+                // Crucial pruning: don't explore the path forward if the score of that path isn't
+                // lower than what is recorded historically.
+                if min_scores_per_tile_direction.update_min_score_if_lower(&forward_action) {
                     potential_actions.push(Reverse(forward_action));
                 }
             }
 
-            for turn_direction in [
-                action.direction.turn_clockwise(),
-                action.direction.turn_counter_clockwise(),
+            for (turn_direction, turn_move) in [
+                (action.direction.turn_clockwise(), Move::TurnClockwise),
+                (
+                    action.direction.turn_counter_clockwise(),
+                    Move::TurnCounterClockwise,
+                ),
             ] {
                 // Try to turn, but only do so if moving forward after the turn is not
                 // facing a wall. We can definitely not find a best path in that case.
@@ -353,46 +441,387 @@ impl ReindeerMaze {
                         position: action.position,
                         direction: turn_direction,
                         score: action.score + COST_TURN,
-                        history_index: actions_history.len(),
-                        previous_action_history_index: Some(action.history_index),
+                        priority: action.priority + COST_TURN,
+                        last_move: Some(turn_move),
                     };
 
-                    // Crucial pruning: don't explore the turn if the score of that path is
-                    // higher than what is recorded historically.
-                    if min_scores_per_tile_direction.update_min_score_if_not_greater(&turn_action) {
-                        actions_history.push(ActionHistory {
-                            position: turn_action.position,
-                            previous_action_history_index: turn_action
-                                .previous_action_history_index,
-                        });
+                    // Crucial pruning: don't explore the turn if the score of that path isn't
+                    // lower than what is recorded historically.
+                    if min_scores_per_tile_direction.update_min_score_if_lower(&turn_action) {
                         potential_actions.push(Reverse(turn_action));
                     }
                 }
             }
         }
 
-        return BestPaths {
-            score: best_paths_score.expect("A best path should have been found"),
-            unique_tiles: best_paths_unique_tiles,
+        let score = best_paths_score.ok_or_else(|| {
+            AocError::NoSolution(String::from("the end of the maze can't be reached"))
+        })?;
+        let mut best_paths = BestPaths {
+            score,
+            unique_tiles: FxHashSet::default(),
+            start_end_pairs: Vec::new(),
         };
+        for &end_position in &self.end_positions {
+            let (unique_tiles, start_positions) =
+                self.walk_back_best_paths(&min_scores_per_tile_direction, end_position, score);
+            best_paths.unique_tiles.extend(unique_tiles);
+            best_paths.start_end_pairs.extend(
+                start_positions
+                    .into_iter()
+                    .map(|start_position| (start_position, end_position)),
+            );
+        }
+        best_paths.start_end_pairs.sort();
+
+        Ok(best_paths)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Find the lowest score from any start to any end of the maze, and which starts and ends the best paths join.
+    Endpoints,
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Endpoints => {
+                let best_paths = SolverImpl::parse(file)?.find_best_paths(Search::Dijkstra)?;
+                println!("Lowest score is: {}", best_paths.score);
+                // Rows and columns start at 0, unlike lines and columns.
+                for (start_position, end_position) in best_paths.start_end_pairs {
+                    println!(
+                        "Best path from line {}, column {} to line {}, column {}",
+                        start_position.row + 1,
+                        start_position.col + 1,
+                        end_position.row + 1,
+                        end_position.col + 1
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Endpoints => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
     }
 }
 
 pub struct SolverImpl {}
 
+impl Parse for SolverImpl {
+    type Input = ReindeerMaze;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let mut maze = Vec::new();
+        let mut start_positions = Vec::new();
+        let mut end_positions = Vec::new();
+
+        for line in lines.by_ref() {
+            let row: Vec<Tile> = line.parse_chars()?;
+            if maze
+                .first()
+                .is_some_and(|first: &Vec<_>| first.len() != row.len())
+            {
+                return Err(line.error("all rows of the maze must have the same length"));
+            }
+            for (j, tile) in row.iter().enumerate() {
+                if tile.is_start() {
+                    start_positions.push(pos!(maze.len(), j));
+                } else if tile.is_end() {
+                    end_positions.push(pos!(maze.len(), j));
+                }
+            }
+            maze.push(row);
+        }
+
+        if start_positions.is_empty() || end_positions.is_empty() {
+            return Err(lines.error_at_end("the start or end of the maze is missing"));
+        }
+
+        Ok(ReindeerMaze {
+            maze: Grid::from_rows(maze),
+            start_positions,
+            end_positions,
+        })
+    }
+}
+
+impl SolverImpl {
+    // Best paths are either found with Dijkstra, or with A*.
+    const PART1_VARIANTS: &[Variant] = &[
+        Variant::new("dijkstra", |file| {
+            Ok(Self::parse(file)?
+                .find_best_paths(Search::Dijkstra)?
+                .score
+                .to_string())
+        }),
+        Variant::new("a-star", |file| {
+            Ok(Self::parse(file)?
+                .find_best_paths(Search::AStar)?
+                .score
+                .to_string())
+        }),
+    ];
+
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("dijkstra", |file| {
+            Ok(Self::parse(file)?
+                .find_best_paths(Search::Dijkstra)?
+                .unique_tiles
+                .len()
+                .to_string())
+        }),
+        Variant::new("a-star", |file| {
+            Ok(Self::parse(file)?
+                .find_best_paths(Search::AStar)?
+                .unique_tiles
+                .len()
+                .to_string())
+        }),
+    ];
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let reindeer_maze = ReindeerMaze::new(file);
-        println!("Lowest score is: {}", reindeer_maze.find_best_paths().score);
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let reindeer_maze = Self::parse_shared(file)?;
+        let answer = reindeer_maze.find_best_paths(Search::Dijkstra)?.score;
+        Ok(Answer::new(format!("Lowest score is: {answer}"), answer))
     }
 
-    fn solve_part2(file: &str) {
-        let reindeer_maze = ReindeerMaze::new(file);
-        println!(
-            "Number of unique tiles on best paths is is: {}",
-            reindeer_maze.find_best_paths().unique_tiles.len()
-        );
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let reindeer_maze = Self::parse_shared(file)?;
+        let answer = reindeer_maze
+            .find_best_paths(Search::Dijkstra)?
+            .unique_tiles
+            .len();
+        Ok(Answer::new(
+            format!("Number of unique tiles on best paths is is: {answer}"),
+            answer,
+        ))
+    }
+
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => Self::PART1_VARIANTS,
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
+    }
+
+    // Maze carved by a randomized depth-first search, with some extra walls knocked down so that there are several
+    // paths to compare. Like in the examples, the start is in the bottom left corner, and the end in the top right
+    // corner, and `size` is the width and height of the maze.
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        // Rooms are on odd rows and columns, separated by walls. The maze must be odd-sized to be surrounded by walls.
+        let size = (size.max(5) - 1) / 2 * 2 + 1;
+        let mut maze = vec![vec!['#'; size]; size];
+        maze[1][1] = '.';
+
+        let mut rooms_to_explore = vec![pos!(1, 1)];
+        while let Some(&room) = rooms_to_explore.last() {
+            // Rooms are on odd tiles, so they never end up on the outer walls of the first row and column.
+            let unexplored_neighbours: Vec<_> = [
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+            ]
+            .into_iter()
+            .filter_map(|direction| room.checked_go(direction, 2, size - 1, size - 1))
+            .filter(|neighbour| maze[neighbour.row][neighbour.col] == '#')
+            .collect();
+
+            let Some(&neighbour) = unexplored_neighbours.choose(rng) else {
+                rooms_to_explore.pop();
+                continue;
+            };
+
+            // Carve the wall between both rooms.
+            maze[(room.row + neighbour.row) / 2][(room.col + neighbour.col) / 2] = '.';
+            maze[neighbour.row][neighbour.col] = '.';
+            rooms_to_explore.push(neighbour);
+        }
+
+        // Walls between rooms are on an odd row and an even column, or vice versa.
+        for _ in 0..size * size / 20 {
+            let (row, col) = (rng.random_range(1..size - 1), rng.random_range(1..size - 1));
+            if (row + col) % 2 == 1 {
+                maze[row][col] = '.';
+            }
+        }
+
+        maze[size - 2][1] = 'S';
+        maze[1][size - 2] = 'E';
+
+        Ok(maze
+            .into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect())
+    }
+}
+
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[
+        LegendEntry::new('#', "wall", Style::Wall),
+        LegendEntry::new('O', "tile on a best path", Style::Path),
+        LegendEntry::new(
+            EXPLORED_SYMBOL,
+            "tile explored by the search",
+            Style::Explored,
+        ),
+        LegendEntry::new('S', "start", Style::Actor),
+        LegendEntry::new('E', "end", Style::Actor),
+    ];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let reindeer_maze = Self::parse(file)?;
+        let frame = match (part, reindeer_maze.find_best_paths(Search::Dijkstra)) {
+            (Part::Part1, Ok(best_paths)) => reindeer_maze.frame(
+                &best_paths.unique_tiles,
+                format!("Lowest score is: {}", best_paths.score),
+            ),
+            (Part::Part2, Ok(best_paths)) => reindeer_maze.frame(
+                &best_paths.unique_tiles,
+                format!(
+                    "Number of unique tiles on best paths (O) is: {}",
+                    best_paths.unique_tiles.len()
+                ),
+            ),
+            (_, Err(e)) => reindeer_maze.frame(&FxHashSet::default(), e.to_string()),
+        };
+
+        Ok(Some(Box::new(std::iter::once(frame))))
+    }
+
+    fn search_frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let reindeer_maze = Self::parse(file)?;
+        let mut recording = SearchRecording::default();
+        let background = reindeer_maze.frame(&FxHashSet::default(), "");
+
+        let (best_tiles, caption) =
+            match reindeer_maze.find_best_paths_observed(Search::Dijkstra, &mut recording) {
+                Ok(best_paths) => {
+                    let caption = match part {
+                        Part::Part1 => format!("Lowest score is: {}", best_paths.score),
+                        Part::Part2 => format!(
+                            "Number of unique tiles on best paths (O) is: {}",
+                            best_paths.unique_tiles.len()
+                        ),
+                    };
+                    (best_paths.unique_tiles.into_iter().collect(), caption)
+                }
+                Err(e) => (Vec::new(), e.to_string()),
+            };
+
+        Ok(Some(play_back_search(
+            background, recording, best_tiles, 'O', caption,
+        )))
     }
 }
 
 generate_benchmark!(day16);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn the_closest_start_and_end_are_paired() {
+        let file = "\
+#######
+#S...E#
+#.###.#
+#S...E#
+#######
+";
+        for search in [Search::Dijkstra, Search::AStar] {
+            let best_paths = SolverImpl::parse(file)
+                .unwrap()
+                .find_best_paths(search)
+                .unwrap();
+            assert_eq!(best_paths.score, 4);
+            assert_eq!(
+                best_paths.start_end_pairs,
+                [(pos!(1, 1), pos!(1, 5)), (pos!(3, 1), pos!(3, 5))]
+            );
+            assert_eq!(best_paths.unique_tiles.len(), 10);
+        }
+    }
+
+    #[test]
+    fn search_frontier_is_played_back() {
+        let file = "\
+#####
+#S.E#
+#.#.#
+#...#
+#####
+";
+        let mut recording = SearchRecording::default();
+        SolverImpl::parse(file)
+            .unwrap()
+            .find_best_paths_observed(Search::Dijkstra, &mut recording)
+            .unwrap();
+        assert_eq!(recording.0.first(), Some(&pos!(1, 1)));
+        assert!(recording.0.contains(&pos!(1, 3)));
+
+        let frames: Vec<_> = SolverImpl::search_frames(Part::Part1, file)
+            .unwrap()
+            .unwrap()
+            .collect();
+        let last_frame = frames.last().unwrap();
+        assert_eq!(last_frame.caption, "Lowest score is: 2");
+        assert_eq!(last_frame.grid[1], ['#', 'S', 'O', 'E', '#']);
+        assert!(
+            frames[..frames.len() - 1]
+                .iter()
+                .all(|frame| frame.caption.starts_with("Expanded"))
+        );
+    }
+
+    #[test]
+    fn only_the_best_pair_is_reported() {
+        let file = "\
+#######
+#S....#
+#.###E#
+#...S.#
+#######
+";
+        let best_paths = SolverImpl::parse(file)
+            .unwrap()
+            .find_best_paths(Search::AStar)
+            .unwrap();
+        // Both starts go right, and need a single turn to reach the end, but the second one is closer.
+        assert_eq!(best_paths.score, 1002);
+        assert_eq!(best_paths.start_end_pairs, [(pos!(3, 4), pos!(2, 5))]);
+        assert_eq!(best_paths.unique_tiles.len(), 3);
+    }
+}
@@ -1,6 +1,19 @@
+use std::fmt::Display;
+
+use clap::Subcommand;
 use itertools::Itertools;
 
-use crate::{solver::Solver, utils::generate_benchmark};
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver, Variant},
+    utils::{FxHashSet, generate_benchmark},
+    viz::Visualize,
+};
+
+// Number of instructions a program may run before it's deemed to never halt, unless configured otherwise.
+const DEFAULT_MAX_STEPS: usize = 10_000_000;
 
 #[derive(Clone, Copy, Debug)]
 enum OpCode {
@@ -14,22 +27,32 @@ enum OpCode {
     Cdv,
 }
 
-impl From<char> for OpCode {
-    fn from(value: char) -> Self {
+impl From<u8> for OpCode {
+    fn from(value: u8) -> Self {
         match value {
-            '0' => Self::Adv,
-            '1' => Self::Bxl,
-            '2' => Self::Bst,
-            '3' => Self::Jnz,
-            '4' => Self::Bxc,
-            '5' => Self::Out,
-            '6' => Self::Bdv,
-            '7' => Self::Cdv,
+            0 => Self::Adv,
+            1 => Self::Bxl,
+            2 => Self::Bst,
+            3 => Self::Jnz,
+            4 => Self::Bxc,
+            5 => Self::Out,
+            6 => Self::Bdv,
+            7 => Self::Cdv,
             _ => unreachable!(),
         }
     }
 }
 
+impl OpCode {
+    // Whether the operand is a combo operand, which may read a register, rather than a literal.
+    fn takes_combo_operand(self) -> bool {
+        matches!(
+            self,
+            Self::Adv | Self::Bst | Self::Out | Self::Bdv | Self::Cdv
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 struct Instruction {
     op_code: OpCode,
@@ -53,7 +76,7 @@ impl Output {
 }
 
 #[derive(Clone, Debug)]
-struct Computer {
+pub struct Computer {
     register_a: u64,
     register_b: u64,
     register_c: u64,
@@ -62,60 +85,70 @@ struct Computer {
     raw_program: Vec<u8>,
 }
 
-impl Computer {
-    fn new(file: &str) -> Self {
-        let mut lines = file.lines();
-
-        let (_, register_a) = lines.next().unwrap().split_once(": ").unwrap();
-        let register_a = register_a.parse().unwrap();
-
-        let (_, register_b) = lines.next().unwrap().split_once(": ").unwrap();
-        let register_b = register_b.parse().unwrap();
-
-        let (_, register_c) = lines.next().unwrap().split_once(": ").unwrap();
-        let register_c = register_c.parse().unwrap();
+/// Reason why a program didn't halt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotHalting {
+    /// The computer came back to a state it was already in, so it would loop forever.
+    Loop { instruction_index: usize },
+    /// The program ran more instructions than allowed, without looping yet.
+    TooManySteps(usize),
+}
 
-        // Ignore empty line.
-        lines.next();
+impl Display for NotHalting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loop { instruction_index } => write!(
+                f,
+                "the program loops forever, back to instruction {instruction_index} in the same state"
+            ),
+            Self::TooManySteps(max_steps) => {
+                write!(f, "the program didn't halt within {max_steps} instructions")
+            }
+        }
+    }
+}
 
-        let (_, program) = lines.next().unwrap().split_once(": ").unwrap();
-        let raw_program = program.split(',').map(|c| c.parse().unwrap()).collect();
-        let program: Vec<_> = program.chars().step_by(2).collect();
-        let program = program
-            .chunks(2)
-            .map(|instruction| {
-                let (op_code, operand) = (
-                    instruction[0].into(),
-                    instruction[1].to_digit(8).unwrap() as u8,
-                );
-                Instruction { op_code, operand }
-            })
-            .collect();
+impl std::error::Error for NotHalting {}
 
-        Self {
-            register_a,
-            register_b,
-            register_c,
-            program,
-            raw_program,
-        }
+impl From<NotHalting> for AocError {
+    fn from(value: NotHalting) -> Self {
+        Self::NoSolution(value.to_string())
     }
+}
 
+impl Computer {
     fn fetch_instruction(&self, instruction_index: usize) -> Option<Instruction> {
         self.program.get(instruction_index).cloned()
     }
 
-    fn run_program(&mut self) -> Option<Output> {
+    // Runs the program until it halts, or fails if it doesn't within `max_steps` instructions, or loops forever.
+    fn run_program(&mut self, max_steps: usize) -> Result<Output, NotHalting> {
         let mut output = Output::default();
         let mut instruction_index = 0;
+        // States of the computer since register A last changed. Register A only ever shifts right, so the computer
+        // can only come back to a previous state while A is unchanged.
+        let mut states = FxHashSet::default();
+        let mut num_steps = 0;
 
         while let Some(Instruction { op_code, operand }) = self.fetch_instruction(instruction_index)
         {
+            if num_steps == max_steps {
+                return Err(NotHalting::TooManySteps(max_steps));
+            }
+            num_steps += 1;
+            if !states.insert((instruction_index, self.register_b, self.register_c)) {
+                return Err(NotHalting::Loop { instruction_index });
+            }
+
             match op_code {
                 OpCode::Adv => {
                     let combo_operand = self.fetch_combo_operand(operand);
 
-                    self.register_a >>= combo_operand;
+                    let register_a = self.register_a;
+                    self.register_a = shift_right(register_a, combo_operand);
+                    if self.register_a != register_a {
+                        states.clear();
+                    }
                 }
                 OpCode::Bxl => {
                     self.register_b ^= operand as u64;
@@ -139,18 +172,22 @@ impl Computer {
 
                     output.push(out);
                 }
-                OpCode::Bdv => todo!(), // Unused instruction.
+                OpCode::Bdv => {
+                    let combo_operand = self.fetch_combo_operand(operand);
+
+                    self.register_b = shift_right(self.register_a, combo_operand);
+                }
                 OpCode::Cdv => {
                     let combo_operand = self.fetch_combo_operand(operand);
 
-                    self.register_c = self.register_a >> combo_operand;
+                    self.register_c = shift_right(self.register_a, combo_operand);
                 }
             }
 
             instruction_index += 1;
         }
 
-        Some(output)
+        Ok(output)
     }
 
     fn fetch_combo_operand(&self, operand: u8) -> u64 {
@@ -159,18 +196,232 @@ impl Computer {
             4 => self.register_a,
             5 => self.register_b,
             6 => self.register_c,
-            7 => unreachable!("reserved operand"),
+            7 => unreachable!("reserved operands are rejected when parsing"),
             _ => unreachable!("operands are 3 bits"),
         }
     }
+
+    // Values of register A making the program output itself, found by reverse engineering the program of the input.
+    // See `solve_part2` for how.
+    fn find_quine_register_as(&self) -> Vec<u64> {
+        let mut valid_as = Vec::new();
+
+        let mut potential_candidates = vec![(0..8_u64, self.raw_program.clone())];
+        while let Some((possible_as, mut program)) = potential_candidates.pop() {
+            let target_output = program.pop().unwrap();
+
+            for possible_a in possible_as {
+                let mut b = possible_a % 8;
+                b ^= 1;
+                let c = possible_a >> b;
+                b ^= 5;
+                b ^= c;
+
+                let out = (b % 8) as u8;
+                if out != target_output {
+                    continue;
+                }
+
+                if program.is_empty() {
+                    valid_as.push(possible_a);
+                    continue;
+                }
+
+                // This value of A matches with the current last output, but we have to explore further
+                // to make sure the rest of the output can also be generated from it.
+                let new_possible_a = possible_a << 3;
+                potential_candidates.push((new_possible_a..new_possible_a + 8, program.clone()));
+            }
+        }
+
+        valid_as.sort();
+        valid_as
+    }
+
+    // Same search as `find_quine_register_as`, but running the program for each candidate instead, so it works for
+    // any program which outputs once per loop, and shifts register A right by 3 bits each time. Candidates making the
+    // program loop forever, or run for more than `max_steps` instructions, aren't quines, so they're skipped.
+    fn find_quine_register_as_by_running(&self, max_steps: usize) -> Vec<u64> {
+        let mut valid_as = Vec::new();
+
+        let mut potential_candidates = vec![(0..8_u64, 1)];
+        while let Some((possible_as, output_len)) = potential_candidates.pop() {
+            let target_output = &self.raw_program[self.raw_program.len() - output_len..];
+
+            for possible_a in possible_as {
+                let mut computer = Self {
+                    register_a: possible_a,
+                    ..self.clone()
+                };
+                match computer.run_program(max_steps) {
+                    Ok(output) if output.0 == target_output => {}
+                    _ => continue,
+                }
+
+                if output_len == self.raw_program.len() {
+                    valid_as.push(possible_a);
+                    continue;
+                }
+
+                // A zero register A isn't shifted from anything, and would only find itself again.
+                if possible_a == 0 {
+                    continue;
+                }
+                let new_possible_a = possible_a << 3;
+                potential_candidates.push((new_possible_a..new_possible_a + 8, output_len + 1));
+            }
+        }
+
+        valid_as.sort();
+        valid_as
+    }
+}
+
+// Shifts `value` right by `shift` bits, which empties it if it's shifted by 64 bits or more.
+fn shift_right(value: u64, shift: u64) -> u64 {
+    value
+        .checked_shr(shift.try_into().unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+// Parses a line like "Register A: 729".
+fn parse_register(line: Line, name: char) -> Result<u64, ParseError> {
+    line.parse(line.strip_prefix(&format!("Register {name}: "))?)
+}
+
+impl Parse for SolverImpl {
+    type Input = Computer;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+
+        let register_a = parse_register(lines.next_line()?, 'A')?;
+        let register_b = parse_register(lines.next_line()?, 'B')?;
+        let register_c = parse_register(lines.next_line()?, 'C')?;
+
+        // Ignore empty line.
+        lines.next_line()?;
+
+        let line = lines.next_line()?;
+        let values: Vec<&str> = line.strip_prefix("Program: ")?.split(',').collect();
+        let raw_program: Vec<u8> = values
+            .iter()
+            .map(|value| match line.parse(value)? {
+                value @ 0..8 => Ok(value),
+                _ => Err(line.error_at(value, "expected a 3-bit number")),
+            })
+            .collect::<Result<_, _>>()?;
+        if !raw_program.len().is_multiple_of(2) {
+            return Err(line.error("expected an operand after each opcode"));
+        }
+
+        let program = raw_program
+            .chunks(2)
+            .zip(values.chunks(2))
+            .map(|(instruction, values)| {
+                let op_code = OpCode::from(instruction[0]);
+                // Combo operand 7 is reserved, and doesn't stand for any value.
+                if op_code.takes_combo_operand() && instruction[1] == 7 {
+                    return Err(line.error_at(values[1], "combo operand 7 is reserved"));
+                }
+                Ok(Instruction {
+                    op_code,
+                    operand: instruction[1],
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Computer {
+            register_a,
+            register_b,
+            register_c,
+            program,
+            raw_program,
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Run the program, like in part 1, giving up after the given number of instructions.
+    Run {
+        #[arg(short, long, default_value_t = DEFAULT_MAX_STEPS)]
+        max_steps: usize,
+    },
+    /// Find the values of register A making the program output itself, like in part 2, by running the program. This
+    /// works for any program outputting once per loop and shifting register A right by 3 bits each time.
+    Quine {
+        #[arg(short, long, default_value_t = DEFAULT_MAX_STEPS)]
+        max_steps: usize,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Run { max_steps } => {
+                let mut computer = SolverImpl::parse(file)?;
+                println!("Output is: {}", computer.run_program(max_steps)?.output());
+            }
+            Self::Quine { max_steps } => {
+                let computer = SolverImpl::parse(file)?;
+                println!(
+                    "Valid values for register A are: {:?}",
+                    computer.find_quine_register_as_by_running(max_steps)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Run { .. } => Some(Part::Part1),
+            Self::Quine { .. } => Some(Part::Part2),
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
 }
 
 pub struct SolverImpl {}
 
+impl SolverImpl {
+    // Valid values of register A are either found by reverse engineering the program of the input, or by running any
+    // program.
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("reverse-engineered", |file| {
+            Ok(format!("{:?}", Self::parse(file)?.find_quine_register_as()))
+        }),
+        Variant::new("interpreted", |file| {
+            Ok(format!(
+                "{:?}",
+                Self::parse(file)?.find_quine_register_as_by_running(DEFAULT_MAX_STEPS)
+            ))
+        }),
+    ];
+}
+
+impl Visualize for SolverImpl {}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let mut computer = Computer::new(file);
-        println!("Output is: {}", computer.run_program().unwrap().output());
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let mut computer = Self::parse_shared(file)?;
+        let answer = computer.run_program(DEFAULT_MAX_STEPS)?.output();
+        Ok(Answer::new(format!("Output is: {answer}"), answer))
     }
 
     // Part 2 is not a generic solution. It works specifically for the given program in the input, which is:
@@ -196,41 +447,86 @@ impl Solver for SolverImpl {
     // We repeat this over and over again until we have backtracked all the way to a register A that generates
     // the whole output. We do this exploration using DFS (though BFS would have worked equally), and keeping track
     // of all potential solutions.
-    fn solve_part2(file: &str) {
-        let computer = Computer::new(file);
-        let mut valid_as = Vec::new();
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let computer = Self::parse_shared(file)?;
+        let register_as = computer.find_quine_register_as();
+        let text = format!("Valid values for register A are: {register_as:?}");
+        // The lowest one is asked for.
+        Ok(match register_as.iter().min() {
+            Some(lowest_register_a) => Answer::new(text, lowest_register_a),
+            None => Answer::without_value(text),
+        })
+    }
 
-        let mut potential_candidates = vec![(0..8 as u64, computer.raw_program)];
-        while let Some((possible_as, mut program)) = potential_candidates.pop() {
-            let target_output = program.pop().unwrap();
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => &[],
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
+    }
+}
 
-            for possible_a in possible_as {
-                let mut b = possible_a % 8;
-                b ^= 1;
-                let c = possible_a >> b;
-                b ^= 5;
-                b ^= c;
+generate_benchmark!(day17);
 
-                let out = (b % 8) as u8;
-                if out != target_output {
-                    continue;
-                }
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
 
-                if program.is_empty() {
-                    valid_as.push(possible_a);
-                    continue;
-                }
+    fn computer(register_a: u64, program: &str) -> Computer {
+        SolverImpl::parse(&format!(
+            "Register A: {register_a}\nRegister B: 0\nRegister C: 0\n\nProgram: {program}"
+        ))
+        .unwrap()
+    }
 
-                // This value of A matches with the current last output, but we have to explore further
-                // to make sure the rest of the output can also be generated from it.
-                let new_possible_a = possible_a << 3;
-                potential_candidates.push((new_possible_a..new_possible_a + 8, program.clone()));
-            }
-        }
+    #[test]
+    fn programs_looping_forever_are_caught() {
+        // Jumps back to the start without ever changing register A.
+        assert_eq!(
+            computer(1, "5,4,3,0").run_program(DEFAULT_MAX_STEPS).err(),
+            Some(NotHalting::Loop {
+                instruction_index: 0
+            })
+        );
+        // Shifts register A one bit at a time, so it halts after 64 loops of 2 instructions.
+        assert_eq!(
+            computer(u64::MAX, "0,1,3,0").run_program(100).err(),
+            Some(NotHalting::TooManySteps(100))
+        );
+        assert_eq!(
+            computer(729, "0,1,5,4,3,0")
+                .run_program(DEFAULT_MAX_STEPS)
+                .unwrap()
+                .output(),
+            "4,6,3,5,6,3,5,2,1,0"
+        );
+    }
 
-        valid_as.sort();
-        println!("Valid values for register A are: {:?}", valid_as);
+    #[test]
+    fn reserved_combo_operands_fail_to_parse() {
+        let error =
+            SolverImpl::parse("Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 1,7,2,7")
+                .unwrap_err();
+
+        assert_eq!((error.line, error.column), (5, Some(16)));
     }
-}
 
-generate_benchmark!(day17);
+    #[test]
+    fn quines_are_found_by_running_the_program() {
+        let valid_as =
+            computer(2024, "0,3,5,4,3,0").find_quine_register_as_by_running(DEFAULT_MAX_STEPS);
+
+        assert_eq!(valid_as.first(), Some(&117440));
+    }
+
+    #[test]
+    fn candidates_which_do_not_halt_are_skipped() {
+        // Shifts register A by its lowest 3 bits, so it loops forever once they're all 0, e.g. from 8.
+        let program = "2,4,0,5,5,4,3,0";
+        let valid_as = computer(0, program).find_quine_register_as_by_running(DEFAULT_MAX_STEPS);
+
+        // No register A makes this program output itself, but the search still has to finish.
+        assert_eq!(valid_as, Vec::<u64>::new());
+        assert!(computer(8, program).run_program(DEFAULT_MAX_STEPS).is_err());
+    }
+}
@@ -1,10 +1,23 @@
 use std::collections::VecDeque;
+use std::fmt::Write;
 
-use itertools::Itertools;
+use clap::Subcommand;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::Serialize;
 
 use crate::{
-    solver::Solver,
+    args::Part,
+    error::AocError,
+    grid::Grid,
+    input::{Line, Lines, Parse, ParseError},
+    output::{Format, write_records},
+    solver::{Answer, Run, Solver, Variant},
     utils::{Position, generate_benchmark, pos},
+    viz::{
+        EXPLORED_SYMBOL, Frame, LegendEntry, SearchObserver, SearchRecording, Style, Visualize,
+        play_back_search,
+    },
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -30,9 +43,55 @@ impl From<&Tile> for char {
     }
 }
 
-struct MemorySpace {
+// Disjoint sets of tiles, identified by their index, which get merged as tiles connect.
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    // Representative of the set of `i`. Paths are halved along the way, to keep future lookups short.
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+
+        i
+    }
+
+    // Merges the sets of `a` and `b`, under the representative of the largest one.
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        let (larger, smaller) = if self.sizes[a] >= self.sizes[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parents[smaller] = larger;
+        self.sizes[larger] += self.sizes[smaller];
+    }
+
+    fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemorySpace {
     // Note that the grid is padded with outside tiles on the side.
-    grid: Vec<Vec<Tile>>,
+    grid: Grid<Tile>,
     start: Position,
     exit: Position,
     // These positions are not corrupting the grid yet -- unless
@@ -41,195 +100,619 @@ struct MemorySpace {
 }
 
 impl MemorySpace {
-    fn new(file: &str) -> Self {
-        let mut lines = file.lines();
-        let grid_size = lines.next().unwrap().parse().unwrap();
+    fn frame(&self, caption: impl Into<String>) -> Frame {
+        Frame::new(self.grid.rows(), caption)
+    }
 
-        // +2 to add outside rows/columns.
-        let mut grid = vec![Vec::new(); grid_size + 2];
-        grid[0].extend(std::iter::repeat_n(Tile::Outside, grid_size + 2));
-        grid.last_mut()
-            .unwrap()
-            .extend(std::iter::repeat_n(Tile::Outside, grid_size + 2));
+    fn is_exit(&self, position: Position) -> bool {
+        self.exit == position
+    }
 
-        for row in 1..=grid_size {
-            // Outside left column.
-            grid[row].push(Tile::Outside);
+    // Shortest path from the start to the exit, both included, if the exit can be reached.
+    fn find_shortest_exit_path(&self) -> Option<Vec<Position>> {
+        self.find_shortest_exit_path_observed(&mut ())
+    }
 
-            // Main grid is by default safe.
-            grid[row].extend(std::iter::repeat_n(Tile::Safe, grid_size));
+    // Same as `find_shortest_exit_path`, reporting each tile explored to the `observer`.
+    fn find_shortest_exit_path_observed(
+        &self,
+        observer: &mut impl SearchObserver,
+    ) -> Option<Vec<Position>> {
+        let mut tiles_to_explore = VecDeque::from([self.start]);
+        // Tile from which each tile was first reached, which also tells which tiles were already visited.
+        let mut previous_tiles = self.grid.map(|_| None);
+        previous_tiles[self.start] = Some(self.start);
+
+        // Iterative BFS.
+        while let Some(position) = tiles_to_explore.pop_front() {
+            observer.expand(position);
+            if self.is_exit(position) {
+                let mut path = vec![position];
+                while let Some(&tile) = path.last()
+                    && tile != self.start
+                {
+                    path.push(previous_tiles[tile].unwrap());
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for neighbour in position.surroundings() {
+                // Crucial pruning: prevent exploring tiles that have already been visited.
+                if !self.grid[neighbour].is_safe() || previous_tiles[neighbour].is_some() {
+                    continue;
+                }
 
-            // Outside right column.
-            grid[row].push(Tile::Outside);
+                previous_tiles[neighbour] = Some(position);
+                tiles_to_explore.push_back(neighbour);
+            }
         }
 
-        let num_bytes = lines.next().unwrap().parse().unwrap();
-        for _ in 0..num_bytes {
-            let line = lines.next().unwrap();
-            let (col, row) = line.split_once(',').unwrap();
-            let (row, col) = (
-                // +1 to include the outside padding.
-                row.parse::<usize>().unwrap() + 1,
-                col.parse::<usize>().unwrap() + 1,
-            );
+        None
+    }
+
+    fn find_shortest_exit_path_len(&self) -> Option<u64> {
+        self.find_shortest_exit_path()
+            .map(|path| path.len() as u64 - 1)
+    }
+
+    // Same as `find_first_partition_byte`, but starts from the memory space once all bytes fell, and removes them in
+    // reverse order while connecting safe tiles, until the start and exit get connected. That makes the last byte
+    // removed the first one that partitions them.
+    fn find_first_partition_byte_by_union_find(&self) -> Option<Position> {
+        self.unfall_bytes(|num_fallen, _, exit_reachable| {
+            // Once all bytes fell, there is no byte left to partition them.
+            exit_reachable.then(|| self.remaining_corrupted_bytes.get(num_fallen).copied())
+        })
+        .flatten()
+    }
+
+    // Starts from the memory space once all bytes fell, and removes them in reverse order while connecting safe
+    // tiles. `visit` is called with the number of remaining bytes still fallen, the number of tiles reachable from the
+    // start, and whether the exit is reachable: first once they all fell, then after each removal. Stops as soon as
+    // `visit` returns something.
+    fn unfall_bytes<T>(&self, mut visit: impl FnMut(usize, usize, bool) -> Option<T>) -> Option<T> {
+        let index = |position| self.grid.index_of(position);
+
+        // A tile may be hit by several bytes, and is only safe again once they're all removed.
+        let mut num_bytes_fallen = self.grid.map(|_| 0);
+        for &byte in &self.remaining_corrupted_bytes {
+            num_bytes_fallen[byte] += 1;
+        }
+        let is_safe = |num_bytes_fallen: &Grid<u32>, position| {
+            self.grid[position].is_safe() && num_bytes_fallen[position] == 0
+        };
+        let mut visit = |num_fallen, num_bytes_fallen: &Grid<u32>, tiles: &mut UnionFind| {
+            let start = tiles.find(index(self.start));
+            let num_reachable_tiles = if is_safe(num_bytes_fallen, self.start) {
+                tiles.sizes[start]
+            } else {
+                0
+            };
+            visit(
+                num_fallen,
+                num_reachable_tiles,
+                tiles.connected(start, index(self.exit)),
+            )
+        };
+
+        // Outside tiles are never safe, so only tiles of the grid get connected.
+        let mut tiles = UnionFind::new(self.grid.num_rows() * self.grid.num_cols());
+        for position in self.grid.positions() {
+            if !is_safe(&num_bytes_fallen, position) {
+                continue;
+            }
+            for neighbour in [position.right(1), position.down(1)] {
+                if is_safe(&num_bytes_fallen, neighbour) {
+                    tiles.union(index(position), index(neighbour));
+                }
+            }
+        }
+        if let Some(visited) = visit(
+            self.remaining_corrupted_bytes.len(),
+            &num_bytes_fallen,
+            &mut tiles,
+        ) {
+            return Some(visited);
+        }
+
+        for (num_fallen, &byte) in self.remaining_corrupted_bytes.iter().enumerate().rev() {
+            num_bytes_fallen[byte] -= 1;
+            if is_safe(&num_bytes_fallen, byte) {
+                for neighbour in byte.surroundings() {
+                    if is_safe(&num_bytes_fallen, neighbour) {
+                        tiles.union(index(byte), index(neighbour));
+                    }
+                }
+            }
+
+            if let Some(visited) = visit(num_fallen, &num_bytes_fallen, &mut tiles) {
+                return Some(visited);
+            }
+        }
+
+        None
+    }
+
+    /// How connected the start is after each of the remaining bytes falls, in the order they fall.
+    fn connectivity_over_time(&self) -> Vec<ConnectivityRecord> {
+        let mut connectivity = vec![(0, false); self.remaining_corrupted_bytes.len() + 1];
+        self.unfall_bytes(|num_fallen, num_reachable_tiles, exit_reachable| {
+            connectivity[num_fallen] = (num_reachable_tiles, exit_reachable);
+            None::<()>
+        });
+
+        self.remaining_corrupted_bytes
+            .iter()
+            .zip(&connectivity[1..])
+            .enumerate()
+            .map(
+                |(i, (byte, &(reachable_tiles, exit_reachable)))| ConnectivityRecord {
+                    num_bytes_fallen: i + 1,
+                    byte: coordinates(*byte),
+                    reachable_tiles,
+                    exit_reachable,
+                },
+            )
+            .collect()
+    }
+
+    // Returns the normalized position (i.e. ignoring outside padding) of the byte
+    // that partitions the start and exit tiles (i.e. cannot be reached). Returns None if
+    // the exit can still be reached once all bytes fell, or if it can't be reached to begin with.
+    fn find_first_partition_byte(&mut self) -> Option<Position> {
+        // We effectively use binary search to find the number of remaining bytes that must fall to partition
+        // the start and exit tiles. Contrarily to a normal binary search, we are not searching for an entry,
+        // but rather the boundary at which point we go from a non-partitioned space to a partitioned space.
+        //
+        // The invariant is that the exit can be reached once the first `lo` bytes fell, but not once the
+        // first `hi` bytes fell. `hi` starts past the last byte, as the exit may never get partitioned.
+        let mut falling_bytes = FallingBytes::new(self);
+        if !falling_bytes.can_reach_exit_after(self, 0) {
+            return None;
+        }
+
+        let (mut lo, mut hi) = (0, self.remaining_corrupted_bytes.len() + 1);
+        while hi - lo > 1 {
+            let mi = (lo + hi) / 2;
+            if falling_bytes.can_reach_exit_after(self, mi) {
+                lo = mi;
+            } else {
+                hi = mi;
+            }
+        }
+
+        // The byte falling after the first `lo` ones is the one partitioning the space, if any.
+        self.remaining_corrupted_bytes.get(lo).copied()
+    }
+}
+
+/// Tiles reachable from the start once a byte fell, along with the number of remaining bytes fallen so far.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ConnectivityRecord {
+    pub num_bytes_fallen: usize,
+    pub byte: String,
+    pub reachable_tiles: usize,
+    pub exit_reachable: bool,
+}
+
+// Tracks how many of the remaining bytes fell on the grid of a memory space, so that the grid can be updated back and
+// forth as the binary search moves. Several bytes may fall on the same tile, which is only safe when none did.
+struct FallingBytes {
+    num_fallen: usize,
+    num_fallen_per_tile: Grid<u32>,
+}
+
+impl FallingBytes {
+    fn new(memory_space: &MemorySpace) -> Self {
+        Self {
+            num_fallen: 0,
+            // Bytes which fell before the remaining ones never get removed.
+            num_fallen_per_tile: memory_space
+                .grid
+                .map(|tile| u32::from(*tile == Tile::Corrupted)),
+        }
+    }
+
+    // Lets exactly the first `num_fallen` remaining bytes fall, then checks whether the exit can be reached.
+    fn can_reach_exit_after(&mut self, memory_space: &mut MemorySpace, num_fallen: usize) -> bool {
+        while self.num_fallen < num_fallen {
+            let byte = memory_space.remaining_corrupted_bytes[self.num_fallen];
+            self.num_fallen_per_tile[byte] += 1;
+            memory_space.grid[byte] = Tile::Corrupted;
+            self.num_fallen += 1;
+        }
+        while self.num_fallen > num_fallen {
+            self.num_fallen -= 1;
+            let byte = memory_space.remaining_corrupted_bytes[self.num_fallen];
+            self.num_fallen_per_tile[byte] -= 1;
+            if self.num_fallen_per_tile[byte] == 0 {
+                memory_space.grid[byte] = Tile::Safe;
+            }
+        }
+
+        memory_space.find_shortest_exit_path_len().is_some()
+    }
+}
+
+// Way larger than in the actual problem, but small enough that the grid fits in memory.
+const MAX_GRID_SIZE: usize = 10_000;
+
+// Parses a line like "5,4" into the position of a byte, in the padded grid of the given size.
+fn parse_byte(line: Line, grid_size: usize) -> Result<Position, ParseError> {
+    let (col, row) = line.split_once(line.text, ",")?;
+    let (row, col): (usize, usize) = (line.parse(row)?, line.parse(col)?);
+    if row >= grid_size || col >= grid_size {
+        return Err(line.error(format!(
+            "byte is outside of the {grid_size}x{grid_size} grid"
+        )));
+    }
+
+    // +1 to include the outside padding.
+    Ok(pos!(row + 1, col + 1))
+}
+
+// Writes the position of a byte in the padded grid like the input does, e.g. "5,4", which is how the answer is given.
+fn coordinates(byte: Position) -> String {
+    // -1 to exclude the outside padding.
+    format!("{},{}", byte.col - 1, byte.row - 1)
+}
 
-            grid[row][col] = Tile::Corrupted;
+impl Parse for SolverImpl {
+    type Input = MemorySpace;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let line = lines.next_line()?;
+        let grid_size = line.parse(line.text)?;
+        if !(1..=MAX_GRID_SIZE).contains(&grid_size) {
+            return Err(line.error(format!(
+                "the grid size must be between 1 and {MAX_GRID_SIZE}"
+            )));
+        }
+
+        // +2 to add outside rows/columns.
+        let mut grid = Grid::filled(grid_size + 2, grid_size + 2, Tile::Outside);
+        // Main grid is by default safe.
+        for row in 1..=grid_size {
+            for col in 1..=grid_size {
+                grid[pos!(row, col)] = Tile::Safe;
+            }
+        }
+
+        let line = lines.next_line()?;
+        let num_bytes = line.parse(line.text)?;
+        for _ in 0..num_bytes {
+            let byte = parse_byte(lines.next_line()?, grid_size)?;
+            grid[byte] = Tile::Corrupted;
         }
 
         let remaining_corrupted_bytes = lines
-            .map(|remaining_line| {
-                let (col, row) = remaining_line.split_once(',').unwrap();
-                let (row, col) = (
-                    // +1 to include the outside padding.
-                    row.parse::<usize>().unwrap() + 1,
-                    col.parse::<usize>().unwrap() + 1,
-                );
-                pos!(row, col)
-            })
-            .collect();
+            .map(|line| parse_byte(line, grid_size))
+            .collect::<Result<_, _>>()?;
 
         let start = pos!(1, 1);
         let exit = pos!(grid_size, grid_size);
 
-        Self {
+        Ok(MemorySpace {
             grid,
             start,
             exit,
             remaining_corrupted_bytes,
-        }
+        })
     }
+}
 
-    fn _display_map(&self) -> String {
-        self.grid
-            .iter()
-            .map(|tiles| {
-                tiles
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report how many tiles can be reached from the start, and whether the exit can, after each remaining byte falls.
+    Connectivity {
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        /// Only find the first byte after which fewer than this many tiles can be reached.
+        #[arg(short, long)]
+        below: Option<usize>,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Connectivity { format, below } => {
+                let connectivity = SolverImpl::parse(file)?.connectivity_over_time();
+                let Some(below) = below else {
+                    return write_records(&connectivity, format);
+                };
+
+                let record = connectivity
                     .iter()
-                    .map(|tile| char::from(tile))
-                    .collect::<String>()
-            })
-            .join("\n")
+                    .find(|record| record.reachable_tiles < below)
+                    .ok_or_else(|| {
+                        AocError::NoSolution(format!(
+                            "{below} tiles or more can still be reached once all bytes fell"
+                        ))
+                    })?;
+                println!(
+                    "First byte after which fewer than {below} tiles can be reached: {} ({} tiles)",
+                    record.byte, record.reachable_tiles
+                );
+            }
+        }
+
+        Ok(())
     }
 
-    fn is_exit(&self, position: Position) -> bool {
-        self.exit == position
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
     }
 
-    fn find_shortest_exit_path_len(&self) -> Option<u64> {
-        let mut tiles_to_explore = VecDeque::from([(self.start, 0)]);
-        let mut visited_tiles = vec![vec![false; self.grid.len()]; self.grid.len()];
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
-        // Iterative BFS.
-        while let Some((position, steps)) = tiles_to_explore.pop_front() {
-            if self.is_exit(position) {
-                return Some(steps);
-            }
+pub struct SolverImpl {}
 
-            // Crucial pruning: prevent exploring tiles that have already been visited.
-            if visited_tiles[position.row][position.col] {
-                continue;
-            } else {
-                visited_tiles[position.row][position.col] = true;
-            }
+impl SolverImpl {
+    // The first partition byte is either found by binary searching how many bytes must fall, or by removing them all one
+    // by one.
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("binary-search", |file| {
+            let mut memory_space = Self::parse(file)?;
+            Ok(format!("{:?}", memory_space.find_first_partition_byte()))
+        }),
+        Variant::new("union-find", |file| {
+            let memory_space = Self::parse(file)?;
+            Ok(format!(
+                "{:?}",
+                memory_space.find_first_partition_byte_by_union_find()
+            ))
+        }),
+    ];
+}
 
-            for neighbour in position.surroundings() {
-                if !self.grid[neighbour.row][neighbour.col].is_safe() {
-                    continue;
-                }
+impl Solver for SolverImpl {
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let memory_space = Self::parse_shared(file)?;
+        let shortest_exit_path_len = memory_space
+            .find_shortest_exit_path_len()
+            .ok_or_else(|| AocError::NoSolution(String::from("the exit can't be reached")))?;
+
+        Ok(Answer::new(
+            format!("Short exit path length: {shortest_exit_path_len}"),
+            shortest_exit_path_len,
+        ))
+    }
 
-                if visited_tiles[neighbour.row][neighbour.col] {
-                    continue;
-                }
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let mut memory_space = Self::parse_shared(file)?;
+        let partition_byte = memory_space.find_first_partition_byte().ok_or_else(|| {
+            AocError::NoSolution(String::from("no byte partitions the start and exit"))
+        })?;
+
+        let answer = coordinates(partition_byte);
+        Ok(Answer::new(
+            format!("First byte that partitions the start and exit: {answer}"),
+            answer,
+        ))
+    }
 
-                tiles_to_explore.push_back((neighbour, steps + 1));
-            }
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => &[],
+            Part::Part2 => Self::PART2_VARIANTS,
         }
+    }
 
-        None
+    // Bytes fall on every position but the start and exit, in a random order, on a grid of the given `size`. The
+    // first bytes all avoid a random path from the start to the exit, so that it can be reached in the first part.
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        let size = size.max(2);
+
+        // Only going right or down, in a random order.
+        let mut moves: Vec<_> = std::iter::repeat_n(true, size - 1)
+            .chain(std::iter::repeat_n(false, size - 1))
+            .collect();
+        moves.shuffle(rng);
+        let mut on_path = vec![vec![false; size]; size];
+        let mut position = pos!(0, 0);
+        on_path[0][0] = true;
+        for go_right in moves {
+            position = if go_right {
+                position.right(1)
+            } else {
+                position.down(1)
+            };
+            on_path[position.row][position.col] = true;
+        }
+
+        let (path_bytes, mut other_bytes): (Vec<_>, Vec<_>) = (0..size)
+            .flat_map(|row| (0..size).map(move |col| pos!(row, col)))
+            .filter(|byte| *byte != pos!(0, 0) && *byte != pos!(size - 1, size - 1))
+            .partition(|byte| on_path[byte.row][byte.col]);
+        other_bytes.shuffle(rng);
+
+        // About the same proportion of bytes as in the actual problem fall in the first part.
+        let num_bytes = (size * size / 5).min(other_bytes.len());
+        let mut remaining_bytes = other_bytes.split_off(num_bytes);
+        remaining_bytes.extend(path_bytes);
+        remaining_bytes.shuffle(rng);
+
+        let mut file = format!("{size}\n{num_bytes}\n");
+        for Position { row, col } in other_bytes.into_iter().chain(remaining_bytes) {
+            writeln!(file, "{col},{row}").unwrap();
+        }
+
+        Ok(file)
     }
+}
 
-    // Returns the normalized position (i.e. ignoring outside padding) of the byte
-    // that partitions the start and exit tiles (i.e. cannot be reached).
-    fn find_first_partition_byte(&mut self) -> Position {
-        // We effectively use binary search to find the corrupt byte that partitions the start
-        // and exit tiles. Contrarily to a normal binary search, we are not searching for an entry,
-        // but rather the boundary between entries at which point we go from a non-partitioned space to a
-        // partitioned space. Therefore, we will always reach the point where the `lo` index
-        // equals the `hi` index.
-        //
-        // Another peculiarity is that after each check, we must update the set of corrupted tiles
-        // in the grid.
-        let mut lo = 0;
-        let mut hi = self.remaining_corrupted_bytes.len() - 1;
-        let mut mi = (lo + hi) / 2;
-
-        // Start by setting the first half of remaining bytes (including `mi`) as corrupted on the grid.
-        for i in lo..=mi {
-            let Position { row, col } = self.remaining_corrupted_bytes[i];
-            self.grid[row][col] = Tile::Corrupted;
-        }
-
-        loop {
-            match self.find_shortest_exit_path_len() {
-                // If setting all remaining bytes up to `lo|hi` resolves a shortest exit path,
-                // then we have found the partition point to be the following byte.
-                Some(_) if lo == hi => {
-                    return self.remaining_corrupted_bytes[lo + 1];
-                }
-                // We have found an exit path, so more bytes must be corrupted to partition the
-                // exit space.
-                Some(_) => {
-                    lo = mi + 1;
-                    mi = (lo + hi) / 2;
-
-                    for i in lo..=mi {
-                        let Position { row, col } = self.remaining_corrupted_bytes[i];
-                        self.grid[row][col] = Tile::Corrupted;
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[
+        LegendEntry::new('#', "corrupted byte", Style::Wall),
+        LegendEntry::new('O', "tile on the shortest exit path", Style::Path),
+        LegendEntry::new(
+            EXPLORED_SYMBOL,
+            "tile explored by the search",
+            Style::Explored,
+        ),
+        LegendEntry::new(
+            'X',
+            "byte partitioning the start and exit",
+            Style::Highlight,
+        ),
+    ];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let mut memory_space = Self::parse(file)?;
+
+        let frame = match part {
+            Part::Part1 => match memory_space.find_shortest_exit_path() {
+                Some(path) => {
+                    let mut frame = memory_space
+                        .frame(format!("Short exit path length (O): {}", path.len() - 1));
+                    for Position { row, col } in path {
+                        frame.grid[row][col] = 'O';
                     }
+                    frame
                 }
-                // If setting all remaining bytes up to `lo|hi` does not resolve a shortest exit path,
-                // then we have found the partition point to be this exact byte.
-                None if lo == hi => {
-                    return self.remaining_corrupted_bytes[lo];
+                None => memory_space.frame("There is no exit path"),
+            },
+            // Show the memory space once the partition byte fell.
+            Part::Part2 => {
+                let partition_byte = memory_space.find_first_partition_byte();
+                let mut memory_space = Self::parse(file)?;
+                for &byte in memory_space
+                    .remaining_corrupted_bytes
+                    .iter()
+                    .take_while(|byte| Some(**byte) != partition_byte)
+                {
+                    memory_space.grid[byte] = Tile::Corrupted;
                 }
-                // We have not found an exit path, so fewer bytes must be corrupted to partition the
-                // exit space.
-                None => {
-                    hi = mi - 1;
-                    mi = (lo + hi) / 2;
-
-                    for i in mi + 1..=hi + 1 {
-                        let Position { row, col } = self.remaining_corrupted_bytes[i];
-                        self.grid[row][col] = Tile::Safe;
+
+                match partition_byte {
+                    Some(partition_byte) => {
+                        let mut frame = memory_space.frame(format!(
+                            "First byte that partitions the start and exit (X): {}",
+                            coordinates(partition_byte)
+                        ));
+                        frame.grid[partition_byte.row][partition_byte.col] = 'X';
+                        frame
                     }
+                    None => memory_space.frame("No byte partitions the start and exit"),
                 }
             }
+        };
+
+        Ok(Some(Box::new(std::iter::once(frame))))
+    }
+
+    // Part 2 bisects over many searches, so only part 1 is played back.
+    fn search_frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        if part == Part::Part2 {
+            return Ok(None);
         }
+
+        let memory_space = Self::parse(file)?;
+        let mut recording = SearchRecording::default();
+        let (path, caption) = match memory_space.find_shortest_exit_path_observed(&mut recording) {
+            Some(path) => {
+                let caption = format!("Short exit path length (O): {}", path.len() - 1);
+                (path, caption)
+            }
+            None => (Vec::new(), "There is no exit path".to_string()),
+        };
+
+        Ok(Some(play_back_search(
+            memory_space.frame(""),
+            recording,
+            path,
+            'O',
+            caption,
+        )))
     }
 }
 
-pub struct SolverImpl {}
+generate_benchmark!(day18);
 
-impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let memory_space = MemorySpace::new(file);
-
-        println!(
-            "Short exit path length: {}",
-            memory_space
-                .find_shortest_exit_path_len()
-                .expect("should find shortest path")
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn search_frontier_is_played_back() {
+        // The byte in the middle forces the path around it.
+        let file = "3\n1\n1,1\n";
+
+        let frames: Vec<_> = SolverImpl::search_frames(Part::Part1, file)
+            .unwrap()
+            .unwrap()
+            .collect();
+        let (last_frame, exploring_frames) = frames.split_last().unwrap();
+        assert_eq!(exploring_frames.len(), 8);
+        assert_eq!(exploring_frames[0].caption, "Expanded 1 tiles");
+        assert_eq!(exploring_frames[0].grid[1][1], EXPLORED_SYMBOL);
+        assert_eq!(exploring_frames[0].grid[1][2], '.');
+        assert_eq!(exploring_frames[7].caption, "Expanded 8 tiles");
+        assert_eq!(last_frame.caption, "Short exit path length (O): 4");
+        assert_eq!(last_frame.grid[2][2], '#');
+        assert_eq!(
+            last_frame
+                .grid
+                .iter()
+                .flatten()
+                .filter(|tile| **tile == 'O')
+                .count(),
+            5
+        );
+        assert!(
+            SolverImpl::search_frames(Part::Part2, file)
+                .unwrap()
+                .is_none()
         );
     }
 
-    fn solve_part2(file: &str) {
-        let mut memory_space = MemorySpace::new(file);
+    #[test]
+    fn connectivity_shrinks_as_bytes_fall() {
+        // The first two bytes leave a way around, but the third one walls off the top row.
+        let memory_space = SolverImpl::parse("3\n0\n1,1\n0,1\n2,1\n1,2\n").unwrap();
 
-        println!(
-            "First byte that partitions the start and exit: {:?}",
-            memory_space.find_first_partition_byte()
+        let records: Vec<_> = memory_space
+            .connectivity_over_time()
+            .into_iter()
+            .map(|record| (record.byte, record.reachable_tiles, record.exit_reachable))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                ("1,1".to_string(), 8, true),
+                ("0,1".to_string(), 7, true),
+                ("2,1".to_string(), 3, false),
+                ("1,2".to_string(), 3, false),
+            ]
+        );
+        assert_eq!(
+            memory_space.find_first_partition_byte_by_union_find(),
+            Some(pos!(2, 3))
         );
     }
 }
-
-generate_benchmark!(day18);
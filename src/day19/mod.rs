@@ -1,8 +1,18 @@
+use clap::Subcommand;
+use serde::Serialize;
 use strum::EnumCount;
 use strum_macros::EnumCount;
 
 use crate::trie::{Trie, TrieElement};
-use crate::{solver::Solver, utils::generate_benchmark};
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    output::{Format, write_records},
+    solver::{Answer, Run, Solver},
+    utils::generate_benchmark,
+    viz::Visualize,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, EnumCount)]
 enum Stripe {
@@ -13,15 +23,29 @@ enum Stripe {
     Green = 4,
 }
 
-impl From<u8> for Stripe {
-    fn from(value: u8) -> Self {
+impl TryFrom<char> for Stripe {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'w' => Ok(Self::White),
+            'u' => Ok(Self::Blue),
+            'b' => Ok(Self::Black),
+            'r' => Ok(Self::Red),
+            'g' => Ok(Self::Green),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<&Stripe> for char {
+    fn from(value: &Stripe) -> Self {
         match value {
-            b'w' => Self::White,
-            b'u' => Self::Blue,
-            b'b' => Self::Black,
-            b'r' => Self::Red,
-            b'g' => Self::Green,
-            _ => unreachable!(),
+            Stripe::White => 'w',
+            Stripe::Blue => 'u',
+            Stripe::Black => 'b',
+            Stripe::Red => 'r',
+            Stripe::Green => 'g',
         }
     }
 }
@@ -32,40 +56,48 @@ impl TrieElement for Stripe {
     }
 }
 
-#[derive(Debug)]
-struct TowelManager {
+// Parses the stripes of `pattern`, a slice of the `line`, e.g. a towel pattern or a desired design.
+fn parse_stripes<'a>(
+    line: Line<'a>,
+    pattern: &'a str,
+) -> impl Iterator<Item = Result<Stripe, ParseError>> + 'a {
+    pattern.char_indices().map(move |(i, c)| {
+        Stripe::try_from(c).map_err(|c| {
+            line.error_at(
+                &pattern[i..i + c.len_utf8()],
+                format!("unexpected stripe {c:?}"),
+            )
+        })
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct TowelManager {
     patterns: Trie<Stripe, { Stripe::COUNT }>,
-    desired_designs: Vec<Vec<Stripe>>,
+    // Stripes of all desired designs, one after the other, so that designs don't need an allocation each.
+    desired_designs_stripes: Vec<Stripe>,
+    // Index in `desired_designs_stripes` where each desired design ends.
+    desired_designs_ends: Vec<usize>,
 }
 
 impl TowelManager {
-    fn new(file: &str) -> Self {
-        let mut lines = file.lines();
-        let patterns = lines
-            .next()
-            .unwrap()
-            .split(", ")
-            .map(|pattern| pattern.bytes().map(Stripe::from))
-            .collect();
-
-        // Ignore empty line.
-        lines.next();
-
-        let desired_designs = lines
-            .map(|line| line.bytes().map(Stripe::from).collect())
-            .collect();
-
-        Self {
-            patterns,
-            desired_designs,
-        }
+    fn desired_designs(&self) -> impl Iterator<Item = &[Stripe]> {
+        let starts = std::iter::once(0).chain(self.desired_designs_ends.iter().copied());
+        starts
+            .zip(&self.desired_designs_ends)
+            .map(|(start, &end)| &self.desired_designs_stripes[start..end])
     }
 
-    fn count_all_possible_designs(&mut self, count_unique_designs: bool) -> u64 {
+    fn count_all_possible_designs(&self, count_unique_designs: bool) -> u64 {
         let mut count_possible_designs = 0;
+        // Reused across designs, which are counted one at a time.
+        let mut count_arrangements_reaching_index = Vec::new();
 
-        for design in &self.desired_designs {
-            match self.patterns.count_all_word_arrangements(&design) {
+        for design in self.desired_designs() {
+            match self
+                .patterns
+                .count_all_word_arrangements(design, &mut count_arrangements_reaching_index)
+            {
                 1.. if count_unique_designs => count_possible_designs += 1,
                 count => count_possible_designs += count,
             }
@@ -73,26 +105,155 @@ impl TowelManager {
 
         count_possible_designs
     }
+
+    fn design_reports(&self) -> Vec<DesignReport> {
+        let mut count_arrangements_reaching_index = Vec::new();
+
+        self.desired_designs()
+            .map(|design| DesignReport {
+                design: design.iter().map(char::from).collect(),
+                arrangements: self
+                    .patterns
+                    .count_all_word_arrangements(design, &mut count_arrangements_reaching_index),
+            })
+            .collect()
+    }
+}
+
+/// Number of ways a desired design can be arranged from the towel patterns, which is zero if it's impossible.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DesignReport {
+    pub design: String,
+    pub arrangements: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report the number of arrangements of each desired design, in the order of the input.
+    Designs {
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Designs { format } => {
+                write_records(&SolverImpl::parse(file)?.design_reports(), format)
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
 }
 
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = TowelManager;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let line = lines.next_line()?;
+        let patterns = line
+            .text
+            .split(", ")
+            .map(|pattern| parse_stripes(line, pattern).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .collect();
+
+        let line = lines.next_line()?;
+        if !line.text.is_empty() {
+            return Err(line.error("expected an empty line between patterns and designs"));
+        }
+
+        let mut desired_designs_stripes = Vec::with_capacity(file.len());
+        let mut desired_designs_ends = Vec::new();
+        for line in lines {
+            for stripe in parse_stripes(line, line.text) {
+                desired_designs_stripes.push(stripe?);
+            }
+            desired_designs_ends.push(desired_designs_stripes.len());
+        }
+
+        Ok(TowelManager {
+            patterns,
+            desired_designs_stripes,
+            desired_designs_ends,
+        })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let mut towel_manager = TowelManager::new(file);
-        println!(
-            "The number of possible designs is {}",
-            towel_manager.count_all_possible_designs(true)
-        );
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let towel_manager = Self::parse_shared(file)?;
+        let answer = towel_manager.count_all_possible_designs(true);
+        Ok(Answer::new(
+            format!("The number of possible designs is {answer}"),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let mut towel_manager = TowelManager::new(file);
-        println!(
-            "The number of all possible design arrangements is {}",
-            towel_manager.count_all_possible_designs(false)
-        );
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let towel_manager = Self::parse_shared(file)?;
+        let answer = towel_manager.count_all_possible_designs(false);
+        Ok(Answer::new(
+            format!("The number of all possible design arrangements is {answer}"),
+            answer,
+        ))
     }
 }
 
 generate_benchmark!(day19);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn designs_are_reported_with_their_arrangements() {
+        let towel_manager = SolverImpl::parse(include_str!("example.txt")).unwrap();
+
+        let reports: Vec<_> = towel_manager
+            .design_reports()
+            .into_iter()
+            .map(|report| (report.design, report.arrangements))
+            .collect();
+        assert_eq!(
+            reports,
+            [
+                ("brwrr", 2),
+                ("bggr", 1),
+                ("gbbr", 4),
+                ("rrbgbr", 6),
+                ("ubwu", 0),
+                ("bwurrg", 1),
+                ("brgr", 2),
+                ("bbrgwb", 0),
+            ]
+            .map(|(design, arrangements)| (design.to_string(), arrangements))
+        );
+    }
+}
@@ -1,60 +1,201 @@
-use crate::{solver::Solver, utils::generate_benchmark};
+use std::fmt::Write;
+use std::num::ParseIntError;
+use std::str::FromStr;
 
-pub struct SolverImpl {}
+use itertools::Itertools;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Solver},
+    utils::{generate_benchmark, pairwise_diffs},
+    viz::Visualize,
+};
+
+/// Levels reported by one of the reactor's reports, e.g. parsed from "7 6 4 2 1".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub levels: Vec<i32>,
+}
 
-impl SolverImpl {
-    fn is_safe_level(levels: &[i32]) -> bool {
-        levels
-            .windows(2)
-            .all(|window| (1..=3).contains(&(window[1] - window[0])))
-            || levels
-                .windows(2)
-                .all(|window| (-3..=-1).contains(&(window[1] - window[0])))
+impl Report {
+    /// Whether levels are either all increasing or all decreasing, by 1 to 3 at a time.
+    pub fn is_safe(&self) -> bool {
+        Self::are_safe_levels(&self.levels)
+    }
+
+    /// Whether the report is safe once the Problem Dampener removes at most one bad level.
+    pub fn is_safe_with_dampener(&self) -> bool {
+        // A report without levels has none to remove, and is as safe as it is without the Problem Dampener.
+        if self.levels.is_empty() {
+            return self.is_safe();
+        }
+
+        // Create a copy of the original levels, with the ith level removed.
+        // Note that already safe reports will still be safe if we remove the first level,
+        // hence why we do not need to first check whether a full report is safe before
+        // moving on to spliced report combinations.
+        (0..self.levels.len()).any(|i| {
+            let spliced_levels = [&self.levels[..i], &self.levels[i + 1..]].concat();
+            Self::are_safe_levels(&spliced_levels)
+        })
+    }
+
+    fn are_safe_levels(levels: &[i32]) -> bool {
+        pairwise_diffs(levels.iter().copied()).all(|diff| (1..=3).contains(&diff))
+            || pairwise_diffs(levels.iter().copied()).all(|diff| (-3..=-1).contains(&diff))
     }
 }
 
-impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let mut num_safe_reports = 0;
+impl FromStr for Report {
+    type Err = ParseIntError;
 
-        for line in file.lines() {
-            let levels: Vec<_> = line
-                .split(' ')
-                .map(|level| level.parse::<i32>().unwrap())
-                .collect();
+    /// Parses levels separated by single spaces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            levels: s.split(' ').map(str::parse).collect::<Result<_, _>>()?,
+        })
+    }
+}
 
-            if Self::is_safe_level(&levels) {
-                num_safe_reports += 1;
-            }
-        }
+#[derive(Clone, Debug)]
+pub struct Reports(Vec<Report>);
 
-        println!("Number of safe reports: {num_safe_reports}");
+impl Reports {
+    fn num_safe_reports(&self) -> usize {
+        self.0.iter().filter(|report| report.is_safe()).count()
     }
 
-    fn solve_part2(file: &str) {
-        let mut num_safe_reports = 0;
+    fn num_safe_reports_with_problem_dampener(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|report| report.is_safe_with_dampener())
+            .count()
+    }
+}
 
-        for line in file.lines() {
-            let levels: Vec<_> = line
-                .split(' ')
-                .map(|level| level.parse::<i32>().unwrap())
-                .collect();
+pub struct SolverImpl {}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Reports;
 
-            for i in 0..levels.len() {
-                // Create a copy of the original `levels`, with the ith level removed.
-                // Note that already safe reports will still be safe if we remove the first level,
-                // hence why we do not need to first check whether a full report is safe before
-                // moving on to spliced report combinations.
-                let spliced_levels = [&levels[..i], &levels[i + 1..]].concat();
-                if Self::is_safe_level(&spliced_levels) {
-                    num_safe_reports += 1;
-                    break;
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Lines::new(file)
+            .map(|line| {
+                if line.text.is_empty() {
+                    return Err(line.error("expected a report with at least one level"));
                 }
+                // Each level is parsed on its own, so that errors point at the offending one.
+                let levels = line
+                    .text
+                    .split(' ')
+                    .map(|level| line.parse(level))
+                    .collect::<Result<_, _>>()?;
+                Ok(Report { levels })
+            })
+            .collect::<Result<_, _>>()
+            .map(Reports)
+    }
+}
+
+impl Solver for SolverImpl {
+    type Command = Part;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.num_safe_reports();
+        Ok(Answer::new(
+            format!("Number of safe reports: {answer}"),
+            answer,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.num_safe_reports_with_problem_dampener();
+        Ok(Answer::new(
+            format!("Number of safe reports: {answer}"),
+            answer,
+        ))
+    }
+
+    // Reports of 5 to 8 levels, each either safe or with one or more bad levels.
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        let mut file = String::new();
+        for _ in 0..size {
+            let num_levels = rng.random_range(5..=8);
+            let direction = if rng.random() { 1 } else { -1 };
+            let mut level: i32 = rng.random_range(20..80);
+            let mut levels = vec![level];
+            for _ in 1..num_levels {
+                // Most changes keep the report safe, but some are too large or in the wrong direction.
+                let change = match rng.random_range(0..10) {
+                    0 => rng.random_range(4..=6),
+                    1 => -rng.random_range(0..=3),
+                    _ => rng.random_range(1..=3),
+                };
+                level += direction * change;
+                levels.push(level);
             }
+
+            writeln!(file, "{}", levels.iter().join(" ")).unwrap();
         }
 
-        println!("Number of safe reports: {num_safe_reports}");
+        Ok(file)
     }
 }
 
 generate_benchmark!(day2);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    fn report(levels: &str) -> Report {
+        levels.parse().unwrap()
+    }
+
+    #[test]
+    fn reports_are_checked_like_the_example() {
+        let reports = [
+            ("7 6 4 2 1", true, true),
+            ("1 2 7 8 9", false, false),
+            ("9 7 6 2 1", false, false),
+            ("1 3 2 4 5", false, true),
+            ("8 6 4 4 1", false, true),
+            ("1 3 6 7 9", true, true),
+        ];
+
+        for (levels, is_safe, is_safe_with_dampener) in reports {
+            assert_eq!(report(levels).is_safe(), is_safe, "{levels}");
+            assert_eq!(
+                report(levels).is_safe_with_dampener(),
+                is_safe_with_dampener,
+                "{levels}"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_levels_fail_to_parse() {
+        assert_eq!(report("1 2").levels, [1, 2]);
+        assert!("1  2".parse::<Report>().is_err());
+        assert!("1 x".parse::<Report>().is_err());
+
+        let error = SolverImpl::parse("7 6 4\n1 x 3\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, Some(3)));
+        let error = SolverImpl::parse("7 6 4\n\n1 2\n").unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn reports_without_levels_are_safe_with_or_without_dampener() {
+        let report = Report { levels: Vec::new() };
+        assert!(report.is_safe());
+        assert!(report.is_safe_with_dampener());
+    }
+}
@@ -1,15 +1,539 @@
-use crate::solver::Solver;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use clap::Subcommand;
+use serde::Serialize;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Lines, Parse, ParseError};
+use crate::output::{Format, write_records};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{Position, generate_benchmark, pos};
+use crate::viz::{Frame, LegendEntry, Style, Visualize};
+
+// Maximum number of picoseconds a cheat can last, in each part of the problem.
+const PART1_CHEAT_DURATION: usize = 2;
+const PART2_CHEAT_DURATION: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tile {
+    Track,
+    Wall,
+    Start,
+    End,
+}
+
+impl Tile {
+    fn is_wall(&self) -> bool {
+        *self == Self::Wall
+    }
+}
+
+impl TryFrom<char> for Tile {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            '.' => Ok(Self::Track),
+            '#' => Ok(Self::Wall),
+            'S' => Ok(Self::Start),
+            'E' => Ok(Self::End),
+            _ => Err(value),
+        }
+    }
+}
+
+// A cheat goes through walls from `start` to `end`, which are both on the track.
+#[derive(Clone, Copy, Debug)]
+struct Cheat {
+    start: Position,
+    end: Position,
+    // Number of picoseconds saved compared to following the track.
+    saving: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct RaceTrack {
+    // Positions of the track, in the order they are visited from the start to the end.
+    track: Vec<Position>,
+    // Number of picoseconds needed to reach each position of the map from the start, if it's on the track.
+    steps: Vec<Vec<Option<usize>>>,
+    // Minimum number of picoseconds a cheat must save to be worth it.
+    min_saving: usize,
+}
+
+impl RaceTrack {
+    // Follows the track of the `grid` from its start to its end. Returns None if the track is interrupted.
+    fn new(grid: &[Vec<Tile>], min_saving: usize) -> Option<Self> {
+        let find_tile = |tile| {
+            grid.iter().enumerate().find_map(|(row, tiles)| {
+                tiles
+                    .iter()
+                    .position(|t| *t == tile)
+                    .map(|col| pos!(row, col))
+            })
+        };
+        let start = find_tile(Tile::Start)?;
+        let end = find_tile(Tile::End)?;
+
+        // There is a single path from the start to the end, so simply follow it, never going back to a position
+        // already on the track. The track is interrupted if it reaches the edge of the map or loops back on itself.
+        let (num_rows, num_cols) = (grid.len(), grid.iter().map(Vec::len).max().unwrap_or(0));
+        let mut track = vec![start];
+        let mut steps = vec![vec![None; num_cols]; num_rows];
+        steps[start.row][start.col] = Some(0);
+        while *track.last().unwrap() != end {
+            let next = track
+                .last()
+                .unwrap()
+                .neighbors_by_offsets(&Position::FOUR_WAY_OFFSETS, num_rows, num_cols)
+                .find(|next| {
+                    grid[next.row]
+                        .get(next.col)
+                        .is_some_and(|tile| !tile.is_wall())
+                        && steps[next.row][next.col].is_none()
+                })?;
+
+            steps[next.row][next.col] = Some(track.len());
+            track.push(next);
+        }
+
+        Some(Self {
+            track,
+            steps,
+            min_saving,
+        })
+    }
+
+    // Iterates over all cheats lasting at most `duration` picoseconds that save at least the minimum saving.
+    // Cheats are ordered by their start along the track.
+    fn cheats(&self, duration: usize) -> impl Iterator<Item = Cheat> + '_ {
+        self.track
+            .iter()
+            .enumerate()
+            .flat_map(move |(start_steps, &start)| {
+                self.cheat_ends(start, duration).filter_map(move |end| {
+                    let end_steps = self.steps[end.row][end.col]?;
+                    let cheat_duration = start.row.abs_diff(end.row) + start.col.abs_diff(end.col);
+
+                    // Only cheat forward, and only if it saves enough time.
+                    (end_steps >= start_steps + cheat_duration + self.min_saving).then(|| Cheat {
+                        start,
+                        end,
+                        saving: end_steps - start_steps - cheat_duration,
+                    })
+                })
+            })
+    }
+
+    // All positions of the map within `duration` moves of `start`, ignoring walls.
+    fn cheat_ends(&self, start: Position, duration: usize) -> impl Iterator<Item = Position> + '_ {
+        let duration = duration as isize;
+        let (num_rows, num_cols) = (self.steps.len(), self.steps[0].len());
+
+        (-duration..=duration).flat_map(move |d_row| {
+            let remaining = duration - d_row.abs();
+            (-remaining..=remaining)
+                .filter_map(move |d_col| start.checked_offset((d_row, d_col), num_rows, num_cols))
+        })
+    }
+
+    // Shows the race track, with the start and end of `cheat` marked as 1 and 2.
+    fn frame(&self, cheat: Option<Cheat>, caption: impl Into<String>) -> Frame {
+        let mut grid: Vec<Vec<_>> = self
+            .steps
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|steps| if steps.is_some() { '.' } else { '#' })
+                    .collect()
+            })
+            .collect();
+
+        let marks = [(self.track[0], 'S'), (*self.track.last().unwrap(), 'E')];
+        let cheat_marks = cheat.map(|cheat| [(cheat.start, '1'), (cheat.end, '2')]);
+        for (Position { row, col }, mark) in
+            marks.into_iter().chain(cheat_marks.into_iter().flatten())
+        {
+            grid[row][col] = mark;
+        }
+
+        Frame {
+            grid,
+            caption: caption.into(),
+        }
+    }
+}
+
+/// Question about cheats: how many of them last at most `duration` picoseconds, and save at least `min_saving`
+/// picoseconds. Written as "DURATION:MIN_SAVING", e.g. "20:100".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheatQuery {
+    pub duration: usize,
+    pub min_saving: usize,
+}
+
+impl FromStr for CheatQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected =
+            || format!("expected a duration and a minimum saving, like \"20:100\", got {s:?}");
+        let (duration, min_saving) = s.split_once(':').ok_or_else(expected)?;
+
+        Ok(Self {
+            duration: duration.parse().map_err(|_| expected())?,
+            min_saving: min_saving.parse().map_err(|_| expected())?,
+        })
+    }
+}
+
+/// Answer to a cheat question.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CheatQueryReport {
+    pub duration: usize,
+    pub min_saving: usize,
+    pub cheats: usize,
+}
+
+/// Number of cheats saving a given number of picoseconds, i.e. one bar of the histogram of savings.
+#[derive(Debug, Serialize)]
+pub struct SavingHistogramBin {
+    pub saving: usize,
+    pub cheats: usize,
+}
+
+impl RaceTrack {
+    // Answers all `queries` from a single pass over the cheats lasting as long as the longest query allows. The time
+    // from the start to each tile, and from each tile to the end, are known from following the track once.
+    fn answer_cheat_queries(&self, queries: &[CheatQuery]) -> Vec<CheatQueryReport> {
+        let max_duration = queries
+            .iter()
+            .map(|query| query.duration)
+            .max()
+            .unwrap_or(0);
+        let race_duration = self.track.len() - 1;
+        let mut num_cheats_per_query = vec![0; queries.len()];
+
+        for (steps_from_start, &start) in self.track.iter().enumerate() {
+            for end in self.cheat_ends(start, max_duration) {
+                let Some(end_steps) = self.steps[end.row][end.col] else {
+                    continue;
+                };
+                let steps_to_end = race_duration - end_steps;
+                let cheat_duration = start.row.abs_diff(end.row) + start.col.abs_diff(end.col);
+                // Only cheat forward.
+                let Some(saving) =
+                    race_duration.checked_sub(steps_from_start + cheat_duration + steps_to_end)
+                else {
+                    continue;
+                };
+
+                for (query, num_cheats) in queries.iter().zip(&mut num_cheats_per_query) {
+                    if cheat_duration <= query.duration && saving >= query.min_saving {
+                        *num_cheats += 1;
+                    }
+                }
+            }
+        }
+
+        queries
+            .iter()
+            .zip(num_cheats_per_query)
+            .map(|(query, cheats)| CheatQueryReport {
+                duration: query.duration,
+                min_saving: query.min_saving,
+                cheats,
+            })
+            .collect()
+    }
+
+    // Histogram of the savings of all cheats worth it, by increasing saving.
+    fn saving_histogram(&self, duration: usize) -> Vec<SavingHistogramBin> {
+        let mut num_cheats_per_saving = BTreeMap::new();
+        for cheat in self.cheats(duration) {
+            *num_cheats_per_saving.entry(cheat.saving).or_default() += 1;
+        }
+
+        num_cheats_per_saving
+            .into_iter()
+            .map(|(saving, cheats)| SavingHistogramBin { saving, cheats })
+            .collect()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// List all cheats saving at least the minimum saving, as CSV rows of their start and end (row, column)
+    /// positions on the map, and the number of picoseconds they save.
+    Cheats {
+        #[arg(short, long, default_value_t = PART1_CHEAT_DURATION)]
+        /// Maximum number of picoseconds a cheat can last.
+        duration: usize,
+
+        #[arg(short, long)]
+        /// Minimum number of picoseconds a cheat must save. Defaults to the one from the input.
+        min_saving: Option<usize>,
+    },
+    /// Report how many cheats save each number of picoseconds, for cheats saving at least the minimum saving.
+    Histogram {
+        #[arg(short, long, default_value_t = PART1_CHEAT_DURATION)]
+        /// Maximum number of picoseconds a cheat can last.
+        duration: usize,
+
+        #[arg(short, long)]
+        /// Minimum number of picoseconds a cheat must save. Defaults to the one from the input.
+        min_saving: Option<usize>,
+
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+    /// Answer several questions about cheats at once, from a single pass over the track, e.g. the questions of both
+    /// parts.
+    Report {
+        #[arg(short, long = "query")]
+        /// Maximum duration and minimum saving of the cheats to count, like "20:100". Can be repeated. Defaults to the
+        /// questions of both parts, with the minimum saving from the input.
+        queries: Vec<CheatQuery>,
+
+        #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Cheats {
+                duration,
+                min_saving,
+            } => {
+                let mut race_track = SolverImpl::parse(file)?;
+                if let Some(min_saving) = min_saving {
+                    race_track.min_saving = min_saving;
+                }
+                println!("start_row,start_col,end_row,end_col,saving");
+                for Cheat { start, end, saving } in race_track.cheats(duration) {
+                    println!(
+                        "{},{},{},{},{saving}",
+                        start.row, start.col, end.row, end.col
+                    );
+                }
+            }
+            Self::Histogram {
+                duration,
+                min_saving,
+                format,
+            } => {
+                let mut race_track = SolverImpl::parse(file)?;
+                if let Some(min_saving) = min_saving {
+                    race_track.min_saving = min_saving;
+                }
+                write_records(&race_track.saving_histogram(duration), format)?;
+            }
+            Self::Report {
+                mut queries,
+                format,
+            } => {
+                let race_track = SolverImpl::parse(file)?;
+                if queries.is_empty() {
+                    queries = [PART1_CHEAT_DURATION, PART2_CHEAT_DURATION]
+                        .map(|duration| CheatQuery {
+                            duration,
+                            min_saving: race_track.min_saving,
+                        })
+                        .to_vec();
+                }
+                write_records(&race_track.answer_cheat_queries(&queries), format)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 pub struct SolverImpl {}
 
+impl Parse for SolverImpl {
+    type Input = RaceTrack;
+
+    // The first line of the input is the minimum saving of cheats, as the examples use a different one than the
+    // actual problem. The map of the race track follows.
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let line = lines.next_line()?;
+        let min_saving = line.parse(line.text)?;
+        let grid: Vec<Vec<_>> = lines
+            .by_ref()
+            .map(|line| line.parse_chars())
+            .collect::<Result<_, _>>()?;
+
+        RaceTrack::new(&grid, min_saving).ok_or_else(|| {
+            lines.error_at_end("there must be a single track from the start to the end")
+        })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let race_track = Self::parse_shared(file)?;
+        let num_cheats = race_track.cheats(PART1_CHEAT_DURATION).count();
+        Ok(Answer::new(
+            format!(
+                "Number of cheats saving at least {} picoseconds: {num_cheats}",
+                race_track.min_saving
+            ),
+            num_cheats,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let race_track = Self::parse_shared(file)?;
+        let num_cheats = race_track.cheats(PART2_CHEAT_DURATION).count();
+        Ok(Answer::new(
+            format!(
+                "Number of longer cheats saving at least {} picoseconds: {num_cheats}",
+                race_track.min_saving
+            ),
+            num_cheats,
+        ))
+    }
+}
+
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[
+        LegendEntry::new('#', "wall", Style::Wall),
+        LegendEntry::new('S', "start", Style::Actor),
+        LegendEntry::new('E', "end", Style::Actor),
+        LegendEntry::new('1', "start of the cheat", Style::Highlight),
+        LegendEntry::new('2', "end of the cheat", Style::Highlight),
+    ];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let race_track = Self::parse(file)?;
+        let duration = match part {
+            Part::Part1 => PART1_CHEAT_DURATION,
+            Part::Part2 => PART2_CHEAT_DURATION,
+        };
+
+        // Show the cheat saving the most time.
+        let mut num_cheats = 0;
+        let best_cheat = race_track
+            .cheats(duration)
+            .inspect(|_| num_cheats += 1)
+            .max_by_key(|cheat| cheat.saving);
+        let caption = match best_cheat {
+            Some(cheat) => format!(
+                "Number of cheats saving at least {} picoseconds: {num_cheats}, the best one (1 -> 2) saves {}",
+                race_track.min_saving, cheat.saving
+            ),
+            None => format!(
+                "No cheat saves at least {} picoseconds",
+                race_track.min_saving
+            ),
+        };
+
+        Ok(Some(Box::new(std::iter::once(
+            race_track.frame(best_cheat, caption),
+        ))))
+    }
+}
+
+generate_benchmark!(day20);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_answered_in_a_single_pass() {
+        let race_track = SolverImpl::parse(include_str!("example.txt")).unwrap();
+        let queries = [
+            CheatQuery {
+                duration: 2,
+                min_saving: 64,
+            },
+            CheatQuery {
+                duration: 2,
+                min_saving: 20,
+            },
+            CheatQuery {
+                duration: 20,
+                min_saving: 76,
+            },
+            CheatQuery {
+                duration: 20,
+                min_saving: 50,
+            },
+        ];
+
+        let num_cheats: Vec<_> = race_track
+            .answer_cheat_queries(&queries)
+            .into_iter()
+            .map(|report| report.cheats)
+            .collect();
+        // Counts from the puzzle's description.
+        assert_eq!(num_cheats, [1, 5, 3, 285]);
+        for (query, num_cheats) in queries.iter().zip(num_cheats) {
+            let mut race_track = race_track.clone();
+            race_track.min_saving = query.min_saving;
+            assert_eq!(race_track.cheats(query.duration).count(), num_cheats);
+        }
+    }
+
+    #[test]
+    fn queries_are_parsed() {
+        assert_eq!(
+            "20:100".parse(),
+            Ok(CheatQuery {
+                duration: 20,
+                min_saving: 100
+            })
+        );
+        assert!("20".parse::<CheatQuery>().is_err());
+        assert!("a:1".parse::<CheatQuery>().is_err());
+    }
+
+    #[test]
+    fn tracks_may_reach_the_edge_of_the_map() {
+        let race_track = SolverImpl::parse("1\n#S#\n#.#\n#E#").unwrap();
+        assert_eq!(race_track.track, [pos!(0, 1), pos!(1, 1), pos!(2, 1)]);
     }
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    #[test]
+    fn interrupted_tracks_fail_to_parse() {
+        // Leaves the map, loops without reaching the end, and is blocked by walls.
+        for map in [
+            "#S.\n###\n#E#",
+            "#####\n#S..#\n#...#\n#####\n#E###",
+            "###\n#S#\n###\n#E#",
+        ] {
+            let error = SolverImpl::parse(&format!("1\n{map}")).unwrap_err();
+            assert!(error.to_string().contains("single track"), "{error}");
+        }
     }
 }
@@ -1,15 +1,406 @@
-use crate::solver::Solver;
+use std::str::FromStr;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use smallvec::SmallVec;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Line, Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{FxHashMap, Position, generate_benchmark, pos};
+use crate::viz::Visualize;
+
+// Number of directional keypads operated by robots, between the numeric keypad and the one I'm pressing.
+const PART1_NUM_ROBOTS: usize = 2;
+const PART2_NUM_ROBOTS: usize = 25;
+
+// Key on which every robot arm starts, and which must be pressed after each move.
+const ACTIVATE: u8 = b'A';
+// Position of a keypad which has no key, and over which arms must never go.
+const GAP: u8 = b' ';
+
+struct Keypad(&'static [&'static [u8]]);
+
+const NUMERIC_KEYPAD: Keypad = Keypad(&[b"789", b"456", b"123", b" 0A"]);
+const DIRECTIONAL_KEYPAD: Keypad = Keypad(&[b" ^A", b"<v>"]);
+
+impl Keypad {
+    fn position(&self, key: u8) -> Position {
+        self.0
+            .iter()
+            .enumerate()
+            .find_map(|(row, keys)| {
+                keys.iter()
+                    .position(|k| *k == key)
+                    .map(|col| pos!(row, col))
+            })
+            .unwrap()
+    }
+
+    // Sequences of directional keys moving the arm from `from` to `to`, then pressing it. Only sequences making all
+    // horizontal moves then all vertical moves, or vice versa, are considered: interleaving them can't be shorter
+    // at the next keypad, as its arm would have to go back and forth instead of pressing the same key repeatedly.
+    fn moves(&self, from: u8, to: u8) -> SmallVec<[Vec<u8>; 2]> {
+        let (from, to, gap) = (self.position(from), self.position(to), self.position(GAP));
+        let horizontal_key = if to.col < from.col { b'<' } else { b'>' };
+        let vertical_key = if to.row < from.row { b'^' } else { b'v' };
+        let horizontal = std::iter::repeat_n(horizontal_key, from.col.abs_diff(to.col));
+        let vertical = std::iter::repeat_n(vertical_key, from.row.abs_diff(to.row));
+
+        let mut moves = SmallVec::new();
+        if pos!(from.row, to.col) != gap {
+            moves.push(
+                horizontal
+                    .clone()
+                    .chain(vertical.clone())
+                    .chain([ACTIVATE])
+                    .collect(),
+            );
+        }
+        // Both orders are the same if the arm only moves in one direction.
+        if pos!(to.row, from.col) != gap && from.row != to.row && from.col != to.col {
+            moves.push(vertical.chain(horizontal).chain([ACTIVATE]).collect());
+        }
+
+        moves
+    }
+}
+
+/// Cost of pressing each key of the directional keypad I'm pressing, e.g. to penalize `<` which is far from the
+/// other keys. With the default costs of 1, the cost of a sequence of presses is its length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PressCosts {
+    pub up: u64,
+    pub down: u64,
+    pub left: u64,
+    pub right: u64,
+    pub activate: u64,
+}
+
+impl PressCosts {
+    fn cost(&self, key: u8) -> u64 {
+        match key {
+            b'^' => self.up,
+            b'v' => self.down,
+            b'<' => self.left,
+            b'>' => self.right,
+            ACTIVATE => self.activate,
+            _ => unreachable!("{} isn't a directional key", key as char),
+        }
+    }
+
+    fn cost_mut(&mut self, key: &str) -> Option<&mut u64> {
+        match key {
+            "^" => Some(&mut self.up),
+            "v" => Some(&mut self.down),
+            "<" => Some(&mut self.left),
+            ">" => Some(&mut self.right),
+            "A" => Some(&mut self.activate),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PressCosts {
+    fn default() -> Self {
+        Self {
+            up: 1,
+            down: 1,
+            left: 1,
+            right: 1,
+            activate: 1,
+        }
+    }
+}
+
+impl FromStr for PressCosts {
+    type Err = String;
+
+    // Parses comma-separated costs like "<=3,v=2". Keys which aren't listed cost 1.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut costs = Self::default();
+        for key_cost in s.split(',') {
+            let (key, cost) = key_cost.split_once('=').ok_or_else(|| {
+                format!("expected a key and its cost, like \"<=3\", got {key_cost:?}")
+            })?;
+            let cost = cost.parse().map_err(|_| {
+                format!("expected the cost of {key:?} to be a number, got {cost:?}")
+            })?;
+            *costs
+                .cost_mut(key)
+                .ok_or_else(|| format!("expected one of ^, v, <, > or A, got {key:?}"))? = cost;
+        }
+
+        Ok(costs)
+    }
+}
+
+// Finds the cheapest presses I must make so that the robots type codes. The cost of a given move on a directional
+// keypad only depends on how many robots are left after it, so it's memoized. The cache is shared across codes, which
+// all go through the same few moves.
+#[derive(Debug, Default)]
+struct KeypadChain {
+    costs: PressCosts,
+    // Maps a (from, to, number of robots) move to the cost of its presses.
+    cache: FxHashMap<(u8, u8, usize), u64>,
+    num_cache_hits: u64,
+    num_cache_misses: u64,
+}
+
+impl KeypadChain {
+    fn with_costs(costs: PressCosts) -> Self {
+        Self {
+            costs,
+            ..Self::default()
+        }
+    }
+
+    // Cost of the cheapest presses needed for a robot to type `code` on the numeric keypad, through `num_robots`
+    // directional keypads operated by robots.
+    fn code_cost(&mut self, code: &[u8], num_robots: usize) -> u64 {
+        std::iter::once(ACTIVATE)
+            .chain(code.iter().copied())
+            .tuple_windows()
+            .map(|(from, to)| {
+                NUMERIC_KEYPAD
+                    .moves(from, to)
+                    .iter()
+                    .map(|sequence| self.sequence_cost(sequence, num_robots))
+                    .min()
+                    .unwrap()
+            })
+            .sum()
+    }
+
+    // Cost of the cheapest presses needed to type `sequence` on a directional keypad, with `num_robots` left to
+    // operate.
+    fn sequence_cost(&mut self, sequence: &[u8], num_robots: usize) -> u64 {
+        if num_robots == 0 {
+            // I'm the one pressing this keypad.
+            return sequence.iter().map(|key| self.costs.cost(*key)).sum();
+        }
+
+        std::iter::once(ACTIVATE)
+            .chain(sequence.iter().copied())
+            .tuple_windows()
+            .map(|(from, to)| self.move_cost(from, to, num_robots))
+            .sum()
+    }
+
+    fn move_cost(&mut self, from: u8, to: u8, num_robots: usize) -> u64 {
+        if let Some(cost) = self.cache.get(&(from, to, num_robots)) {
+            self.num_cache_hits += 1;
+            return *cost;
+        }
+        self.num_cache_misses += 1;
+
+        let cost = DIRECTIONAL_KEYPAD
+            .moves(from, to)
+            .iter()
+            .map(|sequence| self.sequence_cost(sequence, num_robots - 1))
+            .min()
+            .unwrap();
+        self.cache.insert((from, to, num_robots), cost);
+
+        cost
+    }
+
+    // Sum of the complexities of all codes, i.e. the cost of their presses multiplied by their numeric part. With the
+    // default costs, that's their number of presses.
+    fn sum_complexities(&mut self, codes: &[Code], num_robots: usize) -> u64 {
+        codes
+            .iter()
+            .map(|code| self.code_cost(code.keys.as_bytes(), num_robots) * code.numeric_part)
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Code {
+    // Keys of the numeric keypad to press, e.g. "029A".
+    keys: String,
+    numeric_part: u64,
+}
+
+// Parses a line like "029A" into a code, made of digits pressed before activating.
+fn parse_code(line: Line) -> Result<Code, ParseError> {
+    let digits = line
+        .text
+        .strip_suffix(ACTIVATE as char)
+        .ok_or_else(|| line.error("expected the code to end with \"A\""))?;
+    if digits.is_empty() || !digits.bytes().all(|key| key.is_ascii_digit()) {
+        return Err(line.error_at(digits, "expected digits before \"A\""));
+    }
+
+    Ok(Code {
+        keys: line.text.to_owned(),
+        numeric_part: line.parse(digits)?,
+    })
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report how the memoization cache is used while typing each code.
+    Stats {
+        #[arg(short, long, default_value_t = PART2_NUM_ROBOTS)]
+        /// Number of directional keypads operated by robots.
+        robots: usize,
+    },
+    /// Find the cheapest presses to type each code, when some keys of my directional keypad cost more to press.
+    Costs {
+        #[arg(short, long, default_value_t = PART2_NUM_ROBOTS)]
+        /// Number of directional keypads operated by robots.
+        robots: usize,
+
+        #[arg(short, long)]
+        /// Cost of pressing keys of my directional keypad, like "<=3,v=2". Keys which aren't listed cost 1.
+        costs: PressCosts,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Stats { robots } => {
+                let mut keypad_chain = KeypadChain::default();
+                for Code { keys, .. } in SolverImpl::parse(file)? {
+                    let (num_hits, num_misses) =
+                        (keypad_chain.num_cache_hits, keypad_chain.num_cache_misses);
+                    let num_presses = keypad_chain.code_cost(keys.as_bytes(), robots);
+                    println!(
+                        "{keys}: {num_presses} presses, {} cache hits, {} cache misses",
+                        keypad_chain.num_cache_hits - num_hits,
+                        keypad_chain.num_cache_misses - num_misses
+                    );
+                }
+
+                let num_lookups = keypad_chain.num_cache_hits + keypad_chain.num_cache_misses;
+                println!(
+                    "The cache holds {} entries, and {:.1}% of its {num_lookups} lookups were hits",
+                    keypad_chain.cache.len(),
+                    keypad_chain.num_cache_hits as f64 * 100.0 / num_lookups.max(1) as f64
+                );
+            }
+            Self::Costs { robots, costs } => {
+                let codes = SolverImpl::parse(file)?;
+                let mut keypad_chain = KeypadChain::with_costs(costs);
+                for Code { keys, .. } in &codes {
+                    println!(
+                        "{keys}: {}",
+                        keypad_chain.code_cost(keys.as_bytes(), robots)
+                    );
+                }
+                println!(
+                    "Sum of complexities of the codes: {}",
+                    SolverImpl::sum_complexities_with_costs(&codes, robots, costs)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 pub struct SolverImpl {}
 
+impl SolverImpl {
+    /// Sum of the complexities of `codes`, typed through `num_robots` directional keypads operated by robots, when my
+    /// presses cost `costs` rather than 1 each.
+    pub fn sum_complexities_with_costs(
+        codes: &[Code],
+        num_robots: usize,
+        costs: PressCosts,
+    ) -> u64 {
+        KeypadChain::with_costs(costs).sum_complexities(codes, num_robots)
+    }
+}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Vec<Code>;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Lines::new(file).map(parse_code).collect()
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let codes = Self::parse_shared(file)?;
+        let answer = KeypadChain::default().sum_complexities(&codes, PART1_NUM_ROBOTS);
+        Ok(Answer::new(
+            format!("Sum of complexities of the codes: {answer}"),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let codes = Self::parse_shared(file)?;
+        let answer = KeypadChain::default().sum_complexities(&codes, PART2_NUM_ROBOTS);
+        Ok(Answer::new(
+            format!("Sum of complexities of the codes with more robots: {answer}"),
+            answer,
+        ))
+    }
+}
+
+generate_benchmark!(day21);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn default_costs_count_presses() {
+        let codes = SolverImpl::parse(include_str!("example.txt")).unwrap();
+
+        assert_eq!(
+            SolverImpl::sum_complexities_with_costs(
+                &codes,
+                PART1_NUM_ROBOTS,
+                PressCosts::default()
+            ),
+            126384
+        );
+        assert_eq!("A=1".parse(), Ok(PressCosts::default()));
+    }
+
+    #[test]
+    fn costly_keys_are_avoided() {
+        let costs = "<=10".parse().unwrap();
+        assert_eq!(KeypadChain::with_costs(costs).sequence_cost(b"<v<A", 0), 22);
+
+        // Uniform costs scale the cost of every sequence.
+        let costs = "^=3,v=3,<=3,>=3,A=3".parse().unwrap();
+        assert_eq!(KeypadChain::with_costs(costs).code_cost(b"029A", 2), 3 * 68);
+        // Penalizing `<` can't make typing cheaper, and must cost more as every code needs it.
+        let costs = "<=5".parse().unwrap();
+        assert!(KeypadChain::with_costs(costs).code_cost(b"029A", 2) > 68);
+
+        assert!("<".parse::<PressCosts>().is_err());
+        assert!("x=1".parse::<PressCosts>().is_err());
+        assert!("<=a".parse::<PressCosts>().is_err());
     }
 }
@@ -1,15 +1,430 @@
-use crate::solver::Solver;
+use clap::Subcommand;
+use itertools::Itertools;
+use rand::Rng;
+use rand::rngs::StdRng;
+use serde::Serialize;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Lines, Parse, ParseError};
+use crate::output::{Format, write_records};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{generate_benchmark, windows_map};
+use crate::viz::Visualize;
+
+// Number of new secret numbers each buyer generates in a day.
+const NUM_SECRETS: usize = 2000;
+const PRUNE_MODULO: u64 = 16777216;
+
+// Next secret number of the pseudorandom sequence.
+fn next_secret(mut secret: u64) -> u64 {
+    secret = ((secret * 64) ^ secret) % PRUNE_MODULO;
+    secret = ((secret / 32) ^ secret) % PRUNE_MODULO;
+    ((secret * 2048) ^ secret) % PRUNE_MODULO
+}
+
+// Initial secret number of each buyer, followed by the ones they generate.
+fn secrets(initial_secret: u64) -> impl Iterator<Item = u64> {
+    std::iter::successors(Some(initial_secret), |secret| Some(next_secret(*secret)))
+        .take(NUM_SECRETS + 1)
+}
+
+// Number of bits of secret numbers, as they're pruned modulo 2^24.
+const NUM_SECRET_BITS: usize = 24;
+
+// Generating `num_iterations` new secrets at once, for any initial secret. Each step only shifts, xors and keeps the
+// lowest bits, so it's linear over bits: a secret is mapped to the xor of what each of its set bits maps to. Those
+// images are found for a huge number of iterations by squaring the step repeatedly, in as many steps as the number
+// has bits, and are shared by all buyers.
+struct SecretJump {
+    // Secret reached from each power of two.
+    bit_images: [u64; NUM_SECRET_BITS],
+}
+
+impl SecretJump {
+    fn new(mut num_iterations: u64) -> Self {
+        let identity = Self {
+            bit_images: std::array::from_fn(|bit| 1 << bit),
+        };
+        let mut step = Self {
+            bit_images: std::array::from_fn(|bit| next_secret(1 << bit)),
+        };
+        let mut jump = identity;
+        while num_iterations > 0 {
+            if num_iterations & 1 == 1 {
+                jump = jump.then(&step);
+            }
+            step = step.then(&step);
+            num_iterations >>= 1;
+        }
+
+        jump
+    }
+
+    fn apply(&self, secret: u64) -> u64 {
+        self.bit_images
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| secret >> bit & 1 == 1)
+            .fold(0, |image, (_, bit_image)| image ^ bit_image)
+    }
+
+    // This jump followed by the `other` one.
+    fn then(&self, other: &Self) -> Self {
+        Self {
+            bit_images: self.bit_images.map(|bit_image| other.apply(bit_image)),
+        }
+    }
+}
+
+// Four consecutive price changes, after which the monkey sells.
+type ChangeSequence = [i8; 4];
+
+// Prices are single digits, so each price change is one of 19 values, from -9 to 9.
+const NUM_PRICE_CHANGES: usize = 19;
+const NUM_CHANGE_SEQUENCES: usize = NUM_PRICE_CHANGES.pow(4);
+
+// Change sequence packed as a base-19 number, to index flat tables of all change sequences. Packing preserves the
+// order of change sequences.
+fn pack(change_sequence: ChangeSequence) -> usize {
+    change_sequence.iter().fold(0, |key, change| {
+        key * NUM_PRICE_CHANGES + (change + 9) as usize
+    })
+}
+
+fn unpack(mut key: usize) -> ChangeSequence {
+    let mut change_sequence = [0; 4];
+    for change in change_sequence.iter_mut().rev() {
+        *change = (key % NUM_PRICE_CHANGES) as i8 - 9;
+        key /= NUM_PRICE_CHANGES;
+    }
+
+    change_sequence
+}
+
+// Totals of a change sequence, across all buyers. A few thousand buyers with single digit prices fit in 32 bits.
+#[derive(Clone, Copy, Debug, Default)]
+struct Sale {
+    num_bananas: u32,
+    num_buyers: u32,
+}
+
+// Each sequence of four price changes of a buyer, along with the price after it.
+fn change_sequences(initial_secret: u64) -> impl Iterator<Item = (ChangeSequence, i8)> {
+    // Prices are the ones digit of secret numbers.
+    let prices = secrets(initial_secret).map(|secret| (secret % 10) as i8);
+    windows_map(prices, |[a, b, c, d, e]| ([b - a, c - b, d - c, e - d], e))
+}
+
+// Finds how many bananas each change sequence gets, when each buyer sells at the first time it occurs. Sales are
+// indexed by packed change sequence.
+fn sales_per_change_sequence(initial_secrets: &[u64]) -> Vec<Sale> {
+    let mut sales = vec![Sale::default(); NUM_CHANGE_SEQUENCES];
+    // Bit per packed change sequence, set once a buyer went through it.
+    let mut seen_change_sequences = vec![0u64; NUM_CHANGE_SEQUENCES.div_ceil(64)];
+
+    for initial_secret in initial_secrets {
+        seen_change_sequences.fill(0);
+
+        for (change_sequence, price) in change_sequences(*initial_secret) {
+            let key = pack(change_sequence);
+            let (word, bit) = (key / 64, 1 << (key % 64));
+            // The monkey sells the first time the sequence occurs for each buyer.
+            if seen_change_sequences[word] & bit == 0 {
+                seen_change_sequences[word] |= bit;
+                let sale = &mut sales[key];
+                sale.num_bananas += price as u32;
+                sale.num_buyers += 1;
+            }
+        }
+    }
+
+    sales
+}
+
+// Change sequence getting the most bananas. Ties are broken by the smallest sequence, so the result is stable.
+fn best_change_sequence(initial_secrets: &[u64]) -> Option<(ChangeSequence, Sale)> {
+    sales_per_change_sequence(initial_secrets)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, sale)| sale.num_buyers > 0)
+        .max_by_key(|(key, sale)| (sale.num_bananas, std::cmp::Reverse(*key)))
+        .map(|(key, sale)| (unpack(key), sale))
+}
+
+/// Best change sequence to tell the monkey, and what it gets.
+#[derive(Debug, Serialize)]
+pub struct BestSequenceReport {
+    /// Comma-separated price changes, e.g. "-2,1,-1,3".
+    pub changes: String,
+    pub bananas: u64,
+    /// Number of buyers whose prices go through the sequence, and who therefore sell.
+    pub buyers: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Report the best sequence of price changes, the bananas it gets, and how many buyers sell with it.
+    BestSequence {
+        #[arg(short, long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+    /// Sum the secret numbers of all buyers after any number of iterations, jumping over them rather than generating
+    /// each secret, so that even absurd numbers of iterations complete instantly.
+    Secrets {
+        #[arg(short = 'n', long, default_value_t = NUM_SECRETS as u64)]
+        /// Number of new secret numbers each buyer generates.
+        iterations: u64,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::BestSequence { format } => {
+                let (change_sequence, sale) = best_change_sequence(&SolverImpl::parse(file)?)
+                    .ok_or_else(|| AocError::NoSolution(String::from("there are no buyers")))?;
+                let report = BestSequenceReport {
+                    changes: change_sequence.iter().join(","),
+                    bananas: sale.num_bananas.into(),
+                    buyers: sale.num_buyers as usize,
+                };
+                write_records(&[report], format)?;
+            }
+            Self::Secrets { iterations } => {
+                let jump = SecretJump::new(iterations);
+                let sum: u64 = SolverImpl::parse(file)?
+                    .into_iter()
+                    .map(|initial_secret| jump.apply(initial_secret))
+                    .sum();
+                println!("Sum of the secret numbers after {iterations} iterations: {sum}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    // Initial secret number of each buyer.
+    type Input = Vec<u64>;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Lines::new(file)
+            .map(|line| line.parse_decimal(line.text.as_bytes()))
+            .collect()
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let sum: u64 = Self::parse_shared(file)?
+            .into_iter()
+            .map(|initial_secret| secrets(initial_secret).last().unwrap())
+            .sum();
+        Ok(Answer::new(
+            format!("Sum of the {NUM_SECRETS}th secret numbers: {sum}"),
+            sum,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let num_bananas = best_change_sequence(&Self::parse_shared(file)?)
+            .map_or(0, |(_, sale)| sale.num_bananas);
+        Ok(Answer::new(
+            format!("Most bananas that can be bought: {num_bananas}"),
+            num_bananas,
+        ))
+    }
+
+    // Initial secrets are pseudorandom anyway, so they're replaced with new ones.
+    fn scrub(file: &str, rng: &mut StdRng) -> Result<String, AocError> {
+        Ok(Self::parse(file)?
+            .iter()
+            .map(|_| format!("{}\n", rng.random_range(1..PRUNE_MODULO)))
+            .collect())
+    }
+
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        Ok((0..size)
+            .map(|_| format!("{}\n", rng.random_range(1..PRUNE_MODULO)))
+            .collect())
+    }
+}
+
+generate_benchmark!(day22);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn jumping_finds_the_same_secrets() {
+        let initial_secrets = [1, 10, 100, 2024, 123];
+        let mut secrets = initial_secrets;
+        for num_iterations in 0..=NUM_SECRETS as u64 {
+            let jump = SecretJump::new(num_iterations);
+            for (initial_secret, secret) in initial_secrets.iter().zip(&mut secrets) {
+                assert_eq!(jump.apply(*initial_secret), *secret);
+                *secret = next_secret(*secret);
+            }
+        }
+        assert_eq!(SecretJump::new(10).apply(123), 5908254);
+        // Nonzero secrets go through all 2^24 - 1 nonzero values before coming back.
+        let jump = SecretJump::new(u64::MAX);
+        assert_eq!(
+            jump.apply(123),
+            SecretJump::new(u64::MAX % (PRUNE_MODULO - 1)).apply(123)
+        );
+        assert_eq!(jump.apply(0), 0);
+    }
+}
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod hasher_benches {
+    use rand::SeedableRng;
+    use test::Bencher;
+
+    use super::*;
+    use crate::utils::FxHashMap;
+
+    // Real inputs have about as many buyers.
+    const NUM_BUYERS: usize = 2000;
+
+    // Change sequences of generated buyers, in the order part 2 hashes them.
+    fn change_sequences_of_buyers() -> Vec<(ChangeSequence, i8)> {
+        let file = SolverImpl::generate(NUM_BUYERS, &mut StdRng::seed_from_u64(22)).unwrap();
+        SolverImpl::parse(&file)
+            .unwrap()
+            .into_iter()
+            .flat_map(change_sequences)
+            .collect()
     }
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    #[bench]
+    fn bench_day22_sales_default_hasher(b: &mut Bencher) {
+        let change_sequences = change_sequences_of_buyers();
+
+        b.iter(|| {
+            let mut sales = hashbrown::HashMap::<_, u64>::new();
+            for (change_sequence, price) in &change_sequences {
+                *sales.entry(*change_sequence).or_default() += *price as u64;
+            }
+            sales.len()
+        });
+    }
+
+    #[bench]
+    fn bench_day22_sales_fx_hasher(b: &mut Bencher) {
+        let change_sequences = change_sequences_of_buyers();
+
+        b.iter(|| {
+            let mut sales = FxHashMap::<_, u64>::default();
+            for (change_sequence, price) in &change_sequences {
+                *sales.entry(*change_sequence).or_default() += *price as u64;
+            }
+            sales.len()
+        });
+    }
+}
+
+#[cfg(test)]
+mod packing_benches {
+    use rand::SeedableRng;
+    #[cfg(feature = "nightly-bench")]
+    use test::Bencher;
+
+    use super::*;
+    use crate::utils::{FxHashMap, FxHashSet};
+
+    // Real inputs have about as many buyers.
+    const NUM_BUYERS: usize = 2000;
+
+    fn initial_secrets() -> Vec<u64> {
+        let file = SolverImpl::generate(NUM_BUYERS, &mut StdRng::seed_from_u64(22)).unwrap();
+        SolverImpl::parse(&file).unwrap()
+    }
+
+    // How sales were found before change sequences were packed, keyed by change sequence in hash maps.
+    fn sales_per_change_sequence_by_hashing(
+        initial_secrets: &[u64],
+    ) -> FxHashMap<ChangeSequence, Sale> {
+        let mut sales: FxHashMap<ChangeSequence, Sale> = FxHashMap::default();
+        let mut seen_change_sequences = FxHashSet::default();
+
+        for initial_secret in initial_secrets {
+            seen_change_sequences.clear();
+
+            for (change_sequence, price) in change_sequences(*initial_secret) {
+                if seen_change_sequences.insert(change_sequence) {
+                    let sale = sales.entry(change_sequence).or_default();
+                    sale.num_bananas += price as u32;
+                    sale.num_buyers += 1;
+                }
+            }
+        }
+
+        sales
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_day22_sales_hashed(b: &mut Bencher) {
+        let initial_secrets = initial_secrets();
+
+        b.iter(|| sales_per_change_sequence_by_hashing(&initial_secrets).len());
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_day22_sales_packed(b: &mut Bencher) {
+        let initial_secrets = initial_secrets();
+
+        b.iter(|| sales_per_change_sequence(&initial_secrets).len());
+    }
+
+    #[test]
+    fn packing_finds_the_same_sales_as_hashing() {
+        let initial_secrets = initial_secrets();
+        let packed_sales = sales_per_change_sequence(&initial_secrets);
+        let hashed_sales = sales_per_change_sequence_by_hashing(&initial_secrets);
+
+        assert_eq!(
+            packed_sales
+                .iter()
+                .filter(|sale| sale.num_buyers > 0)
+                .count(),
+            hashed_sales.len()
+        );
+        for (change_sequence, sale) in hashed_sales {
+            let key = pack(change_sequence);
+            assert_eq!(unpack(key), change_sequence);
+            assert_eq!(packed_sales[key].num_bananas, sale.num_bananas);
+            assert_eq!(packed_sales[key].num_buyers, sale.num_buyers);
+        }
     }
 }
@@ -1,15 +1,509 @@
-use crate::solver::Solver;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+
+use crate::graph::Graph;
+use crate::output::{Format, write_records};
+use crate::solver::{Answer, Run};
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    solver::Solver,
+    utils::generate_benchmark,
+    viz::Visualize,
+};
+
+// Computers of interest for the first part of the problem have a name starting with this letter.
+const HISTORIAN_COMPUTER_PREFIX: u8 = b't';
+// Computer names are made of two lowercase letters.
+const MAX_NUM_COMPUTERS: usize = 26 * 26;
+const NUM_COMPUTER_SET_WORDS: usize = MAX_NUM_COMPUTERS.div_ceil(u64::BITS as usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Computer([u8; 2]);
+
+impl Computer {
+    fn from_index(index: usize) -> Self {
+        Self([b'a' + (index / 26) as u8, b'a' + (index % 26) as u8])
+    }
+
+    // Unique index in [0:MAX_NUM_COMPUTERS[, which preserves the ordering of computers.
+    fn index(&self) -> usize {
+        (self.0[0] - b'a') as usize * 26 + (self.0[1] - b'a') as usize
+    }
+
+    fn may_be_historian(&self) -> bool {
+        self.0[0] == HISTORIAN_COMPUTER_PREFIX
+    }
+}
+
+impl Display for Computer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.0[0] as char, self.0[1] as char)
+    }
+}
+
+// Set of computers, stored as a fixed bitset where bit N represents the computer with index N.
+// Intersections and counts can then be computed a whole word at a time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ComputerSet([u64; NUM_COMPUTER_SET_WORDS]);
+
+impl ComputerSet {
+    // Set of all possible computers which are strictly greater than `computer`.
+    fn greater_than(computer: Computer) -> Self {
+        let first_index = computer.index() + 1;
+        let first_word = first_index / u64::BITS as usize;
+        let mut set = Self::default();
+
+        set.0[first_word] = u64::MAX << (first_index % u64::BITS as usize);
+        set.0[first_word + 1..].fill(u64::MAX);
+
+        set
+    }
+
+    fn insert(&mut self, computer: Computer) {
+        let index = computer.index();
+        self.0[index / u64::BITS as usize] |= 1 << (index % u64::BITS as usize);
+    }
+
+    fn remove(&mut self, computer: Computer) {
+        let index = computer.index();
+        self.0[index / u64::BITS as usize] &= !(1 << (index % u64::BITS as usize));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & other.0[i]))
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] & !other.0[i]))
+    }
+
+    // Iterates over the computers of the set, in ascending order.
+    fn iter(&self) -> impl Iterator<Item = Computer> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                // Clear the lowest set bit.
+                word &= word - 1;
+                Some(Computer::from_index(i * u64::BITS as usize + bit))
+            })
+        })
+    }
+}
+
+// Parses a line like "kh-tc" into the two computers it connects, whose names are made of two lowercase letters.
+fn parse_connection(line: Line) -> Result<(Computer, Computer), ParseError> {
+    let (a, b) = line.split_once(line.text, "-")?;
+    let parse_computer = |name: &str| match name.as_bytes() {
+        &[first, second] if first.is_ascii_lowercase() && second.is_ascii_lowercase() => {
+            Ok(Computer([first, second]))
+        }
+        _ => Err(line.error_at(name, "expected two lowercase letters")),
+    };
+
+    Ok((parse_computer(a)?, parse_computer(b)?))
+}
+
+#[derive(Clone, Debug)]
+pub struct Network {
+    // Generic representation of the network, which is convenient to explore it.
+    graph: Graph<Computer>,
+    // Bitset representation of the network, which is much faster to solve the problem with.
+    // Indexed by `Computer::index`.
+    neighbours: Vec<ComputerSet>,
+    computers: ComputerSet,
+    historians: ComputerSet,
+}
+
+impl Network {
+    fn new(connections: Vec<(Computer, Computer)>) -> Self {
+        let mut neighbours = vec![ComputerSet::default(); MAX_NUM_COMPUTERS];
+        let mut computers = ComputerSet::default();
+        let mut historians = ComputerSet::default();
+        for &(a, b) in &connections {
+            neighbours[a.index()].insert(b);
+            neighbours[b.index()].insert(a);
+
+            for computer in [a, b] {
+                computers.insert(computer);
+                if computer.may_be_historian() {
+                    historians.insert(computer);
+                }
+            }
+        }
+
+        Self {
+            graph: connections.into_iter().collect(),
+            neighbours,
+            computers,
+            historians,
+        }
+    }
+
+    fn neighbours(&self, computer: Computer) -> &ComputerSet {
+        &self.neighbours[computer.index()]
+    }
+
+    fn count_triangles_with_historian(&self) -> usize {
+        let mut num_triangles = 0;
+
+        // Only count each triangle once, by requiring its computers to be visited in ascending order.
+        for a in self.computers.iter() {
+            for b in self
+                .neighbours(a)
+                .intersection(&ComputerSet::greater_than(a))
+                .iter()
+            {
+                let cs = self
+                    .neighbours(a)
+                    .intersection(self.neighbours(b))
+                    .intersection(&ComputerSet::greater_than(b));
+
+                num_triangles += if a.may_be_historian() || b.may_be_historian() {
+                    cs.len()
+                } else {
+                    cs.intersection(&self.historians).len()
+                };
+            }
+        }
+
+        num_triangles
+    }
+
+    // Same as `Graph::maximum_clique`, but over bitsets, and finding all the cliques tied for the maximum size. Each
+    // clique is sorted, and so are the cliques.
+    fn find_maximum_cliques(&self) -> Vec<Vec<Computer>> {
+        let mut maximum_cliques = Vec::new();
+
+        self.bron_kerbosch(
+            &mut Vec::new(),
+            self.computers,
+            ComputerSet::default(),
+            &mut maximum_cliques,
+        );
+
+        for clique in &mut maximum_cliques {
+            clique.sort();
+        }
+        maximum_cliques.sort();
+        maximum_cliques
+    }
+
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<Computer>,
+        mut candidates: ComputerSet,
+        mut excluded: ComputerSet,
+        maximum_cliques: &mut Vec<Vec<Computer>>,
+    ) {
+        let maximum_size = maximum_cliques.first().map_or(0, Vec::len);
+
+        if candidates.is_empty() && excluded.is_empty() {
+            // Each maximal clique is only reported once, so ties are never duplicates.
+            if clique.len() > maximum_size {
+                *maximum_cliques = vec![clique.clone()];
+            } else if clique.len() == maximum_size && !clique.is_empty() {
+                maximum_cliques.push(clique.clone());
+            }
+            return;
+        }
+
+        // Cliques which can only tie are still explored, to find all of them.
+        if clique.len() + candidates.len() < maximum_size {
+            return;
+        }
+
+        let pivot = candidates
+            .union(&excluded)
+            .iter()
+            .max_by_key(|computer| self.neighbours(*computer).intersection(&candidates).len())
+            .unwrap();
+
+        for computer in candidates.difference(self.neighbours(pivot)).iter() {
+            let computer_neighbours = self.neighbours(computer);
+
+            clique.push(computer);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(computer_neighbours),
+                excluded.intersection(computer_neighbours),
+                maximum_cliques,
+            );
+            clique.pop();
+
+            candidates.remove(computer);
+            excluded.insert(computer);
+        }
+    }
+
+    fn find_cliques(&self, size: usize, prefix: Option<char>) -> Vec<Vec<Computer>> {
+        self.graph.cliques_of_size(size, |computer| match prefix {
+            Some(prefix) => computer.0[0] == prefix as u8,
+            None => true,
+        })
+    }
+
+    fn to_dot(&self) -> String {
+        self.graph
+            .to_dot(&self.graph.maximum_clique().into_iter().collect())
+    }
+
+    // Passwords of all the largest LAN parties, i.e. the names of their computers, sorted and comma-separated.
+    fn find_lan_party_passwords(&self) -> Vec<String> {
+        self.find_maximum_cliques()
+            .into_iter()
+            .map(|clique| clique.iter().join(","))
+            .collect()
+    }
+}
+
+/// One of the largest LAN parties, all of which tie in size.
+#[derive(Debug, Serialize)]
+pub struct LanPartyReport {
+    /// Comma-separated names of the computers of the LAN party, in order, which is its password.
+    pub password: String,
+    pub size: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// List all groups of computers of a given size that are all connected to each other.
+    Cliques {
+        #[arg(short, long, default_value_t = 3)]
+        /// Number of computers in each group.
+        size: usize,
+
+        #[arg(short, long)]
+        /// Only list groups with at least one computer whose name starts with this letter.
+        prefix: Option<char>,
+    },
+    /// Export the network as a Graphviz DOT file, with the LAN party highlighted.
+    Dot {
+        #[arg(short, long, default_value = "day23.dot")]
+        /// Path of the DOT file to write.
+        output: PathBuf,
+    },
+    /// Report all the largest LAN parties, when several tie in size, along with their password.
+    LanParties {
+        #[arg(short, long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Cliques { size, prefix } => {
+                let network = SolverImpl::parse(file)?;
+                let cliques = network.find_cliques(size, prefix);
+                for clique in &cliques {
+                    println!("{}", clique.iter().join(","));
+                }
+                println!("Found {} groups of {size} computers.", cliques.len());
+            }
+            Self::Dot { output } => {
+                let network = SolverImpl::parse(file)?;
+                std::fs::write(&output, network.to_dot()).map_err(|e| AocError::io(&output, e))?;
+                println!("Wrote the network to {}", output.display());
+            }
+            Self::LanParties { format } => {
+                let reports: Vec<_> = SolverImpl::parse(file)?
+                    .find_maximum_cliques()
+                    .into_iter()
+                    .map(|clique| LanPartyReport {
+                        password: clique.iter().join(","),
+                        size: clique.len(),
+                    })
+                    .collect();
+                write_records(&reports, format)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Network;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Lines::new(file)
+            .map(parse_connection)
+            .collect::<Result<_, _>>()
+            .map(Network::new)
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let network = Self::parse_shared(file)?;
+        let answer = network.count_triangles_with_historian();
+        Ok(Answer::new(
+            format!("Number of triangles that may contain the historian: {answer}"),
+            answer,
+        ))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let network = Self::parse_shared(file)?;
+        // Only a single LAN party has an answer.
+        Ok(match network.find_lan_party_passwords().as_slice() {
+            [] => Answer::without_value("There is no LAN party"),
+            [password] => Answer::new(format!("The LAN party password is {password}"), password),
+            passwords => Answer::without_value(format!(
+                "{} LAN parties tie, their passwords are {}",
+                passwords.len(),
+                passwords.join(" and ")
+            )),
+        })
+    }
+
+    // Renames every computer, keeping whether its name starts with 't', and shuffles the connections.
+    fn scrub(file: &str, rng: &mut StdRng) -> Result<String, AocError> {
+        let (mut historian_names, mut other_names): (Vec<_>, Vec<_>) = (0..MAX_NUM_COMPUTERS)
+            .map(Computer::from_index)
+            .partition(Computer::may_be_historian);
+        let (historians, others) = (historian_names.clone(), other_names.clone());
+        historian_names.shuffle(rng);
+        other_names.shuffle(rng);
+        let rename = |computer: Computer| {
+            let (computers, names) = if computer.may_be_historian() {
+                (&historians, &historian_names)
+            } else {
+                (&others, &other_names)
+            };
+            names[computers.binary_search(&computer).unwrap()]
+        };
+
+        let mut connections: Vec<_> = Lines::new(file)
+            .map(|line| {
+                let (a, b) = parse_connection(line)?;
+                let (a, b) = (rename(a), rename(b));
+                Ok(if rng.random() { (a, b) } else { (b, a) })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        connections.shuffle(rng);
+
+        Ok(connections
+            .into_iter()
+            .map(|(a, b)| format!("{a}-{b}\n"))
+            .collect())
+    }
+}
+
+generate_benchmark!(day23);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn tied_lan_parties_are_all_found() {
+        let network = SolverImpl::parse(include_str!("example.txt")).unwrap();
+        assert_eq!(network.find_lan_party_passwords(), ["co,de,ka,ta"]);
+
+        // Two disjoint groups of 4, and a triangle.
+        let network =
+            SolverImpl::parse("ab-ac\nab-ad\nab-ae\nac-ad\nac-ae\nad-ae\nzz-zy\nzz-zx\nzz-zw\nzy-zx\nzy-zw\nzx-zw\nta-tb\ntb-tc\nta-tc\n")
+                .unwrap();
+        assert_eq!(
+            network.find_lan_party_passwords(),
+            ["ab,ac,ad,ae", "zw,zx,zy,zz"]
+        );
+        // Every connection is a LAN party of 2.
+        let network = SolverImpl::parse("ab-cd\ncd-ef\n").unwrap();
+        assert_eq!(network.find_lan_party_passwords(), ["ab,cd", "cd,ef"]);
     }
+}
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod hash_based_benches {
+    use super::*;
+    use test::Bencher;
+
+    fn network() -> Network {
+        SolverImpl::parse(&crate::utils::read_benchmark_input("day23")).unwrap()
+    }
+
+    #[bench]
+    fn bench_day23_triangles_bitset(b: &mut Bencher) {
+        let network = network();
+
+        b.iter(|| network.count_triangles_with_historian());
+    }
+
+    #[bench]
+    fn bench_day23_triangles_hash_based(b: &mut Bencher) {
+        let network = network();
+
+        b.iter(|| {
+            network
+                .graph
+                .cliques_of_size(3, Computer::may_be_historian)
+                .len()
+        });
+    }
+
+    #[bench]
+    fn bench_day23_maximum_clique_bitset(b: &mut Bencher) {
+        let network = network();
+
+        b.iter(|| network.find_maximum_cliques());
+    }
+
+    #[bench]
+    fn bench_day23_maximum_clique_hash_based(b: &mut Bencher) {
+        let network = network();
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+        b.iter(|| network.graph.maximum_clique());
     }
 }
@@ -1,15 +1,947 @@
-use crate::solver::Solver;
+use std::fmt::{Display, Write as _};
+use std::io::{BufRead, Write as _};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::args::Part;
+use crate::dag::{CycleError, topological_sort};
+use crate::error::AocError;
+use crate::input::{Line, Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{FxHashMap, FxHashSet, IterSorted, generate_benchmark};
+use crate::viz::Visualize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Wire([u8; 3]);
+
+impl Wire {
+    // Wire of a name known to be valid, e.g. made up by the solver.
+    fn new(name: &str) -> Self {
+        Self::parse(name).unwrap()
+    }
+
+    // Same as `new`, but for names which may be invalid, e.g. the ones typed by the user.
+    fn parse(name: &str) -> Option<Self> {
+        name.as_bytes().try_into().ok().map(Self)
+    }
+
+    // Parses a wire given on the command line.
+    fn from_arg(name: &str) -> Result<Self, String> {
+        Self::parse(name).ok_or_else(|| String::from("wire names are made of 3 characters"))
+    }
+
+    fn is_input(&self) -> bool {
+        self.0[0] == b'x' || self.0[0] == b'y'
+    }
+
+    fn is_output(&self) -> bool {
+        self.0[0] == b'z'
+    }
+
+    fn numbered(prefix: char, bit: usize) -> Self {
+        Self::new(&format!("{prefix}{bit:02}"))
+    }
+
+    fn output(bit: usize) -> Self {
+        Self::numbered('z', bit)
+    }
+
+    // Bit of the number carried by this x, y, or z wire, which `parse_wire` made sure is numbered.
+    fn bit(&self) -> usize {
+        ((self.0[1] - b'0') * 10 + (self.0[2] - b'0')) as usize
+    }
+
+    // Whether this is the x or y wire of the least significant bit, which is the only one with no carry in.
+    fn is_first_input(&self) -> bool {
+        self.is_input() && &self.0[1..] == b"00"
+    }
+}
+
+impl Display for Wire {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.0[0] as char, self.0[1] as char, self.0[2] as char
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum GateKind {
+    And,
+    Or,
+    Xor,
+}
+
+impl GateKind {
+    fn apply(&self, a: bool, b: bool) -> bool {
+        match self {
+            Self::And => a && b,
+            Self::Or => a || b,
+            Self::Xor => a ^ b,
+        }
+    }
+
+    fn colour(&self) -> &'static str {
+        match self {
+            Self::And => "lightblue",
+            Self::Or => "lightgreen",
+            Self::Xor => "orange",
+        }
+    }
+}
+
+impl FromStr for GateKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "AND" => Ok(Self::And),
+            "OR" => Ok(Self::Or),
+            "XOR" => Ok(Self::Xor),
+            _ => Err("expected AND, OR, or XOR".to_string()),
+        }
+    }
+}
+
+impl Display for GateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Xor => write!(f, "XOR"),
+        }
+    }
+}
+
+// Boolean expression of the x and y wires, in a canonical form so that equivalent expressions often look the same:
+// nested gates of the same kind are flattened into one, their operands are sorted, and constants are folded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Expression {
+    Constant(bool),
+    Wire(Wire),
+    Not(Box<Expression>),
+    // Gate applied to at least 2 operands, none of which is a gate of the same kind or a constant. XOR operands also
+    // never are negations, which are pulled out of the gate.
+    Gate(GateKind, Vec<Expression>),
+}
+
+impl Expression {
+    fn not(self) -> Self {
+        match self {
+            Self::Constant(value) => Self::Constant(!value),
+            Self::Not(expression) => *expression,
+            expression => Self::Not(Box::new(expression)),
+        }
+    }
+
+    // Simplified expression of a gate of the given kind, reading `a` and `b`.
+    fn gate(kind: GateKind, a: Self, b: Self) -> Self {
+        let mut operands = Vec::new();
+        // Whether the whole XOR is negated, which it is for each negated operand or true constant.
+        let mut negated = false;
+        for operand in [a, b] {
+            match operand {
+                Self::Gate(operand_kind, nested_operands) if operand_kind == kind => {
+                    operands.extend(nested_operands)
+                }
+                Self::Constant(value) if kind == GateKind::Xor => negated ^= value,
+                Self::Not(operand) if kind == GateKind::Xor => {
+                    negated = !negated;
+                    match *operand {
+                        Self::Gate(GateKind::Xor, nested_operands) => {
+                            operands.extend(nested_operands)
+                        }
+                        operand => operands.push(operand),
+                    }
+                }
+                operand => operands.push(operand),
+            }
+        }
+        operands.sort();
+
+        let expression = match kind {
+            GateKind::And | GateKind::Or => {
+                // True for OR, false for AND, which decides the result whatever the other operands.
+                let absorbing = kind == GateKind::Or;
+                operands.retain(|operand| *operand != Self::Constant(!absorbing));
+                operands.dedup();
+                if operands.contains(&Self::Constant(absorbing))
+                    || operands
+                        .iter()
+                        .any(|operand| operands.contains(&operand.clone().not()))
+                {
+                    return Self::Constant(absorbing);
+                }
+                if operands.is_empty() {
+                    return Self::Constant(!absorbing);
+                }
+                operands
+            }
+            // Operands XORed an even number of times cancel out.
+            GateKind::Xor => operands
+                .into_iter()
+                .dedup_with_count()
+                .filter(|(count, _)| count % 2 == 1)
+                .map(|(_, operand)| operand)
+                .collect(),
+        };
+
+        let expression = match <[_; 1]>::try_from(expression) {
+            Ok([operand]) => operand,
+            Err(operands) if operands.is_empty() => Self::Constant(false),
+            Err(operands) => Self::Gate(kind, operands),
+        };
+        if negated {
+            expression.not()
+        } else {
+            expression
+        }
+    }
+
+    // Writes the expression as an operand of another one, which needs parentheses if it's a gate itself.
+    fn fmt_operand(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gate(..) => write!(f, "({self})"),
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(value) => write!(f, "{}", *value as u8),
+            Self::Wire(wire) => write!(f, "{wire}"),
+            Self::Not(expression) => {
+                write!(f, "NOT ")?;
+                expression.fmt_operand(f)
+            }
+            Self::Gate(kind, operands) => {
+                for (i, operand) in operands.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {kind} ")?;
+                    }
+                    operand.fmt_operand(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Operands are u64, so x and y wires carry one of their 64 bits.
+const MAX_INPUT_BITS: usize = 64;
+
+// Parses `name`, a slice of the `line`, into a wire. x, y, and z wires must be numbered by the bit they carry.
+fn parse_wire(line: Line, name: &str) -> Result<Wire, ParseError> {
+    let wire = Wire::parse(name)
+        .ok_or_else(|| line.error_at(name, "wire names are made of 3 characters"))?;
+    if !wire.is_input() && !wire.is_output() {
+        return Ok(wire);
+    }
+
+    if !wire.0[1..].iter().all(u8::is_ascii_digit) {
+        return Err(line.error_at(name, "x, y, and z wires are numbered by 2 digits"));
+    }
+    if wire.is_input() && wire.bit() >= MAX_INPUT_BITS {
+        return Err(line.error_at(
+            name,
+            format!("x and y wires are numbered below {MAX_INPUT_BITS}"),
+        ));
+    }
+    Ok(wire)
+}
+
+// Parses a line like "x00 AND y00 -> z00" into a gate.
+fn parse_gate(line: Line) -> Result<Gate, ParseError> {
+    let (expression, output) = line.split_once(line.text, " -> ")?;
+    let [a, kind, b] = expression.split(' ').collect::<Vec<_>>()[..] else {
+        return Err(line.error_at(expression, "expected a gate like \"a AND b\""));
+    };
+
+    Ok(Gate {
+        inputs: [parse_wire(line, a)?, parse_wire(line, b)?],
+        kind: line.parse(kind)?,
+        output: parse_wire(line, output)?,
+    })
+}
+
+#[derive(Clone, Debug)]
+struct Gate {
+    inputs: [Wire; 2],
+    kind: GateKind,
+    output: Wire,
+}
+
+// Numbers given to the circuit through the x and y wires.
+#[derive(Clone, Copy, Debug)]
+pub struct Operands {
+    x: u64,
+    y: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Circuit {
+    gates: Vec<Gate>,
+    // Number of bits of each operand, i.e. the number of x wires.
+    num_bits: usize,
+}
+
+impl Circuit {
+    // Whether `operand` can be given to the circuit, i.e. it fits in its x or y wires.
+    fn fits(&self, operand: u64) -> bool {
+        operand.checked_shr(self.num_bits as u32).unwrap_or(0) == 0
+    }
+
+    // Runs the circuit on the given operands, which must fit in it, and returns the number formed by the bits of all
+    // z wires. That number is wider than the operands, as their sum may carry into one more bit. Fails if the gates
+    // depend on each other in a loop, which can happen after swapping wires.
+    fn simulate(&self, Operands { x, y }: Operands) -> Result<u128, CycleError<Wire>> {
+        assert!(
+            self.fits(x) && self.fits(y),
+            "operands must fit in {} bits",
+            self.num_bits
+        );
+
+        let mut values = FxHashMap::default();
+        for bit in 0..self.num_bits {
+            values.insert(Wire::numbered('x', bit), (x >> bit) & 1 == 1);
+            values.insert(Wire::numbered('y', bit), (y >> bit) & 1 == 1);
+        }
+
+        // Evaluate gates in topological order, so that their inputs are always known by then.
+        let producing_gates: FxHashMap<_, _> =
+            self.gates.iter().map(|gate| (gate.output, gate)).collect();
+        let dependencies = producing_gates
+            .iter()
+            .map(|(wire, gate)| (*wire, gate.inputs.to_vec()))
+            .collect();
+        for wire in topological_sort(&dependencies)? {
+            if let Some(gate) = producing_gates.get(&wire) {
+                let (a, b) = (values[&gate.inputs[0]], values[&gate.inputs[1]]);
+                values.insert(wire, gate.kind.apply(a, b));
+            }
+        }
+
+        Ok(values
+            .into_iter()
+            .filter(|(wire, value)| wire.is_output() && *value)
+            .fold(0, |number, (wire, _)| number | 1_u128 << wire.bit()))
+    }
+
+    // Simplified expression of each z wire in terms of the x and y wires, with z wires in topological order. Fails if
+    // the gates depend on each other in a loop, like `simulate`.
+    fn output_expressions(&self) -> Result<Vec<(Wire, Expression)>, CycleError<Wire>> {
+        let producing_gates: FxHashMap<_, _> =
+            self.gates.iter().map(|gate| (gate.output, gate)).collect();
+        let dependencies = producing_gates
+            .iter()
+            .map(|(wire, gate)| (*wire, gate.inputs.to_vec()))
+            .collect();
+        let wires = topological_sort(&dependencies)?;
+
+        let mut expressions: FxHashMap<_, Expression> = FxHashMap::default();
+        for &wire in &wires {
+            let expression = match producing_gates.get(&wire) {
+                Some(gate) => Expression::gate(
+                    gate.kind,
+                    expressions[&gate.inputs[0]].clone(),
+                    expressions[&gate.inputs[1]].clone(),
+                ),
+                // x and y wires, or wires no gate produces.
+                None => Expression::Wire(wire),
+            };
+            expressions.insert(wire, expression);
+        }
+
+        Ok(wires
+            .into_iter()
+            .filter(Wire::is_output)
+            .map(|wire| (wire, expressions.remove(&wire).unwrap()))
+            .collect())
+    }
+
+    // Swaps the output wires of the gates producing `a` and `b`. Swapping them again undoes the swap.
+    fn swap_outputs(&mut self, a: Wire, b: Wire) -> Result<(), String> {
+        let position = |wire| {
+            self.gates
+                .iter()
+                .position(|gate| gate.output == wire)
+                .ok_or_else(|| format!("no gate outputs {wire}"))
+        };
+        let (i, j) = (position(a)?, position(b)?);
+
+        self.gates[i].output = b;
+        self.gates[j].output = a;
+        Ok(())
+    }
+
+    // Runs the circuit on `num_samples` random operands, and returns how many of them were added correctly.
+    // The operands are always the same, so that results are comparable between circuits.
+    fn count_correct_additions(&self, num_samples: usize) -> Result<usize, CycleError<Wire>> {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Circuits of 64 bits take any operand.
+        let max_operand = 1_u64
+            .checked_shl(self.num_bits as u32)
+            .map_or(u64::MAX, |num_operands| num_operands - 1);
+        let mut num_correct = 0;
+
+        for _ in 0..num_samples {
+            let operands = Operands {
+                x: rng.random_range(0..=max_operand),
+                y: rng.random_range(0..=max_operand),
+            };
+            if self.simulate(operands)? == operands.x as u128 + operands.y as u128 {
+                num_correct += 1;
+            }
+        }
+
+        Ok(num_correct)
+    }
+
+    // Finds all wires whose gate doesn't fit where it should in a ripple-carry adder. For each bit N > 0, the
+    // adder is made of these gates, with the carry in coming from the previous bit:
+    //   xN XOR yN -> sN
+    //   sN XOR carry_in -> zN
+    //   xN AND yN -> aN
+    //   sN AND carry_in -> bN
+    //   aN OR bN -> carry_out
+    // Bit 0 is a half adder (x00 XOR y00 -> z00, x00 AND y00 -> carry_out), and the last carry out is the
+    // most significant z wire. Swapping two outputs puts both gates in a position that doesn't fit that pattern,
+    // so checking each gate on its own yields the swapped wires without having to pair them up. Note that this
+    // can't detect swaps between two gates playing the same role, e.g. aN and bN, but puzzle inputs have none.
+    fn find_swapped_wires(&self) -> Vec<Wire> {
+        let last_output = Wire::output(self.num_bits);
+        // Kinds of gates that read each wire.
+        let mut consuming_gate_kinds: FxHashMap<_, Vec<_>> = FxHashMap::default();
+        for gate in &self.gates {
+            for input in gate.inputs {
+                consuming_gate_kinds
+                    .entry(input)
+                    .or_default()
+                    .push(gate.kind);
+            }
+        }
+        let is_consumed_by = |wire: &Wire, kind: GateKind| {
+            consuming_gate_kinds
+                .get(wire)
+                .is_some_and(|kinds| kinds.contains(&kind))
+        };
+
+        self.gates
+            .iter()
+            .filter(|gate| {
+                let reads_inputs = gate.inputs.iter().all(Wire::is_input);
+                let reads_first_inputs = gate.inputs.iter().all(Wire::is_first_input);
+
+                match gate.kind {
+                    // The carry out of the last bit.
+                    _ if gate.output == last_output => gate.kind != GateKind::Or,
+                    // All other z wires are the XOR of a sum and a carry in, except z00 which is x00 XOR y00.
+                    _ if gate.output.is_output() => gate.kind != GateKind::Xor,
+                    GateKind::Xor if reads_first_inputs => gate.output != Wire::output(0),
+                    // xN XOR yN must be XORed with the carry in.
+                    GateKind::Xor if reads_inputs => !is_consumed_by(&gate.output, GateKind::Xor),
+                    // sN XOR carry_in must output zN, which was handled above.
+                    GateKind::Xor => true,
+                    // x00 AND y00 is the first carry, used by bit 1 like any other carry.
+                    GateKind::And if reads_first_inputs => {
+                        is_consumed_by(&gate.output, GateKind::Or)
+                    }
+                    // Both AND gates of a full adder feed the OR gate computing the carry out.
+                    GateKind::And => !is_consumed_by(&gate.output, GateKind::Or),
+                    // A carry out can't be consumed by an OR gate, it goes into the next bit's XOR and AND gates.
+                    GateKind::Or => is_consumed_by(&gate.output, GateKind::Or),
+                }
+            })
+            .map(|gate| gate.output)
+            .sorted()
+            .collect()
+    }
+
+    // Renders the circuit in the Graphviz DOT format. Gates are nodes coloured by their kind, and wires are edges
+    // between them, except for x, y, and z wires which are nodes of their own. Gates outputting one of the
+    // `highlighted` wires stand out. Fails if a gate reads a wire which no gate outputs.
+    fn to_dot(&self, highlighted: &FxHashSet<Wire>) -> Result<String, String> {
+        let mut dot = String::from("digraph {\n  rankdir=LR;\n");
+        let producing_gates: FxHashMap<_, _> = self
+            .gates
+            .iter()
+            .enumerate()
+            .map(|(i, gate)| (gate.output, i))
+            .collect();
+
+        let input_wires: FxHashSet<_> = self
+            .gates
+            .iter()
+            .flat_map(|gate| gate.inputs)
+            .filter(Wire::is_input)
+            .collect();
+        for wire in input_wires.iter_sorted() {
+            writeln!(dot, "  \"{wire}\" [shape=circle];").unwrap();
+        }
+
+        for (i, gate) in self.gates.iter().enumerate() {
+            write!(
+                dot,
+                "  g{i} [label=\"{}\", shape=box, style=filled, fillcolor={}",
+                gate.kind,
+                gate.kind.colour()
+            )
+            .unwrap();
+            if highlighted.contains(&gate.output) {
+                write!(dot, ", color=red, penwidth=3").unwrap();
+            }
+            writeln!(dot, "];").unwrap();
+
+            for input in gate.inputs {
+                if input.is_input() {
+                    writeln!(dot, "  \"{input}\" -> g{i};").unwrap();
+                } else {
+                    let producing_gate = producing_gates
+                        .get(&input)
+                        .ok_or_else(|| format!("no gate outputs {input}, read by gate {i}"))?;
+                    writeln!(dot, "  g{producing_gate} -> g{i} [label=\"{input}\"];").unwrap();
+                }
+            }
+
+            if gate.output.is_output() {
+                writeln!(dot, "  \"{}\" [shape=doublecircle];", gate.output).unwrap();
+                writeln!(dot, "  g{i} -> \"{}\";", gate.output).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Export the circuit as a Graphviz DOT file.
+    Dot {
+        #[arg(short, long, default_value = "day24.dot")]
+        /// Path of the DOT file to write.
+        output: PathBuf,
+
+        #[arg(long, value_delimiter = ',', value_parser = Wire::from_arg)]
+        /// Comma-separated output wires to highlight, e.g. the ones suspected to be swapped.
+        highlight: Vec<Wire>,
+    },
+    /// Run the circuit on the given operands, rather than the ones from the input.
+    Simulate {
+        #[arg(short)]
+        /// Number given through the x wires. Defaults to the one from the input.
+        x: Option<u64>,
+
+        #[arg(short)]
+        /// Number given through the y wires. Defaults to the one from the input.
+        y: Option<u64>,
+    },
+    /// Interactively swap pairs of output wires, and check whether the circuit then adds correctly.
+    /// Reads commands from stdin: `swap <wire> <wire>`, `reset`, and `quit`.
+    Swap {
+        #[arg(short, long, default_value_t = 100)]
+        /// Number of random additions to check the circuit on after each command.
+        samples: usize,
+    },
+    /// Print the simplified boolean expression of each z wire in terms of the x and y wires, in topological order.
+    /// Wires that don't compute a sum bit stand out.
+    Expressions,
+}
+
+// Prints the swaps currently applied to `circuit`, and whether it now looks and behaves like an adder.
+fn report_swaps(circuit: &Circuit, swaps: &[(Wire, Wire)], num_samples: usize) {
+    if swaps.is_empty() {
+        println!("No wires swapped.");
+    } else {
+        println!(
+            "Swapped wires: {}",
+            swaps.iter().map(|(a, b)| format!("{a}<->{b}")).join(" ")
+        );
+    }
+
+    let suspicious_wires = circuit.find_swapped_wires();
+    if suspicious_wires.is_empty() {
+        println!("All gates fit in a ripple-carry adder.");
+    } else {
+        println!(
+            "Gates that don't fit in a ripple-carry adder output {}",
+            suspicious_wires.into_iter().join(",")
+        );
+    }
+
+    match circuit.count_correct_additions(num_samples) {
+        Ok(num_correct) => println!("{num_correct}/{num_samples} random additions are correct."),
+        Err(e) => println!("The circuit can't be simulated, {e}"),
+    }
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Dot { output, highlight } => {
+                let (circuit, _) = SolverImpl::parse(file)?;
+                let dot = circuit
+                    .to_dot(&highlight.into_iter().collect())
+                    .map_err(|e| {
+                        AocError::NoSolution(format!("the circuit can't be drawn, {e}"))
+                    })?;
+                std::fs::write(&output, dot).map_err(|e| AocError::io(&output, e))?;
+                println!("Wrote the circuit to {}", output.display());
+            }
+            Self::Simulate { x, y } => {
+                let (circuit, operands) = SolverImpl::parse(file)?;
+                let operands = Operands {
+                    x: x.unwrap_or(operands.x),
+                    y: y.unwrap_or(operands.y),
+                };
+                if !circuit.fits(operands.x) || !circuit.fits(operands.y) {
+                    return Err(AocError::Unsupported(format!(
+                        "the operands must fit in the {} bits of the circuit",
+                        circuit.num_bits
+                    )));
+                }
+                match circuit.simulate(operands) {
+                    Ok(z) => println!(
+                        "The z wires output {z} for x={} and y={} (x + y = {})",
+                        operands.x,
+                        operands.y,
+                        operands.x as u128 + operands.y as u128
+                    ),
+                    Err(e) => println!("The circuit can't be simulated, {e}"),
+                }
+            }
+            Self::Swap { samples } => {
+                let (original_circuit, _) = SolverImpl::parse(file)?;
+                let mut circuit = original_circuit.clone();
+                let mut swaps = Vec::new();
+                report_swaps(&circuit, &swaps, samples);
+
+                let stdin = std::io::stdin();
+                loop {
+                    print!("> ");
+                    std::io::stdout().flush().unwrap();
+
+                    let mut line = String::new();
+                    if stdin.lock().read_line(&mut line).unwrap() == 0 {
+                        break;
+                    }
+
+                    match line.split_whitespace().collect::<Vec<_>>()[..] {
+                        ["swap", a, b] => {
+                            let (Some(a), Some(b)) = (Wire::parse(a), Wire::parse(b)) else {
+                                println!("Wire names are made of 3 characters.");
+                                continue;
+                            };
+                            match circuit.swap_outputs(a, b) {
+                                Ok(()) => {
+                                    // Swapping a pair again undoes it.
+                                    match swaps
+                                        .iter()
+                                        .position(|swap| *swap == (a, b) || *swap == (b, a))
+                                    {
+                                        Some(i) => {
+                                            swaps.remove(i);
+                                        }
+                                        None => swaps.push((a, b)),
+                                    }
+                                    report_swaps(&circuit, &swaps, samples);
+                                }
+                                Err(e) => println!("Can't swap {a} and {b}, {e}"),
+                            }
+                        }
+                        ["reset"] => {
+                            circuit = original_circuit.clone();
+                            swaps.clear();
+                            report_swaps(&circuit, &swaps, samples);
+                        }
+                        ["quit"] => break,
+                        [] => {}
+                        _ => println!(
+                            "Unknown command, expected `swap <wire> <wire>`, `reset`, or `quit`."
+                        ),
+                    }
+                }
+            }
+            Self::Expressions => {
+                let (circuit, _) = SolverImpl::parse(file)?;
+                let expressions = circuit
+                    .output_expressions()
+                    .map_err(|e| AocError::NoSolution(e.to_string()))?;
+                for (wire, expression) in expressions {
+                    println!("{wire} = {expression}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    // The circuit, along with the operands it is initially given.
+    type Input = (Circuit, Operands);
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let mut operands = Operands { x: 0, y: 0 };
+        let mut num_bits = 0;
+
+        // Initial values come first, and are separated from gates by an empty line.
+        for line in lines.by_ref() {
+            if line.text.is_empty() {
+                break;
+            }
+
+            let (wire, value) = line.split_once(line.text, ": ")?;
+            let wire = parse_wire(line, wire)?;
+            let value = match value {
+                "0" => 0,
+                "1" => 1,
+                _ => return Err(line.error_at(value, "expected a bit")),
+            };
+            match wire.0[0] {
+                b'x' => operands.x |= value << wire.bit(),
+                b'y' => operands.y |= value << wire.bit(),
+                _ => return Err(line.error("only x and y wires have initial values")),
+            }
+            num_bits = num_bits.max(wire.bit() + 1);
+        }
+
+        let gates = lines
+            .map(|line| Ok((line, parse_gate(line)?)))
+            .collect::<Result<Vec<_>, ParseError>>()?;
+
+        // Gates can only read the operands, and the outputs of other gates.
+        let outputs: FxHashSet<_> = gates.iter().map(|(_, gate)| gate.output).collect();
+        for (line, gate) in &gates {
+            for input in gate.inputs {
+                let is_operand = input.is_input() && input.bit() < num_bits;
+                if !is_operand && !outputs.contains(&input) {
+                    return Err(line.error(format!(
+                        "{input} is neither given an initial value nor output by a gate"
+                    )));
+                }
+            }
+        }
+
+        let gates = gates.into_iter().map(|(_, gate)| gate).collect();
+        Ok((Circuit { gates, num_bits }, operands))
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let (circuit, operands) = Self::parse_shared(file)?;
+        let answer = circuit
+            .simulate(operands)
+            .map_err(|e| AocError::NoSolution(format!("the circuit can't be simulated, {e}")))?;
+        Ok(Answer::new(format!("The z wires output {answer}"), answer))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let (circuit, _) = Self::parse_shared(file)?;
+        let answer = circuit.find_swapped_wires().into_iter().join(",");
+        Ok(Answer::new(
+            format!("The swapped wires are {answer}"),
+            answer,
+        ))
+    }
+}
+
+generate_benchmark!(day24);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn initial_values_form_the_operands() {
+        let (circuit, operands) =
+            SolverImpl::parse("x00: 1\nx01: 0\ny00: 1\ny01: 1\n\nx00 XOR y00 -> z00\n").unwrap();
+
+        assert_eq!((operands.x, operands.y), (0b01, 0b11));
+        assert_eq!(circuit.num_bits, 2);
+        assert_eq!(circuit.gates[0].kind, GateKind::Xor);
+    }
+
+    #[test]
+    fn output_expressions_are_simplified() {
+        let (circuit, _) = SolverImpl::parse(
+            "x00: 1\nx01: 0\ny00: 1\ny01: 1\n\n\
+             x00 XOR y00 -> z00\n\
+             x00 AND y00 -> c00\n\
+             x01 XOR y01 -> s01\n\
+             s01 XOR c00 -> z01\n\
+             y00 XOR x00 -> t00\n\
+             z00 XOR t00 -> z02\n\
+             c00 AND x00 -> z03\n\
+             z03 OR c00 -> z04\n\
+             z01 XOR s01 -> z05\n",
+        )
+        .unwrap();
+
+        let expressions: FxHashMap<_, _> = circuit
+            .output_expressions()
+            .unwrap()
+            .into_iter()
+            .map(|(wire, expression)| (wire.to_string(), expression.to_string()))
+            .collect();
+        assert_eq!(expressions["z00"], "x00 XOR y00");
+        // Nested XORs are flattened.
+        assert_eq!(expressions["z01"], "x01 XOR y01 XOR (x00 AND y00)");
+        // Operands XORed twice cancel out.
+        assert_eq!(expressions["z02"], "0");
+        assert_eq!(expressions["z05"], "x00 AND y00");
+        // Operands ANDed or ORed twice count once.
+        assert_eq!(expressions["z03"], "x00 AND y00");
+        assert_eq!(expressions["z04"], "x00 AND y00");
     }
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    #[test]
+    fn expressions_fold_constants_and_negations() {
+        let [a, b] = [Wire::new("x00"), Wire::new("y00")].map(Expression::Wire);
+
+        let not_a = Expression::gate(GateKind::Xor, a.clone(), Expression::Constant(true));
+        assert_eq!(not_a.to_string(), "NOT x00");
+        assert_eq!(
+            Expression::gate(GateKind::Xor, not_a.clone(), b.clone()).to_string(),
+            "NOT (x00 XOR y00)"
+        );
+        assert_eq!(
+            Expression::gate(GateKind::And, not_a.clone(), a.clone()),
+            Expression::Constant(false)
+        );
+        assert_eq!(
+            Expression::gate(GateKind::Or, a.clone(), not_a),
+            Expression::Constant(true)
+        );
+        assert_eq!(
+            Expression::gate(GateKind::Or, b, Expression::Constant(false)),
+            Expression::Wire(Wire::new("y00"))
+        );
+    }
+
+    #[test]
+    fn looping_circuits_fail_to_be_simulated() {
+        let error = SolverImpl::solve_part1(
+            "x00: 1\ny00: 0\n\nx00 AND abc -> def\ny00 OR def -> abc\ndef XOR abc -> z00\n",
+        )
+        .unwrap_err();
+
+        let message = error.to_string();
+        assert!(
+            message.contains("abc") && message.contains("def"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn operands_must_fit_in_the_circuit() {
+        let (circuit, _) = SolverImpl::parse("x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\n").unwrap();
+        assert!(circuit.fits(1));
+        assert!(!circuit.fits(2));
+
+        let simulate = Command::Simulate {
+            x: Some(5),
+            y: None,
+        };
+        assert!(matches!(
+            Run::<SolverImpl>::run(simulate, "x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\n"),
+            Err(AocError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn circuits_of_64_bits_add_any_operands() {
+        let mut file = String::new();
+        for bit in 0..64 {
+            writeln!(file, "x{bit:02}: 1").unwrap();
+        }
+        for bit in 0..64 {
+            writeln!(file, "y{bit:02}: 0").unwrap();
+        }
+        // Only ORs the operands, which adds them when they have no bit in common.
+        file.push('\n');
+        for bit in 0..64 {
+            writeln!(file, "x{bit:02} OR y{bit:02} -> z{bit:02}").unwrap();
+        }
+        let (circuit, operands) = SolverImpl::parse(&file).unwrap();
+
+        assert_eq!(circuit.simulate(operands).unwrap(), u64::MAX as u128);
+        assert!(circuit.fits(u64::MAX));
+        assert!(circuit.count_correct_additions(10).unwrap() < 10);
+    }
+
+    #[test]
+    fn gates_reading_wires_no_gate_outputs_are_not_drawn() {
+        let (circuit, _) = SolverImpl::parse("x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\n").unwrap();
+        assert!(circuit.to_dot(&FxHashSet::default()).is_ok());
+        assert!(Wire::from_arg("ab").is_err());
+
+        let circuit = Circuit {
+            gates: vec![Gate {
+                inputs: [Wire::new("x00"), Wire::new("qqq")],
+                kind: GateKind::And,
+                output: Wire::new("z00"),
+            }],
+            num_bits: 1,
+        };
+        assert_eq!(
+            circuit.to_dot(&FxHashSet::default()),
+            Err(String::from("no gate outputs qqq, read by gate 0"))
+        );
+    }
+
+    #[test]
+    fn wires_out_of_range_or_undriven_fail_to_parse() {
+        let error = SolverImpl::parse("x99: 1\n\nx99 XOR x99 -> z00\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, Some(1)));
+
+        let error = SolverImpl::parse("x00: 1\n\nx00 XOR xzz -> z00\n").unwrap_err();
+        assert_eq!((error.line, error.column), (3, Some(9)));
+
+        let error = SolverImpl::parse("x00: 1\ny00: 0\n\nx00 XOR qqq -> z00\n").unwrap_err();
+        assert_eq!(error.line, 4);
+        assert!(error.message.contains("qqq"), "{}", error.message);
+    }
+
+    #[test]
+    fn an_unknown_gate_fails_to_parse() {
+        let error = SolverImpl::parse("x00: 1\ny00: 0\n\nx00 NAND y00 -> z00\n").unwrap_err();
+
+        assert_eq!((error.line, error.column), (4, Some(5)));
     }
 }
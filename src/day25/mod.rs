@@ -1,15 +1,466 @@
-use crate::solver::Solver;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use clap::Subcommand;
+use rand::Rng;
+use rand::rngs::StdRng;
+use smallvec::{SmallVec, smallvec};
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Line, Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver, Variant};
+use crate::utils::{
+    chunk_by_blank_lines, generate_benchmark, shard_and_solve_concurrently, until_cancelled,
+};
+use crate::viz::Visualize;
+
+// Cells of a schematic are stored in a single mask, which limits how large schematics can be.
+const MAX_SCHEMATIC_CELLS: usize = u128::BITS as usize;
+// Dimensions of the schematics of puzzle inputs, which generated inputs share.
+const PUZZLE_DIMENSIONS: Dimensions = Dimensions {
+    width: 5,
+    height: 7,
+};
+
+// Size of the schematics of a file, which all share the same dimensions. Puzzle inputs use 5x7 schematics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Dimensions {
+    // Number of pins, i.e. number of columns.
+    width: usize,
+    // Total number of rows, including the full row at the top of locks and the bottom of keys.
+    height: usize,
+}
+
+impl Dimensions {
+    fn of(schematic: &[Line]) -> Self {
+        Self {
+            width: schematic.first().map_or(0, |row| row.text.len()),
+            height: schematic.len(),
+        }
+    }
+
+    // Highest pin that can fit in a lock, once the full row is excluded.
+    fn max_pin_height(&self) -> u8 {
+        (self.height - 2) as u8
+    }
+}
+
+impl Display for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Schematic {
+    // Filled cells, where bit `row * width + col` is set for each `#`. A lock and a key overlap iff their
+    // masks share a bit, since their full rows are on opposite sides.
+    mask: u128,
+    // Height of each column, excluding the full row.
+    heights: SmallVec<[u8; 5]>,
+}
+
+impl Schematic {
+    fn parse(schematic: &[Line], dimensions: Dimensions) -> Result<Self, ParseError> {
+        let mut mask = 0;
+        let mut heights = smallvec![0; dimensions.width];
+        for (row, line) in schematic.iter().enumerate() {
+            if line.text.len() != dimensions.width {
+                return Err(line.error(format!(
+                    "rows must be {} cells wide like the first one",
+                    dimensions.width
+                )));
+            }
+
+            for (col, cell) in line.text.char_indices() {
+                match cell {
+                    '#' => {
+                        mask |= 1 << (row * dimensions.width + col);
+                        heights[col] += 1;
+                    }
+                    '.' => {}
+                    _ => {
+                        return Err(line.error_at(
+                            &line.text[col..col + cell.len_utf8()],
+                            format!("unexpected cell {cell:?}"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Locks have their top row filled, whereas keys have their bottom row filled.
+        let top_row = (1 << dimensions.width) - 1;
+        let bottom_row = top_row << ((dimensions.height - 1) * dimensions.width);
+        if mask & top_row != top_row && mask & bottom_row != bottom_row {
+            return Err(
+                schematic[0].error("schematics must have a full row at the top or the bottom")
+            );
+        }
+
+        // Exclude the full row from the heights.
+        heights.iter_mut().for_each(|height| *height -= 1);
+        Ok(Self { mask, heights })
+    }
+
+    fn is_lock(&self, dimensions: Dimensions) -> bool {
+        // Locks have their top row filled, whereas keys have their bottom row filled.
+        let top_row = (1 << dimensions.width) - 1;
+        self.mask & top_row == top_row
+    }
+
+    fn fits(&self, other: &Self) -> bool {
+        self.mask & other.mask == 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Schematics {
+    dimensions: Dimensions,
+    // Locks and keys, along with their index among all locks or keys in input order.
+    locks: Vec<(usize, Schematic)>,
+    // Keys are bucketed by the height of their first pin, so that a lock only needs to be compared against the
+    // keys whose first pin is short enough. This prunes about half of the comparisons. Keys are shared read-only by
+    // the threads matching locks concurrently.
+    keys_by_first_height: Arc<Vec<Vec<(usize, Schematic)>>>,
+}
+
+// Keys among `keys_by_first_height` which fit in `lock` without overlapping in any column.
+fn fitting_keys<'a>(
+    lock: &'a Schematic,
+    keys_by_first_height: &'a [Vec<(usize, Schematic)>],
+    dimensions: Dimensions,
+) -> impl Iterator<Item = &'a (usize, Schematic)> {
+    keys_by_first_height[..=(dimensions.max_pin_height() - lock.heights[0]) as usize]
+        .iter()
+        .flatten()
+        .filter(move |(_, key)| lock.fits(key))
+}
+
+impl Schematics {
+    // Iterates over all (lock index, key index) pairs which fit together without overlapping in any column.
+    // Pairs are ordered by lock, but the keys of a given lock aren't sorted.
+    fn fitting_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.locks.iter().flat_map(move |(i, lock)| {
+            fitting_keys(lock, &self.keys_by_first_height, self.dimensions)
+                .map(move |(j, _)| (*i, *j))
+        })
+    }
+
+    // Same as `fitting_pairs().count()`, but locks are sharded across threads, which all test them against the same
+    // keys.
+    fn count_fitting_pairs_concurrently(&self) -> usize {
+        let dimensions = self.dimensions;
+        let locks: Vec<_> = self.locks.iter().map(|(_, lock)| lock.clone()).collect();
+
+        shard_and_solve_concurrently(
+            locks,
+            Arc::clone(&self.keys_by_first_height),
+            move |locks, keys_by_first_height| {
+                until_cancelled(locks)
+                    .map(|lock| fitting_keys(&lock, &keys_by_first_height, dimensions).count())
+                    .sum::<usize>()
+            },
+        )
+        .sum()
+    }
+
+    fn find_fitting_pairs(&self) -> Vec<(usize, usize)> {
+        let mut fitting_pairs: Vec<_> = self.fitting_pairs().collect();
+        fitting_pairs.sort_unstable();
+        fitting_pairs
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// List the lock and key pairs that fit together, as CSV rows of their indices in input order.
+    Pairs,
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file)?,
+            Self::Pairs => {
+                let schematics = SolverImpl::parse(file)?;
+                println!("lock,key");
+                for (lock, key) in schematics.find_fitting_pairs() {
+                    println!("{lock},{key}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
+// Random schematic of the puzzle's dimensions, which is as likely to be a lock as a key.
+fn generate_schematic(rng: &mut StdRng) -> String {
+    let Dimensions { width, height } = PUZZLE_DIMENSIONS;
+    let heights: Vec<_> = (0..width)
+        .map(|_| rng.random_range(0..=PUZZLE_DIMENSIONS.max_pin_height() as usize))
+        .collect();
+    let is_lock = rng.random();
+
+    let mut schematic = String::new();
+    for row in 0..height {
+        // Distance of the row from the full one.
+        let depth = if is_lock { row } else { height - 1 - row };
+        schematic.extend(
+            heights
+                .iter()
+                .map(|pin_height| if depth <= *pin_height { '#' } else { '.' }),
+        );
+        schematic.push('\n');
+    }
+
+    schematic
+}
 
 pub struct SolverImpl {}
 
+impl SolverImpl {
+    const PART1_VARIANTS: &[Variant] = &[
+        Variant::new("single-threaded", |file| {
+            Ok(Self::parse(file)?.fitting_pairs().count().to_string())
+        }),
+        Variant::new("sharded", |file| {
+            Ok(Self::parse(file)?
+                .count_fitting_pairs_concurrently()
+                .to_string())
+        }),
+    ];
+}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Schematics;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        // Schematics are separated by empty lines.
+        let blocks: Vec<_> = chunk_by_blank_lines(Lines::new(file)).collect();
+
+        let dimensions = blocks.first().map_or(
+            // Arbitrary, as there is nothing to fit anyway.
+            Dimensions {
+                width: 0,
+                height: 2,
+            },
+            |block| Dimensions::of(block),
+        );
+        if let Some(block) = blocks.first()
+            && (dimensions.height < 2 || dimensions.width * dimensions.height > MAX_SCHEMATIC_CELLS)
+        {
+            return Err(block[0].error(format!(
+                "schematics must have at least 2 rows, and at most {MAX_SCHEMATIC_CELLS} cells, got {dimensions}"
+            )));
+        }
+
+        let mut locks = Vec::new();
+        let mut keys_by_first_height = vec![Vec::new(); dimensions.max_pin_height() as usize + 1];
+        let mut num_keys = 0;
+        for block in blocks {
+            if block.len() != dimensions.height {
+                return Err(
+                    block[0].error(format!("schematic isn't {dimensions} like the first one"))
+                );
+            }
+
+            let schematic = Schematic::parse(&block, dimensions)?;
+            if schematic.is_lock(dimensions) {
+                locks.push((locks.len(), schematic));
+            } else {
+                keys_by_first_height[schematic.heights[0] as usize].push((num_keys, schematic));
+                num_keys += 1;
+            }
+        }
+
+        Ok(Schematics {
+            dimensions,
+            locks,
+            keys_by_first_height: Arc::new(keys_by_first_height),
+        })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let schematics = Self::parse_shared(file)?;
+        let answer = schematics.count_fitting_pairs_concurrently();
+        Ok(Answer::new(
+            format!("Number of lock/key pairs that fit together: {answer}"),
+            answer,
+        ))
+    }
+
+    fn solve_part2(_file: &str) -> Result<Answer, AocError> {
+        Ok(Answer::without_value(
+            "There is no second part on the last day, just deliver the chronicle!",
+        ))
+    }
+
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => Self::PART1_VARIANTS,
+            Part::Part2 => &[],
+        }
+    }
+
+    // `size` is the number of schematics, roughly half of which are locks.
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        Ok((0..size)
+            .map(|_| generate_schematic(rng))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+// There is no second part to benchmark.
+generate_benchmark!(day25, part1);
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod fitting_benches {
+    use super::*;
+    use test::Bencher;
+
+    fn schematics() -> Schematics {
+        SolverImpl::parse(&crate::utils::read_benchmark_input("day25")).unwrap()
+    }
+
+    #[bench]
+    fn bench_day25_fits_bitmask_bucketed(b: &mut Bencher) {
+        let schematics = schematics();
+
+        b.iter(|| schematics.fitting_pairs().count());
+    }
+
+    #[bench]
+    fn bench_day25_fits_bitmask(b: &mut Bencher) {
+        let schematics = schematics();
+        let keys: Vec<_> = schematics.keys_by_first_height.iter().flatten().collect();
+
+        b.iter(|| {
+            schematics
+                .locks
+                .iter()
+                .flat_map(|(_, lock)| keys.iter().filter(|(_, key)| lock.fits(key)))
+                .count()
+        });
+    }
+
+    #[bench]
+    fn bench_day25_fits_heights(b: &mut Bencher) {
+        let schematics = schematics();
+        let keys: Vec<_> = schematics.keys_by_first_height.iter().flatten().collect();
+        let max_pin_height = schematics.dimensions.max_pin_height();
+
+        b.iter(|| {
+            schematics
+                .locks
+                .iter()
+                .flat_map(|(_, lock)| {
+                    keys.iter().filter(|(_, key)| {
+                        lock.heights
+                            .iter()
+                            .zip(&key.heights)
+                            .all(|(lock_height, key_height)| {
+                                lock_height + key_height <= max_pin_height
+                            })
+                    })
+                })
+                .count()
+        });
+    }
+}
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod sharding_benches {
+    use rand::SeedableRng;
+    use test::Bencher;
+
+    use super::*;
+
+    // Far more than the few hundred schematics of puzzle inputs, to make sharding worth it.
+    const NUM_SCHEMATICS: usize = 20_000;
+
+    fn schematics() -> Schematics {
+        let file = SolverImpl::generate(NUM_SCHEMATICS, &mut StdRng::seed_from_u64(25)).unwrap();
+        SolverImpl::parse(&file).unwrap()
+    }
+
+    #[bench]
+    fn bench_day25_generated_fits_single_threaded(b: &mut Bencher) {
+        let schematics = schematics();
+
+        b.iter(|| schematics.fitting_pairs().count());
+    }
+
+    #[bench]
+    fn bench_day25_generated_fits_sharded(b: &mut Bencher) {
+        let schematics = schematics();
+
+        b.iter(|| schematics.count_fitting_pairs_concurrently());
+    }
+}
+
+#[cfg(test)]
+mod stage_tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn sorts_schematics_into_locks_and_keys() {
+        let schematics = SolverImpl::parse("##\n#.\n..\n\n..\n.#\n##\n").unwrap();
+
+        assert_eq!(schematics.locks.len(), 1);
+        assert_eq!(schematics.locks[0].1.heights.as_slice(), [1, 0]);
+        assert_eq!(schematics.find_fitting_pairs(), [(0, 0)]);
+    }
+
+    #[test]
+    fn sharding_finds_as_many_pairs_on_generated_schematics() {
+        let file = SolverImpl::generate(500, &mut StdRng::seed_from_u64(25)).unwrap();
+        let schematics = SolverImpl::parse(&file).unwrap();
+
+        assert_eq!(schematics.dimensions, PUZZLE_DIMENSIONS);
+        assert_eq!(
+            schematics.count_fitting_pairs_concurrently(),
+            schematics.fitting_pairs().count()
+        );
+    }
+
+    #[test]
+    fn schematics_of_different_sizes_fail_to_parse() {
+        let error = SolverImpl::parse("##\n#.\n..\n\n..\n##\n").unwrap_err();
+
+        assert_eq!(error.line, 5);
     }
 
-    fn solve_part2(file: &str) {
-        println!("{file}");
-        unimplemented!()
+    #[test]
+    fn a_schematic_without_a_full_row_fails_to_parse() {
+        assert!(SolverImpl::parse("#.\n..\n.#\n").is_err());
     }
 }
@@ -1,50 +1,243 @@
-use crate::{solver::Solver, utils::generate_benchmark};
-use lazy_static::lazy_static;
-use regex::Regex;
+use std::fmt::Write;
 
-lazy_static! {
-    static ref MUL: Regex = Regex::new(r"mul\((?<a>\d+),(?<b>\d+)\)").unwrap();
-    static ref MUL_WITH_DO_DONT: Regex =
-        Regex::new(r"(?<do>do\(\))|(?<dont>don't\(\))|mul\((?<a>\d+),(?<b>\d+)\)").unwrap();
+use clap::Subcommand;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver},
+    utils::{bytes::digit_run_at, generate_benchmark},
+    viz::Visualize,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Instruction {
+    Do,
+    Dont,
+    Mul(i32, i32),
 }
 
-pub struct SolverImpl {}
+// Valid instructions found in the corrupted memory, in order, for each line.
+#[derive(Clone)]
+pub struct Memory(Vec<Vec<Instruction>>);
 
-impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let sum_of_muls: i32 = MUL
-            .captures_iter(&file)
-            .map(|capture| {
-                capture.name("a").unwrap().as_str().parse::<i32>().unwrap()
-                    * capture.name("b").unwrap().as_str().parse::<i32>().unwrap()
+impl Memory {
+    fn sum_of_muls(&self) -> i32 {
+        self.0
+            .iter()
+            .flatten()
+            .map(|instruction| match instruction {
+                Instruction::Mul(a, b) => a * b,
+                Instruction::Do | Instruction::Dont => 0,
             })
-            .sum();
-
-        println!("Sum of muls: {sum_of_muls}");
+            .sum()
     }
 
-    fn solve_part2(file: &str) {
+    // Sum of the muls enabled by the last `do()` or `don't()` before them. Muls start enabled, and are enabled again
+    // at the start of each line if `reset_per_line` is set, rather than carrying the state across lines.
+    fn sum_of_enabled_muls(&self, reset_per_line: bool) -> i32 {
         let mut enabled = true;
-        let sum_of_muls: i32 = MUL_WITH_DO_DONT
-            .captures_iter(&file)
-            .map(|capture| {
-                if capture.name("do").is_some() {
-                    enabled = true;
-                    0
-                } else if capture.name("dont").is_some() {
-                    enabled = false;
-                    0
-                } else if !enabled {
-                    0
+        let mut sum = 0;
+        for line in &self.0 {
+            if reset_per_line {
+                enabled = true;
+            }
+
+            for instruction in line {
+                match instruction {
+                    Instruction::Do => enabled = true,
+                    Instruction::Dont => enabled = false,
+                    Instruction::Mul(_, _) if !enabled => {}
+                    Instruction::Mul(a, b) => sum += a * b,
+                }
+            }
+        }
+
+        sum
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Sum the muls enabled by `do()` and `don't()`, like in part 2.
+    Enabled {
+        #[arg(long)]
+        /// Enable muls again at the start of each line, rather than carrying the state across lines.
+        reset_per_line: bool,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Enabled { reset_per_line } => {
+                println!(
+                    "Sum of muls: {}",
+                    SolverImpl::parse(file)?.sum_of_enabled_muls(reset_per_line)
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Enabled { .. } => Some(Part::Part2),
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
+pub struct SolverImpl {}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Memory;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Vec::new();
+        for line in Lines::new(file) {
+            let mut instructions = Vec::new();
+            // Memory is scanned as bytes, as it's mostly noise that doesn't need to be decoded. Like a regex would,
+            // this picks the leftmost instruction and resumes after it.
+            let memory = line.text.as_bytes();
+            let mut start = 0;
+            while start < memory.len() {
+                let rest = &memory[start..];
+                if rest.starts_with(b"do()") {
+                    instructions.push(Instruction::Do);
+                    start += b"do()".len();
+                } else if rest.starts_with(b"don't()") {
+                    instructions.push(Instruction::Dont);
+                    start += b"don't()".len();
+                } else if let Some(([a, b], end)) = scan_mul(memory, start) {
+                    instructions.push(Instruction::Mul(
+                        line.parse_decimal(a)?,
+                        line.parse_decimal(b)?,
+                    ));
+                    start = end;
                 } else {
-                    capture.name("a").unwrap().as_str().parse::<i32>().unwrap()
-                        * capture.name("b").unwrap().as_str().parse::<i32>().unwrap()
+                    start += 1;
                 }
-            })
-            .sum();
+            }
+            lines.push(instructions);
+        }
 
-        println!("Sum of muls: {sum_of_muls}");
+        Ok(Memory(lines))
+    }
+}
+
+// Finds the digits of both operands of a "mul(a,b)" instruction starting at `start`, if there's one, along with the
+// index just past the instruction.
+fn scan_mul(memory: &[u8], start: usize) -> Option<([&[u8]; 2], usize)> {
+    let a_start = start + b"mul(".len();
+    if !memory[start..].starts_with(b"mul(") {
+        return None;
+    }
+    let a = digit_run_at(memory, a_start);
+    let b_start = a_start + a.len() + 1;
+    let b = digit_run_at(memory, b_start);
+    let is_mul = !a.is_empty()
+        && memory.get(a_start + a.len()) == Some(&b',')
+        && !b.is_empty()
+        && memory.get(b_start + b.len()) == Some(&b')');
+
+    is_mul.then_some(([a, b], b_start + b.len() + 1))
+}
+
+impl Solver for SolverImpl {
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.sum_of_muls();
+        Ok(Answer::new(format!("Sum of muls: {answer}"), answer))
+    }
+
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.sum_of_enabled_muls(false);
+        Ok(Answer::new(format!("Sum of muls: {answer}"), answer))
+    }
+
+    // Memory mixing valid instructions with corrupted ones and noise.
+    fn generate(size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        const NOISE: &[u8] = b"abdilmnortuxy%&!@^*+-_?[](),' ";
+
+        let mut file = String::new();
+        while file.len() < size {
+            match rng.random_range(0..10) {
+                0 => file.push_str("do()"),
+                1 => file.push_str("don't()"),
+                2..=4 => write!(
+                    file,
+                    "mul({},{})",
+                    rng.random_range(1..1000),
+                    rng.random_range(1..1000)
+                )
+                .unwrap(),
+                // Almost an instruction.
+                5 => write!(
+                    file,
+                    "mul({},{}]",
+                    rng.random_range(1..1000),
+                    rng.random_range(1..1000)
+                )
+                .unwrap(),
+                _ => {
+                    for _ in 0..rng.random_range(1..8) {
+                        file.push(NOISE[rng.random_range(0..NOISE.len())] as char);
+                    }
+                }
+            }
+        }
+        file.push('\n');
+
+        Ok(file)
     }
 }
 
 generate_benchmark!(day3);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn toggles_carry_across_lines_unless_reset() {
+        let memory = SolverImpl::parse("mul(1,2)don't()mul(2,3)\nmul(4,5)do()mul(6,7)\n").unwrap();
+
+        assert_eq!(memory.sum_of_muls(), 2 + 6 + 20 + 42);
+        assert_eq!(memory.sum_of_enabled_muls(false), 2 + 42);
+        assert_eq!(memory.sum_of_enabled_muls(true), 2 + 20 + 42);
+    }
+
+    #[test]
+    fn instructions_are_scanned_like_a_regex() {
+        let Memory(lines) =
+            SolverImpl::parse("xmul(2,4)%mul[3,7]!mul(5,5)mul(1,)don't()\n").unwrap();
+
+        assert_eq!(
+            lines,
+            [vec![
+                Instruction::Mul(2, 4),
+                Instruction::Mul(5, 5),
+                Instruction::Dont
+            ]]
+        );
+    }
+}
@@ -1,46 +1,72 @@
-use crate::solver::Solver;
+use std::str::FromStr;
+
+use clap::Subcommand;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver};
 use crate::utils::{Position, generate_benchmark};
+use crate::viz::Visualize;
 
 // "MAS" is 3 characters long.
 const MAS_LENGTH: usize = 3;
 
+// Letter of the grid, which is any uppercase ASCII letter. The puzzle only looks for X, M, A, and S, but crossed words
+// may be made of any letters.
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum Letter {
-    X,
-    M,
-    A,
-    S,
+struct Letter(u8);
+
+impl Letter {
+    const X: Self = Self(b'X');
+    const M: Self = Self(b'M');
+    const A: Self = Self(b'A');
+    const S: Self = Self(b'S');
+}
+
+impl TryFrom<char> for Letter {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        if value.is_ascii_uppercase() {
+            Ok(Self(value as u8))
+        } else {
+            Err(value)
+        }
+    }
 }
 
-impl From<char> for Letter {
-    fn from(value: char) -> Self {
-        match value {
-            'X' => Self::X,
-            'M' => Self::M,
-            'A' => Self::A,
-            'S' => Self::S,
-            _ => unreachable!(),
+// Word made of the letters found in the grid. Crossed words must have an odd length, so that they have a center letter.
+#[derive(Clone, Debug)]
+pub struct Word(Vec<Letter>);
+
+impl FromStr for Word {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letters = s
+            .chars()
+            .map(Letter::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|c| format!("unexpected letter {c:?}, words are made of uppercase letters"))?;
+        if letters.len().is_multiple_of(2) {
+            return Err(format!(
+                "{s:?} has no center letter, words must have an odd length"
+            ));
         }
+
+        Ok(Self(letters))
     }
 }
 
-#[derive(Debug)]
-struct Grid {
+#[derive(Clone, Debug)]
+pub struct Grid {
     grid: Vec<Vec<Letter>>,
     // The grid is a square of size `size`.
     size: usize,
 }
 
 impl Grid {
-    fn new(file: &str) -> Self {
-        let grid: Vec<_> = file
-            .lines()
-            .map(|line| line.chars().map(Letter::from).collect::<Vec<_>>())
-            .collect();
-        let size = grid.len();
-        Self { grid, size }
-    }
-
     fn at(&self, position: Position) -> Letter {
         self.grid[position.row][position.col]
     }
@@ -131,32 +157,33 @@ impl Grid {
     }
 
     fn count_all_x_mas_occurrences(&self) -> usize {
-        let a_positions = self.find_all_letter_positions(Letter::A);
-        let mut x_mas_occurrences = 0;
-
-        for a_position in a_positions {
-            if a_position.row == 0
-                || a_position.col == self.size - 1
-                || a_position.row == self.size - 1
-                || a_position.col == 0
-            {
-                continue;
-            }
+        self.count_crossed_word_occurrences(&Word(vec![Letter::M, Letter::A, Letter::S]))
+    }
 
-            if ((self.at(a_position.up(1).left(1)) == Letter::M
-                && self.at(a_position.down(1).right(1)) == Letter::S)
-                || (self.at(a_position.up(1).left(1)) == Letter::S
-                    && self.at(a_position.down(1).right(1)) == Letter::M))
-                && ((self.at(a_position.down(1).left(1)) == Letter::M
-                    && self.at(a_position.up(1).right(1)) == Letter::S)
-                    || (self.at(a_position.down(1).left(1)) == Letter::S
-                        && self.at(a_position.up(1).right(1)) == Letter::M))
-            {
-                x_mas_occurrences += 1;
-            }
-        }
+    // Counts the places where `word` is written twice along both diagonals, crossing at its center letter. Each arm may
+    // be read in either direction.
+    fn count_crossed_word_occurrences(&self, Word(word): &Word) -> usize {
+        let half_length = (word.len() / 2) as isize;
+        let center_positions = self.find_all_letter_positions(word[word.len() / 2]);
+        let matches_diagonal = |center: Position, (d_row, d_col): (isize, isize)| {
+            let letters: Option<Vec<_>> = (-half_length..=half_length)
+                .map(|i| {
+                    center
+                        .checked_offset((i * d_row, i * d_col), self.size, self.size)
+                        .map(|position| self.at(position))
+                })
+                .collect();
+            letters.is_some_and(|letters| {
+                letters.iter().eq(word.iter()) || letters.iter().eq(word.iter().rev())
+            })
+        };
 
-        x_mas_occurrences
+        center_positions
+            .into_iter()
+            .filter(|center| {
+                matches_diagonal(*center, (1, 1)) && matches_diagonal(*center, (1, -1))
+            })
+            .count()
     }
 
     fn find_all_letter_positions(&self, letter: Letter) -> Vec<Position> {
@@ -174,21 +201,127 @@ impl Grid {
     }
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Count the places where a word crosses itself in an X, like "MAS" in part 2.
+    Crossed {
+        /// Word to look for, of odd length and made of uppercase letters, e.g. "XMASX".
+        word: Word,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Crossed { word } => {
+                let grid = SolverImpl::parse(file)?;
+                println!(
+                    "Crossed word appeared {} times.",
+                    grid.count_crossed_word_occurrences(&word)
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Crossed { .. } => Some(Part::Part2),
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Grid;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let grid: Vec<_> = Lines::new(file)
+            .map(|line| line.parse_chars())
+            .collect::<Result<_, _>>()?;
+        let size = grid.len();
+        Ok(Grid { grid, size })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let grid = Grid::new(&file);
-        println!("XMAS appeared {} times.", grid.count_all_xmas_occurrences());
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let grid = Self::parse_shared(file)?;
+        let answer = grid.count_all_xmas_occurrences();
+        Ok(Answer::new(
+            format!("XMAS appeared {answer} times."),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let grid = Grid::new(&file);
-        println!(
-            "X-MAS appeared {} times.",
-            grid.count_all_x_mas_occurrences()
-        );
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let grid = Self::parse_shared(file)?;
+        let answer = grid.count_all_x_mas_occurrences();
+        Ok(Answer::new(
+            format!("X-MAS appeared {answer} times."),
+            answer,
+        ))
     }
 }
 
 generate_benchmark!(day4);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn crossed_words_are_read_both_ways() {
+        let grid = SolverImpl::parse("MXS\nXAX\nMXS\n").unwrap();
+        assert_eq!(grid.count_all_x_mas_occurrences(), 1);
+
+        let grid = SolverImpl::parse("XXXXS\nXMXAX\nXXAXX\nXMXAX\nXXXXS\n").unwrap();
+        assert_eq!(
+            grid.count_crossed_word_occurrences(&"XMASA".parse().unwrap()),
+            0
+        );
+        assert_eq!(
+            grid.count_crossed_word_occurrences(&"XMAAS".parse().unwrap()),
+            1
+        );
+        assert_eq!(
+            grid.count_crossed_word_occurrences(&"A".parse().unwrap()),
+            3
+        );
+    }
+
+    #[test]
+    fn crossed_words_have_a_center() {
+        assert!("XMAS".parse::<Word>().is_err());
+        assert!("XMb".parse::<Word>().is_err());
+    }
+
+    #[test]
+    fn crossed_words_may_use_any_letter() {
+        let grid = SolverImpl::parse("CXT\nXAX\nCXT\n").unwrap();
+        assert_eq!(
+            grid.count_crossed_word_occurrences(&"CAT".parse().unwrap()),
+            1
+        );
+        assert_eq!(grid.count_all_x_mas_occurrences(), 0);
+        assert!(SolverImpl::parse("XMAS\nxmas\n").is_err());
+    }
+}
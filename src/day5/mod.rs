@@ -1,14 +1,20 @@
-use std::{
-    collections::{HashMap, HashSet},
-    num::ParseIntError,
-    ops::{Deref, DerefMut},
-    str::{FromStr, Lines},
+use std::{cmp::Ordering, num::ParseIntError, ops::Deref, str::FromStr};
+
+use itertools::Itertools;
+
+use crate::{
+    args::Part,
+    dag::Dag,
+    error::AocError,
+    input::{Line, Lines, Parse, ParseError},
+    solver::{Answer, Solver, Variant},
+    utils::{FxHashMap, FxHashSet, generate_benchmark},
+    viz::Visualize,
 };
 
-use crate::{solver::Solver, utils::generate_benchmark};
-
+/// Page number of a safety manual.
 #[derive(Eq, PartialEq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
-struct Page(u16);
+pub struct Page(pub u16);
 
 impl FromStr for Page {
     type Err = ParseIntError;
@@ -19,6 +25,12 @@ impl FromStr for Page {
     }
 }
 
+impl std::fmt::Display for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 impl Deref for Page {
     type Target = u16;
 
@@ -27,182 +39,347 @@ impl Deref for Page {
     }
 }
 
-#[derive(Default, Debug)]
-struct TopologicalPage {
-    // Set of pages that must be after this page
-    // (i.e. outgoing in a topological graph).
-    must_be_after_pages: HashSet<Page>,
-    // Num of pages in the setup that must be before this page
-    // (i.e. incoming in a topological graph).
-    num_must_be_before_pages: usize,
+// Sorts topologically `pages` based on the `ordering_rules` which apply to them.
+// If `pages` was already sorted topologically, returns None.
+// Otherwise, returns the topologically sorted list of pages. Fails if the rules go in circles.
+fn sort_topologically(
+    ordering_rules: &OrderingRules,
+    pages: &[Page],
+) -> Result<Option<Vec<Page>>, AocError> {
+    let pages_of_interest: FxHashSet<_> = pages.iter().copied().collect();
+    let mut dag = Dag::default();
+    for page in pages {
+        dag.add_node(*page);
+        // Only the rules regarding pages of this update apply.
+        for must_be_after_page in ordering_rules
+            .get(page)
+            .into_iter()
+            .flatten()
+            .filter(|must_be_after_page| pages_of_interest.contains(*must_be_after_page))
+        {
+            dag.add_edge(*page, *must_be_after_page);
+        }
+    }
+
+    let topologically_sorted_pages = dag.topological_order().map_err(|e| {
+        AocError::NoSolution(format!("the pages of an update can't be ordered, {e}"))
+    })?;
+    Ok((pages != topologically_sorted_pages).then_some(topologically_sorted_pages))
 }
 
-#[derive(Debug)]
-struct TopologicalPages {
-    topological_pages: HashMap<Page, TopologicalPage>,
+/// Rules such as "47|53", stating that page 47 must be printed before page 53 when an update has both.
+#[derive(Clone, Debug)]
+pub struct OrderingRules {
+    // Maps a Page to the set of Pages that the former must be present before.
+    // I.e. 5 => {3,9} means that page 5 must be before both page 3 and page 9.
+    page_and_before_pages: FxHashMap<Page, FxHashSet<Page>>,
 }
 
-impl Deref for TopologicalPages {
-    type Target = HashMap<Page, TopologicalPage>;
+impl OrderingRules {
+    /// Compares pages by the rule between them, if any. Pages without a rule between them compare as equal, so this
+    /// is only a total order over pages which all have a rule between each other, see `is_total_order_over`.
+    pub fn cmp(&self, page_a: Page, page_b: Page) -> Ordering {
+        let must_be_before = |before: Page, after: Page| {
+            self.get(&before)
+                .is_some_and(|after_pages| after_pages.contains(&after))
+        };
+
+        if must_be_before(page_a, page_b) {
+            Ordering::Less
+        } else if must_be_before(page_b, page_a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.topological_pages
+    /// Whether the rules order `pages` completely and consistently, i.e. every pair of distinct pages has a rule in
+    /// exactly one direction, and rules don't go in circles. Only then can `cmp` sort them.
+    pub fn is_total_order_over(&self, pages: &[Page]) -> bool {
+        let pages: Vec<_> = pages.iter().copied().unique().collect();
+        let is_complete = pages.iter().tuple_combinations().all(|(page_a, page_b)| {
+            self.cmp(*page_a, *page_b) != Ordering::Equal
+                && self.cmp(*page_a, *page_b) == self.cmp(*page_b, *page_a).reverse()
+        });
+        if !is_complete {
+            return false;
+        }
+
+        // Rules between all pairs are transitive iff they don't go in circles, in which case the first page is before
+        // all others, the second before all others but the first, and so on: each page is before a different number
+        // of pages. Sorting can't tell instead, as it may panic when rules go in circles.
+        let num_pages_after: FxHashSet<_> = pages
+            .iter()
+            .map(|page_a| {
+                pages
+                    .iter()
+                    .filter(|page_b| self.cmp(*page_a, **page_b) == Ordering::Less)
+                    .count()
+            })
+            .collect();
+        num_pages_after.len() == pages.len()
     }
 }
 
-impl DerefMut for TopologicalPages {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.topological_pages
+// Sorts `pages` with the ordering rules as a comparator, which must be a total order over them, see
+// `is_total_order_over`: sorting may panic otherwise.
+fn sort_by_rules(ordering_rules: &OrderingRules, pages: &[Page]) -> Vec<Page> {
+    let mut sorted_pages = pages.to_vec();
+    sorted_pages.sort_by(|page_a, page_b| ordering_rules.cmp(*page_a, *page_b));
+    sorted_pages
+}
+
+// How to reorder the pages of an update.
+#[derive(Clone, Copy, Debug)]
+enum Sort {
+    Topological,
+    // Only valid if the rules are a total order over the pages of every update.
+    Comparator,
+}
+
+impl Deref for OrderingRules {
+    type Target = FxHashMap<Page, FxHashSet<Page>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.page_and_before_pages
     }
 }
 
-impl TopologicalPages {
-    fn new(ordering_rules: &OrderingRules, pages_of_interest: &HashSet<Page>) -> Self {
-        let mut topological_pages = HashMap::<Page, TopologicalPage>::default();
+// Parses a line like "75,47,61,53,29" into the pages of an update.
+fn parse_update(line: Line) -> Result<Vec<Page>, ParseError> {
+    line.text.split(',').map(|page| line.parse(page)).collect()
+}
 
-        for page_of_interest in pages_of_interest {
-            // Check if the page of interest has rules regarding pages that must be present after.
-            // If not, nothing to do here.
-            // If so, only grab the ones that apply to this problem.
-            let must_be_after_pages = match ordering_rules.get(page_of_interest) {
-                Some(must_be_after_pages) => must_be_after_pages.intersection(pages_of_interest),
-                None => continue,
-            };
+#[derive(Clone)]
+pub struct SafetyManual {
+    ordering_rules: OrderingRules,
+    updates: Vec<Vec<Page>>,
+    // Whether the rules are a total order over the pages of every update, in which case pages can be reordered by
+    // comparing them rather than by sorting them topologically.
+    is_ordering_total: bool,
+}
 
-            // Update the set of outgoing nodes (i.e. pages that must be after this one).
-            topological_pages
-                .entry(*page_of_interest)
-                .or_default()
-                .must_be_after_pages
-                .extend(must_be_after_pages.clone());
-
-            // Increment the counter of incoming edge to each outgoing node.
-            for must_be_after_page in must_be_after_pages {
-                topological_pages
-                    .entry(*must_be_after_page)
-                    .or_default()
-                    .num_must_be_before_pages += 1;
-            }
+impl SafetyManual {
+    fn new(ordering_rules: OrderingRules, updates: Vec<Vec<Page>>) -> Self {
+        let is_ordering_total = updates
+            .iter()
+            .all(|pages| ordering_rules.is_total_order_over(pages));
+
+        Self {
+            ordering_rules,
+            updates,
+            is_ordering_total,
         }
+    }
 
-        Self { topological_pages }
-    }
-
-    // Sorts topologically `pages` based on the `ordering_rules`.
-    // If `pages` was already sorted topologically, returns None.
-    // Otherwise, returns the topologically sorted list of pages.
-    fn sort_topologically(ordering_rules: &OrderingRules, pages: &[Page]) -> Option<Vec<Page>> {
-        let mut topological_pages = Self::new(ordering_rules, &pages.iter().cloned().collect());
-        let mut topologically_sorted_pages = Vec::new();
-
-        // Topological sorting is a loop that:
-        //   1. Finds the root of the sub-DAG, i.e. the node in a DAG with no incoming edges.
-        //   2. Pushes the root in the topologically sorted list.
-        //   3. Removes the root of the sub-DAG.
-        //   4. Decrements the counter of incoming edges for each node which the root connected to.
-        while !topological_pages.is_empty() {
-            let topological_root_page = *topological_pages
-                .iter()
-                .find(|(_, topological_page)| topological_page.num_must_be_before_pages == 0)
-                .unwrap()
-                .0;
-            topologically_sorted_pages.push(topological_root_page);
-            let topological_root_page = topological_pages.remove(&topological_root_page).unwrap();
-
-            for must_be_after_page in topological_root_page.must_be_after_pages {
-                topological_pages
-                    .get_mut(&must_be_after_page)
-                    .unwrap()
-                    .num_must_be_before_pages -= 1;
+    pub fn ordering_rules(&self) -> &OrderingRules {
+        &self.ordering_rules
+    }
+
+    // Reorders `pages` with the given `sort`, returning None if they were already in order.
+    fn reorder(&self, pages: &[Page], sort: Sort) -> Result<Option<Vec<Page>>, AocError> {
+        match sort {
+            Sort::Topological => sort_topologically(&self.ordering_rules, pages),
+            Sort::Comparator => {
+                let sorted_pages = sort_by_rules(&self.ordering_rules, pages);
+                Ok((pages != sorted_pages).then_some(sorted_pages))
             }
         }
+    }
 
-        if pages == topologically_sorted_pages {
-            None
+    // Sorting by comparator is fastest, but requires rules to be a total order.
+    fn fastest_sort(&self) -> Sort {
+        if self.is_ordering_total {
+            Sort::Comparator
         } else {
-            Some(topologically_sorted_pages)
+            Sort::Topological
         }
     }
-}
 
-#[derive(Debug)]
-struct OrderingRules {
-    // Maps a Page to the set of Pages that the former must be present before.
-    // I.e. 5 => {3,9} means that page 5 must be before both page 3 and page 9.
-    page_and_before_pages: HashMap<Page, HashSet<Page>>,
-}
+    fn sum_of_ordered_middle_pages(&self) -> Result<u16, AocError> {
+        let sort = self.fastest_sort();
+        let mut sum = 0;
+        for pages in &self.updates {
+            if self.reorder(pages, sort)?.is_none() {
+                sum += *pages[(pages.len() - 1) / 2];
+            }
+        }
+        Ok(sum)
+    }
 
-impl Deref for OrderingRules {
-    type Target = HashMap<Page, HashSet<Page>>;
+    fn sum_of_reordered_middle_pages(&self, sort: Sort) -> Result<u16, AocError> {
+        if matches!(sort, Sort::Comparator) && !self.is_ordering_total {
+            return Err(AocError::Unsupported(String::from(
+                "the ordering rules don't define a total order over the pages of every update",
+            )));
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.page_and_before_pages
+        let mut sum = 0;
+        for pages in &self.updates {
+            if let Some(sorted_pages) = self.reorder(pages, sort)? {
+                sum += *sorted_pages[(sorted_pages.len() - 1) / 2];
+            }
+        }
+        Ok(sum)
     }
 }
 
-impl OrderingRules {
-    fn new(lines: &mut Lines) -> Self {
-        let mut page_and_before_pages = HashMap::<Page, HashSet<Page>>::default();
+pub struct SolverImpl {}
+
+impl SolverImpl {
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("topological", |file| {
+            Ok(Self::parse(file)?
+                .sum_of_reordered_middle_pages(Sort::Topological)?
+                .to_string())
+        }),
+        Variant::new("comparator", |file| {
+            Ok(Self::parse(file)?
+                .sum_of_reordered_middle_pages(Sort::Comparator)?
+                .to_string())
+        }),
+    ];
+}
+
+impl Visualize for SolverImpl {}
 
-        for line in lines {
-            if line.is_empty() {
+impl Parse for SolverImpl {
+    type Input = SafetyManual;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let mut page_and_before_pages = FxHashMap::<Page, FxHashSet<Page>>::default();
+
+        // Ordering rules come first, and are separated from updates by an empty line.
+        for line in lines.by_ref() {
+            if line.text.is_empty() {
                 break;
             }
 
-            let (before, after) = line.split_once('|').unwrap();
-            let (before, after) = (before.parse().unwrap(), after.parse().unwrap());
-
+            let (before, after) = line.split_once(line.text, "|")?;
             page_and_before_pages
-                .entry(before)
+                .entry(line.parse(before)?)
                 .or_default()
-                .insert(after);
+                .insert(line.parse(after)?);
         }
 
-        Self {
-            page_and_before_pages,
-        }
+        Ok(SafetyManual::new(
+            OrderingRules {
+                page_and_before_pages,
+            },
+            lines.map(parse_update).collect::<Result<_, _>>()?,
+        ))
     }
 }
 
-pub struct SolverImpl {}
-
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let mut lines = file.lines();
-        let ordering_rules = OrderingRules::new(&mut lines);
-        let mut sum_middle_pages = 0;
+    type Command = Part;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::parse_shared(file)?.sum_of_ordered_middle_pages()?;
+        Ok(Answer::new(
+            format!("The sum of valid middle pages is {answer}"),
+            answer,
+        ))
+    }
 
-        for line in lines {
-            let pages: Vec<Page> = line.split(',').map(|page| page.parse().unwrap()).collect();
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let safety_manual = Self::parse_shared(file)?;
+        let answer = safety_manual.sum_of_reordered_middle_pages(safety_manual.fastest_sort())?;
+        Ok(Answer::new(
+            format!("The sum of valid middle pages is {answer}"),
+            answer,
+        ))
+    }
 
-            if TopologicalPages::sort_topologically(&ordering_rules, &pages).is_none() {
-                let middle_page = *pages[(pages.len() - 1) / 2];
-                sum_middle_pages += middle_page;
-            }
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => &[],
+            Part::Part2 => Self::PART2_VARIANTS,
         }
+    }
+}
+
+generate_benchmark!(day5);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
 
-        println!("The sum of valid middle pages is {sum_middle_pages}");
+    fn pages(pages: &[u16]) -> Vec<Page> {
+        pages.iter().copied().map(Page).collect()
     }
 
-    fn solve_part2(file: &str) {
-        let mut lines = file.lines();
-        let ordering_rules = OrderingRules::new(&mut lines);
-        let mut sum_middle_pages = 0;
+    #[test]
+    fn rules_compare_pages() {
+        let safety_manual = SolverImpl::parse("47|53\n97|47\n97|53\n\n97,47,53\n").unwrap();
+        let ordering_rules = safety_manual.ordering_rules();
+
+        assert_eq!(ordering_rules.cmp(Page(47), Page(53)), Ordering::Less);
+        assert_eq!(ordering_rules.cmp(Page(53), Page(97)), Ordering::Greater);
+        assert_eq!(ordering_rules.cmp(Page(53), Page(1)), Ordering::Equal);
+        assert!(ordering_rules.is_total_order_over(&pages(&[53, 97, 47])));
+        assert!(!ordering_rules.is_total_order_over(&pages(&[53, 97, 1])));
+        assert!(safety_manual.is_ordering_total);
+    }
 
-        for line in lines {
-            let pages: Vec<Page> = line.split(',').map(|page| page.parse().unwrap()).collect();
+    #[test]
+    fn circular_rules_are_not_a_total_order() {
+        let safety_manual = SolverImpl::parse("1|2\n2|3\n3|1\n\n1,2,3\n").unwrap();
+
+        assert!(!safety_manual.is_ordering_total);
+        assert!(
+            safety_manual
+                .sum_of_reordered_middle_pages(Sort::Comparator)
+                .is_err()
+        );
+    }
 
-            if let Some(topologically_sorted_pages) =
-                TopologicalPages::sort_topologically(&ordering_rules, &pages)
-            {
-                let middle_page =
-                    *topologically_sorted_pages[(topologically_sorted_pages.len() - 1) / 2];
-                sum_middle_pages += middle_page;
+    #[test]
+    fn complete_circular_rules_fail_to_order_pages() {
+        // Enough pages that sorting them would use the algorithm which may panic on circular rules.
+        let num_pages = 24;
+        let mut file = String::new();
+        for page_a in 0..num_pages {
+            for page_b in page_a + 1..num_pages {
+                // Each page is before the next 11 ones, wrapping around, so that every pair has a rule.
+                let (before, after) = if page_b - page_a < num_pages / 2 {
+                    (page_a, page_b)
+                } else {
+                    (page_b, page_a)
+                };
+                file += &format!("{before}|{after}\n");
             }
         }
+        file += "\n";
+        file += &(0..num_pages).join(",");
+        file += "\n";
+        let safety_manual = SolverImpl::parse(&file).unwrap();
+
+        assert!(!safety_manual.is_ordering_total);
+        assert!(matches!(
+            SolverImpl::solve_part1(&file),
+            Err(AocError::NoSolution(_))
+        ));
+        assert!(matches!(
+            SolverImpl::solve_part2(&file),
+            Err(AocError::NoSolution(_))
+        ));
+    }
 
-        println!("The sum of valid middle pages is {sum_middle_pages}");
+    #[test]
+    fn sorts_agree_on_the_example() {
+        let safety_manual = SolverImpl::parse(include_str!("example.txt")).unwrap();
+
+        assert!(safety_manual.is_ordering_total);
+        assert_eq!(
+            safety_manual
+                .sum_of_reordered_middle_pages(Sort::Comparator)
+                .unwrap(),
+            safety_manual
+                .sum_of_reordered_middle_pages(Sort::Topological)
+                .unwrap()
+        );
     }
 }
-
-generate_benchmark!(day5);
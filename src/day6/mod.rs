@@ -1,6 +1,16 @@
-use crate::solver::Solver;
-use crate::utils::{Position, generate_benchmark, shard_and_solve_concurrently};
-use hashbrown::HashSet;
+use clap::Subcommand;
+use itertools::Itertools;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::grid::Grid;
+use crate::input::{Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver};
+use crate::utils::{
+    FxHashSet, IterSorted, Position, Simulation, generate_benchmark, shard_and_solve_concurrently,
+    until_cancelled,
+};
+use crate::viz::{Frame, LegendEntry, Style, Visualize};
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 enum Tile {
@@ -10,22 +20,32 @@ enum Tile {
     Outside,
 }
 
-impl From<char> for Tile {
-    fn from(value: char) -> Self {
+impl TryFrom<char> for Tile {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
         match value {
-            '.' => Self::Unvisited,
-            '^' => Self::Visited,
-            '#' => Self::Obstructed,
-            _ => unreachable!(),
+            '.' => Ok(Self::Unvisited),
+            // Guards start on a visited tile.
+            '^' | '>' | 'v' | '<' => Ok(Self::Visited),
+            '#' => Ok(Self::Obstructed),
+            _ => Err(value),
         }
     }
 }
 
-impl Tile {
-    fn is_visited(&self) -> bool {
-        self == &Self::Visited
+impl From<&Tile> for char {
+    fn from(value: &Tile) -> Self {
+        match value {
+            Tile::Visited => 'X',
+            Tile::Unvisited => '.',
+            Tile::Obstructed => '#',
+            Tile::Outside => ' ',
+        }
     }
+}
 
+impl Tile {
     fn is_unvisited(&self) -> bool {
         self == &Self::Unvisited
     }
@@ -47,6 +67,38 @@ enum Direction {
     Left,
 }
 
+impl Direction {
+    // Bit of the direction within the directions seen on a tile.
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl TryFrom<char> for Direction {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            '^' => Ok(Self::Up),
+            '>' => Ok(Self::Right),
+            'v' => Ok(Self::Down),
+            '<' => Ok(Self::Left),
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<Direction> for char {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 struct Guard {
     position: Position,
@@ -58,9 +110,9 @@ impl Guard {
     // That is, try to move one tile into the current direction. If the new tile is obstructed, rotate to the right,
     // and try in that new direction. Stops there if the tile to the right is also obstructed.
     // Returns true if the guard is still patrolling, aka it is not out of bounds. Otherwise, returns false.
-    fn patrol(&mut self, lab: &Vec<Vec<Tile>>) -> bool {
+    fn patrol(&mut self, lab: &Grid<Tile>) -> bool {
         let Position { row, col } = self.position;
-        if lab[row][col].is_outside() {
+        if lab[self.position].is_outside() {
             return false;
         }
 
@@ -84,8 +136,8 @@ impl Guard {
                 ),
             };
 
-        let new_tile = lab[new_position.row][new_position.col];
-        let alternative_new_tile = lab[alternative_new_position.row][alternative_new_position.col];
+        let new_tile = lab[new_position];
+        let alternative_new_tile = lab[alternative_new_position];
         if new_tile.is_outside() {
             false
         } else if !new_tile.is_obstructed() {
@@ -105,141 +157,582 @@ impl Guard {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PatrolState {
+    Patrolling,
+    Exited,
+    Looping,
+}
+
+// Two guards running into each other while patrolling at the same time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encounter {
+    // Both guards moved onto the same tile.
+    Met,
+    // Both guards swapped tiles, walking past each other.
+    Crossed,
+}
+
+// Patrols of all guards of the lab, moving one tile each at every step.
 #[derive(Clone, Debug)]
-struct LabSimulation {
+struct Patrols {
+    // Tiles visited by any guard.
+    visited_tiles: FxHashSet<Position>,
+    // How each guard's patrol ended, and after how many steps, in the order guards were found.
+    outcomes: Vec<(PatrolState, usize)>,
+    // Encounters between two guards, as the step and position at which they happened, and the guards' indices.
+    encounters: Vec<(usize, Position, (usize, usize), Encounter)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LabSimulation {
     // Note that the lab is padded all around with "outside" tiles.
-    lab: Vec<Vec<Tile>>,
+    lab: Grid<Tile>,
+    // Guard patrolling in parts 1 and 2, which is the first one found.
     guard: Guard,
-    visited_tiles: HashSet<Position>,
-    previous_guards: HashSet<Guard>,
+    // All guards found in the lab, reading it line by line, including `guard`.
+    initial_guards: Vec<Guard>,
+    visited_tiles: FxHashSet<Position>,
+    // Directions the guard faced on each tile, as bits. Cheaper to check than hashing every guard seen.
+    seen_directions: Grid<u8>,
+    state: PatrolState,
+    num_steps: usize,
 }
 
 impl LabSimulation {
-    fn new(file: &str) -> Self {
-        let mut lab = Vec::new();
-        let mut guard_position = None;
+    fn at(&self, position: Position) -> &Tile {
+        &self.lab[position]
+    }
 
-        // Top empty row for the "outside" tiles.
-        lab.push(Vec::new());
+    fn at_mut(&mut self, position: Position) -> &mut Tile {
+        &mut self.lab[position]
+    }
 
-        for (mut row, line) in file.lines().enumerate() {
-            // Plus one to include the top "outside" row.
-            row += 1;
+    // Both parts of the problem are about a single guard.
+    fn ensure_single_guard(&self) -> Result<(), AocError> {
+        if self.initial_guards.len() > 1 {
+            return Err(AocError::Unsupported(format!(
+                "found {} guards, but both parts are about a single one, simulate them with `guards` instead",
+                self.initial_guards.len()
+            )));
+        }
 
-            // Add an "outside" tile to the left of the lab.
-            lab.push(vec![Tile::Outside; 1]);
+        Ok(())
+    }
 
-            for (mut col, tile) in line.chars().enumerate() {
-                // Plus one to include the left "outside" column.
-                col += 1;
+    // Runs the patrols of all guards at once, each moving one tile per step, until they all exited the lab or got
+    // stuck in a loop. Guards don't block each other, but their encounters are recorded.
+    fn run_guard_patrols(&self) -> Patrols {
+        let num_guards = self.initial_guards.len();
+        let mut guards = self.initial_guards.clone();
+        let mut outcomes = vec![(PatrolState::Patrolling, 0); num_guards];
+        let mut seen_directions = vec![self.seen_directions.map(|_| 0); num_guards];
+        for (guard, seen_directions) in guards.iter().zip(&mut seen_directions) {
+            seen_directions[guard.position] = guard.direction.bit();
+        }
+        let mut visited_tiles: FxHashSet<_> = guards.iter().map(|guard| guard.position).collect();
+        let mut encounters = Vec::new();
+
+        let mut step = 0;
+        while outcomes
+            .iter()
+            .any(|(state, _)| *state == PatrolState::Patrolling)
+        {
+            step += 1;
+            let previous_positions: Vec<_> = guards.iter().map(|guard| guard.position).collect();
+            // Only guards moving at this step can run into each other.
+            let mut moved = vec![false; num_guards];
+            for (i, guard) in guards.iter_mut().enumerate() {
+                let (state, num_steps) = &mut outcomes[i];
+                if *state != PatrolState::Patrolling {
+                    continue;
+                }
+                if !guard.patrol(&self.lab) {
+                    *state = PatrolState::Exited;
+                    continue;
+                }
+                *num_steps += 1;
+                moved[i] = true;
 
-                let tile: Tile = tile.into();
-                if tile.is_visited() {
-                    guard_position = Some(Position { row, col });
+                visited_tiles.insert(guard.position);
+                let seen_directions = &mut seen_directions[i][guard.position];
+                if *seen_directions & guard.direction.bit() != 0 {
+                    *state = PatrolState::Looping;
                 }
+                *seen_directions |= guard.direction.bit();
+            }
 
-                lab[row].push(tile);
+            for (i, j) in (0..num_guards).tuple_combinations() {
+                if !moved[i] || !moved[j] {
+                    continue;
+                }
+
+                let (position_i, position_j) = (guards[i].position, guards[j].position);
+                if position_i == position_j && previous_positions[i] != previous_positions[j] {
+                    encounters.push((step, position_i, (i, j), Encounter::Met));
+                } else if position_i == previous_positions[j]
+                    && position_j == previous_positions[i]
+                    && position_i != position_j
+                {
+                    encounters.push((step, position_i, (i, j), Encounter::Crossed));
+                }
             }
+        }
 
-            // Add an "outside" tile to the right of the lab.
-            lab[row].push(Tile::Outside);
+        Patrols {
+            visited_tiles,
+            outcomes,
+            encounters,
         }
+    }
 
-        // Minus one to discard the top "outside" row.
-        let lab_size = lab.len() - 1;
-        // Populate the top "outside" row now that we know its size.
-        // Plus two to include the left "outside" column and the right "outside" column.
-        lab[0].extend(std::iter::repeat_n(Tile::Outside, lab_size + 2));
-        // Populate the bottom "outside" row now that we know its size.
-        // Plus two to include the left "outside" column and the right "outside" column.
-        lab.push(vec![Tile::Outside; lab_size + 2]);
+    // Runs the guard patrol, and returns the set of tiles visited by the guard
+    // until it exited the lab. Returns None if the guard got stuck in a loop.
+    fn run_guard_patrol(mut self) -> Option<FxHashSet<Position>> {
+        self.run_to_completion();
+        match self.state {
+            PatrolState::Exited => Some(self.visited_tiles),
+            PatrolState::Patrolling | PatrolState::Looping => None,
+        }
+    }
 
-        let position = guard_position.unwrap();
-        let guard = Guard {
-            position,
-            direction: Direction::Up,
-        };
+    // Finds all tiles where adding an obstruction would get the guard stuck in a loop.
+    fn find_loop_obstruction_sites(&self) -> Vec<Position> {
+        let initial_guard_position = self.guard.position;
+        let mut potential_obstruction_sites = self.clone().run_guard_patrol().unwrap();
+        // Problem states that the initial guard position cannot be a potential obstruction site.
+        potential_obstruction_sites.remove(&initial_guard_position);
+        // Sets iterate in an arbitrary order, so sites are sorted to be tried in a stable one.
+        let potential_obstruction_sites: Vec<_> =
+            potential_obstruction_sites.iter_sorted().copied().collect();
 
-        Self {
-            lab,
-            guard,
-            visited_tiles: HashSet::from([position]),
-            previous_guards: HashSet::from([guard]),
+        shard_and_solve_concurrently(
+            potential_obstruction_sites,
+            self.clone(),
+            |potential_obstruction_sites, lab_simulation| {
+                let mut loop_obstruction_sites = Vec::new();
+                for potential_obstruction_site in until_cancelled(potential_obstruction_sites) {
+                    let mut tentative_lab_simulation = lab_simulation.clone();
+                    *tentative_lab_simulation.at_mut(potential_obstruction_site) = Tile::Obstructed;
+
+                    if tentative_lab_simulation.run_guard_patrol().is_none() {
+                        loop_obstruction_sites.push(potential_obstruction_site);
+                    }
+                }
+
+                loop_obstruction_sites
+            },
+        )
+        .flatten()
+        .collect()
+    }
+
+    fn frame(&self, caption: impl Into<String>) -> Frame {
+        let mut frame = Frame::new(self.lab.rows(), caption);
+        let Position { row, col } = self.guard.position;
+        frame.grid[row][col] = self.guard.direction.into();
+        frame
+    }
+}
+
+impl Simulation for LabSimulation {
+    // Moves the guard one tile, and records where it went.
+    fn step(&mut self) {
+        if !self.guard.patrol(&self.lab) {
+            self.state = PatrolState::Exited;
+            return;
         }
+        self.num_steps += 1;
+
+        let guard_position = self.guard.position;
+        if self.at(guard_position).is_unvisited() {
+            self.visited_tiles.insert(guard_position);
+            *self.at_mut(guard_position) = Tile::Visited;
+        } else if self.seen_directions[guard_position] & self.guard.direction.bit() != 0 {
+            // The guard has previously been at this position looking in
+            // the very same direction. This is a loop, exit!
+            self.state = PatrolState::Looping;
+            return;
+        }
+
+        self.seen_directions[guard_position] |= self.guard.direction.bit();
     }
 
-    fn at(&self, position: Position) -> &Tile {
-        &self.lab[position.row][position.col]
+    fn is_done(&self) -> bool {
+        self.state != PatrolState::Patrolling
     }
 
-    fn at_mut(&mut self, position: Position) -> &mut Tile {
-        &mut self.lab[position.row][position.col]
+    fn render(&self) -> Frame {
+        let num_steps = self.num_steps;
+        self.frame(match self.state {
+            PatrolState::Patrolling => format!("Step {num_steps}."),
+            PatrolState::Exited => format!(
+                "The guard left the lab after {num_steps} steps, visiting {} unique tiles.",
+                self.visited_tiles.len()
+            ),
+            PatrolState::Looping => {
+                format!("The guard got stuck in a loop after {num_steps} steps.")
+            }
+        })
     }
+}
 
-    // Runs the guard patrol, and returns the set of tiles visited by the guard
-    // until it exited the lab. Returns None if the guard got stuck in a loop.
-    fn run_guard_patrol(mut self) -> Option<HashSet<Position>> {
-        while self.guard.patrol(&self.lab) {
-            let guard_position = self.guard.position;
-
-            if self.at(guard_position).is_unvisited() {
-                self.visited_tiles.insert(guard_position);
-                *self.at_mut(guard_position) = Tile::Visited;
-            } else if self.previous_guards.contains(&self.guard) {
-                // The guard has previously been at this position looking in
-                // the very same direction. This is a loop, exit!
-                return None;
+pub struct SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = LabSimulation;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let mut lines = Lines::new(file);
+        let mut rows = Vec::new();
+        let mut initial_guards = Vec::new();
+
+        for line in lines.by_ref() {
+            // Add an "outside" tile to the left and to the right of the lab.
+            let mut row = vec![Tile::Outside];
+            row.extend(line.parse_chars::<Tile>()?);
+            row.push(Tile::Outside);
+            if rows
+                .first()
+                .is_some_and(|first: &Vec<_>| first.len() != row.len())
+            {
+                return Err(line.error("all rows of the lab must have the same length"));
             }
 
-            self.previous_guards.insert(self.guard);
+            for (col, c) in line.text.chars().enumerate() {
+                if let Ok(direction) = Direction::try_from(c) {
+                    // The top and left "outside" tiles make line numbers and columns match rows and columns.
+                    initial_guards.push(Guard {
+                        position: Position {
+                            row: line.number,
+                            col: col + 1,
+                        },
+                        direction,
+                    });
+                }
+            }
+            rows.push(row);
         }
 
-        Some(self.visited_tiles)
+        // Add "outside" rows at the top and at the bottom of the lab, now that we know their size.
+        let outside_row = vec![Tile::Outside; rows.first().map_or(0, Vec::len)];
+        let lab = Grid::from_rows(
+            std::iter::once(outside_row.clone())
+                .chain(rows)
+                .chain([outside_row]),
+        );
+
+        let guard = *initial_guards
+            .first()
+            .ok_or_else(|| lines.error_at_end("the guard is missing"))?;
+
+        let mut seen_directions = Grid::filled(lab.num_rows(), lab.num_cols(), 0);
+        seen_directions[guard.position] = guard.direction.bit();
+
+        Ok(LabSimulation {
+            lab,
+            guard,
+            initial_guards,
+            visited_tiles: FxHashSet::from_iter([guard.position]),
+            seen_directions,
+            state: PatrolState::Patrolling,
+            num_steps: 0,
+        })
     }
 }
 
-pub struct SolverImpl {}
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Simulate the patrols of all guards of the lab at once, each starting in the direction it faces, and report
+    /// the tiles they visited along with the guards running into each other.
+    Guards,
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Guards => {
+                let lab_simulation = SolverImpl::parse(file)?;
+                let patrols = lab_simulation.run_guard_patrols();
+                for (guard, (state, num_steps)) in
+                    lab_simulation.initial_guards.iter().zip(&patrols.outcomes)
+                {
+                    let Position { row, col } = guard.position;
+                    let outcome = match state {
+                        PatrolState::Exited => "left the lab",
+                        _ => "got stuck in a loop",
+                    };
+                    println!(
+                        "The guard starting at line {row}, column {col} {outcome} after {num_steps} steps."
+                    );
+                }
+                for (step, Position { row, col }, (i, j), encounter) in &patrols.encounters {
+                    let encounter = match encounter {
+                        Encounter::Met => "met at",
+                        Encounter::Crossed => "walked past each other next to",
+                    };
+                    println!(
+                        "Guards {} and {} {encounter} line {row}, column {col} at step {step}.",
+                        i + 1,
+                        j + 1
+                    );
+                }
+                println!(
+                    "The guards visited {} unique tiles.",
+                    patrols.visited_tiles.len()
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Guards => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
 
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let lab_simulation = LabSimulation::new(file);
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let lab_simulation = Self::parse_shared(file)?;
+        lab_simulation.ensure_single_guard()?;
         let unique_visited_tiles = lab_simulation.run_guard_patrol().unwrap();
-        println!(
-            "The guard visited {} unique tiles.",
-            unique_visited_tiles.len()
-        );
+        let answer = unique_visited_tiles.len();
+        Ok(Answer::new(
+            format!("The guard visited {answer} unique tiles."),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let lab_simulation = LabSimulation::new(file);
-        let initial_guard_position = lab_simulation.guard.position;
-        let mut potential_obstruction_sites = lab_simulation.clone().run_guard_patrol().unwrap();
-        // Problem states that the initial guard position cannot be a potential obstruction site.
-        potential_obstruction_sites.remove(&initial_guard_position);
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let lab_simulation = Self::parse_shared(file)?;
+        lab_simulation.ensure_single_guard()?;
+        let count_loopable_configurations = lab_simulation.find_loop_obstruction_sites().len();
+
+        Ok(Answer::new(
+            format!(
+                "We could find {count_loopable_configurations} configurations that resulted in a loop."
+            ),
+            count_loopable_configurations,
+        ))
+    }
+}
 
-        let count_loopable_configurations = shard_and_solve_concurrently(
-            potential_obstruction_sites,
-            lab_simulation,
-            |potential_obstruction_sites, lab_simulation| {
-                let mut count_loopable_configurations = 0;
-                for potential_obstruction_site in potential_obstruction_sites {
-                    let mut tentative_lab_simulation = lab_simulation.clone();
-                    *tentative_lab_simulation.at_mut(potential_obstruction_site) = Tile::Obstructed;
+impl Visualize for SolverImpl {
+    const LEGEND: &[LegendEntry] = &[
+        LegendEntry::new('#', "obstruction", Style::Wall),
+        LegendEntry::new('X', "visited tile", Style::Path),
+        LegendEntry::new('^', "guard", Style::Actor),
+        LegendEntry::new('>', "guard", Style::Actor),
+        LegendEntry::new('v', "guard", Style::Actor),
+        LegendEntry::new('<', "guard", Style::Actor),
+        LegendEntry::new('O', "obstruction resulting in a loop", Style::Highlight),
+    ];
+
+    fn frames(
+        part: Part,
+        file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        let mut lab_simulation = Self::parse(file)?;
+
+        match part {
+            // Show the patrol every time the guard turns.
+            Part::Part1 => Ok(Some(Box::new(std::iter::from_fn(move || {
+                while !lab_simulation.is_done() {
+                    let direction = lab_simulation.guard.direction;
+                    lab_simulation.step();
+                    if lab_simulation.is_done() {
+                        return Some(lab_simulation.render());
+                    }
 
-                    if tentative_lab_simulation.run_guard_patrol().is_none() {
-                        count_loopable_configurations += 1;
+                    if lab_simulation.guard.direction != direction {
+                        return Some(lab_simulation.frame(format!(
+                            "Step {}: the guard turned.",
+                            lab_simulation.num_steps
+                        )));
                     }
                 }
 
-                count_loopable_configurations
-            },
-        )
-        .sum::<i32>();
+                None
+            })))),
+            // Show the patrol, along with all obstructions that would get the guard stuck in a loop.
+            Part::Part2 => {
+                let loop_obstruction_sites = lab_simulation.find_loop_obstruction_sites();
+                let initial_guard = lab_simulation.guard;
+                lab_simulation.run_to_completion();
+                lab_simulation.guard = initial_guard;
+
+                let mut frame = lab_simulation.frame(format!(
+                    "Obstructions (O) resulting in a loop: {}",
+                    loop_obstruction_sites.len()
+                ));
+                for Position { row, col } in loop_obstruction_sites {
+                    frame.grid[row][col] = 'O';
+                }
 
-        println!(
-            "We could find {count_loopable_configurations} configurations that resulted in a loop."
-        );
+                Ok(Some(Box::new(std::iter::once(frame))))
+            }
+        }
+    }
+
+    fn simulation(part: Part, file: &str) -> Result<Option<Box<dyn Simulation + '_>>, ParseError> {
+        Ok(match part {
+            Part::Part1 => Some(Box::new(Self::parse(file)?)),
+            // Each potential obstruction is simulated separately, there's not a single simulation to show.
+            Part::Part2 => None,
+        })
     }
 }
 
 generate_benchmark!(day6);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn guards_face_the_direction_of_their_character() {
+        let lab_simulation = SolverImpl::parse("..v\n>.#\n").unwrap();
+
+        assert_eq!(
+            lab_simulation.initial_guards,
+            [
+                Guard {
+                    position: Position { row: 1, col: 3 },
+                    direction: Direction::Down
+                },
+                Guard {
+                    position: Position { row: 2, col: 1 },
+                    direction: Direction::Right
+                },
+            ]
+        );
+        assert_eq!(lab_simulation.guard, lab_simulation.initial_guards[0]);
+        assert!(lab_simulation.ensure_single_guard().is_err());
+    }
+
+    #[test]
+    fn guards_patrol_simultaneously() {
+        // The first guard walks right and the second one left, so they walk past each other in the middle, then both
+        // leave the lab. The third guard loops around the obstructions, meeting nobody.
+        let lab_simulation = SolverImpl::parse(">..<\n....\n.#..\n#^.#\n..#.\n").unwrap();
+        let patrols = lab_simulation.run_guard_patrols();
+
+        assert_eq!(
+            patrols.outcomes,
+            [
+                (PatrolState::Exited, 3),
+                (PatrolState::Exited, 3),
+                (PatrolState::Looping, 4)
+            ]
+        );
+        assert_eq!(
+            patrols.encounters,
+            [(2, Position { row: 1, col: 3 }, (0, 1), Encounter::Crossed)]
+        );
+        assert_eq!(patrols.visited_tiles.len(), 4 + 1 + 1);
+    }
+}
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod hasher_benches {
+    use test::Bencher;
+
+    use super::*;
+
+    // Guards along the patrol of the benchmark input, which part 2 hashes over and over to detect loops.
+    fn patrol_guards() -> Vec<Guard> {
+        let mut lab_simulation =
+            SolverImpl::parse(&crate::utils::read_benchmark_input("day6")).unwrap();
+        let mut guards = vec![lab_simulation.guard];
+        while !lab_simulation.is_done() {
+            lab_simulation.step();
+            guards.push(lab_simulation.guard);
+        }
+
+        guards
+    }
+
+    #[bench]
+    fn bench_day6_guards_default_hasher(b: &mut Bencher) {
+        let guards = patrol_guards();
+
+        b.iter(|| guards.iter().collect::<hashbrown::HashSet<_>>().len());
+    }
+
+    #[bench]
+    fn bench_day6_guards_fx_hasher(b: &mut Bencher) {
+        let guards = patrol_guards();
+
+        b.iter(|| guards.iter().collect::<FxHashSet<_>>().len());
+    }
+}
+
+#[cfg(all(test, feature = "nightly-bench"))]
+mod loop_detection_benches {
+    use test::Bencher;
+
+    use super::*;
+
+    // Guards seen along the patrol of the benchmark input, looped over twice so that half of them are seen again.
+    fn looping_guards() -> (LabSimulation, Vec<Guard>) {
+        let mut lab_simulation =
+            SolverImpl::parse(&crate::utils::read_benchmark_input("day6")).unwrap();
+        let mut guards = vec![lab_simulation.guard];
+        let initial_lab_simulation = lab_simulation.clone();
+        while !lab_simulation.is_done() {
+            lab_simulation.step();
+            guards.push(lab_simulation.guard);
+        }
+        guards.extend_from_within(..);
+
+        (initial_lab_simulation, guards)
+    }
+
+    #[bench]
+    fn bench_day6_loop_detection_hash_set(b: &mut Bencher) {
+        let (_, guards) = looping_guards();
+
+        b.iter(|| {
+            let mut previous_guards = FxHashSet::default();
+            guards
+                .iter()
+                .filter(|guard| !previous_guards.insert(**guard))
+                .count()
+        });
+    }
+
+    #[bench]
+    fn bench_day6_loop_detection_direction_bits(b: &mut Bencher) {
+        let (lab_simulation, guards) = looping_guards();
+
+        b.iter(|| {
+            let mut seen_directions = lab_simulation.seen_directions.map(|_| 0u8);
+            guards
+                .iter()
+                .filter(|guard| {
+                    let seen = &mut seen_directions[guard.position];
+                    let already_seen = *seen & guard.direction.bit() != 0;
+                    *seen |= guard.direction.bit();
+                    already_seen
+                })
+                .count()
+        });
+    }
+}
@@ -1,119 +1,420 @@
-use crate::utils::{generate_benchmark, shard_and_solve_concurrently};
-use std::str::FromStr;
+use crate::utils::math::{checked_concat_digits, num_digits, pow10};
+use crate::utils::{generate_benchmark, shard_and_solve_concurrently, until_cancelled};
 
-use anyhow::anyhow;
+use clap::{Subcommand, ValueEnum};
 use itertools::Itertools;
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
 
-use crate::solver::Solver;
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::{Line, Lines, Parse, ParseError};
+use crate::solver::{Answer, Run, Solver, Variant};
+use crate::viz::Visualize;
 
-#[derive(Clone, Debug, EnumIter, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Operator {
     Add,
     Multiply,
     Concatenation,
 }
 
-impl Operator {
-    fn is_concatenation(&self) -> bool {
-        self == &Self::Concatenation
+const PART1_OPERATORS: &[Operator] = &[Operator::Add, Operator::Multiply];
+const PART2_OPERATORS: &[Operator] = &[Operator::Add, Operator::Multiply, Operator::Concatenation];
+
+// How operators making an equation true are searched for.
+#[derive(Clone, Copy, Debug)]
+enum Search {
+    // Tries every combination of operators, from the first operand to the last.
+    Forward,
+    // Undoes operators from the last operand to the first, only trying the ones that can be undone.
+    Backward,
+}
+
+/// What makes an assignment of operators costly, when looking for the cheapest one.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Cost {
+    /// Each multiplication costs 1.
+    Multiplications,
+    /// Each concatenation costs 1.
+    Concatenations,
+    /// Each operator costs 1, 2, and 3 for additions, multiplications, and concatenations respectively.
+    Weighted,
+}
+
+impl Cost {
+    fn of(self, operator: Operator) -> u64 {
+        match (self, operator) {
+            (Self::Multiplications, Operator::Multiply)
+            | (Self::Concatenations, Operator::Concatenation) => 1,
+            (Self::Multiplications | Self::Concatenations, _) => 0,
+            (Self::Weighted, Operator::Add) => 1,
+            (Self::Weighted, Operator::Multiply) => 2,
+            (Self::Weighted, Operator::Concatenation) => 3,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Multiplications => "multiplications",
+            Self::Concatenations => "concatenations",
+            Self::Weighted => "weighted operators",
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Equation {
-    value: i64,
-    operands: Vec<i64>,
+    value: u64,
+    operands: Vec<u64>,
 }
 
 impl Equation {
+    // Results overflowing can't be the value anymore, unless a later operand is zero, which inputs don't have.
     fn try_compute(&self, operators: Vec<Operator>) -> Option<()> {
         let mut result = self.operands[0];
-        for (right_operand, operator) in self.operands.iter().skip(1).zip(operators.into_iter()) {
+        for (right_operand, operator) in self.operands.iter().skip(1).zip(operators) {
             result = match operator {
                 Operator::Add => result.checked_add(*right_operand),
                 Operator::Multiply => result.checked_mul(*right_operand),
-                Operator::Concatenation => {
-                    let right_operand_num_digits = 1 + right_operand.checked_ilog10()?;
-                    result
-                        .checked_mul(10_i64.checked_pow(right_operand_num_digits)?)?
-                        .checked_add(*right_operand)
-                }
+                Operator::Concatenation => checked_concat_digits(result, *right_operand).ok(),
             }?;
         }
 
         if result == self.value { Some(()) } else { None }
     }
-}
 
-impl FromStr for Equation {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (value, operands) = s
-            .split_once(": ")
-            .ok_or(anyhow!("Could not split the value from the operands"))?;
-        let operands = operands
-            .split_whitespace()
-            .map(|operand| operand.parse())
-            .collect::<Result<_, _>>()?;
-
-        Ok(Self {
-            value: value.parse()?,
-            operands,
+    fn is_solvable(&self, operators: &[Operator], search: Search) -> bool {
+        match search {
+            Search::Forward => (0..self.operands.len() - 1)
+                .map(|_| operators.iter().copied())
+                .multi_cartesian_product()
+                .any(|tentative_operators| self.try_compute(tentative_operators).is_some()),
+            Search::Backward => Self::can_undo(self.value, &self.operands, operators),
+        }
+    }
+
+    // Whether `value` can be reached by combining the `operands` with the `operators`, by undoing the operator
+    // applied to the last operand. Operators are applied left to right, so the last one is applied last.
+    fn can_undo(value: u64, operands: &[u64], operators: &[Operator]) -> bool {
+        let (&last_operand, operands) = operands.split_last().unwrap();
+        if operands.is_empty() {
+            return value == last_operand;
+        }
+
+        operators.iter().any(|operator| match operator {
+            Operator::Add => {
+                value >= last_operand && Self::can_undo(value - last_operand, operands, operators)
+            }
+            Operator::Multiply => {
+                if last_operand == 0 {
+                    value == 0
+                } else {
+                    value.is_multiple_of(last_operand)
+                        && Self::can_undo(value / last_operand, operands, operators)
+                }
+            }
+            Operator::Concatenation => {
+                // The value is at most 20 digits long, so a longer shift can't be undone.
+                let Ok(shift) = pow10(num_digits(last_operand)) else {
+                    return false;
+                };
+                value % shift == last_operand && Self::can_undo(value / shift, operands, operators)
+            }
         })
     }
+
+    // Lowest cost of the operators making the equation true, if any do. Unlike `is_solvable`, the search can't stop
+    // at the first assignment found, so it undoes operators like `can_undo`, but skips branches already costing at
+    // least as much as the cheapest assignment found so far.
+    fn min_cost(&self, operators: &[Operator], cost: Cost) -> Option<u64> {
+        let mut min_cost = None;
+        Self::undo_cheapest(
+            self.value,
+            &self.operands,
+            operators,
+            cost,
+            0,
+            &mut min_cost,
+        );
+        min_cost
+    }
+
+    fn undo_cheapest(
+        value: u64,
+        operands: &[u64],
+        operators: &[Operator],
+        cost: Cost,
+        spent: u64,
+        min_cost: &mut Option<u64>,
+    ) {
+        if min_cost.is_some_and(|min_cost| spent >= min_cost) {
+            return;
+        }
+
+        let (&last_operand, operands) = operands.split_last().unwrap();
+        if operands.is_empty() {
+            if value == last_operand {
+                *min_cost = Some(spent);
+            }
+            return;
+        }
+
+        for operator in operators {
+            let previous_value = match operator {
+                Operator::Add => value.checked_sub(last_operand),
+                Operator::Multiply if last_operand == 0 => None,
+                Operator::Multiply => value
+                    .is_multiple_of(last_operand)
+                    .then(|| value / last_operand),
+                Operator::Concatenation => pow10(num_digits(last_operand))
+                    .ok()
+                    .filter(|shift| value % shift == last_operand)
+                    .map(|shift| value / shift),
+            };
+            if let Some(previous_value) = previous_value {
+                let spent = spent + cost.of(*operator);
+                Self::undo_cheapest(previous_value, operands, operators, cost, spent, min_cost);
+            }
+        }
+    }
 }
 
-pub struct SolverImpl {}
+// Parses a line like "190: 10 19" into an equation.
+fn parse_equation(line: Line) -> Result<Equation, ParseError> {
+    let (value, operands) = line.split_once(line.text, ": ")?;
+    let operands: Vec<_> = operands
+        .split_whitespace()
+        .map(|operand| line.parse(operand))
+        .collect::<Result<_, _>>()?;
+    if operands.is_empty() {
+        return Err(line.error("expected at least one operand"));
+    }
 
-impl SolverImpl {
-    fn solve<I>(file: &str, operators: I)
-    where
-        I: Iterator<Item = Operator> + Clone + Send + 'static,
-    {
-        let total_calibration_result = shard_and_solve_concurrently(
-            file.lines().map(|line| line.to_string()),
-            operators,
-            |lines, operators| {
-                let mut total_calibration_result = 0;
-
-                'equations: for line in lines {
-                    let equation: Equation = line.parse().unwrap();
-                    let num_operators = equation.operands.len() - 1;
-
-                    for tentative_operators in (0..num_operators)
-                        .map(|_| operators.clone())
-                        .multi_cartesian_product()
-                    {
-                        if equation.try_compute(tentative_operators).is_some() {
-                            total_calibration_result += equation.value;
-                            continue 'equations;
-                        }
-                    }
-                }
+    Ok(Equation {
+        value: line.parse(value)?,
+        operands,
+    })
+}
+
+#[derive(Clone)]
+pub struct Calibrations(Vec<Equation>);
 
-                total_calibration_result
+impl Calibrations {
+    fn total_calibration_result(self, operators: &'static [Operator], search: Search) -> u64 {
+        shard_and_solve_concurrently(
+            self.0,
+            (operators, search),
+            |equations, (operators, search)| {
+                until_cancelled(equations)
+                    .filter(|equation| equation.is_solvable(operators, search))
+                    .map(|equation| equation.value)
+                    .sum::<u64>()
             },
         )
-        .sum::<i64>();
+        .sum()
+    }
 
-        println!("The total calibration result is {total_calibration_result}");
+    // Number of equations that can be made true, and the total of their lowest costs.
+    fn total_min_cost(self, operators: &'static [Operator], cost: Cost) -> (usize, u64) {
+        shard_and_solve_concurrently(self.0, (operators, cost), |equations, (operators, cost)| {
+            until_cancelled(equations)
+                .filter_map(|equation| equation.min_cost(operators, cost))
+                .fold((0, 0), |(num_equations, total), min_cost| {
+                    (num_equations + 1, total + min_cost)
+                })
+        })
+        .fold(
+            (0, 0),
+            |(num_equations, total), (shard_num_equations, shard_total)| {
+                (num_equations + shard_num_equations, total + shard_total)
+            },
+        )
     }
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Find the cheapest operators making each equation true, using all operators of part 2.
+    Cheapest {
+        #[arg(short, long, value_enum, default_value_t = Cost::Multiplications)]
+        /// What makes operators costly.
+        cost: Cost,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Cheapest { cost } => {
+                let (num_equations, total_min_cost) =
+                    SolverImpl::parse(file)?.total_min_cost(PART2_OPERATORS, cost);
+                println!(
+                    "{num_equations} equations can be made true, with {total_min_cost} {} at the least.",
+                    cost.name()
+                );
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Cheapest { .. } => Some(Part::Part2),
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
+pub struct SolverImpl {}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Calibrations;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        Lines::new(file)
+            .map(parse_equation)
+            .collect::<Result<_, _>>()
+            .map(Calibrations)
+    }
+}
+
+impl SolverImpl {
+    const PART1_VARIANTS: &[Variant] = &[
+        Variant::new("forward", |file| {
+            Ok(Self::parse(file)?
+                .total_calibration_result(PART1_OPERATORS, Search::Forward)
+                .to_string())
+        }),
+        Variant::new("backward", |file| {
+            Ok(Self::parse(file)?
+                .total_calibration_result(PART1_OPERATORS, Search::Backward)
+                .to_string())
+        }),
+    ];
+
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("forward", |file| {
+            Ok(Self::parse(file)?
+                .total_calibration_result(PART2_OPERATORS, Search::Forward)
+                .to_string())
+        }),
+        Variant::new("backward", |file| {
+            Ok(Self::parse(file)?
+                .total_calibration_result(PART2_OPERATORS, Search::Backward)
+                .to_string())
+        }),
+    ];
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        Self::solve(
-            file,
-            Operator::iter().filter(|operator| !operator.is_concatenation()),
-        );
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let total_calibration_result =
+            Self::parse_shared(file)?.total_calibration_result(PART1_OPERATORS, Search::Forward);
+        Ok(Answer::new(
+            format!("The total calibration result is {total_calibration_result}"),
+            total_calibration_result,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        Self::solve(file, Operator::iter());
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let total_calibration_result =
+            Self::parse_shared(file)?.total_calibration_result(PART2_OPERATORS, Search::Forward);
+        Ok(Answer::new(
+            format!("The total calibration result is {total_calibration_result}"),
+            total_calibration_result,
+        ))
+    }
+
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => Self::PART1_VARIANTS,
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
     }
 }
 
 generate_benchmark!(day7);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_value_and_operands() {
+        let Calibrations(equations) = SolverImpl::parse("190: 10 19\n").unwrap();
+
+        assert_eq!(equations[0].value, 190);
+        assert_eq!(equations[0].operands, [10, 19]);
+    }
+
+    #[test]
+    fn an_equation_without_operands_fails_to_parse() {
+        assert!(SolverImpl::parse("190: \n").is_err());
+    }
+
+    #[test]
+    fn concatenation_only_helps_in_part2() {
+        let equation = Equation {
+            value: 156,
+            operands: vec![15, 6],
+        };
+
+        for search in [Search::Forward, Search::Backward] {
+            assert!(!equation.is_solvable(PART1_OPERATORS, search));
+            assert!(equation.is_solvable(PART2_OPERATORS, search));
+        }
+    }
+
+    #[test]
+    fn cheapest_operators_are_found() {
+        // Evaluated left to right, 2 + 2 + 2 and 2 * 2 + 2 are both 6, but only the first has no multiplication.
+        let equation = Equation {
+            value: 6,
+            operands: vec![2, 2, 2],
+        };
+        assert_eq!(
+            equation.min_cost(PART2_OPERATORS, Cost::Multiplications),
+            Some(0)
+        );
+        assert_eq!(equation.min_cost(PART2_OPERATORS, Cost::Weighted), Some(2));
+
+        // 81 * 40 + 27 = 3267, as is 81 + 40 * 27.
+        let equation = Equation {
+            value: 3267,
+            operands: vec![81, 40, 27],
+        };
+        assert_eq!(
+            equation.min_cost(PART2_OPERATORS, Cost::Multiplications),
+            Some(1)
+        );
+
+        // 15 || 6 is the only way to 156.
+        let equation = Equation {
+            value: 156,
+            operands: vec![15, 6],
+        };
+        assert_eq!(
+            equation.min_cost(PART2_OPERATORS, Cost::Concatenations),
+            Some(1)
+        );
+        assert_eq!(
+            equation.min_cost(PART1_OPERATORS, Cost::Concatenations),
+            None
+        );
+    }
+}
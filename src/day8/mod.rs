@@ -1,11 +1,18 @@
-use crate::utils::pos;
-use crate::{solver::Solver, utils::generate_benchmark};
-use hashbrown::{HashMap, HashSet};
+use crate::utils::{FxHashMap, FxHashSet, math::gcd, pos};
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver},
+    utils::generate_benchmark,
+    viz::Visualize,
+};
+use clap::Subcommand;
 use itertools::Itertools;
 
 type Position = crate::utils::Position<i16>;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Antenna {
     position: Position,
 }
@@ -16,38 +23,18 @@ impl Antenna {
     }
 }
 
-#[derive(Debug)]
-struct Map {
-    antennas_by_frequency: HashMap<char, Vec<Antenna>>,
+#[derive(Clone, Debug)]
+pub struct Map {
+    antennas_by_frequency: FxHashMap<char, Vec<Antenna>>,
     map_size: i16,
 }
 
 impl Map {
-    fn new(file: &str) -> Self {
-        let mut antennas_by_frequency: HashMap<_, Vec<_>> = HashMap::default();
-        let map_size = file.lines().next().unwrap().len() as i16;
-
-        for (row, line) in file.lines().enumerate() {
-            for (col, c) in line.char_indices() {
-                if Antenna::is_valid_frequency(c) {
-                    antennas_by_frequency.entry(c).or_default().push(Antenna {
-                        position: pos!(row as i16, col as i16),
-                    });
-                }
-            }
-        }
-
-        Self {
-            antennas_by_frequency,
-            map_size,
-        }
-    }
-
     fn compute_all_antinode_positions(
         &self,
         include_reasonant_harmonics: bool,
-    ) -> HashSet<Position> {
-        let mut antinode_positions = HashSet::default();
+    ) -> FxHashSet<Position> {
+        let mut antinode_positions = FxHashSet::default();
 
         for antennas in self.antennas_by_frequency.values() {
             for antennas_pair in antennas.iter().combinations(2) {
@@ -77,26 +64,103 @@ impl Map {
         let delta_row = second.position.row - first.position.row;
         let delta_col = second.position.col - first.position.col;
 
-        for ((delta_row, delta_col), mut antinode_position) in [
-            ((delta_row, delta_col), second.position),
-            ((-delta_row, -delta_col), first.position),
-        ] {
-            loop {
-                antinode_position.row += delta_row;
-                antinode_position.col += delta_col;
-
-                let in_bound = self.is_position_inbound(antinode_position);
-                if in_bound {
-                    antinode_positions.push(antinode_position);
+        self.walk_antinodes(
+            second.position,
+            (delta_row, delta_col),
+            include_reasonant_harmonics,
+            &mut antinode_positions,
+        );
+        self.walk_antinodes(
+            first.position,
+            (-delta_row, -delta_col),
+            include_reasonant_harmonics,
+            &mut antinode_positions,
+        );
+
+        antinode_positions
+    }
+
+    // Experimental antinodes of triples of antennas, which are only created by three antennas on the same line, and
+    // spaced so that both gaps between them are multiples of the smallest one, e.g. when one is exactly the midpoint
+    // of the others. Like with pairs, antinodes continue the evenly spaced antennas one step beyond each end, with
+    // that smallest gap as the step, or on and on with resonant harmonics.
+    fn compute_all_triple_antinode_positions(
+        &self,
+        include_reasonant_harmonics: bool,
+    ) -> FxHashSet<Position> {
+        let mut antinode_positions = Vec::new();
+
+        for antennas in self.antennas_by_frequency.values() {
+            for antennas_triple in antennas.iter().combinations(3) {
+                // Positions sorted by row, then column, are also sorted along any line going through them.
+                let mut positions = [0, 1, 2].map(|i| antennas_triple[i].position);
+                positions.sort();
+                let [first, middle, last] = positions;
+                let gaps = [
+                    (middle.row - first.row, middle.col - first.col),
+                    (last.row - middle.row, last.col - middle.col),
+                ];
+                let is_collinear = gaps[0].0 * gaps[1].1 == gaps[0].1 * gaps[1].0;
+                // Number of smallest steps along the line in each gap.
+                let [first_gap, last_gap] = gaps.map(|(delta_row, delta_col)| {
+                    gcd(
+                        delta_row.unsigned_abs() as u64,
+                        delta_col.unsigned_abs() as u64,
+                    )
+                });
+                let (min_gap, max_gap) = (first_gap.min(last_gap), first_gap.max(last_gap));
+                if !is_collinear || !max_gap.is_multiple_of(min_gap) {
+                    continue;
                 }
 
-                if !in_bound || !include_reasonant_harmonics {
-                    break;
+                if include_reasonant_harmonics {
+                    antinode_positions.extend([first, middle, last]);
                 }
+                let (delta_row, delta_col) = if first_gap <= last_gap {
+                    gaps[0]
+                } else {
+                    gaps[1]
+                };
+                self.walk_antinodes(
+                    last,
+                    (delta_row, delta_col),
+                    include_reasonant_harmonics,
+                    &mut antinode_positions,
+                );
+                self.walk_antinodes(
+                    first,
+                    (-delta_row, -delta_col),
+                    include_reasonant_harmonics,
+                    &mut antinode_positions,
+                );
             }
         }
 
-        antinode_positions
+        antinode_positions.into_iter().collect()
+    }
+
+    // Adds the antinodes found by stepping by `delta` from `position`, which is only one step unless resonant
+    // harmonics are included, in which case it goes on until it leaves the map.
+    fn walk_antinodes(
+        &self,
+        mut antinode_position: Position,
+        (delta_row, delta_col): (i16, i16),
+        include_reasonant_harmonics: bool,
+        antinode_positions: &mut Vec<Position>,
+    ) {
+        loop {
+            antinode_position.row += delta_row;
+            antinode_position.col += delta_col;
+
+            let in_bound = self.is_position_inbound(antinode_position);
+            if in_bound {
+                antinode_positions.push(antinode_position);
+            }
+
+            if !in_bound || !include_reasonant_harmonics {
+                break;
+            }
+        }
     }
 
     fn is_position_inbound(&self, position: Position) -> bool {
@@ -104,20 +168,140 @@ impl Map {
     }
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Count antinode positions, optionally with the experimental antinodes of antenna triples.
+    Antinodes {
+        #[arg(long)]
+        /// Include resonant harmonics, like in part 2.
+        resonant_harmonics: bool,
+
+        #[arg(long)]
+        /// Only count antinodes of three antennas on the same line, spaced by multiples of their smallest gap.
+        triples: bool,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Antinodes {
+                resonant_harmonics,
+                triples,
+            } => {
+                let map = SolverImpl::parse(file)?;
+                let antinode_positions = if triples {
+                    map.compute_all_triple_antinode_positions(resonant_harmonics)
+                } else {
+                    map.compute_all_antinode_positions(resonant_harmonics)
+                };
+                println!("We found {} antinode positions.", antinode_positions.len());
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Antinodes { .. } => None,
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
+}
+
 pub struct SolverImpl {}
 
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = Map;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let lines = Lines::new(file);
+        let mut antennas_by_frequency: FxHashMap<_, Vec<_>> = FxHashMap::default();
+        let map_size = lines.clone().next_line()?.text.len() as i16;
+
+        for line in lines {
+            let row = line.number - 1;
+            for (col, c) in line.text.char_indices() {
+                if Antenna::is_valid_frequency(c) {
+                    antennas_by_frequency.entry(c).or_default().push(Antenna {
+                        position: pos!(row as i16, col as i16),
+                    });
+                }
+            }
+        }
+
+        Ok(Map {
+            antennas_by_frequency,
+            map_size,
+        })
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let map = Map::new(file);
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let map = Self::parse_shared(file)?;
         let antinode_positions = map.compute_all_antinode_positions(false);
-        println!("We found {} antinode positions.", antinode_positions.len());
+        let answer = antinode_positions.len();
+        Ok(Answer::new(
+            format!("We found {answer} antinode positions."),
+            answer,
+        ))
     }
 
-    fn solve_part2(file: &str) {
-        let map = Map::new(file);
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let map = Self::parse_shared(file)?;
         let antinode_positions = map.compute_all_antinode_positions(true);
-        println!("We found {} antinode positions.", antinode_positions.len());
+        let answer = antinode_positions.len();
+        Ok(Answer::new(
+            format!("We found {answer} antinode positions."),
+            answer,
+        ))
     }
 }
 
 generate_benchmark!(day8);
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    #[test]
+    fn triples_need_evenly_divided_gaps() {
+        // The 3 antennas on the diagonal are 1 and 2 steps apart, while the 4th one isn't on their line.
+        let map = SolverImpl::parse(
+            "........\n.a......\n..a.....\n........\n....a...\n.......a\n........\n........\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.compute_all_triple_antinode_positions(false)
+                .into_iter()
+                .sorted()
+                .collect_vec(),
+            [pos!(0, 0), pos!(5, 5)]
+        );
+        assert_eq!(map.compute_all_triple_antinode_positions(true).len(), 7);
+        assert!(
+            SolverImpl::parse("a..\n.a.\n..b\n")
+                .unwrap()
+                .compute_all_triple_antinode_positions(true)
+                .is_empty()
+        );
+    }
+}
@@ -1,6 +1,16 @@
 use std::{cmp::Reverse, collections::BinaryHeap};
 
-use crate::{solver::Solver, utils::generate_benchmark};
+use clap::{Subcommand, ValueEnum};
+use itertools::Itertools;
+
+use crate::{
+    args::Part,
+    error::AocError,
+    input::{Lines, Parse, ParseError},
+    solver::{Answer, Run, Solver, Variant},
+    utils::{bytes::digit_values, generate_benchmark},
+    viz::Visualize,
+};
 
 macro_rules! offset_based_ord_and_eq {
     ($T:ident) => {
@@ -43,28 +53,118 @@ struct FreeBlock {
 
 offset_based_ord_and_eq!(FreeBlock);
 
+// Stretch of the disk holding either a whole file, or free space. It is linked to its neighbours on the disk, by
+// their index in the `SegmentList`.
+#[derive(Debug)]
+struct Segment {
+    file_id: Option<usize>,
+    pos_offset: usize,
+    num_blocks: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+// Doubly-linked list of the segments of the disk, stored in a `Vec`. The first segment is the first file, which can
+// never move as there is no free space before it, so it stays the head of the list.
+#[derive(Debug)]
+struct SegmentList {
+    segments: Vec<Segment>,
+}
+
+impl SegmentList {
+    // Segments are initially in the order of the disk map, so the segment at index `i` is the `i`th entry of the map.
+    fn new(disk_map: &DiskMap) -> Self {
+        let mut pos_offset = 0;
+        let segments = disk_map
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, &num_blocks)| {
+                let segment = Segment {
+                    file_id: (i % 2 == 0).then_some(i / 2),
+                    pos_offset,
+                    num_blocks,
+                    prev: i.checked_sub(1),
+                    next: (i + 1 < disk_map.0.len()).then_some(i + 1),
+                };
+                pos_offset += num_blocks;
+                segment
+            })
+            .collect();
+
+        Self { segments }
+    }
+
+    // Moves the file of the segment at `file_index` to the start of the free segment at `free_index`, which must be
+    // large enough. The file leaves free space behind.
+    fn move_file(&mut self, file_index: usize, free_index: usize) {
+        let file_id = self.segments[file_index].file_id.take();
+        let num_blocks = self.segments[file_index].num_blocks;
+        let moved_file_index = self.segments.len();
+        let free_segment = &mut self.segments[free_index];
+        let pos_offset = free_segment.pos_offset;
+        let prev = free_segment
+            .prev
+            .expect("free segments come after the head of the list");
+        free_segment.pos_offset += num_blocks;
+        free_segment.num_blocks -= num_blocks;
+        free_segment.prev = Some(moved_file_index);
+
+        self.segments[prev].next = Some(moved_file_index);
+        self.segments.push(Segment {
+            file_id,
+            pos_offset,
+            num_blocks,
+            prev: Some(prev),
+            next: Some(free_index),
+        });
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Segment> {
+        std::iter::successors(self.segments.first(), |segment| {
+            Some(&self.segments[segment.next?])
+        })
+    }
+}
+
+/// Backend moving whole files to the left-most free space that can fit them.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Backend {
+    /// A min-heap of free spaces per number of unused blocks.
+    Heaps,
+    /// A doubly-linked list of file and free segments, searched first-fit.
+    SegmentList,
+}
+
+// Alternating numbers of blocks of files and of free space, starting and ending with a file.
+#[derive(Clone, Debug)]
+pub struct DiskMap(Vec<usize>);
+
 #[derive(Debug)]
 struct Compaction {
     file_blocks: Vec<FileBlock>,
+    // Number of moves made, each moving either a block or a whole file, depending on the compaction.
+    num_moves: usize,
 }
 
 impl Compaction {
-    fn new(disk_map: &str) -> Self {
-        assert!(disk_map.len() % 2 == 1);
-
+    // Compacts the disk by moving blocks one at a time, from the end of the disk to the left-most free block. Stops
+    // after `max_moves` blocks moved if given, leaving the remaining blocks where they were.
+    fn new(disk_map: &DiskMap, max_moves: Option<usize>) -> Self {
+        let mut moves_left = max_moves.unwrap_or(usize::MAX);
         let mut file_blocks = Vec::new();
-        let mut space_layout = disk_map.chars();
+        // Entries of the disk map, along with their index, so that the original position of entries left untouched
+        // can be found if compaction stops early.
+        let mut space_layout = disk_map.0.iter().copied().enumerate();
         let mut pos_offset = 0;
         let mut front_id = 0;
-        let mut back_id = (disk_map.len() - 1) / 2;
-        let mut num_back_blocks_to_move =
-            space_layout.next_back().unwrap().to_digit(10).unwrap() as usize;
+        let mut back_id = (disk_map.0.len() - 1) / 2;
+        let mut num_back_blocks_to_move = space_layout.next_back().unwrap().1;
 
         'compactions: loop {
-            let mut num_free_blocks = match space_layout.next() {
-                Some(num_front_blocks) => {
+            let (mut num_free_blocks, front_exhausted) = match space_layout.next() {
+                Some((_, num_front_blocks)) => {
                     // Move forward, and append this file block.
-                    let num_front_blocks = num_front_blocks.to_digit(10).unwrap() as usize;
                     file_blocks.push(FileBlock {
                         id: front_id,
                         pos_offset,
@@ -73,23 +173,44 @@ impl Compaction {
                     pos_offset += num_front_blocks;
                     front_id += 1;
 
-                    space_layout
-                        .next()
-                        .expect(
-                            "It should be impossible to fail getting the next number of free blocks if we were able to get the previous number of front file blocks",
-                        )
-                        .to_digit(10)
-                        .unwrap() as usize
+                    let (_, num_free_blocks) = space_layout.next().expect(
+                        "It should be impossible to fail getting the next number of free blocks if we were able to get the previous number of front file blocks",
+                    );
+                    (num_free_blocks, false)
                 }
                 // If we couldn't advance forward, then we may still be trying to compact
                 // a file block from the back. Make it simple and tell the compaction
-                // that there is effectively no maximum of free blocks left.
-                None => usize::MAX,
+                // that there is effectively no maximum of free blocks left. The blocks left are then
+                // right after the compacted ones, so they don't actually move.
+                None => (usize::MAX, true),
             };
 
             // Try to compact files from the back.
             while num_back_blocks_to_move > 0 && num_free_blocks > 0 {
-                let num_back_blocks_moved = num_back_blocks_to_move.min(num_free_blocks);
+                if moves_left == 0 && !front_exhausted {
+                    // Files between the front and the back are left untouched, as are the blocks of the back file
+                    // which haven't moved yet.
+                    let original_offset = |index: usize| disk_map.0[..index].iter().sum::<usize>();
+                    for (index, num_blocks) in space_layout.by_ref().step_by(2) {
+                        file_blocks.push(FileBlock {
+                            id: index / 2,
+                            pos_offset: original_offset(index),
+                            num_blocks,
+                        });
+                    }
+                    file_blocks.push(FileBlock {
+                        id: back_id,
+                        pos_offset: original_offset(back_id * 2),
+                        num_blocks: num_back_blocks_to_move,
+                    });
+                    break 'compactions;
+                }
+
+                let mut num_back_blocks_moved = num_back_blocks_to_move.min(num_free_blocks);
+                if !front_exhausted {
+                    num_back_blocks_moved = num_back_blocks_moved.min(moves_left);
+                    moves_left -= num_back_blocks_moved;
+                }
                 if num_back_blocks_moved > 0 {
                     file_blocks.push(FileBlock {
                         id: back_id,
@@ -102,9 +223,9 @@ impl Compaction {
                 }
 
                 // There is still more to compact from this file at the back, but there is evidently
-                // no free spaces left at the front. Stop for now.
+                // no free spaces left at the front, or no moves left.
                 if num_back_blocks_to_move > 0 {
-                    continue 'compactions;
+                    continue;
                 }
 
                 // We succeeded in compacting a full file from the back.
@@ -116,8 +237,8 @@ impl Compaction {
 
                 // Try another file to compact. If there is none, we are done!
                 match space_layout.next_back() {
-                    Some(num_back_blocks) => {
-                        num_back_blocks_to_move = num_back_blocks.to_digit(10).unwrap() as usize;
+                    Some((_, num_back_blocks)) => {
+                        num_back_blocks_to_move = num_back_blocks;
                         back_id -= 1;
                     }
                     None => break 'compactions,
@@ -125,10 +246,15 @@ impl Compaction {
             }
         }
 
-        Self { file_blocks }
+        Self {
+            file_blocks,
+            num_moves: max_moves.unwrap_or(usize::MAX) - moves_left,
+        }
     }
 
-    fn new_without_fragmentation(disk_map: &str) -> Self {
+    // Compacts the disk by moving whole files, right to left, to the left-most free space fitting them. Stops after
+    // `max_moves` files moved if given, leaving the remaining files where they were.
+    fn new_without_fragmentation(disk_map: &DiskMap, max_moves: Option<usize>) -> Self {
         // Array of length 10.
         // Index 0 stores all the FreeBlocks with 0 unused blocks;
         // Index 1 stores all the FreeBlocks with 1 unused blocks;
@@ -142,9 +268,7 @@ impl Compaction {
         let mut file_blocks = Vec::new();
         let mut pos_offset = 0;
 
-        for (i, num_blocks) in disk_map.char_indices() {
-            let num_blocks = num_blocks.to_digit(10).unwrap() as usize;
-
+        for (i, &num_blocks) in disk_map.0.iter().enumerate() {
             if i % 2 == 0 {
                 // Efficient division by 2, as we know `i` is a multiple of 2.
                 // Ids only increment each FileBlock, which is every 2 iterations.
@@ -175,21 +299,26 @@ impl Compaction {
         // inside the left-most FreeBlock that can fit them. The latter will itself need to be moved
         // to reflect the number of blocks left in it.
         let mut unmoved_file_blocks = Vec::new();
+        let mut num_moves = 0;
         for mut file_block in file_blocks.into_iter().rev() {
             let num_file_blocks = file_block.num_blocks;
+            // Empty files have nothing to checksum, so they aren't worth a move.
+            if num_file_blocks == 0 || max_moves.is_some_and(|max_moves| num_moves == max_moves) {
+                unmoved_file_blocks.push(file_block);
+                continue;
+            }
 
             match free_blocks_by_unused_size[num_file_blocks..]
                 .iter()
                 .enumerate()
-                .map(|(free_blocks_index_offset, free_blocks)| {
+                // Ignore empty min-heaps.
+                .filter_map(|(free_blocks_index_offset, free_blocks)| {
                     // The `free_blocks_index_offset` is propagated so we can keep track of which index to access
                     // once we've found the left-most FreeBlock, i.e. the one with the smallest position offset.
                     // Note that the tuple is sorted lexicographically, and because all offsets are unique,
                     // the 2nd entry in the tuple is never used to find the minimum.
                     Some((free_blocks.peek()?.0.pos_offset, free_blocks_index_offset))
                 })
-                // Ignore empty min-heaps.
-                .flatten()
                 .min()
             {
                 // Case where we found a FreeBlock with enough space and which is to the left of the FileBlock.
@@ -212,6 +341,7 @@ impl Compaction {
                     };
                     free_block.moved_file_blocks.push(file_block);
                     free_blocks_by_unused_size[unused_blocks].push(Reverse(free_block));
+                    num_moves += 1;
                 }
                 // Otherwise, the FileBlock is unmoved.
                 _ => unmoved_file_blocks.push(file_block),
@@ -224,12 +354,88 @@ impl Compaction {
             free_blocks_by_unused_size
                 .into_iter()
                 .flatten()
-                .map(|free_block| free_block.0.moved_file_blocks)
-                .flatten(),
+                .flat_map(|free_block| free_block.0.moved_file_blocks),
         );
         file_blocks.sort();
 
-        Self { file_blocks }
+        Self {
+            file_blocks,
+            num_moves,
+        }
+    }
+
+    // Same compaction as `new_without_fragmentation`, on a `SegmentList` instead. Unlike the min-heaps, which are
+    // indexed by single digit sizes, this works with files and free spaces of any size.
+    fn new_without_fragmentation_by_segment_list(
+        disk_map: &DiskMap,
+        max_moves: Option<usize>,
+    ) -> Self {
+        let mut segment_list = SegmentList::new(disk_map);
+        let max_file_blocks = disk_map.0.iter().step_by(2).copied().max().unwrap_or(0);
+        // Index of where to start searching for free space fitting a file, per number of file blocks. All free
+        // segments before it are known to be too small. That is only ever truer as files move, so searches for a
+        // given size never go back, and walk through the list at most once overall. Once the search of a file reaches
+        // the file itself, no free space to its left fits that size, so neither does any for the files to move next.
+        let mut first_fit_search_starts = vec![Some(0); max_file_blocks + 1];
+        let mut num_moves = 0;
+
+        // Move files right to left. They are the even entries of the disk map, hence of the initial segments.
+        for file_index in (0..disk_map.0.len()).step_by(2).rev() {
+            if max_moves.is_some_and(|max_moves| num_moves == max_moves) {
+                break;
+            }
+            let Segment {
+                pos_offset: file_pos_offset,
+                num_blocks,
+                ..
+            } = segment_list.segments[file_index];
+            // Empty files have nothing to checksum, wherever they are.
+            if num_blocks == 0 {
+                continue;
+            }
+
+            let Some(mut index) = first_fit_search_starts[num_blocks] else {
+                continue;
+            };
+            first_fit_search_starts[num_blocks] = loop {
+                let segment = &segment_list.segments[index];
+                if segment.pos_offset >= file_pos_offset {
+                    break None;
+                }
+                if segment.file_id.is_none() && segment.num_blocks >= num_blocks {
+                    segment_list.move_file(file_index, index);
+                    num_moves += 1;
+                    break Some(index);
+                }
+
+                index = segment
+                    .next
+                    .expect("the search stops at the file at the latest");
+            };
+        }
+
+        Self {
+            file_blocks: segment_list
+                .iter()
+                .filter_map(|segment| {
+                    Some(FileBlock {
+                        id: segment.file_id?,
+                        pos_offset: segment.pos_offset,
+                        num_blocks: segment.num_blocks,
+                    })
+                })
+                .collect(),
+            num_moves,
+        }
+    }
+
+    fn with_backend(disk_map: &DiskMap, backend: Backend, max_moves: Option<usize>) -> Self {
+        match backend {
+            Backend::Heaps => Self::new_without_fragmentation(disk_map, max_moves),
+            Backend::SegmentList => {
+                Self::new_without_fragmentation_by_segment_list(disk_map, max_moves)
+            }
+        }
     }
 
     fn check_sum(&self) -> usize {
@@ -242,20 +448,251 @@ impl Compaction {
             })
             .sum()
     }
+
+    // Blocks of a disk of `disk_size` blocks, as the id of the file they belong to, or '.' if they're free. Ids are
+    // separated by spaces, unless they are all single digits, like in the puzzle.
+    fn layout(&self, disk_size: usize) -> String {
+        let mut blocks = vec![None; disk_size];
+        for file_block in &self.file_blocks {
+            blocks[file_block.pos_offset..file_block.pos_offset + file_block.num_blocks]
+                .fill(Some(file_block.id));
+        }
+
+        let separator = if self.file_blocks.iter().all(|file_block| file_block.id < 10) {
+            ""
+        } else {
+            " "
+        };
+        blocks
+            .into_iter()
+            .map(|block| block.map_or(".".to_string(), |id| id.to_string()))
+            .join(separator)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(flatten)]
+    Part(Part),
+    /// Compact the disk by moving whole files, like in part 2, with the given backend.
+    Compact {
+        #[arg(short, long, value_enum, default_value_t = Backend::Heaps)]
+        backend: Backend,
+        /// Move blocks one at a time instead, like in part 1. The backend is then ignored.
+        #[arg(long)]
+        fragment: bool,
+        /// Stop compacting after this many moves, each of a block or of a whole file, and show the disk's layout.
+        #[arg(long)]
+        max_moves: Option<usize>,
+    },
+}
+
+impl Run<SolverImpl> for Command {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        match self {
+            Self::Part(part) => Run::<SolverImpl>::run(part, file),
+            Self::Compact {
+                backend,
+                fragment,
+                max_moves,
+            } => {
+                let disk_map = SolverImpl::parse(file)?;
+                let compaction = if fragment {
+                    Compaction::new(&disk_map, max_moves)
+                } else {
+                    Compaction::with_backend(&disk_map, backend, max_moves)
+                };
+                println!("The checksum is {}", compaction.check_sum());
+                if max_moves.is_some() {
+                    println!(
+                        "After {} moves, the disk looks like:\n{}",
+                        compaction.num_moves,
+                        compaction.layout(disk_map.0.iter().sum())
+                    );
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            Self::Compact { fragment, .. } => {
+                Some(if *fragment { Part::Part1 } else { Part::Part2 })
+            }
+        }
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        match self {
+            Self::Part(part) => Some(*part),
+            _ => None,
+        }
+    }
 }
 
 pub struct SolverImpl {}
 
+impl SolverImpl {
+    // Checksum of the disk once whole files are moved with the given backend.
+    fn compact(file: &str, backend: Backend) -> Result<usize, AocError> {
+        let disk_map = Self::parse(file)?;
+
+        Ok(Compaction::with_backend(&disk_map, backend, None).check_sum())
+    }
+
+    const PART2_VARIANTS: &[Variant] = &[
+        Variant::new("heaps", |file| {
+            Ok(Self::compact(file, Backend::Heaps)?.to_string())
+        }),
+        Variant::new("segment-list", |file| {
+            Ok(Self::compact(file, Backend::SegmentList)?.to_string())
+        }),
+    ];
+}
+
+impl Visualize for SolverImpl {}
+
+impl Parse for SolverImpl {
+    type Input = DiskMap;
+
+    #[tracing::instrument(name = "parse", skip_all)]
+    fn parse(file: &str) -> Result<Self::Input, ParseError> {
+        let line = Lines::new(file).next_line()?;
+        let bytes = line.text.as_bytes();
+        let disk_map = digit_values(bytes)
+            .map(|digit| digit.map(usize::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|i| line.error_at(&bytes[i..=i], "expected a digit"))?;
+        if disk_map.len() % 2 == 0 {
+            return Err(line.error("the disk map must end with a file"));
+        }
+
+        Ok(DiskMap(disk_map))
+    }
+}
+
 impl Solver for SolverImpl {
-    fn solve_part1(file: &str) {
-        let compaction = Compaction::new(file);
-        println!("The checksum is {}", compaction.check_sum());
+    type Command = Command;
+
+    fn solve_part1(file: &str) -> Result<Answer, AocError> {
+        let compaction = Compaction::new(&Self::parse_shared(file)?, None);
+        let answer = compaction.check_sum();
+        Ok(Answer::new(format!("The checksum is {answer}"), answer))
     }
 
-    fn solve_part2(file: &str) {
-        let compaction = Compaction::new_without_fragmentation(file);
-        println!("The checksum is {}", compaction.check_sum());
+    fn solve_part2(file: &str) -> Result<Answer, AocError> {
+        let answer = Self::compact(file, Backend::Heaps)?;
+        Ok(Answer::new(format!("The checksum is {answer}"), answer))
+    }
+
+    fn variants(part: Part) -> &'static [Variant] {
+        match part {
+            Part::Part1 => &[],
+            Part::Part2 => Self::PART2_VARIANTS,
+        }
     }
 }
 
 generate_benchmark!(day9);
+
+#[cfg(test)]
+mod backend_benches {
+    #[cfg(feature = "nightly-bench")]
+    use test::Bencher;
+
+    use super::*;
+
+    fn disk_map() -> DiskMap {
+        SolverImpl::parse(&crate::utils::read_benchmark_input("day9")).unwrap()
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_day9_compaction_heaps(b: &mut Bencher) {
+        let disk_map = disk_map();
+
+        b.iter(|| Compaction::new_without_fragmentation(&disk_map, None).check_sum());
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_day9_compaction_segment_list(b: &mut Bencher) {
+        let disk_map = disk_map();
+
+        b.iter(|| {
+            Compaction::new_without_fragmentation_by_segment_list(&disk_map, None).check_sum()
+        });
+    }
+
+    #[test]
+    fn both_backends_compact_the_same() {
+        let disk_map = disk_map();
+
+        assert_eq!(
+            Compaction::new_without_fragmentation(&disk_map, None).check_sum(),
+            Compaction::new_without_fragmentation_by_segment_list(&disk_map, None).check_sum()
+        );
+    }
+}
+
+#[cfg(test)]
+mod stage_tests {
+    use super::*;
+
+    const DISK_MAP: &str = "2333133121414131402";
+
+    fn compactions(max_moves: Option<usize>) -> [Compaction; 3] {
+        let disk_map = SolverImpl::parse(DISK_MAP).unwrap();
+        [
+            Compaction::new(&disk_map, max_moves),
+            Compaction::new_without_fragmentation(&disk_map, max_moves),
+            Compaction::new_without_fragmentation_by_segment_list(&disk_map, max_moves),
+        ]
+    }
+
+    #[test]
+    fn no_moves_keep_the_original_layout() {
+        for compaction in compactions(Some(0)) {
+            assert_eq!(compaction.num_moves, 0);
+            assert_eq!(
+                compaction.layout(42),
+                "00...111...2...333.44.5555.6666.777.888899"
+            );
+        }
+    }
+
+    #[test]
+    fn bounded_compactions_stop_midway() {
+        let [fragmented, heaps, segment_list] = compactions(Some(3));
+
+        assert_eq!(fragmented.num_moves, 3);
+        assert_eq!(
+            fragmented.layout(42),
+            "00998111...2...333.44.5555.6666.777.888..."
+        );
+        assert_eq!(heaps.num_moves, 3);
+        assert_eq!(
+            heaps.layout(42),
+            "0099.111777244.333....5555.6666.....8888.."
+        );
+        assert_eq!(segment_list.layout(42), heaps.layout(42));
+        assert_eq!(segment_list.check_sum(), heaps.check_sum());
+    }
+
+    #[test]
+    fn unbounded_compactions_match_the_parts() {
+        let [fragmented, heaps, segment_list] = compactions(None);
+
+        assert_eq!(fragmented.check_sum(), 1928);
+        assert_eq!(
+            fragmented.layout(42),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(heaps.check_sum(), 2858);
+        assert_eq!(segment_list.check_sum(), 2858);
+        assert_eq!(heaps.num_moves, segment_list.num_moves);
+    }
+}
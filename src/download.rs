@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::error::AocError;
+use crate::web;
+
+// Name of the file each day's input is saved as, like the default `--input` of days.
+const INPUT_NAME: &str = "input.txt";
+
+fn input_path(dir: &Path, day: u8) -> PathBuf {
+    dir.join(format!("day{day}")).join(INPUT_NAME)
+}
+
+// Whether the input of `day` was already downloaded. Placeholder inputs left empty don't count.
+fn is_present(dir: &Path, day: u8) -> bool {
+    std::fs::metadata(input_path(dir, day)).is_ok_and(|metadata| metadata.len() > 0)
+}
+
+/// Downloads the input of each day of the 2024 edition to `dir/dayN/input.txt`, unless it's already there, with the
+/// session cookie set in `AOC_SESSION`. Requests are at least `delay` apart, so that adventofcode.com isn't hammered.
+/// Returns the days whose input was downloaded.
+pub fn fetch_all(dir: &Path, delay: Duration) -> Result<Vec<u8>, AocError> {
+    fetch_all_with(dir, delay, |day| {
+        web::fetch(&format!("/2024/day/{day}/input"))
+    })
+}
+
+// Same as `fetch_all`, but inputs are fetched with `fetch`, given the day.
+fn fetch_all_with(
+    dir: &Path,
+    delay: Duration,
+    mut fetch: impl FnMut(u8) -> Result<String, AocError>,
+) -> Result<Vec<u8>, AocError> {
+    let mut fetched_days = Vec::new();
+    let mut last_request: Option<Instant> = None;
+
+    for day in 1..=25 {
+        if is_present(dir, day) {
+            println!("Day {day}: already downloaded, skipped");
+            continue;
+        }
+
+        if let Some(last_request) = last_request {
+            std::thread::sleep(delay.saturating_sub(last_request.elapsed()));
+        }
+        last_request = Some(Instant::now());
+        let input = fetch(day)?;
+
+        let path = input_path(dir, day);
+        let day_dir = path.parent().unwrap();
+        std::fs::create_dir_all(day_dir).map_err(|e| AocError::io(day_dir, e))?;
+        std::fs::write(&path, &input).map_err(|e| AocError::io(&path, e))?;
+        println!("Day {day}: downloaded to {}", path.display());
+        fetched_days.push(day);
+    }
+
+    Ok(fetched_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_missing_inputs_are_fetched() {
+        let dir = std::env::temp_dir().join(format!("aoc-download-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("day2")).unwrap();
+        std::fs::write(input_path(&dir, 2), "1 2 3\n").unwrap();
+        // Empty placeholders are replaced.
+        std::fs::create_dir_all(dir.join("day3")).unwrap();
+        std::fs::write(input_path(&dir, 3), "").unwrap();
+
+        let mut requested_days = Vec::new();
+        let fetched_days = fetch_all_with(&dir, Duration::ZERO, |day| {
+            requested_days.push(day);
+            Ok(format!("input of day {day}\n"))
+        })
+        .unwrap();
+
+        assert_eq!(fetched_days, requested_days);
+        assert_eq!(fetched_days.len(), 24);
+        assert!(!fetched_days.contains(&2));
+        assert_eq!(
+            std::fs::read_to_string(input_path(&dir, 2)).unwrap(),
+            "1 2 3\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(input_path(&dir, 3)).unwrap(),
+            "input of day 3\n"
+        );
+
+        // Everything is there now, so nothing is fetched again.
+        let fetched_days =
+            fetch_all_with(&dir, Duration::ZERO, |_| unreachable!("nothing to fetch")).unwrap();
+        assert!(fetched_days.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn requests_are_spaced_out() {
+        let dir = std::env::temp_dir().join(format!("aoc-download-delay-{}", std::process::id()));
+        let delay = Duration::from_millis(5);
+        let mut request_times = Vec::new();
+
+        let result = fetch_all_with(&dir, delay, |day| {
+            request_times.push(Instant::now());
+            if day == 3 {
+                return Err(AocError::Unsupported(String::from("stop")));
+            }
+            Ok(String::from("input\n"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(request_times.len(), 3);
+        assert!(
+            request_times
+                .windows(2)
+                .all(|times| times[1] - times[0] >= delay)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
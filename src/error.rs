@@ -0,0 +1,83 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::input::ParseError;
+
+/// Reasons why a day could not be solved, or explored, for a given input.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AocError {
+    /// A file could not be read or written.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The input file is malformed.
+    Parse(ParseError),
+    /// The input is well-formed, but the problem has no solution for it.
+    NoSolution(String),
+    /// What was asked isn't supported by this day.
+    Unsupported(String),
+    /// Implementations of the same part found different answers.
+    Disagreement(String),
+    /// Solving took longer than allowed.
+    Timeout(std::time::Duration),
+    /// Answers differ from the ones expected for the input.
+    WrongAnswer(String),
+}
+
+impl AocError {
+    pub fn io(path: &Path, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    /// Exit code of the process when failing with this error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Io { .. } => 2,
+            Self::Parse(_) => 3,
+            Self::NoSolution(_) => 4,
+            Self::Unsupported(_) => 5,
+            Self::Disagreement(_) => 6,
+            Self::Timeout(_) => 7,
+            Self::WrongAnswer(_) => 8,
+        }
+    }
+}
+
+impl Display for AocError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "could not access {}: {source}", path.display()),
+            Self::Parse(e) => write!(f, "could not parse the input, {e}"),
+            Self::NoSolution(reason) => write!(f, "no solution: {reason}"),
+            Self::Unsupported(reason) => write!(f, "unsupported: {reason}"),
+            Self::Disagreement(reason) => write!(f, "the implementations disagree, {reason}"),
+            Self::Timeout(timeout) => write!(f, "gave up solving after {timeout:?}"),
+            Self::WrongAnswer(reason) => write!(f, "wrong answer: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for AocError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse(e) => Some(e),
+            Self::NoSolution(_)
+            | Self::Unsupported(_)
+            | Self::Disagreement(_)
+            | Self::Timeout(_)
+            | Self::WrongAnswer(_) => None,
+        }
+    }
+}
+
+impl From<ParseError> for AocError {
+    fn from(value: ParseError) -> Self {
+        Self::Parse(value)
+    }
+}
@@ -0,0 +1,159 @@
+//! C interface to the solvers, declared in `include/aoc.h`. The library is built as a shared library exposing it with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::args::Part;
+use crate::error::AocError;
+
+thread_local! {
+    // Why the last call to `aoc_solve` from this thread failed, if it did.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Solves the `part` (1 or 2) of a `day` (between 1 and 25) for the UTF-8 input of `input_len` bytes at `input`, and
+/// returns the text of its answer, a sentence holding it, e.g. "Total is 11". Returns null if it failed instead, in
+/// which case `aoc_last_error` tells why. Solvers panicking fail like any other error, rather than unwinding into C.
+/// The returned string is owned by the caller, who must release it with `aoc_free`.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` bytes which are readable for the duration of the call. It may only be null if
+/// `input_len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aoc_solve(
+    day: u8,
+    part: u8,
+    input: *const u8,
+    input_len: usize,
+) -> *mut c_char {
+    let input = if input_len == 0 {
+        &[]
+    } else {
+        // SAFETY: the caller guarantees that `input` points to `input_len` readable bytes.
+        unsafe { std::slice::from_raw_parts(input, input_len) }
+    };
+
+    let result =
+        panic::catch_unwind(AssertUnwindSafe(|| solve(day, part, input))).unwrap_or_else(|panic| {
+            let reason = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown reason");
+            Err(format!("the solver panicked: {reason}"))
+        });
+
+    match result {
+        Ok(answer) => {
+            set_last_error(None);
+            answer.into_raw()
+        }
+        Err(reason) => {
+            set_last_error(Some(reason));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Why the last call to `aoc_solve` from the current thread failed, or null if it succeeded or there was none. The
+/// returned string is owned by the caller, who must release it with `aoc_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn aoc_last_error() -> *mut c_char {
+    LAST_ERROR.with_borrow(|error| {
+        error
+            .clone()
+            .map_or(std::ptr::null_mut(), CString::into_raw)
+    })
+}
+
+/// Releases a string returned by this library. Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must have been returned by `aoc_solve` or `aoc_last_error`, and not be released already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn aoc_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the caller guarantees that `string` was allocated by `CString::into_raw`, and is released only once.
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+fn solve(day: u8, part: u8, input: &[u8]) -> Result<CString, String> {
+    let part = match part {
+        1 => Part::Part1,
+        2 => Part::Part2,
+        part => return Err(format!("expected part 1 or 2, got {part}")),
+    };
+    let input = std::str::from_utf8(input).map_err(|e| format!("the input isn't UTF-8: {e}"))?;
+
+    let answer = crate::solve(day, part, input).map_err(|e: AocError| e.to_string())?;
+    CString::new(answer.text).map_err(|_| String::from("the answer holds a null character"))
+}
+
+fn set_last_error(reason: Option<String>) {
+    // Reasons come from messages which never hold null characters, but that can't be assumed in C.
+    let reason = reason.map(|reason| {
+        CString::new(reason.replace('\0', " ")).expect("null characters were replaced")
+    });
+    LAST_ERROR.set(reason);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    // Calls `aoc_solve`, and returns its answer, or why it failed.
+    fn solve_through_ffi(day: u8, part: u8, input: &[u8]) -> Result<String, String> {
+        // SAFETY: the input is a valid slice, and strings returned are released once read.
+        unsafe {
+            let answer = aoc_solve(day, part, input.as_ptr(), input.len());
+            let (string, result): (_, fn(String) -> _) = if answer.is_null() {
+                (aoc_last_error(), Err)
+            } else {
+                (answer, Ok)
+            };
+            let text = CStr::from_ptr(string).to_string_lossy().into_owned();
+            aoc_free(string);
+            result(text)
+        }
+    }
+
+    #[test]
+    fn answers_are_returned_as_c_strings() {
+        let input = b"3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+
+        assert_eq!(
+            solve_through_ffi(1, 1, input),
+            Ok("Total is 11".to_string())
+        );
+        assert!(aoc_last_error().is_null());
+        assert_eq!(
+            solve_through_ffi(26, 1, b""),
+            Err("unsupported: there is no day 26".to_string())
+        );
+    }
+
+    #[test]
+    fn failures_are_reported_as_the_last_error() {
+        assert_eq!(
+            solve_through_ffi(1, 3, b""),
+            Err("expected part 1 or 2, got 3".to_string())
+        );
+        assert!(
+            solve_through_ffi(1, 1, b"3   4\xff\n")
+                .unwrap_err()
+                .starts_with("the input isn't UTF-8")
+        );
+        // SAFETY: null pointers are only read when the length is 0, and ignored when released.
+        unsafe {
+            aoc_free(aoc_solve(1, 1, std::ptr::null(), 0));
+            aoc_free(std::ptr::null_mut());
+        }
+    }
+}
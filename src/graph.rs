@@ -0,0 +1,316 @@
+use std::fmt::{Debug, Display, Write};
+use std::hash::Hash;
+
+use itertools::Itertools;
+
+use crate::utils::{FxHashMap, FxHashSet, IterSorted};
+
+/// Undirected graph, stored as a set of neighbours per node.
+#[derive(Clone, Debug)]
+pub struct Graph<T> {
+    neighbours: FxHashMap<T, FxHashSet<T>>,
+}
+
+impl<T> Graph<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Adds a node without any edge, if it isn't in the graph already.
+    pub fn add_node(&mut self, node: T) {
+        self.neighbours.entry(node).or_default();
+    }
+
+    /// Adds an edge between `a` and `b`, along with both nodes.
+    pub fn add_edge(&mut self, a: T, b: T) {
+        self.neighbours.entry(a).or_default().insert(b);
+        self.neighbours.entry(b).or_default().insert(a);
+    }
+
+    /// All nodes of the graph, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = T> + '_ {
+        self.neighbours.keys().copied()
+    }
+
+    /// Nodes sharing an edge with `node`, in no particular order.
+    pub fn neighbours(&self, node: T) -> impl Iterator<Item = T> + '_ {
+        self.neighbours.get(&node).into_iter().flatten().copied()
+    }
+
+    /// Number of edges of `node`, which is 0 if it isn't in the graph.
+    pub fn degree(&self, node: T) -> usize {
+        self.neighbours.get(&node).map_or(0, FxHashSet::len)
+    }
+
+    /// Visits depth-first all nodes connected to `start`, including itself, each once in the order they're
+    /// first reached.
+    pub fn dfs(&self, start: T) -> Vec<T> {
+        let mut visited = FxHashSet::default();
+        let mut order = Vec::new();
+        let mut to_visit = vec![start];
+        while let Some(node) = to_visit.pop() {
+            if visited.insert(node) {
+                order.push(node);
+                to_visit.extend(
+                    self.neighbours(node)
+                        .filter(|neighbour| !visited.contains(neighbour)),
+                );
+            }
+        }
+
+        order
+    }
+
+    /// Splits the nodes into groups connected to each other, but not to any node from another group.
+    /// Groups come in no particular order, as do the nodes within them.
+    pub fn connected_components(&self) -> Vec<Vec<T>> {
+        let mut visited = FxHashSet::default();
+        let mut components = Vec::new();
+        for node in self.nodes() {
+            if visited.contains(&node) {
+                continue;
+            }
+
+            let component = self.dfs(node);
+            visited.extend(component.iter().copied());
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Finds a maximum clique of the graph, i.e. the largest set of nodes that are all connected to each other.
+    /// If several cliques share the maximum size, any of them may be returned.
+    /// This uses Bron–Kerbosch with pivoting, which in practice visits far fewer candidates than the naive
+    /// enumeration of all maximal cliques.
+    pub fn maximum_clique(&self) -> Vec<T> {
+        let mut maximum_clique = Vec::new();
+
+        self.bron_kerbosch(
+            &mut Vec::new(),
+            self.nodes().collect(),
+            FxHashSet::default(),
+            &mut maximum_clique,
+        );
+
+        maximum_clique
+    }
+
+    /// Enumerates all cliques of exactly `size` nodes which contain at least one node matching `filter`.
+    /// Each clique is returned once, with its nodes in ascending order.
+    pub fn cliques_of_size(&self, size: usize, filter: impl Fn(&T) -> bool) -> Vec<Vec<T>>
+    where
+        T: Ord,
+    {
+        let mut cliques = Vec::new();
+        let nodes: Vec<_> = self.nodes().sorted().collect();
+
+        self.extend_clique(&mut Vec::new(), &nodes, size, &filter, &mut cliques);
+
+        cliques
+    }
+
+    // Grows `clique` with each of the `candidates`, which are sorted and all connected to every node in `clique`.
+    // Only candidates after the one just added are considered afterwards, so that no clique is visited twice.
+    fn extend_clique(
+        &self,
+        clique: &mut Vec<T>,
+        candidates: &[T],
+        size: usize,
+        filter: &impl Fn(&T) -> bool,
+        cliques: &mut Vec<Vec<T>>,
+    ) where
+        T: Ord,
+    {
+        if clique.len() == size {
+            if clique.iter().any(filter) {
+                cliques.push(clique.clone());
+            }
+            return;
+        }
+
+        for (i, node) in candidates.iter().enumerate() {
+            let node_neighbours = &self.neighbours[node];
+            let next_candidates: Vec<_> = candidates[i + 1..]
+                .iter()
+                .filter(|candidate| node_neighbours.contains(*candidate))
+                .copied()
+                .collect();
+
+            // Not enough candidates left to ever reach the requested size.
+            if clique.len() + 1 + next_candidates.len() < size {
+                continue;
+            }
+
+            clique.push(*node);
+            self.extend_clique(clique, &next_candidates, size, filter, cliques);
+            clique.pop();
+        }
+    }
+
+    /// Renders the graph in the Graphviz DOT format. Nodes in `highlighted`, and the edges between
+    /// them, are drawn in a different colour.
+    pub fn to_dot(&self, highlighted: &FxHashSet<T>) -> String
+    where
+        T: Display + Ord,
+    {
+        let mut dot = String::from("graph {\n");
+
+        for (node, _) in self.neighbours.iter_sorted() {
+            if highlighted.contains(node) {
+                writeln!(dot, "  \"{node}\" [style=filled, fillcolor=red];").unwrap();
+            } else {
+                writeln!(dot, "  \"{node}\";").unwrap();
+            }
+        }
+
+        for (node, neighbours) in self.neighbours.iter_sorted() {
+            // Each edge is stored twice, only write it from its smallest node.
+            for neighbour in neighbours
+                .iter()
+                .filter(|neighbour| node < *neighbour)
+                .sorted()
+            {
+                if highlighted.contains(node) && highlighted.contains(neighbour) {
+                    writeln!(
+                        dot,
+                        "  \"{node}\" -- \"{neighbour}\" [color=red, penwidth=2];"
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(dot, "  \"{node}\" -- \"{neighbour}\";").unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    // `clique` is the clique currently being grown, `candidates` are the nodes that could extend it, and
+    // `excluded` are the nodes that could also extend it, but have already been explored in a previous branch.
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<T>,
+        mut candidates: FxHashSet<T>,
+        mut excluded: FxHashSet<T>,
+        maximum_clique: &mut Vec<T>,
+    ) {
+        if candidates.is_empty() && excluded.is_empty() {
+            // `clique` is maximal, but that doesn't mean it's maximum.
+            if clique.len() > maximum_clique.len() {
+                *maximum_clique = clique.clone();
+            }
+            return;
+        }
+
+        // Crucial pruning: even if all candidates were part of this clique, it wouldn't beat the best one.
+        if clique.len() + candidates.len() <= maximum_clique.len() {
+            return;
+        }
+
+        // Pick the pivot with the most neighbours among the candidates. Any maximal clique must contain either
+        // the pivot or one of its non-neighbours, so the pivot's neighbours don't need their own branch.
+        let pivot = *candidates
+            .iter()
+            .chain(excluded.iter())
+            .max_by_key(|node| self.neighbours[*node].intersection(&candidates).count())
+            .unwrap();
+        let pivot_neighbours = &self.neighbours[&pivot];
+        let branching_nodes: Vec<_> = candidates.difference(pivot_neighbours).copied().collect();
+
+        for node in branching_nodes {
+            let node_neighbours = &self.neighbours[&node];
+
+            clique.push(node);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(node_neighbours).copied().collect(),
+                excluded.intersection(node_neighbours).copied().collect(),
+                maximum_clique,
+            );
+            clique.pop();
+
+            candidates.remove(&node);
+            excluded.insert(node);
+        }
+    }
+}
+
+impl<T> Default for Graph<T> {
+    fn default() -> Self {
+        Self {
+            neighbours: FxHashMap::default(),
+        }
+    }
+}
+
+impl<T> FromIterator<(T, T)> for Graph<T>
+where
+    T: Copy + Eq + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut graph = Graph::default();
+
+        for (a, b) in iter {
+            graph.add_edge(a, b);
+        }
+
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> Graph<char> {
+        let mut graph: Graph<_> = [('a', 'b'), ('b', 'c'), ('c', 'a'), ('c', 'd'), ('e', 'f')]
+            .into_iter()
+            .collect();
+        graph.add_node('g');
+        graph
+    }
+
+    #[test]
+    fn degrees_count_neighbours() {
+        let graph = graph();
+
+        assert_eq!(graph.nodes().count(), 7);
+        assert_eq!(graph.degree('c'), 3);
+        assert_eq!(graph.degree('g'), 0);
+        assert_eq!(graph.degree('z'), 0);
+        assert_eq!(
+            graph.neighbours('c').sorted().collect_vec(),
+            ['a', 'b', 'd']
+        );
+        assert_eq!(graph.neighbours('z').count(), 0);
+    }
+
+    #[test]
+    fn dfs_reaches_connected_nodes() {
+        let graph = graph();
+
+        let order = graph.dfs('d');
+        assert_eq!(order[..2], ['d', 'c']);
+        assert_eq!(
+            order.into_iter().sorted().collect_vec(),
+            ['a', 'b', 'c', 'd']
+        );
+        assert_eq!(graph.dfs('g'), ['g']);
+    }
+
+    #[test]
+    fn components_partition_nodes() {
+        let components = graph()
+            .connected_components()
+            .into_iter()
+            .map(|component| component.into_iter().sorted().collect_vec())
+            .sorted()
+            .collect_vec();
+
+        assert_eq!(
+            components,
+            [vec!['a', 'b', 'c', 'd'], vec!['e', 'f'], vec!['g']]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::ops::{Index, IndexMut};
+
+use crate::utils::{Position, pos};
+
+/// Rectangular grid of tiles, stored row after row in a single `Vec`. Compared to a `Vec` per row, looking up a tile
+/// doesn't chase a pointer to its row, and neighbouring rows are next to each other in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid<T> {
+    tiles: Vec<T>,
+    num_cols: usize,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from its rows, which must all have the same length.
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<T>>) -> Self {
+        let mut tiles = Vec::new();
+        let mut num_cols = None;
+        for row in rows {
+            assert_eq!(
+                *num_cols.get_or_insert(row.len()),
+                row.len(),
+                "all rows of a grid must have the same length"
+            );
+            tiles.extend(row);
+        }
+
+        Self {
+            tiles,
+            num_cols: num_cols.unwrap_or(0),
+        }
+    }
+
+    pub fn filled(num_rows: usize, num_cols: usize, tile: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            tiles: vec![tile; num_rows * num_cols],
+            num_cols,
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.tiles.len().checked_div(self.num_cols).unwrap_or(0)
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    /// Index of the tile at `position` among all tiles, row after row. Handy to identify tiles with a number.
+    pub fn index_of(&self, position: Position) -> usize {
+        debug_assert!(
+            position.col < self.num_cols,
+            "{position:?} is out of bounds"
+        );
+        position.row * self.num_cols + position.col
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // Chunks can't be empty, but then neither are there any tiles.
+        self.tiles.chunks_exact(self.num_cols.max(1))
+    }
+
+    /// Positions of all tiles, row after row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> + use<T> {
+        let num_cols = self.num_cols;
+        (0..self.num_rows()).flat_map(move |row| (0..num_cols).map(move |col| pos!(row, col)))
+    }
+
+    /// Builds a grid of the same size, where each tile is mapped with `f`.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            tiles: self.tiles.iter().map(f).collect(),
+            num_cols: self.num_cols,
+        }
+    }
+
+    // Builds a grid of the given size, where the tile at each position is copied from the position mapped by `from`.
+    fn rearranged(
+        &self,
+        num_rows: usize,
+        num_cols: usize,
+        from: impl Fn(Position) -> Position,
+    ) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            tiles: (0..num_rows)
+                .flat_map(|row| (0..num_cols).map(move |col| pos!(row, col)))
+                .map(|position| self[from(position)].clone())
+                .collect(),
+            num_cols,
+        }
+    }
+
+    /// Builds the grid mirrored along its main diagonal, so that rows become columns.
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.rearranged(self.num_cols, self.num_rows(), |Position { row, col }| {
+            pos!(col, row)
+        })
+    }
+
+    /// Builds the grid rotated a quarter turn clockwise, so that the first column becomes the first row, reversed.
+    pub fn rotate_cw(&self) -> Self
+    where
+        T: Clone,
+    {
+        let last_row = self.num_rows().saturating_sub(1);
+        self.rearranged(self.num_cols, self.num_rows(), |Position { row, col }| {
+            pos!(last_row - col, row)
+        })
+    }
+
+    /// Builds the grid mirrored left to right, so that each row is reversed.
+    pub fn flip_horizontal(&self) -> Self
+    where
+        T: Clone,
+    {
+        let last_col = self.num_cols.saturating_sub(1);
+        self.rearranged(self.num_rows(), self.num_cols, |Position { row, col }| {
+            pos!(row, last_col - col)
+        })
+    }
+
+    /// Builds the grid mirrored top to bottom, so that rows are in reverse order.
+    pub fn flip_vertical(&self) -> Self
+    where
+        T: Clone,
+    {
+        let last_row = self.num_rows().saturating_sub(1);
+        self.rearranged(self.num_rows(), self.num_cols, |Position { row, col }| {
+            pos!(last_row - row, col)
+        })
+    }
+}
+
+impl<T> Index<Position> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: Position) -> &Self::Output {
+        &self.tiles[self.index_of(position)]
+    }
+}
+
+impl<T> IndexMut<Position> for Grid<T> {
+    fn index_mut(&mut self, position: Position) -> &mut Self::Output {
+        let index = self.index_of(position);
+        &mut self.tiles[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_are_indexed_by_row_then_column() {
+        let mut grid = Grid::from_rows([vec![1, 2, 3], vec![4, 5, 6]]);
+        grid[pos!(1, 0)] = 7;
+
+        assert_eq!((grid.num_rows(), grid.num_cols()), (2, 3));
+        assert_eq!(grid[pos!(0, 2)], 3);
+        assert_eq!(grid.index_of(pos!(1, 2)), 5);
+        assert_eq!(grid.rows().collect::<Vec<_>>(), [[1, 2, 3], [7, 5, 6]]);
+    }
+
+    #[test]
+    fn positions_go_row_after_row() {
+        let grid = Grid::filled(2, 2, ());
+
+        assert_eq!(
+            grid.positions().collect::<Vec<_>>(),
+            [pos!(0, 0), pos!(0, 1), pos!(1, 0), pos!(1, 1)]
+        );
+    }
+
+    #[test]
+    fn grids_are_reoriented() {
+        let grid = Grid::from_rows([vec![1, 2, 3], vec![4, 5, 6]]);
+
+        let rows = |grid: Grid<i32>| grid.rows().map(<[_]>::to_vec).collect::<Vec<_>>();
+        assert_eq!(rows(grid.transpose()), [[1, 4], [2, 5], [3, 6]]);
+        assert_eq!(rows(grid.rotate_cw()), [[4, 1], [5, 2], [6, 3]]);
+        assert_eq!(rows(grid.flip_horizontal()), [[3, 2, 1], [6, 5, 4]]);
+        assert_eq!(rows(grid.flip_vertical()), [[4, 5, 6], [1, 2, 3]]);
+        assert_eq!(grid.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), grid);
+        assert_eq!(Grid::<i32>::from_rows([]).rotate_cw(), Grid::from_rows([]));
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn ragged_rows_are_rejected() {
+        Grid::from_rows([vec![1, 2], vec![3]]);
+    }
+}
+
+#[cfg(all(test, feature = "day16"))]
+mod layout_benches {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    #[cfg(feature = "nightly-bench")]
+    use test::Bencher;
+
+    use super::*;
+    use crate::solver::Solver;
+
+    // Walls of a maze like the ones of day 16, which is about as large as the actual inputs.
+    fn maze_walls() -> Grid<bool> {
+        let maze = crate::day16::SolverImpl::generate(141, &mut StdRng::seed_from_u64(16)).unwrap();
+        Grid::from_rows(
+            maze.lines()
+                .map(|row| row.chars().map(|tile| tile == '#').collect()),
+        )
+    }
+
+    // Both searches compute the distance from the top left room to every tile, and sum them up. The maze is surrounded
+    // by walls, so neighbours never go out of bounds.
+    fn sum_distances_nested(walls: &[Vec<bool>]) -> usize {
+        let mut distances = vec![vec![usize::MAX; walls[0].len()]; walls.len()];
+        let mut tiles_to_explore = std::collections::VecDeque::from([pos!(1, 1)]);
+        distances[1][1] = 0;
+
+        while let Some(position) = tiles_to_explore.pop_front() {
+            for neighbour in position.surroundings() {
+                if walls[neighbour.row][neighbour.col]
+                    || distances[neighbour.row][neighbour.col] != usize::MAX
+                {
+                    continue;
+                }
+
+                distances[neighbour.row][neighbour.col] = distances[position.row][position.col] + 1;
+                tiles_to_explore.push_back(neighbour);
+            }
+        }
+
+        distances
+            .iter()
+            .flatten()
+            .filter(|d| **d != usize::MAX)
+            .sum()
+    }
+
+    fn sum_distances_flat(walls: &Grid<bool>) -> usize {
+        let mut distances = Grid::filled(walls.num_rows(), walls.num_cols(), usize::MAX);
+        let mut tiles_to_explore = std::collections::VecDeque::from([pos!(1, 1)]);
+        distances[pos!(1, 1)] = 0;
+
+        while let Some(position) = tiles_to_explore.pop_front() {
+            for neighbour in position.surroundings() {
+                if walls[neighbour] || distances[neighbour] != usize::MAX {
+                    continue;
+                }
+
+                distances[neighbour] = distances[position] + 1;
+                tiles_to_explore.push_back(neighbour);
+            }
+        }
+
+        distances.tiles.iter().filter(|d| **d != usize::MAX).sum()
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_grid_bfs_nested(b: &mut Bencher) {
+        let walls: Vec<Vec<bool>> = maze_walls().rows().map(<[bool]>::to_vec).collect();
+
+        b.iter(|| sum_distances_nested(&walls));
+    }
+
+    #[cfg(feature = "nightly-bench")]
+    #[bench]
+    fn bench_grid_bfs_flat(b: &mut Bencher) {
+        let walls = maze_walls();
+
+        b.iter(|| sum_distances_flat(&walls));
+    }
+
+    #[test]
+    fn both_layouts_find_the_same_distances() {
+        let walls = maze_walls();
+        let nested: Vec<Vec<bool>> = walls.rows().map(<[bool]>::to_vec).collect();
+
+        assert_eq!(sum_distances_nested(&nested), sum_distances_flat(&walls));
+    }
+}
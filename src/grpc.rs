@@ -0,0 +1,235 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Instant;
+
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::registry::registry;
+use crate::viz;
+
+include!(concat!(env!("OUT_DIR"), "/aoc.Solver.rs"));
+
+use solver_server::SolverServer;
+
+// Messages of `proto/aoc.proto`, which documents them.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveRequest {
+    #[prost(uint32, tag = "1")]
+    pub day: u32,
+    #[prost(uint32, tag = "2")]
+    pub part: u32,
+    #[prost(string, tag = "3")]
+    pub input: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SolveResponse {
+    #[prost(string, tag = "1")]
+    pub answer: String,
+    #[prost(uint64, tag = "2")]
+    pub solve_micros: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Frame {
+    #[prost(string, repeated, tag = "1")]
+    pub rows: Vec<String>,
+    #[prost(string, tag = "2")]
+    pub caption: String,
+}
+
+impl From<viz::Frame> for Frame {
+    fn from(frame: viz::Frame) -> Self {
+        Self {
+            rows: frame.grid.iter().map(String::from_iter).collect(),
+            caption: frame.caption,
+        }
+    }
+}
+
+impl From<AocError> for Status {
+    fn from(e: AocError) -> Self {
+        let message = e.to_string();
+        match e {
+            AocError::Parse(_) => Status::invalid_argument(message),
+            AocError::NoSolution(_) => Status::failed_precondition(message),
+            AocError::Unsupported(_) => Status::unimplemented(message),
+            AocError::Timeout(_) => Status::deadline_exceeded(message),
+            _ => Status::internal(message),
+        }
+    }
+}
+
+// Day and part of a request, checked to exist. Fails with the reason why they don't otherwise.
+fn day_and_part(request: &SolveRequest) -> Result<(u8, Part), String> {
+    let day = match request.day {
+        day @ 1..=25 => day as u8,
+        day => return Err(format!("expected a day between 1 and 25, got {day}")),
+    };
+    let part = match request.part {
+        1 => Part::Part1,
+        2 => Part::Part2,
+        part => return Err(format!("expected part 1 or 2, got {part}")),
+    };
+
+    Ok((day, part))
+}
+
+/// Implementation of the `Solver` service of `proto/aoc.proto`. Solvers are CPU-bound, so they run on blocking
+/// threads rather than on those of the async runtime.
+#[derive(Debug, Default)]
+pub struct SolverService;
+
+#[tonic::async_trait]
+impl solver_server::Solver for SolverService {
+    async fn solve(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<SolveResponse>, Status> {
+        let request = request.into_inner();
+        let (day, part) = day_and_part(&request).map_err(Status::invalid_argument)?;
+
+        let (answer, elapsed) = tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            let answer = crate::solve(day, part, &request.input);
+            (answer, start.elapsed())
+        })
+        .await
+        .map_err(|e| Status::internal(format!("the solver failed: {e}")))?;
+
+        Ok(Response::new(SolveResponse {
+            answer: answer?.text,
+            solve_micros: elapsed.as_micros() as u64,
+        }))
+    }
+
+    type VisualizeStream = ReceiverStream<Result<Frame, Status>>;
+
+    async fn visualize(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<Self::VisualizeStream>, Status> {
+        let request = request.into_inner();
+        let (day, part) = day_and_part(&request).map_err(Status::invalid_argument)?;
+
+        // Frames are sent as they're rendered, a few at a time, so that slow clients slow down the rendering rather
+        // than piling frames up in memory. Whether there are frames at all, e.g. if the input parses, is known before
+        // the first one, so that the request fails rather than its stream.
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            // Days are checked to exist when reading requests.
+            let solver = registry().get(day).unwrap();
+            let frames = match solver.frames(part, &request.input) {
+                Ok(Some(frames)) => frames,
+                Ok(None) => {
+                    let _ = started_tx
+                        .send(Err(Status::unimplemented("this day has no visualization")));
+                    return;
+                }
+                Err(e) => {
+                    let _ = started_tx.send(Err(AocError::from(e).into()));
+                    return;
+                }
+            };
+            // The client went away.
+            if started_tx.send(Ok(())).is_err() {
+                return;
+            }
+            for frame in frames {
+                if tx.blocking_send(Ok(frame.into())).is_err() {
+                    return;
+                }
+            }
+        });
+
+        started_rx
+            .await
+            .map_err(|e| Status::internal(format!("the visualization failed: {e}")))??;
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Serves the `Solver` service on `address`, until the process is stopped.
+pub fn serve(address: SocketAddr) -> Result<(), AocError> {
+    let to_io_error = |e| AocError::io(Path::new(&address.to_string()), e);
+    let runtime = tokio::runtime::Runtime::new().map_err(to_io_error)?;
+
+    println!("Serving the solvers on {address}");
+    runtime
+        .block_on(
+            tonic::transport::Server::builder()
+                .add_service(SolverServer::new(SolverService))
+                .serve(address),
+        )
+        .map_err(|e| to_io_error(std::io::Error::other(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::solver_server::Solver;
+    use super::*;
+
+    fn request(day: u32, part: u32, example: &str) -> Request<SolveRequest> {
+        Request::new(SolveRequest {
+            day,
+            part,
+            input: std::fs::read_to_string(format!("src/day{day}/{example}.txt")).unwrap(),
+        })
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn solves_parts() {
+        let response = block_on(SolverService.solve(request(1, 2, "example"))).unwrap();
+
+        assert_eq!(response.into_inner().answer, "Total is 31");
+    }
+
+    #[test]
+    fn rejects_unknown_parts_and_malformed_inputs() {
+        let status = block_on(SolverService.solve(request(1, 3, "example"))).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let mut malformed = request(1, 1, "example");
+        malformed.get_mut().input.push_str("1 2\n");
+        let status = block_on(SolverService.solve(malformed)).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn streams_visualization_frames() {
+        let frames: Vec<_> = block_on(async {
+            let stream = SolverService
+                .visualize(request(16, 1, "example"))
+                .await
+                .unwrap()
+                .into_inner();
+            stream.collect::<Vec<_>>().await
+        });
+
+        let last_frame = frames.last().unwrap().as_ref().unwrap();
+        assert_eq!(last_frame.rows.len(), 15);
+        assert!(last_frame.caption.contains("7036"));
+    }
+
+    #[test]
+    fn rejects_visualizing_malformed_inputs() {
+        let mut malformed = request(16, 1, "example");
+        malformed.get_mut().input.push_str("#?#\n");
+        let status = block_on(SolverService.visualize(malformed)).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let status = block_on(SolverService.visualize(request(1, 1, "example"))).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::hash::BuildHasher;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rustc_hash::FxBuildHasher;
+use serde::Serialize;
+use tracing::{Subscriber, span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::args::Part;
+use crate::error::AocError;
+
+/// Record of a part solved by the binary, appended as a JSON line to a history log so that answers and timings can be
+/// compared across commits.
+#[derive(Debug, Serialize)]
+pub struct RunRecord {
+    /// Seconds since the Unix epoch, when the run ended.
+    pub timestamp: u64,
+    pub day: u8,
+    pub part: u8,
+    /// Name of the input file, e.g. `input` or `example`.
+    pub input: String,
+    /// Hash of the content of the input, to tell apart runs on different inputs with the same name.
+    pub input_hash: String,
+    /// Text of the answer returned by the solver, e.g. "Total is 11". Empty for day commands solving a part their own
+    /// way, which print what they found rather than returning it.
+    pub answer: String,
+    /// Microseconds spent in each span of the solver, e.g. `parse` and `solve`, summed up per span name, along with
+    /// the `total` of the run.
+    pub durations_us: BTreeMap<&'static str, u128>,
+    /// Revision of the git checkout the run happened in, as described by `git describe --always --dirty`.
+    pub git_revision: Option<String>,
+}
+
+impl RunRecord {
+    pub fn new(
+        day: u8,
+        part: Part,
+        input: &str,
+        file: &str,
+        answer: &str,
+        durations: BTreeMap<&'static str, Duration>,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            day,
+            part: match part {
+                Part::Part1 => 1,
+                Part::Part2 => 2,
+            },
+            input: input.to_owned(),
+            input_hash: format!("{:016x}", FxBuildHasher.hash_one(file)),
+            answer: answer.trim().to_owned(),
+            durations_us: durations
+                .into_iter()
+                .map(|(name, duration)| (name, duration.as_micros()))
+                .collect(),
+            git_revision: git_revision(),
+        }
+    }
+}
+
+// None when git isn't installed, or when not running in a checkout.
+fn git_revision() -> Option<String> {
+    let output = Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Appends the `record` to the history log at `path` as a single JSON line, creating the log if needed.
+pub fn append(path: &Path, record: &RunRecord) -> Result<(), AocError> {
+    let to_io_error = |e| AocError::io(path, e);
+    let mut line = serde_json::to_string(record).map_err(|e| to_io_error(e.into()))?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut log| log.write_all(line.as_bytes()))
+        .map_err(to_io_error)
+}
+
+/// Tracing layer adding up how long spans took, per span name. Clones share the same durations.
+#[derive(Clone, Debug, Default)]
+pub struct SpanDurations(Arc<Mutex<BTreeMap<&'static str, Duration>>>);
+
+impl SpanDurations {
+    /// Durations of the spans closed since the last call, per span name, which are forgotten from then on.
+    pub fn take(&self) -> BTreeMap<&'static str, Duration> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+// When a span was created, kept along with it.
+struct SpanStart(Instant);
+
+impl<S> Layer<S> for SpanDurations
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(SpanStart(start)) = span.extensions().get::<SpanStart>() {
+            *self.0.lock().unwrap().entry(span.name()).or_default() += start.elapsed();
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::utils::FxHashMap;
+
+/// Implemented by days parsing their input into a typed representation before solving it.
+pub trait Parse {
+    type Input;
+
+    fn parse(file: &str) -> Result<Self::Input, ParseError>;
+
+    /// Same as `parse`, but within `share_parsed_inputs`, the input is only parsed by the first part solving it. Later
+    /// parts get a clone of it instead.
+    fn parse_shared(file: &str) -> Result<Self::Input, ParseError>
+    where
+        Self: 'static,
+        Self::Input: Clone + 'static,
+    {
+        let start = Instant::now();
+        let input = parse_cached::<Self>(file);
+        let parsed_for = PARSE_DURATION.get().unwrap_or_default() + start.elapsed();
+        PARSE_DURATION.set(Some(parsed_for));
+        input
+    }
+}
+
+// Parses the `file` with `P`, unless it was already parsed within `share_parsed_inputs`.
+fn parse_cached<P>(file: &str) -> Result<P::Input, ParseError>
+where
+    P: Parse + ?Sized + 'static,
+    P::Input: Clone + 'static,
+{
+    let key = (TypeId::of::<P>(), file.as_ptr() as usize, file.len());
+    let cached = SHARED_INPUTS.with_borrow(|shared_inputs| {
+        let input = shared_inputs.as_ref()?.get(&key)?;
+        input.downcast_ref::<P::Input>().cloned()
+    });
+    if let Some(input) = cached {
+        return Ok(input);
+    }
+
+    let input = P::parse(file)?;
+    SHARED_INPUTS.with_borrow_mut(|shared_inputs| {
+        if let Some(shared_inputs) = shared_inputs {
+            shared_inputs.insert(key, Box::new(input.clone()));
+        }
+    });
+    Ok(input)
+}
+
+// Inputs parsed within `share_parsed_inputs`, by the day that parsed them, and the address and length of their file.
+type SharedInputs = FxHashMap<(TypeId, usize, usize), Box<dyn Any>>;
+
+thread_local! {
+    static SHARED_INPUTS: RefCell<Option<SharedInputs>> = const { RefCell::new(None) };
+    // Time spent parsing inputs with `Parse::parse_shared` so far, if any were.
+    static PARSE_DURATION: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+/// Runs `f`, within which inputs parsed with `Parse::parse_shared` are only parsed once per file, e.g. to solve both
+/// parts of a day without parsing its input twice. Files must outlive `f`, so that their address isn't reused. Nested
+/// calls keep sharing the inputs of the outermost one, whose files must outlive it instead.
+pub fn share_parsed_inputs<T>(f: impl FnOnce() -> T) -> T {
+    if SHARED_INPUTS.with_borrow(Option::is_some) {
+        return f();
+    }
+
+    SHARED_INPUTS.set(Some(SharedInputs::default()));
+    let result = f();
+    SHARED_INPUTS.set(None);
+    result
+}
+
+/// Runs `f`, and returns how long it spent parsing inputs with `Parse::parse_shared` on the current thread, if it did.
+pub(crate) fn measure_parsing<T>(f: impl FnOnce() -> T) -> (T, Option<Duration>) {
+    let previous = PARSE_DURATION.replace(None);
+    let result = f();
+    (result, PARSE_DURATION.replace(previous))
+}
+
+/// Error found while parsing an input file, pointing at where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line of the input on which the error was found, starting at 1.
+    pub line: usize,
+    /// Column of the line at which the offending text starts, starting at 1, if known.
+    pub column: Option<usize>,
+    /// Text of the offending line.
+    pub text: String,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {column}")?;
+        }
+        write!(f, ": {} in {:?}", self.message, self.text)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Line of an input file, which knows where it is so that errors can point at it.
+#[derive(Clone, Copy, Debug)]
+pub struct Line<'a> {
+    /// Number of the line in the input, starting at 1.
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.number,
+            column: None,
+            text: self.text.to_owned(),
+            message: message.into(),
+        }
+    }
+
+    /// Error pointing at `part`, which must be a slice of this line's text, or of its bytes.
+    pub fn error_at(&self, part: impl AsRef<[u8]>, message: impl Into<String>) -> ParseError {
+        let offset = (part.as_ref().as_ptr() as usize).wrapping_sub(self.text.as_ptr() as usize);
+        ParseError {
+            column: (offset <= self.text.len()).then_some(offset + 1),
+            ..self.error(message)
+        }
+    }
+
+    pub fn strip_prefix(&self, prefix: &str) -> Result<&'a str, ParseError> {
+        self.text
+            .strip_prefix(prefix)
+            .ok_or_else(|| self.error(format!("expected {prefix:?} at the start of the line")))
+    }
+
+    /// Splits `text`, a slice of this line, on the first occurrence of `delimiter`.
+    pub fn split_once(
+        &self,
+        text: &'a str,
+        delimiter: &str,
+    ) -> Result<(&'a str, &'a str), ParseError> {
+        text.split_once(delimiter)
+            .ok_or_else(|| self.error_at(text, format!("expected {delimiter:?}")))
+    }
+
+    /// Parses `field`, a slice of this line, into a value.
+    pub fn parse<T>(&self, field: &'a str) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        field
+            .parse()
+            .map_err(|e| self.error_at(field, format!("invalid value {field:?}: {e}")))
+    }
+
+    /// Parses `digits`, a slice of this line's bytes, into a number. Unlike `parse`, this works on bytes found without
+    /// going through `str`, e.g. by a `regex::bytes::Regex`.
+    pub fn parse_decimal<T: FromDecimal>(&self, digits: &'a [u8]) -> Result<T, ParseError> {
+        T::from_decimal(digits).ok_or_else(|| {
+            self.error_at(
+                digits,
+                format!("expected a number of up to {} digits", T::MAX_DIGITS),
+            )
+        })
+    }
+
+    /// Parses each character of this line, e.g. into a row of tiles of a grid. Conversions fail with the unexpected
+    /// character.
+    pub fn parse_chars<T>(&self) -> Result<Vec<T>, ParseError>
+    where
+        T: TryFrom<char, Error = char>,
+    {
+        self.text
+            .char_indices()
+            .map(|(i, c)| {
+                T::try_from(c).map_err(|c| {
+                    self.error_at(
+                        &self.text[i..i + c.len_utf8()],
+                        format!("unexpected character {c:?}"),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Parses each character of this line as a decimal digit.
+    pub fn parse_digits(&self) -> Result<Vec<u8>, ParseError> {
+        self.text
+            .char_indices()
+            .map(|(i, c)| {
+                c.to_digit(10).map(|digit| digit as u8).ok_or_else(|| {
+                    self.error_at(&self.text[i..i + c.len_utf8()], "expected a digit")
+                })
+            })
+            .collect()
+    }
+}
+
+/// Numbers parsed from their decimal digits, straight from the bytes of an input.
+pub trait FromDecimal: Sized {
+    /// Number of digits of the largest number, for error messages.
+    const MAX_DIGITS: u32;
+
+    /// Parses `digits`, which must be made of ASCII digits only, without a sign. Fails if there are no digits, or if
+    /// the number is too large.
+    fn from_decimal(digits: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_from_decimal {
+    ($($number:ty),+) => {
+        $(
+            impl FromDecimal for $number {
+                const MAX_DIGITS: u32 = <$number>::MAX.ilog10() + 1;
+
+                fn from_decimal(digits: &[u8]) -> Option<Self> {
+                    if digits.is_empty() {
+                        return None;
+                    }
+
+                    digits.iter().try_fold(0 as $number, |number, digit| {
+                        if !digit.is_ascii_digit() {
+                            return None;
+                        }
+                        number.checked_mul(10)?.checked_add((digit - b'0') as $number)
+                    })
+                }
+            }
+        )+
+    };
+}
+
+impl_from_decimal!(u8, u16, u32, u64, usize, i32, i64);
+
+/// Lines of an input file, numbered as they're consumed.
+#[derive(Clone, Debug)]
+pub struct Lines<'a> {
+    lines: std::str::Lines<'a>,
+    num_lines_read: usize,
+}
+
+impl<'a> Lines<'a> {
+    pub fn new(file: &'a str) -> Self {
+        Self {
+            lines: file.lines(),
+            num_lines_read: 0,
+        }
+    }
+
+    /// Next line, which must exist.
+    pub fn next_line(&mut self) -> Result<Line<'a>, ParseError> {
+        match self.next() {
+            Some(line) => Ok(line),
+            None => Err(self.error_at_end("unexpected end of input")),
+        }
+    }
+
+    /// Error about the input as a whole, e.g. when something is missing from it, pointing past the lines read so far.
+    pub fn error_at_end(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.num_lines_read + 1,
+            column: None,
+            text: String::new(),
+            message: message.into(),
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.lines.next()?;
+        self.num_lines_read += 1;
+        Some(Line {
+            number: self.num_lines_read,
+            text,
+        })
+    }
+}
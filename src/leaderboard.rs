@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::AocError;
+use crate::web;
+
+// Advent of Code asks not to fetch a private leaderboard more often than this.
+const MIN_FETCH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+// Where fetched leaderboards are cached, relative to the root of the repository like inputs.
+const CACHE_DIR: &str = ".aoc-cache";
+
+/// Private leaderboard, as served by adventofcode.com.
+#[derive(Debug, Deserialize)]
+pub struct Leaderboard {
+    members: BTreeMap<String, Member>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Member {
+    id: u64,
+    // Anonymous users have no name.
+    name: Option<String>,
+    stars: u32,
+    local_score: u32,
+    // Parts solved, by day then by part. Both are numbers from 1, as strings.
+    completion_day_level: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+}
+
+impl Member {
+    fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("(anonymous user #{})", self.id))
+    }
+
+    // Stars of the member on a day: `*` when both parts are solved, `+` for the first one only, `.` otherwise.
+    fn day_stars(&self, day: u8) -> char {
+        match self
+            .completion_day_level
+            .get(&day.to_string())
+            .map(BTreeMap::len)
+        {
+            Some(2..) => '*',
+            Some(1) => '+',
+            _ => '.',
+        }
+    }
+}
+
+impl Leaderboard {
+    /// Table of the members, from the most points to the least, with their stars on each day.
+    pub fn render(&self) -> String {
+        let mut members: Vec<_> = self.members.values().collect();
+        members.sort_by_key(|member| (std::cmp::Reverse(member.local_score), member.id));
+        let name_width = members
+            .iter()
+            .map(|member| member.name().chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Name".len());
+
+        let mut table = String::new();
+        writeln!(
+            table,
+            "Rank  {:name_width$}  Points  Stars  1        10        20",
+            "Name"
+        )
+        .unwrap();
+        for (rank, member) in members.iter().enumerate() {
+            let days: String = (1..=25).map(|day| member.day_stars(day)).collect();
+            writeln!(
+                table,
+                "{:>4}  {:name_width$}  {:>6}  {:>5}  {days}",
+                rank + 1,
+                member.name(),
+                member.local_score,
+                member.stars
+            )
+            .unwrap();
+        }
+
+        table
+    }
+}
+
+fn cache_path(id: u64) -> PathBuf {
+    Path::new(CACHE_DIR).join(format!("leaderboard-{id}.json"))
+}
+
+// Cached leaderboard, if it was fetched recently enough to not fetch it again.
+fn read_recent_cache(path: &Path) -> Option<String> {
+    let age = std::fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .elapsed()
+        .ok()?;
+    if age >= MIN_FETCH_INTERVAL {
+        return None;
+    }
+
+    std::fs::read_to_string(path).ok()
+}
+
+/// Fetches the private leaderboard `id` of the 2024 edition, unless it was fetched less than 15 minutes ago, in which
+/// case the cached one is used.
+pub fn fetch(id: u64) -> Result<Leaderboard, AocError> {
+    let path = cache_path(id);
+    let json = match read_recent_cache(&path) {
+        Some(json) => json,
+        None => {
+            let json = web::fetch(&format!("/2024/leaderboard/private/view/{id}.json"))?;
+            std::fs::create_dir_all(CACHE_DIR)
+                .map_err(|e| AocError::io(Path::new(CACHE_DIR), e))?;
+            std::fs::write(&path, &json).map_err(|e| AocError::io(&path, e))?;
+            json
+        }
+    };
+
+    serde_json::from_str(&json).map_err(|e| AocError::io(&path, e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_are_ranked_by_points() {
+        let leaderboard: Leaderboard = serde_json::from_str(
+            r#"{
+                "event": "2024",
+                "owner_id": 1,
+                "members": {
+                    "1": {
+                        "id": 1, "name": "Ada", "stars": 3, "local_score": 10, "global_score": 0,
+                        "last_star_ts": 0,
+                        "completion_day_level": {
+                            "1": {"1": {"get_star_ts": 0, "star_index": 0}, "2": {"get_star_ts": 0, "star_index": 1}},
+                            "3": {"1": {"get_star_ts": 0, "star_index": 2}}
+                        }
+                    },
+                    "22": {
+                        "id": 22, "name": null, "stars": 4, "local_score": 12, "global_score": 0,
+                        "last_star_ts": 0,
+                        "completion_day_level": {
+                            "1": {"1": {"get_star_ts": 0, "star_index": 0}, "2": {"get_star_ts": 0, "star_index": 1}},
+                            "2": {"1": {"get_star_ts": 0, "star_index": 2}, "2": {"get_star_ts": 0, "star_index": 3}}
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            leaderboard.render(),
+            "\
+Rank  Name                  Points  Stars  1        10        20
+   1  (anonymous user #22)      12      4  **.......................
+   2  Ada                       10      3  *.+......................
+"
+        );
+    }
+}
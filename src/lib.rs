@@ -0,0 +1,152 @@
+//! Solvers of the [Advent of Code 2024](https://adventofcode.com/2024) problems.
+//!
+//! The stable surface of the library is `solve`, which solves a part of a day for an input, along with the
+//! utilities re-exported here, which other solvers may reuse. The modules of each day, and their `SolverImpl`, are
+//! public for the binary of this crate, but may change at any time.
+
+#![cfg_attr(feature = "nightly-bench", feature(test))]
+// Helpers shared by days go unused when only some of them are built, e.g. with `--features day16`.
+#![cfg_attr(not(all_days), allow(dead_code, unused_imports))]
+#[cfg(feature = "nightly-bench")]
+extern crate test;
+
+pub mod alloc_stats;
+pub mod args;
+mod dag;
+pub mod download;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod graph;
+mod grid;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod history;
+pub mod input;
+pub mod leaderboard;
+pub mod metrics;
+pub mod output;
+pub mod registry;
+pub mod solver;
+pub mod trace;
+mod trie;
+mod utils;
+pub mod verify;
+pub mod viz;
+mod web;
+mod weighted_graph;
+
+#[cfg(feature = "day1")]
+pub mod day1;
+#[cfg(feature = "day10")]
+pub mod day10;
+#[cfg(feature = "day11")]
+pub mod day11;
+#[cfg(feature = "day12")]
+pub mod day12;
+#[cfg(feature = "day13")]
+pub mod day13;
+#[cfg(feature = "day14")]
+pub mod day14;
+#[cfg(feature = "day15")]
+pub mod day15;
+#[cfg(feature = "day16")]
+pub mod day16;
+#[cfg(feature = "day17")]
+pub mod day17;
+#[cfg(feature = "day18")]
+pub mod day18;
+#[cfg(feature = "day19")]
+pub mod day19;
+#[cfg(feature = "day2")]
+pub mod day2;
+#[cfg(feature = "day20")]
+pub mod day20;
+#[cfg(feature = "day21")]
+pub mod day21;
+#[cfg(feature = "day22")]
+pub mod day22;
+#[cfg(feature = "day23")]
+pub mod day23;
+#[cfg(feature = "day24")]
+pub mod day24;
+#[cfg(feature = "day25")]
+pub mod day25;
+#[cfg(feature = "day3")]
+pub mod day3;
+#[cfg(feature = "day4")]
+pub mod day4;
+#[cfg(feature = "day5")]
+pub mod day5;
+#[cfg(feature = "day6")]
+pub mod day6;
+#[cfg(feature = "day7")]
+pub mod day7;
+#[cfg(feature = "day8")]
+pub mod day8;
+#[cfg(feature = "day9")]
+pub mod day9;
+
+// Days left out of the build by their feature are replaced with a solver failing as unsupported, so that the binary
+// still dispatches to every day. Days are registered in the order they're listed, which must be theirs.
+macro_rules! days {
+    ($($day:ident $feature:literal),+ $(,)?) => {
+        $(
+            #[cfg(not(feature = $feature))]
+            pub mod $day {
+                crate::solver::disabled_day!($feature);
+            }
+        )+
+
+        // Solvers of every day, in order, for the registry.
+        fn registered_days() -> Vec<Box<dyn registry::DynSolver>> {
+            vec![$(
+                Box::new(registry::Registered::<$day::SolverImpl>::new(|part| match part {
+                    Some(Part::Part1) => tracing::info_span!(concat!(stringify!($day), ".part1")),
+                    Some(Part::Part2) => tracing::info_span!(concat!(stringify!($day), ".part2")),
+                    None => tracing::info_span!(stringify!($day)),
+                })),
+            )+]
+        }
+    };
+}
+
+days!(
+    day1 "day1",
+    day2 "day2",
+    day3 "day3",
+    day4 "day4",
+    day5 "day5",
+    day6 "day6",
+    day7 "day7",
+    day8 "day8",
+    day9 "day9",
+    day10 "day10",
+    day11 "day11",
+    day12 "day12",
+    day13 "day13",
+    day14 "day14",
+    day15 "day15",
+    day16 "day16",
+    day17 "day17",
+    day18 "day18",
+    day19 "day19",
+    day20 "day20",
+    day21 "day21",
+    day22 "day22",
+    day23 "day23",
+    day24 "day24",
+    day25 "day25",
+);
+
+pub use args::Part;
+pub use dag::{CycleError, Dag};
+pub use error::AocError;
+pub use graph::Graph;
+pub use grid::Grid;
+pub use input::ParseError;
+pub use solver::{Answer, solve};
+pub use trie::{Trie, TrieElement};
+pub use utils::matrix::Matrix;
+pub use utils::{Direction, Position, bytes, math};
+pub use weighted_graph::{ShortestPaths, WeightedGraph};
@@ -1,56 +1,929 @@
-#![feature(test)]
-extern crate test;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
 
+use advent_of_code_2024 as aoc;
 use clap::Parser;
-use seq_macro::seq;
-
-mod args;
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day2;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-mod solver;
-mod trie;
-mod utils;
-
-use args::{Args, Day};
-use solver::Solver;
-
-fn main() {
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use aoc::args::{Args, Command, Day, DayOptions, Part};
+use aoc::error::AocError;
+use aoc::history::{self, RunRecord, SpanDurations};
+use aoc::output::{self, Format};
+use aoc::registry::{DynSolver, registry};
+use aoc::solver::{self, Answer};
+use aoc::verify::{self, Verdict};
+use aoc::{alloc_stats, metrics, trace, viz};
+
+// Only counts allocations with `--alloc-stats`.
+#[global_allocator]
+static ALLOCATOR: alloc_stats::CountingAllocator = alloc_stats::CountingAllocator;
+
+fn main() -> ExitCode {
     let cli = Args::parse();
+    if cli.no_color {
+        viz::disable_colour();
+    }
+    if cli.sequential {
+        solver::force_sequential();
+    }
+    if cli.alloc_stats {
+        alloc_stats::enable();
+    }
+    let recording = (cli.history.is_some() || cli.metrics_out.is_some()).then(|| Recording {
+        history: cli.history.clone(),
+        metrics_out: cli.metrics_out.clone(),
+        span_durations: SpanDurations::default(),
+        records: RefCell::default(),
+    });
+    let _trace_guard = trace::init(
+        cli.trace,
+        cli.trace_chrome.as_deref(),
+        recording
+            .as_ref()
+            .map(|recording| recording.span_durations.clone()),
+    );
+
+    let timings = cli
+        .time
+        .map(|format| Timings::new(format, cli.time_out.clone()));
+    let result = match cli.command {
+        Command::Day(day) => solve_with_timeout(cli.timeout, move || {
+            let result = run_day(
+                day,
+                cli.mmap,
+                recording.as_ref(),
+                timings.as_ref(),
+                &mut InputCache::default(),
+            );
+            write_recorded(result, recording.as_ref(), timings.as_ref())
+        }),
+        Command::All { input } => solve_with_timeout(cli.timeout, move || {
+            let result = solve_all(&input, cli.mmap, recording.as_ref(), timings.as_ref());
+            write_recorded(result, recording.as_ref(), timings.as_ref())
+        }),
+        Command::Verify { input } => solve_with_timeout(cli.timeout, move || {
+            let result = verify(&input, cli.mmap, recording.as_ref(), timings.as_ref());
+            write_recorded(result, recording.as_ref(), timings.as_ref())
+        }),
+        Command::Repl if cli.timeout.is_some() => Err(AocError::Unsupported(String::from(
+            "solving can't time out from the prompt",
+        ))),
+        Command::Repl => repl(cli.mmap, recording.as_ref()),
+        Command::Leaderboard { id } => {
+            aoc::leaderboard::fetch(id).map(|leaderboard| print!("{}", leaderboard.render()))
+        }
+        #[cfg(feature = "grpc")]
+        Command::Serve { address } => aoc::grpc::serve(address),
+        Command::Scrub { day, input, seed } => scrub(day, &input, seed, cli.mmap),
+        Command::Gen { day, size, seed } => generate(day, size, seed),
+        Command::FetchAll { dir, delay } => aoc::download::fetch_all(&dir, delay).map(|days| {
+            println!("Downloaded the inputs of {} days", days.len());
+        }),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+// Content of an input file, either read into memory, or memory-mapped.
+enum InputFile {
+    Read(String),
+    // Only built from UTF-8 content.
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for InputFile {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Read(content) => content,
+            // SAFETY: the content was checked to be UTF-8 when mapped.
+            Self::Mapped(mmap) => unsafe { std::str::from_utf8_unchecked(mmap) },
+        }
+    }
+}
+
+// Path of the `input` of a day: the file itself if it has a directory or an extension, or the day's input of that name
+// otherwise.
+fn input_path(day: u8, input: &str) -> PathBuf {
+    let path = Path::new(input);
+    if path
+        .parent()
+        .is_some_and(|parent| !parent.as_os_str().is_empty())
+        || path.extension().is_some()
+    {
+        path.to_owned()
+    } else {
+        PathBuf::from(format!("src/day{day}/{input}.txt"))
+    }
+}
+
+fn read_input(day: u8, input: &str, mmap: bool) -> Result<InputFile, AocError> {
+    if input == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| AocError::io(Path::new("<stdin>"), e))?;
+        return Ok(InputFile::Read(content));
+    }
+
+    let path = input_path(day, input);
+    let io_error = |e| AocError::io(&path, e);
+    if !mmap {
+        return std::fs::read_to_string(&path)
+            .map(InputFile::Read)
+            .map_err(io_error);
+    }
+
+    let file = File::open(&path).map_err(io_error)?;
+    // SAFETY: inputs aren't expected to be modified while being solved. If they are, answers may be wrong, but the
+    // content is only ever read.
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+    std::str::from_utf8(&mmap)
+        .map_err(|e| io_error(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+
+    Ok(InputFile::Mapped(mmap))
+}
+
+// Input files read so far, by day and name. The REPL keeps them around between commands, so that each is only read
+// once.
+#[derive(Default)]
+struct InputCache(HashMap<(u8, String), Rc<InputFile>>);
+
+impl InputCache {
+    fn get(&mut self, day: u8, input: &str, mmap: bool) -> Result<Rc<InputFile>, AocError> {
+        let key = (day, input.to_owned());
+        if let Some(file) = self.0.get(&key) {
+            return Ok(file.clone());
+        }
+
+        let file = Rc::new(read_input(day, input, mmap)?);
+        self.0.insert(key, file.clone());
+        Ok(file)
+    }
+}
+
+// Where to record solved parts, along with the durations of their spans.
+struct Recording {
+    history: Option<PathBuf>,
+    metrics_out: Option<PathBuf>,
+    span_durations: SpanDurations,
+    // Parts recorded so far, which are all written as metrics once the command is done.
+    records: RefCell<Vec<RunRecord>>,
+}
+
+impl Recording {
+    // Writes the parts recorded so far as metrics, if requested. Parts recorded before a failure are still written.
+    fn write_metrics(recording: Option<&Self>) -> Result<(), AocError> {
+        match recording {
+            Some(Self {
+                metrics_out: Some(metrics_out),
+                records,
+                ..
+            }) => metrics::write(metrics_out, &records.borrow()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Runs `solve` on a worker thread, and gives up once it takes longer than `timeout`, if any. Solvers are then cancelled,
+// but not waited for, as not all of them have cancellation points: exiting the process stops them.
+fn solve_with_timeout(
+    timeout: Option<Duration>,
+    solve: impl FnOnce() -> Result<(), AocError> + Send + 'static,
+) -> Result<(), AocError> {
+    let Some(timeout) = timeout else {
+        return solve();
+    };
+
+    // Solving may have been cancelled by an earlier deadline, which doesn't apply to this one.
+    solver::reset_cancellation();
+    let (tx, rx) = channel();
+    // Threads don't inherit the current span, so the worker is explicitly traced as part of it.
+    let span = tracing::Span::current();
+    let worker = std::thread::spawn(move || {
+        let _span = span.entered();
+        // Only fails once the deadline passed, when nobody waits for the result anymore.
+        let _ = tx.send(solve());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            solver::cancel();
+            Err(AocError::Timeout(timeout))
+        }
+        // The worker panicked, so its panic is resumed here.
+        Err(RecvTimeoutError::Disconnected) => match worker.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("the worker always sends its result"),
+        },
+    }
+}
+
+fn run_day(
+    day: Day,
+    mmap: bool,
+    recording: Option<&Recording>,
+    timings: Option<&Timings>,
+    inputs: &mut InputCache,
+) -> Result<(), AocError> {
+    let (day, command, options) = day.split();
+    let solver = registry()
+        .get(day)
+        .expect("days are validated when parsing arguments");
+    let DayOptions {
+        input,
+        example,
+        mut visualize,
+        visualize_out,
+        frontier,
+        simulate,
+        cross_check,
+        inputs: input_names,
+    } = options;
+    let input = if example { "example" } else { &input };
 
-    seq!(N in 1..=25 {
-        match cli.day {
-            #(
-                Day::Day~N {part, input} => {
-                    let path = format!("src/day{}/{}.txt", N, input);
-                    match std::fs::read_to_string(&path) {
-                        Ok(file_content) => day~N::SolverImpl::solve(part, &file_content),
-                        Err(e) => panic!("Could not read content of file {}, err: {}", path, e),
-                    };
+    let Some(command) = command else {
+        if cross_check || simulate.is_some() || visualize || visualize_out.is_some() || frontier {
+            return Err(AocError::Unsupported(String::from(
+                "pick a part to explore it",
+            )));
+        }
+        let _span = solver.span(None).entered();
+        if !input_names.is_empty() {
+            return compare_inputs(
+                solver,
+                day,
+                &[Part::Part1, Part::Part2],
+                &input_names,
+                mmap,
+                inputs,
+            );
+        }
+        let file_content = inputs.get(day, input, mmap)?;
+        return solve_both_parts(solver, day, recording, timings, input, &file_content);
+    };
+
+    visualize |= frontier && visualize_out.is_none();
+    let part = command.part();
+    let _span = solver.span(part).entered();
+    if !input_names.is_empty() {
+        return match part {
+            Some(part)
+                if !cross_check && simulate.is_none() && !visualize && visualize_out.is_none() =>
+            {
+                compare_inputs(solver, day, &[part], &input_names, mmap, inputs)
+            }
+            _ => Err(AocError::Unsupported(String::from(
+                "only solving parts can be compared across inputs",
+            ))),
+        };
+    }
+    if !command.reads_input() {
+        if cross_check || simulate.is_some() || visualize || visualize_out.is_some() {
+            return Err(AocError::Unsupported(String::from(
+                "only part1 and part2 can be explored",
+            )));
+        }
+        return command.run("");
+    }
+    let file_content = inputs.get(day, input, mmap)?;
+    if cross_check {
+        solver::cross_check(solver, part, &file_content)
+    } else if let Some(mode) = simulate {
+        viz::simulate(solver, part, &file_content, mode)
+    } else if visualize || visualize_out.is_some() {
+        viz::visualize(
+            solver,
+            part,
+            &file_content,
+            visualize,
+            visualize_out.as_deref(),
+            frontier,
+        )
+    } else if let Some(part) = command.as_part() {
+        // Commands only solving a part are solved through the solver, so that their answer can be recorded.
+        let answer = report_alloc_stats(day, part, || {
+            record_run(
+                recording,
+                day,
+                part,
+                input,
+                &file_content,
+                answer_text,
+                || {
+                    time_part(timings, day, part, input, || {
+                        solver.solve(part, &file_content)
+                    })
                 },
-            )*
+            )
+        })?;
+        println!("{answer}");
+        Ok(())
+    } else if let Some(part) = part {
+        // Day commands print what they found their own way, so there's no answer to record.
+        report_alloc_stats(day, part, || {
+            record_run(
+                recording,
+                day,
+                part,
+                input,
+                &file_content,
+                |()| "",
+                || time_part(timings, day, part, input, || command.run(&file_content)),
+            )
+        })
+    } else {
+        command.run(&file_content)
+    }
+}
+
+// Solves both parts of a day, one after the other, parsing the input only once for days sharing it between parts.
+fn solve_both_parts(
+    solver: &dyn DynSolver,
+    day: u8,
+    recording: Option<&Recording>,
+    timings: Option<&Timings>,
+    input: &str,
+    file_content: &str,
+) -> Result<(), AocError> {
+    aoc::input::share_parsed_inputs(|| {
+        for part in [Part::Part1, Part::Part2] {
+            let _span = match part {
+                Part::Part1 => tracing::info_span!("part1"),
+                Part::Part2 => tracing::info_span!("part2"),
+            }
+            .entered();
+            let answer = report_alloc_stats(day, part, || {
+                record_run(
+                    recording,
+                    day,
+                    part,
+                    input,
+                    file_content,
+                    answer_text,
+                    || {
+                        time_part(timings, day, part, input, || {
+                            solver.solve(part, file_content)
+                        })
+                    },
+                )
+            })?;
+            println!("{answer}");
         }
+
+        Ok(())
+    })
+}
+
+// Runs `run`, solving a part of a day, then prints on stderr how long it took and what it allocated, if allocation
+// stats are requested.
+fn report_alloc_stats<T>(
+    day: u8,
+    part: Part,
+    run: impl FnOnce() -> Result<T, AocError>,
+) -> Result<T, AocError> {
+    if !alloc_stats::is_enabled() {
+        return run();
+    }
+
+    let start = Instant::now();
+    let (result, stats) = alloc_stats::measure(run);
+    let part = match part {
+        Part::Part1 => 1,
+        Part::Part2 => 2,
+    };
+    eprintln!("Day {day} part {part} took {:?}: {stats}", start.elapsed());
+
+    result
+}
+
+// Timings of the parts solved, measured with `--time`, and written once they're all solved.
+struct Timings {
+    format: Format,
+    // Written on stderr otherwise.
+    out: Option<PathBuf>,
+    records: RefCell<Vec<TimingRecord>>,
+}
+
+// How long solving a part took, as written with `--time`.
+#[derive(Serialize)]
+struct TimingRecord {
+    day: u8,
+    part: u8,
+    input: String,
+    // Missing for days parsing their input as they solve it.
+    parse_us: Option<u128>,
+    solve_us: u128,
+}
+
+impl Timings {
+    fn new(format: Format, out: Option<PathBuf>) -> Self {
+        Self {
+            format,
+            out,
+            records: RefCell::default(),
+        }
+    }
+
+    fn write(&self) -> Result<(), AocError> {
+        let records = self.records.borrow();
+        match &self.out {
+            Some(path) => {
+                let file = File::create(path).map_err(|e| AocError::io(path, e))?;
+                output::write_records_into(file, path, &records, self.format)
+            }
+            None => output::write_records_into(
+                std::io::stderr().lock(),
+                Path::new("<stderr>"),
+                &records,
+                self.format,
+            ),
+        }
+    }
+}
+
+// Writes the metrics and timings recorded while running a command, if asked for. They're written even if the command
+// failed, for the parts it solved, and it's the error of the command which is then returned.
+fn write_recorded(
+    result: Result<(), AocError>,
+    recording: Option<&Recording>,
+    timings: Option<&Timings>,
+) -> Result<(), AocError> {
+    let metrics_written = Recording::write_metrics(recording);
+    let timings_written = timings.map_or(Ok(()), Timings::write);
+    result.and(metrics_written).and(timings_written)
+}
+
+// Solves a part with `run`, and records how long it spent parsing its input and solving it, if timings are measured.
+fn time_part<T>(
+    timings: Option<&Timings>,
+    day: u8,
+    part: Part,
+    input: &str,
+    run: impl FnOnce() -> Result<T, AocError>,
+) -> Result<T, AocError> {
+    let Some(timings) = timings else {
+        return run();
+    };
+
+    let (result, timing) = solver::timed(run);
+    let result = result?;
+    timings.records.borrow_mut().push(TimingRecord {
+        day,
+        part: match part {
+            Part::Part1 => 1,
+            Part::Part2 => 2,
+        },
+        input: input.to_owned(),
+        parse_us: timing.parse.map(|parse| parse.as_micros()),
+        solve_us: timing.solve.as_micros(),
     });
+
+    Ok(result)
+}
+
+// Solves `parts` of a day on each of the inputs named `input_names`, then prints their answers and how long they took
+// side by side. Inputs which fail are reported in the table, without stopping the others.
+fn compare_inputs(
+    solver: &dyn DynSolver,
+    day: u8,
+    parts: &[Part],
+    input_names: &[String],
+    mmap: bool,
+    inputs: &mut InputCache,
+) -> Result<(), AocError> {
+    let mut rows = Vec::new();
+    for input in input_names {
+        let file_content = inputs.get(day, input, mmap);
+        for &part in parts {
+            let mut row = vec![input.clone(), part_name(part).into()];
+            match &file_content {
+                Ok(file_content) => row.extend(solved_cells(|| solver.solve(part, file_content)).0),
+                Err(e) => row.extend(unsolved_cells(e)),
+            }
+            rows.push(row);
+        }
+    }
+
+    print_table(&["Input", "Part", "Time", "Allocations", "Answer"], &rows);
+
+    Ok(())
+}
+
+// Solves both parts of every day on its input named `input`, then prints their answers and how long they took, along
+// with the total. Days which fail are reported in the table, without stopping the others.
+fn solve_all(
+    input: &str,
+    mmap: bool,
+    recording: Option<&Recording>,
+    timings: Option<&Timings>,
+) -> Result<(), AocError> {
+    let mut rows = Vec::new();
+    let mut total = Duration::ZERO;
+    for (day, solver) in registry().iter() {
+        let file_content = read_input(day, input, mmap);
+        aoc::input::share_parsed_inputs(|| {
+            for part in [Part::Part1, Part::Part2] {
+                let mut row = vec![day.to_string(), part_name(part).into()];
+                match &file_content {
+                    Ok(file_content) => {
+                        let (cells, elapsed) = solved_cells(|| {
+                            record_run(
+                                recording,
+                                day,
+                                part,
+                                input,
+                                file_content,
+                                answer_text,
+                                || {
+                                    time_part(timings, day, part, input, || {
+                                        solver.solve(part, file_content)
+                                    })
+                                },
+                            )
+                        });
+                        row.extend(cells);
+                        total += elapsed;
+                    }
+                    Err(e) => row.extend(unsolved_cells(e)),
+                }
+                rows.push(row);
+            }
+        });
+    }
+
+    print_table(&["Day", "Part", "Time", "Allocations", "Answer"], &rows);
+    println!("Total: {total:?}");
+
+    Ok(())
+}
+
+// Solves both parts of every day on its input named `input`, then prints whether their answers are the expected ones,
+// and fails if any isn't. Days which fail to be solved are reported in the table, without stopping the others.
+fn verify(
+    input: &str,
+    mmap: bool,
+    recording: Option<&Recording>,
+    timings: Option<&Timings>,
+) -> Result<(), AocError> {
+    let mut rows = Vec::new();
+    let (mut passed, mut failed, mut unknown) = (0, 0, 0);
+    for (day, solver) in registry().iter() {
+        let expected = verify::expected_answers(day, input)?;
+        let file_content = read_input(day, input, mmap);
+        aoc::input::share_parsed_inputs(|| {
+            for part in [Part::Part1, Part::Part2] {
+                let mut row = vec![day.to_string(), part_name(part).into()];
+                // Parts which couldn't be solved have no verdict, and fail.
+                let mut verdict = None;
+                match &file_content {
+                    Ok(file_content) => {
+                        let (cells, _) = solved_cells(|| {
+                            let answer = record_run(
+                                recording,
+                                day,
+                                part,
+                                input,
+                                file_content,
+                                answer_text,
+                                || {
+                                    time_part(timings, day, part, input, || {
+                                        solver.solve(part, file_content)
+                                    })
+                                },
+                            )?;
+                            verdict = Some(Verdict::of(&answer, expected.get(part)));
+                            Ok(answer)
+                        });
+                        row.extend(cells);
+                    }
+                    Err(e) => row.extend(unsolved_cells(e)),
+                }
+                let result = match verdict {
+                    Some(Verdict::Pass) => {
+                        passed += 1;
+                        "PASS".to_string()
+                    }
+                    Some(Verdict::Fail { expected }) => {
+                        failed += 1;
+                        format!("FAIL, expected {expected}")
+                    }
+                    Some(Verdict::Unknown) => {
+                        unknown += 1;
+                        "UNKNOWN".to_string()
+                    }
+                    None => {
+                        failed += 1;
+                        "FAIL".to_string()
+                    }
+                };
+                row.insert(2, result);
+                rows.push(row);
+            }
+        });
+    }
+
+    print_table(
+        &["Day", "Part", "Result", "Time", "Allocations", "Answer"],
+        &rows,
+    );
+    println!("{passed} passed, {failed} failed, {unknown} without an expected answer");
+
+    if failed > 0 {
+        return Err(AocError::WrongAnswer(format!(
+            "{failed} parts don't have the expected answer"
+        )));
+    }
+    Ok(())
+}
+
+fn part_name(part: Part) -> &'static str {
+    match part {
+        Part::Part1 => "part1",
+        Part::Part2 => "part2",
+    }
+}
+
+// Solves a part with `solve`, and returns how long it took, what it allocated, and its answer or error, as cells of a
+// table row. Also returns how long it took, to add it up.
+fn solved_cells(solve: impl FnOnce() -> Result<Answer, AocError>) -> ([String; 3], Duration) {
+    let start = Instant::now();
+    let (result, stats) = alloc_stats::measure(solve);
+    let elapsed = start.elapsed();
+    let answer = match result {
+        // Only the first line of answers fits in a row, e.g. without the drawing of day 14.
+        Ok(answer) => answer
+            .text
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(e) => format!("error: {e}"),
+    };
+    let allocations = format!(
+        "{} allocations, {} bytes peak",
+        stats.num_allocations, stats.peak_live_bytes
+    );
+
+    ([format!("{elapsed:?}"), allocations, answer], elapsed)
+}
+
+// Cells of a table row for a part which couldn't be solved at all, e.g. as its input is missing.
+fn unsolved_cells(e: &AocError) -> [String; 3] {
+    [String::new(), String::new(), format!("error: {e}")]
+}
+
+// Prints `rows` under the `header`, with their columns aligned. Times are right-aligned, and the last column isn't
+// padded. The allocations column is only printed if allocation stats are requested.
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let header: Vec<_> = header.iter().map(ToString::to_string).collect();
+    let columns: Vec<_> = (0..header.len())
+        .filter(|&col| header[col] != "Allocations" || alloc_stats::is_enabled())
+        .collect();
+    let widths: Vec<_> = (0..header.len())
+        .map(|col| {
+            std::iter::once(&header)
+                .chain(rows)
+                .map(|row| row[col].chars().count())
+                .max()
+                .unwrap()
+        })
+        .collect();
+
+    for row in std::iter::once(&header).chain(rows) {
+        let mut line = String::new();
+        for (i, &col) in columns.iter().enumerate() {
+            let (cell, width) = (&row[col], widths[col]);
+            line += &if i == columns.len() - 1 {
+                cell.clone()
+            } else if header[col] == "Time" {
+                format!("{cell:>width$}  ")
+            } else {
+                format!("{cell:width$}  ")
+            };
+        }
+        println!("{line}");
+    }
+}
+
+// Solves a part with `run`, then appends a record of it and its answer, as told by `answer`, to the history log, and
+// keeps it to be written as metrics, if recording is requested.
+fn record_run<T>(
+    recording: Option<&Recording>,
+    day: u8,
+    part: Part,
+    input: &str,
+    file_content: &str,
+    answer: impl FnOnce(&T) -> &str,
+    run: impl FnOnce() -> Result<T, AocError>,
+) -> Result<T, AocError> {
+    let Some(recording) = recording else {
+        return run();
+    };
+
+    // Spans closed beforehand belong to other parts.
+    recording.span_durations.take();
+    let start = Instant::now();
+    let result = run()?;
+    let total = start.elapsed();
+
+    let mut durations = recording.span_durations.take();
+    durations.insert("total", total);
+    let record = RunRecord::new(day, part, input, file_content, answer(&result), durations);
+    if let Some(history) = &recording.history {
+        history::append(history, &record)?;
+    }
+    recording.records.borrow_mut().push(record);
+
+    Ok(result)
+}
+
+fn answer_text(answer: &Answer) -> &str {
+    &answer.text
+}
+
+const REPL_HELP: &str = "\
+Commands:
+  16 2              Solve part 2 of day 16. Same as `day16 part2`.
+  time 22 2         Solve part 2 of day 22, and report how long it took.
+  viz 15 [2]        Visualize part 1 (or 2) of day 15 on the terminal.
+  day12 regions -e  Anything else the binary accepts for a day, e.g. flags or day commands.
+  reload            Forget the inputs read and parsed so far, e.g. after editing them.
+  help              Show this help.
+  quit              Leave the prompt. So does the end of input.";
+
+// Expands the shorthands of the prompt into the arguments of the binary, e.g. `16 2` into `day16 part2`. Also returns
+// whether the command is timed.
+fn expand_repl_shorthands(words: &[&str]) -> (bool, Vec<String>) {
+    let (timed, words) = match words {
+        ["time", words @ ..] => (true, words),
+        _ => (false, words),
+    };
+    let (visualize, words) = match words {
+        ["viz", words @ ..] => (true, words),
+        _ => (false, words),
+    };
+
+    let mut args = vec![String::from("aoc")];
+    let mut words = words.iter();
+    if let Some(day) = words.next() {
+        args.push(match day.parse::<u8>() {
+            Ok(day) => format!("day{day}"),
+            Err(_) => day.to_string(),
+        });
+    }
+    match words.next() {
+        Some(part) if part.parse::<u8>().is_ok() => args.push(format!("part{part}")),
+        Some(command) => args.push(command.to_string()),
+        None if visualize => args.push(String::from("part1")),
+        None => {}
+    }
+    args.extend(words.map(ToString::to_string));
+    if visualize {
+        args.push(String::from("--visualize"));
+    }
+
+    (timed, args)
+}
+
+// Solves days one command after the other, read from stdin, until it ends. Errors are reported without leaving the
+// prompt. Inputs are only read and parsed once, until they're reloaded.
+fn repl(mmap: bool, recording: Option<&Recording>) -> Result<(), AocError> {
+    loop {
+        // Inputs parsed during the session point into the files read for it, so those are kept until it ends.
+        let mut inputs = InputCache::default();
+        let reload =
+            aoc::input::share_parsed_inputs(|| repl_session(mmap, recording, &mut inputs))?;
+        if !reload {
+            return Ok(());
+        }
+    }
+}
+
+// Runs the commands of the prompt until its input ends, or until inputs are reloaded, in which case it returns true.
+fn repl_session(
+    mmap: bool,
+    recording: Option<&Recording>,
+    inputs: &mut InputCache,
+) -> Result<bool, AocError> {
+    let stdin_error = |e| AocError::io(Path::new("<stdin>"), e);
+    let mut line = String::new();
+
+    loop {
+        print!("aoc> ");
+        std::io::stdout().flush().map_err(stdin_error)?;
+        line.clear();
+        if std::io::stdin().read_line(&mut line).map_err(stdin_error)? == 0 {
+            println!();
+            return Ok(false);
+        }
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["quit" | "exit"] => return Ok(false),
+            ["help"] => {
+                println!("{REPL_HELP}");
+                continue;
+            }
+            ["reload"] => return Ok(true),
+            _ => {}
+        }
+
+        let (timed, args) = expand_repl_shorthands(&words);
+        let cli = match Args::try_parse_from(args) {
+            Ok(cli) => cli,
+            Err(e) => {
+                // Also prints help when asked for with `--help`.
+                let _ = e.print();
+                continue;
+            }
+        };
+        let start = Instant::now();
+        let result = match cli.command {
+            _ if cli.timeout.is_some() => Err(AocError::Unsupported(String::from(
+                "solving can't time out from the prompt",
+            ))),
+            _ if !startup_flags(&cli).is_empty() => Err(AocError::Unsupported(format!(
+                "{} can only be given when starting the prompt",
+                startup_flags(&cli).join(", ")
+            ))),
+            Command::Day(day) => {
+                let timings = cli.time.map(|format| Timings::new(format, cli.time_out));
+                let result = run_day(day, mmap || cli.mmap, recording, timings.as_ref(), inputs);
+                write_recorded(result, recording, timings.as_ref())
+            }
+            _ => Err(AocError::Unsupported(String::from(
+                "only days can be solved from the prompt",
+            ))),
+        };
+        match result {
+            Ok(()) if timed => println!("Took {:?}", start.elapsed()),
+            Ok(()) => {}
+            Err(e) => eprintln!("Error: {e}"),
+        }
+    }
+}
+
+// Global flags given to `cli` which set up the whole process, and so can't be changed from the prompt.
+fn startup_flags(cli: &Args) -> Vec<&'static str> {
+    [
+        ("--no-color", cli.no_color),
+        ("--trace", cli.trace),
+        ("--trace-chrome", cli.trace_chrome.is_some()),
+        ("--sequential", cli.sequential),
+        ("--alloc-stats", cli.alloc_stats),
+        ("--history", cli.history.is_some()),
+        ("--metrics-out", cli.metrics_out.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(flag, given)| given.then_some(flag))
+    .collect()
+}
+
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    StdRng::seed_from_u64(seed.unwrap_or_else(|| rand::rng().random()))
+}
+
+fn scrub(day: u8, input: &str, seed: Option<u64>, mmap: bool) -> Result<(), AocError> {
+    let file_content = read_input(day, input, mmap)?;
+    let mut rng = seeded_rng(seed);
+
+    let solver = registry()
+        .get(day)
+        .expect("days are validated when parsing arguments");
+    print!("{}", solver.scrub(&file_content, &mut rng)?);
+
+    Ok(())
+}
+
+fn generate(day: u8, size: usize, seed: Option<u64>) -> Result<(), AocError> {
+    let mut rng = seeded_rng(seed);
+
+    let solver = registry()
+        .get(day)
+        .expect("days are validated when parsing arguments");
+    print!("{}", solver.generate(size, &mut rng)?);
+
+    Ok(())
 }
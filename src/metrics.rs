@@ -0,0 +1,119 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::error::AocError;
+use crate::history::RunRecord;
+
+// Escapes a label value of the Prometheus text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+// Labels identifying the part solved by a run.
+fn part_labels(record: &RunRecord) -> String {
+    format!(
+        r#"day="{}",part="{}",input="{}""#,
+        record.day,
+        record.part,
+        escape(&record.input)
+    )
+}
+
+/// Renders the `records` as Prometheus gauges, in the text exposition format.
+pub fn render(records: &[RunRecord]) -> String {
+    let mut metrics = String::new();
+
+    metrics.push_str("# HELP aoc_duration_seconds Time spent in each phase of solving a part.\n");
+    metrics.push_str("# TYPE aoc_duration_seconds gauge\n");
+    for record in records {
+        for (phase, duration_us) in &record.durations_us {
+            writeln!(
+                metrics,
+                r#"aoc_duration_seconds{{{},phase="{phase}"}} {}"#,
+                part_labels(record),
+                *duration_us as f64 / 1e6
+            )
+            .unwrap();
+        }
+    }
+
+    metrics.push_str(
+        "# HELP aoc_answer_info Answer found for a part, and what it was found with. Always 1.\n",
+    );
+    metrics.push_str("# TYPE aoc_answer_info gauge\n");
+    for record in records {
+        writeln!(
+            metrics,
+            r#"aoc_answer_info{{{},input_hash="{}",answer="{}",git_revision="{}"}} 1"#,
+            part_labels(record),
+            record.input_hash,
+            escape(&record.answer),
+            escape(record.git_revision.as_deref().unwrap_or_default())
+        )
+        .unwrap();
+    }
+
+    metrics.push_str(
+        "# HELP aoc_solved_timestamp_seconds When a part was last solved, since the Unix epoch.\n",
+    );
+    metrics.push_str("# TYPE aoc_solved_timestamp_seconds gauge\n");
+    for record in records {
+        writeln!(
+            metrics,
+            "aoc_solved_timestamp_seconds{{{}}} {}",
+            part_labels(record),
+            record.timestamp
+        )
+        .unwrap();
+    }
+
+    metrics
+}
+
+/// Replaces the file at `path` with the `records` rendered as Prometheus gauges. The metrics are written next to it
+/// first, then moved in place, so that a scraper never reads them half-written.
+pub fn write(path: &Path, records: &[RunRecord]) -> Result<(), AocError> {
+    let mut partial_path = path.as_os_str().to_owned();
+    partial_path.push(".partial");
+
+    std::fs::write(&partial_path, render(records))
+        .map_err(|e| AocError::io(Path::new(&partial_path), e))?;
+    std::fs::rename(&partial_path, path).map_err(|e| AocError::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn gauges_are_labelled_by_part_with_escaped_values() {
+        let record = RunRecord {
+            timestamp: 1733011200,
+            day: 3,
+            part: 2,
+            input: String::from("example"),
+            input_hash: String::from("0123456789abcdef"),
+            answer: String::from("Say \"hi\"\nC:\\"),
+            durations_us: BTreeMap::from([("parse", 1500), ("total", 2000)]),
+            git_revision: None,
+        };
+
+        assert_eq!(
+            render(&[record])
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<_>>(),
+            [
+                r#"aoc_duration_seconds{day="3",part="2",input="example",phase="parse"} 0.0015"#,
+                r#"aoc_duration_seconds{day="3",part="2",input="example",phase="total"} 0.002"#,
+                r#"aoc_answer_info{day="3",part="2",input="example",input_hash="0123456789abcdef",answer="Say \"hi\"\nC:\\",git_revision=""} 1"#,
+                r#"aoc_solved_timestamp_seconds{day="3",part="2",input="example"} 1733011200"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::AocError;
+
+/// Formats in which reports can be written.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// A JSON array, with one object per record.
+    Json,
+    /// A CSV table, with a header row naming the fields of the records.
+    Csv,
+}
+
+// Name given to stdout in errors, which expect a path.
+const STDOUT: &str = "<stdout>";
+
+/// Writes the `records` to stdout, in the given format.
+pub fn write_records<T: Serialize>(records: &[T], format: Format) -> Result<(), AocError> {
+    write_records_into(std::io::stdout().lock(), Path::new(STDOUT), records, format)
+}
+
+/// Writes the `records` to `out`, in the given format. Errors name `out` after `path`.
+pub fn write_records_into<T: Serialize>(
+    mut out: impl Write,
+    path: &Path,
+    records: &[T],
+    format: Format,
+) -> Result<(), AocError> {
+    let to_io_error = |e| AocError::io(path, e);
+
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, records).map_err(|e| to_io_error(e.into()))?;
+            writeln!(out).map_err(to_io_error)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in records {
+                writer
+                    .serialize(record)
+                    .map_err(|e| to_io_error(std::io::Error::other(e)))?;
+            }
+            writer.flush().map_err(to_io_error)?;
+        }
+    }
+
+    Ok(())
+}
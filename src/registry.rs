@@ -0,0 +1,215 @@
+use std::marker::PhantomData;
+use std::sync::LazyLock;
+
+use rand::rngs::StdRng;
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::input::ParseError;
+use crate::solver::{Answer, Run, Solver, Variant};
+use crate::utils::Simulation;
+use crate::viz::{Frame, LegendEntry};
+
+/// What a day offers, without knowing which day it is, so that days are looked up by number rather than matched on.
+/// Days register themselves by being listed in `days!`, and each of them is behind its `SolverImpl`.
+pub trait DynSolver: Send + Sync {
+    /// Span within which this day runs, named after the day, and the part if any, e.g. `day16.part2`.
+    fn span(&self, part: Option<Part>) -> tracing::Span;
+
+    /// See `Solver::solve`.
+    fn solve(&self, part: Part, file: &str) -> Result<Answer, AocError>;
+
+    /// See `Solver::variants`.
+    fn variants(&self, part: Part) -> &'static [Variant];
+
+    /// See `Solver::scrub`.
+    fn scrub(&self, file: &str, rng: &mut StdRng) -> Result<String, AocError>;
+
+    /// See `Solver::generate`.
+    fn generate(&self, size: usize, rng: &mut StdRng) -> Result<String, AocError>;
+
+    /// See `Visualize::LEGEND`.
+    fn legend(&self) -> &'static [LegendEntry];
+
+    /// See `Visualize::frames`.
+    fn frames<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + 'a>>, ParseError>;
+
+    /// See `Visualize::search_frames`.
+    fn search_frames<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + 'a>>, ParseError>;
+
+    /// See `Visualize::simulation`.
+    fn simulation<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Simulation + 'a>>, ParseError>;
+}
+
+/// Day solved by `S`. Spans need names known at compile time, so they're made by `span`, which `days!` writes for
+/// each day.
+pub struct Registered<S> {
+    span: fn(Option<Part>) -> tracing::Span,
+    solver: PhantomData<fn() -> S>,
+}
+
+impl<S> Registered<S> {
+    pub(crate) fn new(span: fn(Option<Part>) -> tracing::Span) -> Self {
+        Self {
+            span,
+            solver: PhantomData,
+        }
+    }
+}
+
+impl<S: Solver> DynSolver for Registered<S> {
+    fn span(&self, part: Option<Part>) -> tracing::Span {
+        (self.span)(part)
+    }
+
+    fn solve(&self, part: Part, file: &str) -> Result<Answer, AocError> {
+        S::solve(part, file)
+    }
+
+    fn variants(&self, part: Part) -> &'static [Variant] {
+        S::variants(part)
+    }
+
+    fn scrub(&self, file: &str, rng: &mut StdRng) -> Result<String, AocError> {
+        S::scrub(file, rng)
+    }
+
+    fn generate(&self, size: usize, rng: &mut StdRng) -> Result<String, AocError> {
+        S::generate(size, rng)
+    }
+
+    fn legend(&self) -> &'static [LegendEntry] {
+        S::LEGEND
+    }
+
+    fn frames<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + 'a>>, ParseError> {
+        S::frames(part, file)
+    }
+
+    fn search_frames<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + 'a>>, ParseError> {
+        S::search_frames(part, file)
+    }
+
+    fn simulation<'a>(
+        &self,
+        part: Part,
+        file: &'a str,
+    ) -> Result<Option<Box<dyn Simulation + 'a>>, ParseError> {
+        S::simulation(part, file)
+    }
+}
+
+/// Command of a day, e.g. a part or a mode exploring its input, which is run without knowing which day it's for.
+pub trait DynCommand {
+    fn run(self: Box<Self>, file: &str) -> Result<(), AocError>;
+
+    /// Part of the problem solved by this command, if any.
+    fn part(&self) -> Option<Part>;
+
+    /// See `Run::as_part`.
+    fn as_part(&self) -> Option<Part>;
+
+    /// See `Run::reads_input`.
+    fn reads_input(&self) -> bool;
+}
+
+/// Command of the day solved by `S`.
+pub struct DayCommand<S: Solver>(pub S::Command);
+
+impl<S: Solver> DynCommand for DayCommand<S> {
+    fn run(self: Box<Self>, file: &str) -> Result<(), AocError> {
+        Run::<S>::run(self.0, file)
+    }
+
+    fn part(&self) -> Option<Part> {
+        Run::<S>::part(&self.0)
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        Run::<S>::as_part(&self.0)
+    }
+
+    fn reads_input(&self) -> bool {
+        Run::<S>::reads_input(&self.0)
+    }
+}
+
+/// Solvers of all days, by day.
+pub struct SolverRegistry {
+    solvers: Vec<Box<dyn DynSolver>>,
+}
+
+impl SolverRegistry {
+    /// Solver of the `day`, between 1 and 25.
+    pub fn get(&self, day: u8) -> Option<&dyn DynSolver> {
+        let index = usize::from(day).checked_sub(1)?;
+        self.solvers.get(index).map(Box::as_ref)
+    }
+
+    /// Every day along with its solver, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &dyn DynSolver)> {
+        (1..).zip(self.solvers.iter().map(Box::as_ref))
+    }
+}
+
+static REGISTRY: LazyLock<SolverRegistry> = LazyLock::new(|| SolverRegistry {
+    solvers: crate::registered_days(),
+});
+
+/// Registry of the solvers of all days.
+pub fn registry() -> &'static SolverRegistry {
+    &REGISTRY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_are_registered_in_order() {
+        let registry = registry();
+        let days: Vec<_> = registry.iter().map(|(day, _)| day).collect();
+
+        // Days left out of the build are still registered, as failing solvers.
+        assert_eq!(days, (1..=25).collect::<Vec<_>>());
+        assert!(registry.get(0).is_none());
+        assert!(registry.get(26).is_none());
+    }
+
+    #[cfg(all_days)]
+    #[test]
+    fn days_are_looked_up_by_number() {
+        let registry = registry();
+
+        assert_eq!(
+            registry
+                .get(1)
+                .unwrap()
+                .solve(Part::Part2, "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n")
+                .unwrap()
+                .text,
+            "Total is 31"
+        );
+        assert!(!registry.get(16).unwrap().legend().is_empty());
+    }
+}
@@ -1,13 +1,259 @@
+use std::fmt::{Debug, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::Subcommand;
+use rand::rngs::StdRng;
+
 use crate::args::Part;
+use crate::error::AocError;
+use crate::registry::DynSolver;
+use crate::viz::Visualize;
+
+pub trait Solver: Sized + Visualize {
+    /// Subcommand selecting what to run for this day. This is usually just the `Part` of the problem to solve,
+    /// but a day can offer extra modes to explore its input.
+    type Command: Subcommand + Debug + Run<Self>;
 
-pub trait Solver {
-    fn solve(part: Part, file: &str) {
+    /// Solves the `part` of the problem for the input `file`, and returns its answer rather than printing it.
+    fn solve(part: Part, file: &str) -> Result<Answer, AocError> {
+        let _span = tracing::info_span!("solve").entered();
         match part {
             Part::Part1 => Self::solve_part1(file),
             Part::Part2 => Self::solve_part2(file),
         }
     }
 
-    fn solve_part1(file: &str);
-    fn solve_part2(file: &str);
+    fn solve_part1(file: &str) -> Result<Answer, AocError>;
+    fn solve_part2(file: &str) -> Result<Answer, AocError>;
+
+    /// Every implementation of the given part, for days solving it in several ways, so that their answers can be
+    /// compared with `--cross-check`. Days with a single implementation have none.
+    fn variants(_part: Part) -> &'static [Variant] {
+        &[]
+    }
+
+    /// Rewrites the input `file` into one with the same structure but different values, picked with `rng`, so that
+    /// it can be shared without leaking the original input. Only some days support it.
+    fn scrub(_file: &str, _rng: &mut StdRng) -> Result<String, AocError> {
+        Err(AocError::Unsupported(String::from(
+            "this day's input can't be scrubbed",
+        )))
+    }
+
+    /// Generates a valid input of the given `size`, picking its values with `rng`, e.g. to measure how the solver
+    /// scales. What `size` stands for depends on the day. Only some days support it.
+    fn generate(_size: usize, _rng: &mut StdRng) -> Result<String, AocError> {
+        Err(AocError::Unsupported(String::from(
+            "this day has no input generator",
+        )))
+    }
+}
+
+/// Solver of a day left out of the build by its `$feature`, which fails to solve anything as unsupported.
+#[cfg(not(all_days))]
+macro_rules! disabled_day {
+    ($feature:literal) => {
+        pub struct SolverImpl {}
+
+        impl crate::viz::Visualize for SolverImpl {}
+
+        impl crate::solver::Solver for SolverImpl {
+            type Command = crate::args::Part;
+
+            fn solve_part1(_file: &str) -> Result<crate::solver::Answer, crate::error::AocError> {
+                Err(Self::disabled())
+            }
+
+            fn solve_part2(_file: &str) -> Result<crate::solver::Answer, crate::error::AocError> {
+                Err(Self::disabled())
+            }
+        }
+
+        impl SolverImpl {
+            fn disabled() -> crate::error::AocError {
+                crate::error::AocError::Unsupported(String::from(concat!(
+                    "this day isn't built, enable its `",
+                    $feature,
+                    "` feature"
+                )))
+            }
+        }
+    };
+}
+#[cfg(not(all_days))]
+pub(crate) use disabled_day;
+
+/// Runs a parsed day command on the given input file, for the solver `S`.
+pub trait Run<S> {
+    fn run(self, file: &str) -> Result<(), AocError>;
+
+    /// Part of the problem solved by this command, if any.
+    fn part(&self) -> Option<Part>;
+
+    /// Part solved by this command if it does nothing else, as `Part` does, so that it can be solved with
+    /// `Solver::solve` instead, and its answer recorded. Day commands solving a part their own way, e.g. with options,
+    /// have none.
+    fn as_part(&self) -> Option<Part>;
+
+    /// Whether this command works on the day's input, which is then read for it. Commands reading their own files
+    /// don't, and get an empty input.
+    fn reads_input(&self) -> bool {
+        true
+    }
+}
+
+// Parts print their answer.
+impl<S: Solver> Run<S> for Part {
+    fn run(self, file: &str) -> Result<(), AocError> {
+        println!("{}", S::solve(self, file)?);
+
+        Ok(())
+    }
+
+    fn part(&self) -> Option<Part> {
+        Some(*self)
+    }
+
+    fn as_part(&self) -> Option<Part> {
+        Some(*self)
+    }
+}
+
+// Set by `--sequential`, to never solve shards concurrently.
+static SEQUENTIAL: AtomicBool = AtomicBool::new(false);
+
+/// Makes solvers sharding their work solve it on the current thread, over a single shard holding all inputs in order,
+/// so that outputs and timings are reproducible.
+pub fn force_sequential() {
+    SEQUENTIAL.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn is_sequential() -> bool {
+    SEQUENTIAL.load(Ordering::Relaxed)
+}
+
+// Set once solving ran out of time, so that solvers stop at their next cancellation point.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Asks solvers to stop as soon as they can, e.g. once they ran out of time. Their answers are meaningless from then on.
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::Relaxed);
+}
+
+/// Lets solvers run again after `cancel`, before solving anything else under a deadline. Solvers which were cancelled
+/// and are still running then carry on, so they should be given up on for good before this.
+pub fn reset_cancellation() {
+    CANCELLED.store(false, Ordering::Relaxed);
+}
+
+pub(crate) fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+/// One of the implementations solving a part of a day, which returns its answer rather than printing it.
+pub struct Variant {
+    pub name: &'static str,
+    pub solve: fn(&str) -> Result<String, AocError>,
+}
+
+impl Variant {
+    pub const fn new(name: &'static str, solve: fn(&str) -> Result<String, AocError>) -> Self {
+        Self { name, solve }
+    }
+}
+
+/// Solves the `part` picked by a day command with each variant of the `solver`, printing their answers along with how
+/// long they took, and fails unless they all agree.
+pub fn cross_check(solver: &dyn DynSolver, part: Option<Part>, file: &str) -> Result<(), AocError> {
+    let part = part.ok_or_else(|| {
+        AocError::Unsupported(String::from("only part1 and part2 can be cross-checked"))
+    })?;
+    let variants = solver.variants(part);
+    if variants.len() < 2 {
+        return Err(AocError::Unsupported(String::from(
+            "this part has a single implementation",
+        )));
+    }
+
+    let mut answers = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let _span = tracing::info_span!("variant", name = variant.name).entered();
+        let start = Instant::now();
+        let answer = (variant.solve)(file)?;
+        println!("{}: {answer} ({:?})", variant.name, start.elapsed());
+        answers.push((variant.name, answer));
+    }
+
+    let (expected_name, expected_answer) = &answers[0];
+    for (name, answer) in &answers[1..] {
+        if answer != expected_answer {
+            return Err(AocError::Disagreement(format!(
+                "{expected_name} found {expected_answer}, but {name} found {answer}"
+            )));
+        }
+    }
+    println!("All {} implementations agree.", answers.len());
+
+    Ok(())
+}
+
+/// How long solving a part took, split between parsing its input and the rest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// Time spent parsing the input with `Parse::parse_shared`. Days parsing their input as they solve it have none.
+    pub parse: Option<Duration>,
+    /// Time spent solving, once the input was parsed.
+    pub solve: Duration,
+}
+
+/// Runs `f`, which solves a part, and measures how long it spent parsing its input and solving it.
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, Timing) {
+    let start = Instant::now();
+    let (result, parse) = crate::input::measure_parsing(f);
+    let total = start.elapsed();
+    let solve = total.saturating_sub(parse.unwrap_or_default());
+
+    (result, Timing { parse, solve })
+}
+
+/// Answer found by a solver.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Answer {
+    /// Sentence holding the answer, e.g. "Total is 11", which is what gets printed.
+    pub text: String,
+    /// The answer alone, as submitted to Advent of Code, e.g. "11". Some parts have none, e.g. when there's no
+    /// solution to tell apart from others.
+    pub value: Option<String>,
+}
+
+impl Answer {
+    pub fn new(text: impl Into<String>, value: impl ToString) -> Self {
+        Self {
+            text: text.into(),
+            value: Some(value.to_string()),
+        }
+    }
+
+    pub fn without_value(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            value: None,
+        }
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Solves the `part` of a `day`, between 1 and 25, for the given `input`.
+pub fn solve(day: u8, part: Part, input: &str) -> Result<Answer, AocError> {
+    crate::registry::registry()
+        .get(day)
+        .ok_or_else(|| AocError::Unsupported(format!("there is no day {day}")))?
+        .solve(part, input)
 }
@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+
+use crate::history::SpanDurations;
+
+/// Records the spans of the solvers, printing how long each took on stderr if `print` is set, writing them as a
+/// Chrome trace to `chrome_output` if given, and adding up their durations in `durations` if given. Nothing is
+/// recorded if none is requested.
+/// The returned guard must be kept alive until solving is done, so that the Chrome trace is fully written.
+pub fn init(
+    print: bool,
+    chrome_output: Option<&Path>,
+    durations: Option<SpanDurations>,
+) -> Option<FlushGuard> {
+    if !print && chrome_output.is_none() && durations.is_none() {
+        return None;
+    }
+
+    let fmt_layer = print.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_span_events(FmtSpan::CLOSE)
+    });
+    let (chrome_layer, guard) = match chrome_output {
+        Some(path) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .file(path)
+                .include_args(true)
+                .build();
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(chrome_layer)
+        .with(durations)
+        .init();
+
+    guard
+}
@@ -1,10 +1,13 @@
 use std::marker::PhantomData;
 
+/// Element of the words stored in a `Trie`, e.g. a letter.
 pub trait TrieElement {
+    /// Index of the element in its alphabet, which must be below the alphabet size of the trie.
     fn index(&self) -> usize;
 }
 
-#[derive(Debug)]
+/// Prefix tree of words, made of elements from an alphabet of `N` elements. Built by collecting words.
+#[derive(Clone, Debug)]
 pub struct Trie<T, const N: usize> {
     trie_entries: Vec<TrieEntry<N>>,
     element: PhantomData<T>,
@@ -33,12 +36,35 @@ where
         self.trie_entries[last_trie_entry_index].terminal = true;
     }
 
-    pub fn count_all_word_arrangements(&self, word: &[T]) -> u64 {
+    // Only needed by tests for now, arrangements being all that days look for.
+    #[cfg(test)]
+    fn contains(&self, word: impl IntoIterator<Item = T>) -> bool {
+        let mut last_trie_entry_index = 0;
+
+        for c in word {
+            match self.trie_entries[last_trie_entry_index].entries[c.index()] {
+                Some(trie_entry_index) => last_trie_entry_index = trie_entry_index,
+                None => return false,
+            }
+        }
+
+        self.trie_entries[last_trie_entry_index].terminal
+    }
+
+    /// Counts the ways `word` can be made by concatenating words of the trie.
+    /// `count_arrangements_reaching_index` is scratch space, which callers counting many words can reuse between them
+    /// rather than allocating it for each word.
+    pub fn count_all_word_arrangements(
+        &self,
+        word: &[T],
+        count_arrangements_reaching_index: &mut Vec<u64>,
+    ) -> u64 {
         // +1 because index 0 is the special index to start with. What this records,
         // using dynamic programming, is that at index N+1, X arrangements reach N.
         // This could be one word from 0 to N, or maybe one word from 0 to K and one from
         // K+1 to N, and so on.
-        let mut count_arrangements_reaching_index = vec![0; word.len() + 1];
+        count_arrangements_reaching_index.clear();
+        count_arrangements_reaching_index.resize(word.len() + 1, 0);
         count_arrangements_reaching_index[0] = 1;
 
         // Iterate in-order through prefixes starting at all positions of the word.
@@ -101,7 +127,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct TrieEntry<const N: usize> {
     entries: [Option<usize>; N],
     terminal: bool,
@@ -115,3 +141,74 @@ impl<const N: usize> Default for TrieEntry<N> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::utils::FxHashSet;
+
+    const ALPHABET_SIZE: usize = 3;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    struct Letter(u8);
+
+    impl TrieElement for Letter {
+        fn index(&self) -> usize {
+            self.0 as usize
+        }
+    }
+
+    fn word(max_len: usize) -> impl Strategy<Value = Vec<Letter>> {
+        prop::collection::vec((0..ALPHABET_SIZE as u8).prop_map(Letter), 1..=max_len)
+    }
+
+    // Tries every pattern at the start of the word, then arranges the rest of it.
+    fn count_arrangements_by_brute_force(
+        patterns: &FxHashSet<Vec<Letter>>,
+        word: &[Letter],
+    ) -> u64 {
+        if word.is_empty() {
+            return 1;
+        }
+
+        patterns
+            .iter()
+            .filter(|pattern| word.starts_with(pattern))
+            .map(|pattern| count_arrangements_by_brute_force(patterns, &word[pattern.len()..]))
+            .sum()
+    }
+
+    proptest! {
+        #[test]
+        fn contains_exactly_the_inserted_words(
+            words in prop::collection::vec(word(5), 0..20),
+            other_words in prop::collection::vec(word(5), 0..20),
+        ) {
+            let trie: Trie<Letter, ALPHABET_SIZE> = words.iter().map(|word| word.iter().copied()).collect();
+
+            for word in &words {
+                prop_assert!(trie.contains(word.iter().copied()));
+            }
+            for word in &other_words {
+                prop_assert_eq!(trie.contains(word.iter().copied()), words.contains(word));
+            }
+        }
+
+        #[test]
+        fn counts_arrangements_like_brute_force(
+            patterns in prop::collection::hash_set(word(3), 1..8),
+            word in word(12),
+        ) {
+            let trie: Trie<Letter, ALPHABET_SIZE> =
+                patterns.iter().map(|pattern| pattern.iter().copied()).collect();
+            let patterns: FxHashSet<_> = patterns.into_iter().collect();
+
+            prop_assert_eq!(
+                trie.count_all_word_arrangements(&word, &mut Vec::new()),
+                count_arrangements_by_brute_force(&patterns, &word)
+            );
+        }
+    }
+}
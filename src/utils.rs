@@ -1,6 +1,58 @@
+use std::ops::Sub;
 use std::sync::mpsc::channel;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use itertools::Itertools;
+use rustc_hash::FxBuildHasher;
+
+use crate::input::Line;
+use crate::viz::Frame;
+
+pub mod bytes;
+pub mod math;
+pub mod matrix;
+
+/// Hash map using FxHash, which is much faster than the default hasher on the small keys used throughout. It doesn't
+/// resist HashDoS attacks, which puzzle inputs aren't.
+pub type FxHashMap<K, V> = hashbrown::HashMap<K, V, FxBuildHasher>;
+/// Hash set using FxHash, see `FxHashMap`.
+pub type FxHashSet<T> = hashbrown::HashSet<T, FxBuildHasher>;
+
+/// Iteration over a hash container in ascending order rather than its arbitrary one, which changes as soon as the
+/// hasher or the insertion order does. Anything shown to users should go through it, so that runs can be diffed.
+pub trait IterSorted {
+    type Item<'a>
+    where
+        Self: 'a;
+
+    fn iter_sorted(&self) -> std::vec::IntoIter<Self::Item<'_>>;
+}
+
+impl<T: Ord> IterSorted for FxHashSet<T> {
+    type Item<'a>
+        = &'a T
+    where
+        T: 'a;
+
+    fn iter_sorted(&self) -> std::vec::IntoIter<&T> {
+        self.iter().sorted()
+    }
+}
+
+impl<K: Ord, V> IterSorted for FxHashMap<K, V> {
+    type Item<'a>
+        = (&'a K, &'a V)
+    where
+        K: 'a,
+        V: 'a;
+
+    /// Entries are sorted by key.
+    fn iter_sorted(&self) -> std::vec::IntoIter<(&K, &V)> {
+        self.iter().sorted_by_key(|(key, _)| *key)
+    }
+}
+
+/// Position of a tile on a grid, starting from (0, 0) at the top left. Positions are ordered row by row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Position<T = usize> {
     pub row: T,
     pub col: T,
@@ -16,35 +68,97 @@ macro_rules! pos {
 }
 pub(crate) use pos;
 
+// Benchmarks each part of a day against its input, and tests that it finds the expected answers of its examples.
 macro_rules! generate_benchmark {
     ($day:ident) => {
+        $crate::utils::generate_benchmark!($day, part1, part2);
+    };
+    // Only benchmarks and tests the given parts, e.g. when a day has no second part to solve.
+    ($day:ident, $($part:ident),+) => {
         use paste::paste;
 
         paste! {
-            #[cfg(test)]
+            #[cfg(all(test, feature = "nightly-bench"))]
             mod tests {
                 use super::*;
                 use test::Bencher;
 
-                #[bench]
-                fn [<bench_ $day _part1>](b: &mut Bencher) {
-                    let file = std::fs::read_to_string(concat!("src/", stringify!($day), "/input.txt")).unwrap();
+                $(
+                    #[bench]
+                    fn [<bench_ $day _ $part>](b: &mut Bencher) {
+                        let file = $crate::utils::read_benchmark_input(stringify!($day));
 
-                    b.iter(|| SolverImpl::solve_part1(&file));
-                }
-
-                #[bench]
-                fn [<bench_ $day _part2>](b: &mut Bencher) {
-                    let file = std::fs::read_to_string(concat!("src/", stringify!($day), "/input.txt")).unwrap();
+                        b.iter(|| SolverImpl::[<solve_ $part>](&file).unwrap());
+                    }
+                )+
+            }
 
-                    b.iter(|| SolverImpl::solve_part2(&file));
-                }
+            #[cfg(test)]
+            mod example_tests {
+                $(
+                    #[test]
+                    fn [<$day _ $part _solves_examples>]() {
+                        $crate::utils::check_example_answers(
+                            stringify!($day),
+                            stringify!($part),
+                            $crate::args::Part::[<$part:camel>],
+                        );
+                    }
+                )+
             }
         }
     };
 }
 pub(crate) use generate_benchmark;
 
+/// Solves `part` of `day` on each of its examples, and checks that its answer is the one expected for it in
+/// `src/{day}/expected.toml`, under its `part_name`, e.g. `part1 = 11` under `[example]`. Examples with no answer for
+/// this part are skipped. Those are the answers `verify` checks the examples against, rather than ones kept apart in an
+/// `expected.txt`, so that they're only written down once, for every example of a day.
+#[cfg(test)]
+pub(crate) fn check_example_answers(day: &str, part_name: &str, part: crate::args::Part) {
+    let expected_path = format!("src/{day}/expected.toml");
+    let expected_answers = crate::verify::read_expected_answers(expected_path.as_ref())
+        .unwrap_or_else(|e| panic!("could not read {expected_path}: {e}"));
+    let day_number = day.trim_start_matches("day").parse().unwrap();
+
+    for (example, answers) in expected_answers {
+        let Some(expected) = answers.get(part) else {
+            continue;
+        };
+
+        let example_path = format!("src/{day}/{example}.txt");
+        let file = std::fs::read_to_string(&example_path)
+            .unwrap_or_else(|e| panic!("could not read {example_path}: {e}"));
+        let answer = crate::solver::solve(day_number, part, &file)
+            .unwrap_or_else(|e| panic!("{day}/{example} {part_name} failed: {e}"));
+        assert_eq!(
+            answer.value.as_deref(),
+            Some(expected),
+            "{day}/{example} {part_name}: expected {expected}, got {:?}",
+            answer.text
+        );
+    }
+}
+
+/// Reads the input of `day` to benchmark it against, or to compare alternative implementations on. Real inputs aren't
+/// always around, e.g. on a fresh clone where `input.txt` is missing or left empty, in which case the example input is
+/// used, as noted on stderr.
+#[cfg(test)]
+pub(crate) fn read_benchmark_input(day: &str) -> String {
+    let input_path = format!("src/{day}/input.txt");
+    let missing = match std::fs::read_to_string(&input_path) {
+        Ok(file) if !file.trim().is_empty() => return file,
+        Ok(_) => "empty",
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "not found",
+        Err(e) => panic!("could not read {input_path}: {e}"),
+    };
+
+    let example_path = format!("src/{day}/example.txt");
+    eprintln!("{input_path} {missing}, using {example_path} instead");
+    std::fs::read_to_string(&example_path).unwrap()
+}
+
 impl Position {
     // Note that all of these Position helpers assume that the operation is valid.
     // That is, one should not call `up` on a (0,0) position, as (-1,0) is out of bounds.
@@ -89,8 +203,71 @@ impl Position {
             Direction::Left => self.left(1),
         }
     }
+
+    /// Moves `n` tiles towards `direction`, unless that leaves a grid of `num_rows` by `num_cols` tiles.
+    pub fn checked_go(
+        &self,
+        direction: Direction,
+        n: usize,
+        num_rows: usize,
+        num_cols: usize,
+    ) -> Option<Self> {
+        let position = match direction {
+            Direction::Up => pos!(self.row.checked_sub(n)?, self.col),
+            Direction::Right => pos!(self.row, self.col.checked_add(n)?),
+            Direction::Down => pos!(self.row.checked_add(n)?, self.col),
+            Direction::Left => pos!(self.row, self.col.checked_sub(n)?),
+        };
+
+        (position.row < num_rows && position.col < num_cols).then_some(position)
+    }
+
+    /// Offsets of the 4 tiles sharing a side with a tile, as (rows, columns) to move by.
+    pub const FOUR_WAY_OFFSETS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+    /// Offsets of the 8 tiles sharing a side or a corner with a tile, as (rows, columns) to move by.
+    pub const EIGHT_WAY_OFFSETS: [(isize, isize); 8] = [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+        (1, 0),
+        (1, -1),
+        (0, -1),
+    ];
+
+    /// Moves by `d_row` rows and `d_col` columns, unless that leaves a grid of `num_rows` by `num_cols` tiles.
+    pub fn checked_offset(
+        &self,
+        (d_row, d_col): (isize, isize),
+        num_rows: usize,
+        num_cols: usize,
+    ) -> Option<Self> {
+        let position = pos!(
+            self.row.checked_add_signed(d_row)?,
+            self.col.checked_add_signed(d_col)?
+        );
+
+        (position.row < num_rows && position.col < num_cols).then_some(position)
+    }
+
+    /// Positions reached by moving by each of `offsets`, e.g. `FOUR_WAY_OFFSETS` or knight moves, in order. Those
+    /// leaving a grid of `num_rows` by `num_cols` tiles are skipped.
+    pub fn neighbors_by_offsets<'a>(
+        &self,
+        offsets: &'a [(isize, isize)],
+        num_rows: usize,
+        num_cols: usize,
+    ) -> impl Iterator<Item = Self> + 'a {
+        let position = *self;
+        offsets
+            .iter()
+            .filter_map(move |&offset| position.checked_offset(offset, num_rows, num_cols))
+    }
 }
 
+/// Direction to move towards on a grid.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     Up,
@@ -140,6 +317,8 @@ impl From<char> for Direction {
 /// Ultimately, this returns an iterator over the output from each shard.
 /// Using this helper only makes sense if `f` takes a substantial amount of time to run, otherwise the cost of sharding
 /// and spawning threads will outweigh possible runtime gains.
+/// Once `solver::force_sequential` is called, `f` runs once on the current thread instead, over all inputs.
+/// Shards aren't interrupted when solving gets cancelled, so `f` should iterate over its inputs with `until_cancelled`.
 pub fn shard_and_solve_concurrently<Is, I, C, F, O>(
     inputs: Is,
     capture: C,
@@ -153,20 +332,262 @@ where
     O: Send + 'static,
 {
     let (tx, rx) = channel();
+    if crate::solver::is_sequential() {
+        let inputs: Vec<_> = inputs.into_iter().collect();
+        let _span = tracing::info_span!("shard", index = 0, len = inputs.len()).entered();
+        tx.send(f(inputs, capture)).unwrap();
+        return rx.into_iter();
+    }
+
     let available_parallelism = std::thread::available_parallelism().unwrap().get();
     let mut shards: Vec<_> = (0..available_parallelism).map(|_| Vec::new()).collect();
     for (i, input) in inputs.into_iter().enumerate() {
         shards[i % available_parallelism].push(input);
     }
 
-    for shard in shards {
+    // Threads don't inherit the current span, so shards are explicitly traced as part of it.
+    let parent_span = tracing::Span::current();
+    for (index, shard) in shards.into_iter().enumerate() {
         let capture = capture.clone();
         let f = f.clone();
         let tx = tx.clone();
+        let span = tracing::info_span!(parent: &parent_span, "shard", index, len = shard.len());
         std::thread::spawn(move || {
+            let _span = span.entered();
             tx.send(f(shard, capture)).unwrap();
         });
     }
 
     rx.into_iter()
 }
+
+/// Iterates over `inputs` until solving gets cancelled with `solver::cancel`, e.g. once a `--timeout` is reached. This
+/// is the cancellation point of long loops, typically those of the shards of `shard_and_solve_concurrently`.
+pub fn until_cancelled<I: IntoIterator>(inputs: I) -> impl Iterator<Item = I::Item> {
+    inputs
+        .into_iter()
+        .take_while(|_| !crate::solver::is_cancelled())
+}
+
+/// Maps each window of `N` consecutive elements of `sequence` with `f`, in order, e.g. each run of four price changes.
+/// Sequences shorter than `N` have no window.
+pub fn windows_map<T, U, const N: usize>(
+    sequence: impl IntoIterator<Item = T>,
+    mut f: impl FnMut([T; N]) -> U,
+) -> impl Iterator<Item = U>
+where
+    T: Copy,
+{
+    assert!(N > 0, "windows can't be empty");
+    let mut sequence = sequence.into_iter();
+    let mut window: Option<[T; N]> = None;
+
+    std::iter::from_fn(move || {
+        let next_window = match window {
+            None => sequence
+                .by_ref()
+                .take(N)
+                .collect::<Vec<_>>()
+                .try_into()
+                .ok()?,
+            Some(mut window) => {
+                window.rotate_left(1);
+                window[N - 1] = sequence.next()?;
+                window
+            }
+        };
+        window = Some(next_window);
+        Some(f(next_window))
+    })
+}
+
+/// Differences between each element of `sequence` and the one before it, e.g. `[1, 3, 2]` has differences `[2, -1]`.
+pub fn pairwise_diffs<T>(sequence: impl IntoIterator<Item = T>) -> impl Iterator<Item = T::Output>
+where
+    T: Copy + Sub,
+{
+    windows_map(sequence, |[a, b]| b - a)
+}
+
+/// Groups `lines` into the blocks separated by blank lines, e.g. the schematics of day 25. Blocks are never empty,
+/// even when several blank lines follow each other.
+pub fn chunk_by_blank_lines<'a>(
+    lines: impl IntoIterator<Item = Line<'a>>,
+) -> impl Iterator<Item = Vec<Line<'a>>> {
+    let mut lines = lines.into_iter().peekable();
+
+    std::iter::from_fn(move || {
+        while lines.next_if(|line| line.text.is_empty()).is_some() {}
+        let mut block = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.text.is_empty()) {
+            block.push(line);
+        }
+
+        (!block.is_empty()).then_some(block)
+    })
+}
+
+/// State evolving one step at a time, until it's done. Days built this way can be driven step by step from the CLI.
+pub trait Simulation {
+    /// Advances the simulation by one step. Must not be called once the simulation is done.
+    fn step(&mut self);
+
+    fn is_done(&self) -> bool;
+
+    /// Shows the current state of the simulation.
+    fn render(&self) -> Frame;
+
+    /// Steps until the simulation is done. Simulations that can skip ahead should override this.
+    fn run_to_completion(&mut self) {
+        while !self.is_done() {
+            self.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn direction() -> impl Strategy<Value = Direction> {
+        prop_oneof![
+            Just(Direction::Up),
+            Just(Direction::Right),
+            Just(Direction::Down),
+            Just(Direction::Left),
+        ]
+    }
+
+    #[test]
+    fn windows_are_mapped_in_order() {
+        let sums: Vec<_> = windows_map(1..=5, |[a, b, c]| a + b + c).collect();
+        assert_eq!(sums, [6, 9, 12]);
+        assert_eq!(windows_map(1..=2, |[a, b, c]| a + b + c).count(), 0);
+        assert_eq!(pairwise_diffs([1, 3, 2, 2]).collect::<Vec<_>>(), [2, -1, 0]);
+    }
+
+    #[test]
+    fn blocks_are_separated_by_blank_lines() {
+        let blocks: Vec<Vec<_>> =
+            chunk_by_blank_lines(crate::input::Lines::new("\na\nb\n\n\nc\n\n"))
+                .map(|block| block.iter().map(|line| (line.number, line.text)).collect())
+                .collect();
+
+        assert_eq!(blocks, [vec![(2, "a"), (3, "b")], vec![(6, "c")]]);
+    }
+
+    #[test]
+    fn hash_containers_iterate_in_order() {
+        let positions = FxHashSet::from_iter([pos!(1, 0), pos!(0, 5), pos!(0, 2)]);
+        assert_eq!(
+            positions.iter_sorted().copied().collect::<Vec<_>>(),
+            [pos!(0, 2), pos!(0, 5), pos!(1, 0)]
+        );
+
+        let counts = FxHashMap::from_iter([('b', 1), ('a', 2)]);
+        assert_eq!(
+            counts.iter_sorted().collect::<Vec<_>>(),
+            [(&'a', &2), (&'b', &1)]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn going_back_returns_to_the_same_position(
+            row in 1..1000usize,
+            col in 1..1000usize,
+            direction in direction(),
+        ) {
+            let position = pos!(row, col);
+            let back = direction.turn_clockwise().turn_clockwise();
+            prop_assert_eq!(position.go(direction).go(back), position);
+        }
+
+        #[test]
+        fn checked_go_stays_in_the_grid(
+            row in 0..20usize,
+            col in 0..20usize,
+            n in 0..25usize,
+            num_rows in 1..20usize,
+            num_cols in 1..20usize,
+            direction in direction(),
+        ) {
+            let position = pos!(row, col);
+            let (d_row, d_col) = match direction {
+                Direction::Up => (-1, 0),
+                Direction::Right => (0, 1),
+                Direction::Down => (1, 0),
+                Direction::Left => (0, -1),
+            };
+            let expected_row = row as isize + d_row * n as isize;
+            let expected_col = col as isize + d_col * n as isize;
+            let in_grid = (0..num_rows as isize).contains(&expected_row)
+                && (0..num_cols as isize).contains(&expected_col);
+
+            match position.checked_go(direction, n, num_rows, num_cols) {
+                Some(moved) => {
+                    prop_assert!(in_grid);
+                    prop_assert_eq!(moved, pos!(expected_row as usize, expected_col as usize));
+                    // The way back may still leave the grid, if the position wasn't in it to begin with.
+                    let back = direction.turn_clockwise().turn_clockwise();
+                    let started_in_grid = row < num_rows && col < num_cols;
+                    prop_assert_eq!(
+                        moved.checked_go(back, n, num_rows, num_cols),
+                        started_in_grid.then_some(position)
+                    );
+                }
+                None => prop_assert!(!in_grid),
+            }
+        }
+
+        #[test]
+        fn neighbors_by_offsets_are_those_in_the_grid(
+            row in 0..10usize,
+            col in 0..10usize,
+            num_rows in 1..10usize,
+            num_cols in 1..10usize,
+        ) {
+            let knight_moves = [(-2, -1), (-2, 1), (-1, 2), (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2)];
+            let expected: Vec<_> = knight_moves
+                .iter()
+                .map(|(d_row, d_col)| (row as isize + d_row, col as isize + d_col))
+                .filter(|(row, col)| {
+                    (0..num_rows as isize).contains(row) && (0..num_cols as isize).contains(col)
+                })
+                .map(|(row, col)| pos!(row as usize, col as usize))
+                .collect();
+
+            prop_assert_eq!(
+                pos!(row, col)
+                    .neighbors_by_offsets(&knight_moves, num_rows, num_cols)
+                    .collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        #[test]
+        fn four_turns_are_a_full_circle(direction in direction()) {
+            let clockwise = direction
+                .turn_clockwise()
+                .turn_clockwise()
+                .turn_clockwise()
+                .turn_clockwise();
+            prop_assert_eq!(clockwise, direction);
+            let counter_clockwise = direction
+                .turn_counter_clockwise()
+                .turn_counter_clockwise()
+                .turn_counter_clockwise()
+                .turn_counter_clockwise();
+            prop_assert_eq!(counter_clockwise, direction);
+        }
+
+        #[test]
+        fn opposite_turns_cancel_out(direction in direction()) {
+            prop_assert_eq!(direction.turn_clockwise().turn_counter_clockwise(), direction);
+            prop_assert_eq!(direction.turn_counter_clockwise().turn_clockwise(), direction);
+            prop_assert_ne!(direction.turn_clockwise().sideways(), direction.sideways());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::input::FromDecimal;
+
+/// Longest run of ASCII digits starting at `start`, which is empty if there's no digit there.
+pub fn digit_run_at(bytes: &[u8], start: usize) -> &[u8] {
+    let rest = bytes.get(start..).unwrap_or_default();
+    let len = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+    &rest[..len]
+}
+
+/// Parses the number whose digits start at `start`, returning it along with the index just past its last digit.
+/// Returns `None` if there's no digit at `start`, or if the number doesn't fit in 64 bits.
+pub fn parse_u64_at(bytes: &[u8], start: usize) -> Option<(u64, usize)> {
+    let digits = digit_run_at(bytes, start);
+    Some((u64::from_decimal(digits)?, start + digits.len()))
+}
+
+/// Runs of consecutive ASCII digits found in `bytes`, in order.
+pub fn digit_runs(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes
+        .split(|byte| !byte.is_ascii_digit())
+        .filter(|digits| !digits.is_empty())
+}
+
+/// Numbers written in `bytes`, i.e. its runs of digits, ignoring anything in between. Numbers too large for `T` are
+/// returned as their digits, so that they can be reported.
+pub fn iter_numbers<T: FromDecimal>(bytes: &[u8]) -> impl Iterator<Item = Result<T, &[u8]>> {
+    digit_runs(bytes).map(|digits| T::from_decimal(digits).ok_or(digits))
+}
+
+/// Value of each byte of `bytes` as a decimal digit, or the index of a byte that isn't a digit.
+pub fn digit_values(bytes: &[u8]) -> impl Iterator<Item = Result<u8, usize>> + '_ {
+    bytes.iter().enumerate().map(|(i, byte)| {
+        if byte.is_ascii_digit() {
+            Ok(byte - b'0')
+        } else {
+            Err(i)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_found_between_noise() {
+        let bytes = b"mul(12,345)+7x99999999999999999999";
+
+        assert_eq!(digit_run_at(bytes, 4), b"12");
+        assert_eq!(digit_run_at(bytes, 3), b"");
+        assert_eq!(digit_run_at(bytes, 100), b"");
+        assert_eq!(parse_u64_at(bytes, 7), Some((345, 10)));
+        assert_eq!(parse_u64_at(bytes, 0), None);
+        assert_eq!(parse_u64_at(bytes, 14), None);
+        assert_eq!(digit_runs(bytes).count(), 4);
+        assert_eq!(
+            iter_numbers::<u64>(bytes).collect::<Vec<_>>(),
+            [Ok(12), Ok(345), Ok(7), Err(&b"99999999999999999999"[..])]
+        );
+        assert!(iter_numbers::<u8>(b"").next().is_none());
+    }
+
+    #[test]
+    fn digits_are_decoded() {
+        assert_eq!(
+            digit_values(b"2333").collect::<Result<Vec<_>, _>>(),
+            Ok(vec![2, 3, 3, 3])
+        );
+        assert_eq!(digit_values(b"12a4").collect::<Result<Vec<_>, _>>(), Err(2));
+    }
+}
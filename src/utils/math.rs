@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use crate::error::AocError;
+
+/// Error returned when the result of a computation doesn't fit in 64 bits, rather than silently wrapping around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OverflowError;
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a result overflows 64-bit integers")
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+impl From<OverflowError> for AocError {
+    fn from(value: OverflowError) -> Self {
+        Self::NoSolution(value.to_string())
+    }
+}
+
+/// Number of decimal digits of `n`. Zero has one digit, like when it's written.
+pub fn num_digits(n: u64) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// 10 to the power of `exponent`.
+pub fn pow10(exponent: u32) -> Result<u64, OverflowError> {
+    10_u64.checked_pow(exponent).ok_or(OverflowError)
+}
+
+/// `a * b + c`, computed on 128 bits so that only the final result has to fit in 64 bits.
+pub fn mul_add(a: i64, b: i64, c: i64) -> Result<i64, OverflowError> {
+    (a as i128 * b as i128 + c as i128)
+        .try_into()
+        .map_err(|_| OverflowError)
+}
+
+/// Same as `mul_add`, for unsigned integers.
+pub fn mul_add_unsigned(a: u64, b: u64, c: u64) -> Result<u64, OverflowError> {
+    (a as u128 * b as u128 + c as u128)
+        .try_into()
+        .map_err(|_| OverflowError)
+}
+
+/// Sum of `numbers`, failing if any of them failed, or if the sum doesn't fit in 64 bits.
+pub fn checked_sum(
+    numbers: impl IntoIterator<Item = Result<u64, OverflowError>>,
+) -> Result<u64, OverflowError> {
+    numbers.into_iter().try_fold(0_u64, |sum, number| {
+        sum.checked_add(number?).ok_or(OverflowError)
+    })
+}
+
+/// Number written with the digits of `a` followed by the digits of `b`, e.g. 12 and 345 give 12345.
+pub fn checked_concat_digits(a: u64, b: u64) -> Result<u64, OverflowError> {
+    mul_add_unsigned(a, pow10(num_digits(b))?, b)
+}
+
+/// Greatest common divisor of `a` and `b`, which is 0 only if both are.
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_are_concatenated() {
+        assert_eq!(checked_concat_digits(12, 345), Ok(12345));
+        assert_eq!(checked_concat_digits(1, 0), Ok(10));
+        assert_eq!(checked_concat_digits(0, 7), Ok(7));
+        assert_eq!(checked_concat_digits(u64::MAX / 10, 9), Err(OverflowError));
+        assert_eq!(checked_concat_digits(1, u64::MAX), Err(OverflowError));
+    }
+
+    #[test]
+    fn only_final_results_must_fit() {
+        assert_eq!(mul_add(i64::MAX, 2, -i64::MAX), Ok(i64::MAX));
+        assert_eq!(mul_add(i64::MAX, 2, 0), Err(OverflowError));
+        assert_eq!(mul_add_unsigned(u64::MAX, 1, 1), Err(OverflowError));
+        assert_eq!(pow10(19), Ok(10_000_000_000_000_000_000));
+        assert_eq!(pow10(20), Err(OverflowError));
+        assert_eq!((num_digits(0), num_digits(9), num_digits(10)), (1, 1, 2));
+        assert_eq!(checked_sum([Ok(1), Ok(2)]), Ok(3));
+        assert_eq!(checked_sum([Ok(u64::MAX), Ok(1)]), Err(OverflowError));
+        assert_eq!((gcd(12, 18), gcd(7, 0), gcd(0, 0)), (6, 7, 0));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::utils::math::OverflowError;
+
+/// Square matrix of `N` by `N` integers. A linear recurrence steps its state by multiplying it with a matrix, so its
+/// state after `k` steps is the `k`th power of that matrix times the initial state, which only takes `log(k)`
+/// multiplications rather than `k`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Matrix<const N: usize> {
+    rows: [[u64; N]; N],
+}
+
+impl<const N: usize> Matrix<N> {
+    pub const fn new(rows: [[u64; N]; N]) -> Self {
+        Self { rows }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(std::array::from_fn(|row| {
+            std::array::from_fn(|col| (row == col) as u64)
+        }))
+    }
+
+    pub fn rows(&self) -> &[[u64; N]; N] {
+        &self.rows
+    }
+
+    /// Product of this matrix and `other`, with entries reduced modulo `modulus` if given. Fails if an entry doesn't
+    /// fit in 64 bits, which can't happen under a modulus.
+    pub fn mul(&self, other: &Self, modulus: Option<u64>) -> Result<Self, OverflowError> {
+        let mut product = Self::new([[0; N]; N]);
+        for row in 0..N {
+            for col in 0..N {
+                product.rows[row][col] = dot_product(
+                    (0..N).map(|i| (self.rows[row][i], other.rows[i][col])),
+                    modulus,
+                )?;
+            }
+        }
+
+        Ok(product)
+    }
+
+    /// This matrix to the power of `exponent`, with entries reduced modulo `modulus` if given. Fails if an entry
+    /// doesn't fit in 64 bits, which can't happen under a modulus.
+    pub fn pow(&self, mut exponent: u64, modulus: Option<u64>) -> Result<Self, OverflowError> {
+        // Square-and-multiply, going through the bits of the exponent from the lowest.
+        let mut power = Self::identity().reduce(modulus);
+        let mut square = self.reduce(modulus);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                power = power.mul(&square, modulus)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                square = square.mul(&square, modulus)?;
+            }
+        }
+
+        Ok(power)
+    }
+
+    /// Product of this matrix and the column `vector`, e.g. the next state of a recurrence. Entries are reduced
+    /// modulo `modulus` if given.
+    pub fn apply(
+        &self,
+        vector: &[u64; N],
+        modulus: Option<u64>,
+    ) -> Result<[u64; N], OverflowError> {
+        let mut product = [0; N];
+        for (row, entry) in product.iter_mut().enumerate() {
+            *entry = dot_product(
+                self.rows[row].iter().copied().zip(vector.iter().copied()),
+                modulus,
+            )?;
+        }
+
+        Ok(product)
+    }
+
+    fn reduce(mut self, modulus: Option<u64>) -> Self {
+        if let Some(modulus) = modulus {
+            self.rows
+                .iter_mut()
+                .flatten()
+                .for_each(|entry| *entry %= modulus);
+        }
+        self
+    }
+}
+
+// Sum of the products of the pairs, computed on 128 bits, and reduced modulo `modulus` if given.
+fn dot_product(
+    pairs: impl Iterator<Item = (u64, u64)>,
+    modulus: Option<u64>,
+) -> Result<u64, OverflowError> {
+    let mut sum: u128 = 0;
+    for (a, b) in pairs {
+        let product = a as u128 * b as u128;
+        sum = match modulus {
+            Some(modulus) => (sum + product % modulus as u128) % modulus as u128,
+            None => sum.checked_add(product).ok_or(OverflowError)?,
+        };
+    }
+
+    sum.try_into().map_err(|_| OverflowError)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn fibonacci_numbers_are_powers() {
+        let fibonacci = Matrix::new([[1, 1], [1, 0]]);
+
+        assert_eq!(fibonacci.pow(10, None).unwrap().rows()[0][1], 55);
+        // F(93) is the largest Fibonacci number fitting in 64 bits.
+        assert_eq!(
+            fibonacci.pow(92, None).unwrap().rows()[0][0],
+            12200160415121876738
+        );
+        assert_eq!(fibonacci.pow(93, None), Err(OverflowError));
+        // F(1000) modulo 10^9 + 7.
+        assert_eq!(
+            fibonacci.pow(1000, Some(1_000_000_007)).unwrap().rows()[0][1],
+            517691607
+        );
+        assert_eq!(fibonacci.pow(0, None).unwrap(), Matrix::identity());
+    }
+
+    fn matrix() -> impl Strategy<Value = Matrix<3>> {
+        prop::array::uniform3(prop::array::uniform3(0..1000u64)).prop_map(Matrix::new)
+    }
+
+    proptest! {
+        #[test]
+        fn powers_match_repeated_steps(
+            matrix in matrix(),
+            state in prop::array::uniform3(0..1000u64),
+            steps in 0..50u64,
+            modulus in 2..1_000_000u64,
+        ) {
+            let mut stepped = state;
+            for _ in 0..steps {
+                stepped = matrix.apply(&stepped, Some(modulus)).unwrap();
+            }
+
+            let power = matrix.pow(steps, Some(modulus)).unwrap();
+            prop_assert_eq!(power.apply(&state, Some(modulus)).unwrap(), stepped.map(|entry| entry % modulus));
+        }
+
+        #[test]
+        fn powers_without_modulus_match_repeated_products(matrix in matrix(), steps in 0..4u64) {
+            let mut product = Matrix::identity();
+            for _ in 0..steps {
+                product = product.mul(&matrix, None).unwrap();
+            }
+
+            prop_assert_eq!(matrix.pow(steps, None).unwrap(), product);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Answers expected from the solvers, to check them against. Answers to the examples are committed along with them,
+//! in each day's `expected.toml`, under the name of the example:
+//!
+//! ```toml
+//! [example]
+//! part1 = 161
+//! part2 = 48
+//! ```
+//!
+//! Answers to the real inputs are specific to one's input, so they're kept out of the repository, in `answers.toml`
+//! at its root, under the name of the day:
+//!
+//! ```toml
+//! [day1]
+//! part1 = 1234
+//! part2 = 5678
+//! ```
+//!
+//! Answers are written as integers whenever possible, otherwise as strings.
+
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::args::Part;
+use crate::error::AocError;
+use crate::solver::Answer;
+
+/// Answers expected for each part of a day on an input, if they're known.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpectedAnswers {
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+}
+
+impl ExpectedAnswers {
+    pub fn get(&self, part: Part) -> Option<&str> {
+        match part {
+            Part::Part1 => self.part1.as_deref(),
+            Part::Part2 => self.part2.as_deref(),
+        }
+    }
+
+    // Answers within a table of parts, e.g. `{ part1 = 161, part2 = 48 }`.
+    fn from_table(table: &toml::Table) -> Self {
+        let answer = |part: &str| {
+            table.get(part).map(|answer| match answer {
+                toml::Value::String(answer) => answer.clone(),
+                answer => answer.to_string(),
+            })
+        };
+        Self {
+            part1: answer("part1"),
+            part2: answer("part2"),
+        }
+    }
+}
+
+/// How the answer of a solver compares with the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail {
+        expected: String,
+    },
+    /// There's no expected answer to compare with.
+    Unknown,
+}
+
+impl Verdict {
+    /// Compares the value of the `answer` with the `expected` one.
+    pub fn of(answer: &Answer, expected: Option<&str>) -> Self {
+        match expected {
+            Some(expected) if answer.value.as_deref() == Some(expected) => Self::Pass,
+            Some(expected) => Self::Fail {
+                expected: expected.to_string(),
+            },
+            None => Self::Unknown,
+        }
+    }
+}
+
+/// File holding the answers expected for the `input` of a `day`, and the name of the table they're under.
+fn answers_location(day: u8, input: &str) -> (PathBuf, String) {
+    if input == "input" {
+        (PathBuf::from("answers.toml"), format!("day{day}"))
+    } else {
+        (
+            PathBuf::from(format!("src/day{day}/expected.toml")),
+            input.to_string(),
+        )
+    }
+}
+
+/// Answers expected for the input of a `day` named `input`, e.g. `example`, as found under the current directory.
+/// Answers are unknown if their file doesn't exist.
+pub fn expected_answers(day: u8, input: &str) -> Result<ExpectedAnswers, AocError> {
+    let (path, name) = answers_location(day, input);
+    Ok(read_expected_answers(&path)?
+        .remove(&name)
+        .unwrap_or_default())
+}
+
+/// Reads every table of answers found in the file at `path`, keyed by their name, e.g. `example` in a day's
+/// `expected.toml`, or `day1` in `answers.toml`. There are none if the file doesn't exist.
+pub fn read_expected_answers(path: &Path) -> Result<BTreeMap<String, ExpectedAnswers>, AocError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(AocError::io(path, e)),
+    };
+    let tables: toml::Table = contents
+        .parse()
+        .map_err(|e| AocError::io(path, std::io::Error::new(ErrorKind::InvalidData, e)))?;
+
+    Ok(tables
+        .into_iter()
+        .filter_map(|(name, answers)| match answers {
+            toml::Value::Table(answers) => Some((name, ExpectedAnswers::from_table(&answers))),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_values_are_compared() {
+        let answers =
+            ExpectedAnswers::from_table(&"part1 = 11\npart2 = \"de,ka\"\n".parse().unwrap());
+        assert_eq!(answers.get(Part::Part1), Some("11"));
+        assert_eq!(answers.get(Part::Part2), Some("de,ka"));
+
+        let answer = Answer::new("Cheats saving at least 50 picoseconds: 285", 285);
+        assert_eq!(Verdict::of(&answer, Some("285")), Verdict::Pass);
+        // The sentence holding a number doesn't make it the answer.
+        assert_eq!(
+            Verdict::of(&answer, Some("50")),
+            Verdict::Fail {
+                expected: "50".to_string()
+            }
+        );
+        assert_eq!(Verdict::of(&answer, None), Verdict::Unknown);
+        assert_eq!(
+            Verdict::of(&Answer::without_value("No answer"), Some("50")),
+            Verdict::Fail {
+                expected: "50".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn example_answers_are_read_from_the_day() {
+        let answers = expected_answers(3, "example2").unwrap();
+
+        assert_eq!(answers.part1.as_deref(), Some("161"));
+        assert_eq!(answers.part2.as_deref(), Some("48"));
+        assert_eq!(
+            expected_answers(3, "missing").unwrap(),
+            ExpectedAnswers::default()
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use super::{Frame, LegendEntry, Style, style_of};
+
+// SGR parameters of the ANSI escape code used for each style, if any.
+fn sgr_parameters(style: Style) -> Option<&'static str> {
+    match style {
+        Style::Empty => None,
+        // Bright black, i.e. grey.
+        Style::Wall => Some("90"),
+        Style::Path => Some("32"),
+        Style::Object => Some("33"),
+        Style::Explored => Some("34"),
+        // Bold bright cyan.
+        Style::Actor => Some("1;96"),
+        // Bold bright red.
+        Style::Highlight => Some("1;91"),
+    }
+}
+
+/// Renders the frame with ANSI escape codes, colouring each tile based on its style.
+pub fn render(frame: &Frame, legend: &[LegendEntry]) -> String {
+    let mut rendered = String::new();
+
+    for row in &frame.grid {
+        // Only change colours between runs of tiles with different styles.
+        for (style, run) in &row.iter().chunk_by(|symbol| style_of(**symbol, legend)) {
+            match sgr_parameters(style) {
+                Some(parameters) => write!(
+                    rendered,
+                    "\x1b[{parameters}m{}\x1b[0m",
+                    String::from_iter(run)
+                )
+                .unwrap(),
+                None => rendered.extend(run),
+            }
+        }
+        rendered.push('\n');
+    }
+
+    rendered
+}
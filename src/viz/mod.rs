@@ -0,0 +1,369 @@
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::args::{Part, SimulationMode};
+use crate::error::AocError;
+use crate::input::ParseError;
+use crate::registry::DynSolver;
+use crate::utils::{Position, Simulation};
+
+mod ansi;
+mod svg;
+
+// How long each frame stays on screen when rendering to the terminal.
+const FRAME_DELAY: Duration = Duration::from_millis(50);
+// Number of frames in which a search is played back, however many tiles it explores.
+const NUM_SEARCH_FRAMES: usize = 100;
+
+/// Character of the tiles explored by a search, when playing it back.
+pub const EXPLORED_SYMBOL: char = '+';
+// Character of the empty tiles of all days, which are the only ones shaded in when explored.
+const EMPTY_SYMBOL: char = '.';
+
+// Set by `--no-color`, to never colour frames rendered on the terminal.
+static COLOUR_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Prevents frames from being coloured when rendered on the terminal.
+pub fn disable_colour() {
+    COLOUR_DISABLED.store(true, Ordering::Relaxed);
+}
+
+// Frames are only coloured when printed to a terminal, unless disabled with `--no-color` or the `NO_COLOR`
+// environment variable.
+fn use_colour() -> bool {
+    !COLOUR_DISABLED.load(Ordering::Relaxed)
+        && std::env::var_os("NO_COLOR").is_none()
+        && std::io::stdout().is_terminal()
+}
+
+/// Snapshot of a grid, rendered as one frame of a visualization.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Rows of the grid, with one character per tile.
+    pub grid: Vec<Vec<char>>,
+    /// Short description of what the frame shows.
+    pub caption: String,
+}
+
+impl Frame {
+    /// Builds a frame from the rows of a grid, whose tiles know which character represents them.
+    pub fn new<'a, R, T>(rows: impl IntoIterator<Item = R>, caption: impl Into<String>) -> Self
+    where
+        R: IntoIterator<Item = &'a T>,
+        T: 'a,
+        char: From<&'a T>,
+    {
+        Self {
+            grid: rows
+                .into_iter()
+                .map(|row| row.into_iter().map(char::from).collect())
+                .collect(),
+            caption: caption.into(),
+        }
+    }
+
+    /// Renders the frame for the terminal, coloured based on the `legend` if the terminal supports it.
+    /// The caption isn't included.
+    pub fn render(&self, legend: &[LegendEntry]) -> String {
+        if use_colour() {
+            ansi::render(self, legend)
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.grid {
+            writeln!(f, "{}", String::from_iter(row))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How a kind of tile is drawn, which every renderer interprets in its own way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Empty,
+    Wall,
+    Path,
+    Object,
+    /// Tile a search went through.
+    Explored,
+    Actor,
+    Highlight,
+}
+
+/// Describes what a character of the frames stands for.
+#[derive(Clone, Copy, Debug)]
+pub struct LegendEntry {
+    pub symbol: char,
+    pub description: &'static str,
+    pub style: Style,
+}
+
+impl LegendEntry {
+    pub const fn new(symbol: char, description: &'static str, style: Style) -> Self {
+        Self {
+            symbol,
+            description,
+            style,
+        }
+    }
+}
+
+/// Implemented by all solvers, so that days solved on a grid can show how they get solved.
+pub trait Visualize {
+    /// Meaning of the characters found in the frames. Characters without an entry are drawn as empty tiles.
+    const LEGEND: &'static [LegendEntry] = &[];
+
+    /// Frames showing how the given part of the problem is solved for the input `file`, in order.
+    /// Returns `None` if this day has no visualization, and fails if the input can't be parsed.
+    fn frames(
+        _part: Part,
+        _file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        Ok(None)
+    }
+
+    /// Simulation solving the given part of the problem step by step, for days solved that way.
+    fn simulation(
+        _part: Part,
+        _file: &str,
+    ) -> Result<Option<Box<dyn Simulation + '_>>, ParseError> {
+        Ok(None)
+    }
+
+    /// Frames playing back the search solving the given part of the problem, for days solved by searching a grid,
+    /// typically built with `play_back_search`. Returns `None` if this part isn't solved by a search.
+    fn search_frames(
+        _part: Part,
+        _file: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Frame> + '_>>, ParseError> {
+        Ok(None)
+    }
+}
+
+/// Hook through which searches report their progress, so that they can be played back without each day recording it
+/// in its own way. The unit type ignores everything, for searches which aren't watched.
+pub trait SearchObserver {
+    /// Called each time the search expands `position`, i.e. takes it off its frontier. A position may be expanded
+    /// several times, e.g. once per direction it's faced in.
+    fn expand(&mut self, position: Position);
+}
+
+impl SearchObserver for () {
+    fn expand(&mut self, _position: Position) {}
+}
+
+/// Positions expanded by a search, in order.
+#[derive(Clone, Debug, Default)]
+pub struct SearchRecording(pub Vec<Position>);
+
+impl SearchObserver for SearchRecording {
+    fn expand(&mut self, position: Position) {
+        self.0.push(position);
+    }
+}
+
+/// Frames playing back a `recording` over the `background` frame: the explored tiles shade in as `EXPLORED_SYMBOL`,
+/// in the order they were expanded, then the `path` found is drawn last with `path_symbol`, along with the `caption`.
+/// Only empty tiles are drawn over, so that walls, starts, and ends stay visible.
+pub fn play_back_search(
+    mut background: Frame,
+    recording: SearchRecording,
+    path: Vec<Position>,
+    path_symbol: char,
+    caption: impl Into<String>,
+) -> Box<dyn Iterator<Item = Frame>> {
+    let positions_per_frame = recording.0.len().div_ceil(NUM_SEARCH_FRAMES).max(1);
+    let mut num_explored = 0;
+    let exploring_frames: Vec<_> = recording
+        .0
+        .chunks(positions_per_frame)
+        .map(|positions| {
+            for position in positions {
+                let tile = &mut background.grid[position.row][position.col];
+                if *tile == EMPTY_SYMBOL {
+                    *tile = EXPLORED_SYMBOL;
+                }
+            }
+            num_explored += positions.len();
+            background.caption = format!("Expanded {num_explored} tiles");
+            background.clone()
+        })
+        .collect();
+
+    for position in path {
+        let tile = &mut background.grid[position.row][position.col];
+        if [EMPTY_SYMBOL, EXPLORED_SYMBOL].contains(tile) {
+            *tile = path_symbol;
+        }
+    }
+    background.caption = caption.into();
+
+    Box::new(exploring_frames.into_iter().chain([background]))
+}
+
+/// Frames of `simulation` every `steps_per_frame` steps, from its initial state until it's done.
+pub fn simulation_frames<'a>(
+    mut simulation: Box<dyn Simulation + 'a>,
+    steps_per_frame: usize,
+) -> Box<dyn Iterator<Item = Frame> + 'a> {
+    let first_frame = simulation.render();
+    Box::new(
+        std::iter::once(first_frame).chain(std::iter::from_fn(move || {
+            if simulation.is_done() {
+                return None;
+            }
+
+            for _ in 0..steps_per_frame {
+                if simulation.is_done() {
+                    break;
+                }
+                simulation.step();
+            }
+
+            Some(simulation.render())
+        })),
+    )
+}
+
+// Finds how the `symbol` character is drawn, based on the `legend`.
+fn style_of(symbol: char, legend: &[LegendEntry]) -> Style {
+    legend
+        .iter()
+        .find(|entry| entry.symbol == symbol)
+        .map_or(Style::Empty, |entry| entry.style)
+}
+
+/// Renders the frames of the `part` picked by a day command, one after the other on the terminal if `animate` is
+/// set. If an `output` path is given, the last frame is also written there as an SVG image. With `frontier` set, the
+/// frames play back the search solving the part instead.
+pub fn visualize(
+    solver: &dyn DynSolver,
+    part: Option<Part>,
+    file: &str,
+    animate: bool,
+    output: Option<&Path>,
+    frontier: bool,
+) -> Result<(), AocError> {
+    let Some(part) = part else {
+        return Err(AocError::Unsupported(String::from(
+            "only part1 and part2 can be visualized",
+        )));
+    };
+    let frames = if frontier {
+        solver.search_frames(part, file)?.ok_or_else(|| {
+            AocError::Unsupported(String::from("this part isn't solved by searching a grid"))
+        })?
+    } else {
+        solver
+            .frames(part, file)?
+            .ok_or_else(|| AocError::Unsupported(String::from("this day has no visualization")))?
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    let mut last_frame = None;
+    for frame in frames {
+        if animate {
+            // Clear the screen, and go back to its top left corner.
+            writeln!(
+                stdout,
+                "\x1b[2J\x1b[H{}{}",
+                frame.render(solver.legend()),
+                frame.caption
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+            std::thread::sleep(FRAME_DELAY);
+        }
+
+        last_frame = Some(frame);
+    }
+
+    if let (Some(output), Some(frame)) = (output, last_frame) {
+        std::fs::write(output, svg::render(&frame, solver.legend()))
+            .map_err(|e| AocError::io(output, e))?;
+        println!("Wrote the visualization to {}", output.display());
+    }
+
+    Ok(())
+}
+
+/// Drives the simulation of the `part` picked by a day command, either until it's done, or interactively from stdin.
+pub fn simulate(
+    solver: &dyn DynSolver,
+    part: Option<Part>,
+    file: &str,
+    mode: SimulationMode,
+) -> Result<(), AocError> {
+    let Some(part) = part else {
+        return Err(AocError::Unsupported(String::from(
+            "only part1 and part2 can be simulated",
+        )));
+    };
+    let Some(mut simulation) = solver.simulation(part, file)? else {
+        return Err(AocError::Unsupported(String::from(
+            "this part isn't solved by a simulation",
+        )));
+    };
+
+    let print_state = |simulation: &dyn Simulation| {
+        let frame = simulation.render();
+        println!("{}{}", frame.render(solver.legend()), frame.caption);
+    };
+
+    match mode {
+        SimulationMode::Complete => simulation.run_to_completion(),
+        SimulationMode::Interactive => {
+            let stdin = std::io::stdin();
+            print_state(simulation.as_ref());
+            println!("Press enter to step once, or type a number of steps, `complete`, or `quit`.");
+
+            while !simulation.is_done() {
+                print!("> ");
+                std::io::stdout().flush().unwrap();
+
+                let mut line = String::new();
+                if stdin.read_line(&mut line).unwrap() == 0 {
+                    return Ok(());
+                }
+
+                match line.trim() {
+                    "" => simulation.step(),
+                    "complete" => simulation.run_to_completion(),
+                    "quit" => return Ok(()),
+                    num_steps => match num_steps.parse::<usize>() {
+                        Ok(num_steps) => {
+                            for _ in 0..num_steps {
+                                if simulation.is_done() {
+                                    break;
+                                }
+                                simulation.step();
+                            }
+                        }
+                        Err(_) => {
+                            println!(
+                                "Unknown command, expected a number of steps, `complete`, or `quit`."
+                            );
+                            continue;
+                        }
+                    },
+                }
+
+                if !simulation.is_done() {
+                    print_state(simulation.as_ref());
+                }
+            }
+        }
+    }
+
+    print_state(simulation.as_ref());
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use super::{Frame, LegendEntry, Style, style_of};
+
+// Size of each tile, in pixels.
+const TILE_SIZE: usize = 10;
+// Height of each line of text below the grid, in pixels.
+const LINE_HEIGHT: usize = 20;
+const FONT_SIZE: usize = 14;
+
+fn colour(style: Style) -> &'static str {
+    match style {
+        Style::Empty => "#f5f5f5",
+        Style::Wall => "#616161",
+        Style::Path => "#66bb6a",
+        Style::Object => "#ffa726",
+        Style::Explored => "#90caf9",
+        Style::Actor => "#1e88e5",
+        Style::Highlight => "#e53935",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Renders the frame as an SVG image, where each tile is a coloured square. Its caption, and the legend of the
+/// characters it contains, are written below the grid.
+pub fn render(frame: &Frame, legend: &[LegendEntry]) -> String {
+    let num_cols = frame.grid.iter().map(Vec::len).max().unwrap_or(0);
+    let grid_height = frame.grid.len() * TILE_SIZE;
+
+    // Only list the characters which are actually drawn.
+    let shown_legend: Vec<_> = legend
+        .iter()
+        .filter(|entry| {
+            frame
+                .grid
+                .iter()
+                .flatten()
+                .any(|symbol| *symbol == entry.symbol)
+        })
+        .collect();
+    let width = (num_cols * TILE_SIZE).max(400);
+    // One line for the caption, one per legend entry, and some margin at the bottom.
+    let height = grid_height + (shown_legend.len() + 1) * LINE_HEIGHT + LINE_HEIGHT / 2;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"monospace\" font-size=\"{FONT_SIZE}\">"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        colour(Style::Empty)
+    )
+    .unwrap();
+
+    for (row, symbols) in frame.grid.iter().enumerate() {
+        // Draw each run of tiles with the same style as a single rectangle, which keeps large grids small.
+        for (style, run) in &symbols
+            .iter()
+            .map(|symbol| style_of(*symbol, legend))
+            .enumerate()
+            .chunk_by(|(_, style)| *style)
+        {
+            if style == Style::Empty {
+                continue;
+            }
+
+            let run: Vec<_> = run.collect();
+            writeln!(
+                svg,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{TILE_SIZE}\" fill=\"{}\"/>",
+                run[0].0 * TILE_SIZE,
+                row * TILE_SIZE,
+                run.len() * TILE_SIZE,
+                colour(style)
+            )
+            .unwrap();
+        }
+    }
+
+    // Baseline of each line of text.
+    let text_y = |line: usize| grid_height + (line + 1) * LINE_HEIGHT;
+    writeln!(
+        svg,
+        "  <text x=\"0\" y=\"{}\">{}</text>",
+        text_y(0),
+        escape(&frame.caption)
+    )
+    .unwrap();
+    for (i, entry) in shown_legend.iter().enumerate() {
+        let y = text_y(i + 1);
+        writeln!(
+            svg,
+            "  <rect x=\"0\" y=\"{}\" width=\"{TILE_SIZE}\" height=\"{TILE_SIZE}\" fill=\"{}\" stroke=\"black\"/>",
+            y - TILE_SIZE,
+            colour(entry.style)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "  <text x=\"{}\" y=\"{y}\">{} {}</text>",
+            2 * TILE_SIZE,
+            escape(&entry.symbol.to_string()),
+            escape(entry.description)
+        )
+        .unwrap();
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use crate::error::AocError;
+
+// Environment variable holding the session cookie of adventofcode.com, found in the browser's cookies once logged in.
+const SESSION_VAR: &str = "AOC_SESSION";
+
+// Advent of Code asks automated tools to say who they are, so that they can be contacted if they misbehave.
+const USER_AGENT: &str = concat!(
+    "github.com/jonathanGB/advent-of-code-2024 ",
+    env!("CARGO_PKG_VERSION"),
+    " (ureq)"
+);
+
+// Session cookie authenticating requests to adventofcode.com.
+fn session_token() -> Result<String, AocError> {
+    std::env::var(SESSION_VAR).map_err(|_| {
+        AocError::Unsupported(format!(
+            "set {SESSION_VAR} to the session cookie of adventofcode.com to fetch from it"
+        ))
+    })
+}
+
+/// Fetches the page at `path` of adventofcode.com, e.g. `/2024/day/1/input`, as the logged in user.
+pub fn fetch(path: &str) -> Result<String, AocError> {
+    let url = format!("https://adventofcode.com{path}");
+    let to_io_error = |e| AocError::io(Path::new(&url), e);
+
+    ureq::get(&url)
+        .set("Cookie", &format!("session={}", session_token()?))
+        .set("User-Agent", USER_AGENT)
+        .call()
+        .map_err(|e| to_io_error(std::io::Error::other(e)))?
+        .into_string()
+        .map_err(to_io_error)
+}
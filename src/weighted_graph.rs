@@ -0,0 +1,209 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+use crate::utils::FxHashMap;
+
+/// Directed graph whose edges have a non-negative weight, stored as the outgoing edges of each node. Unlike the
+/// searches over grids, nodes are arbitrary IDs, so any problem can be modeled by listing its edges explicitly, e.g.
+/// a race track where cheats are extra edges skipping through walls.
+#[derive(Clone, Debug)]
+pub struct WeightedGraph<T> {
+    edges: FxHashMap<T, Vec<(T, u64)>>,
+}
+
+impl<T> WeightedGraph<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Adds a node without any edge, if it isn't in the graph already.
+    pub fn add_node(&mut self, node: T) {
+        self.edges.entry(node).or_default();
+    }
+
+    /// Adds an edge from `from` to `to` costing `weight`, along with both nodes. Parallel edges are allowed, only the
+    /// cheapest one matters when searching.
+    pub fn add_edge(&mut self, from: T, to: T, weight: u64) {
+        self.edges.entry(from).or_default().push((to, weight));
+        self.add_node(to);
+    }
+
+    /// All nodes of the graph, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = T> + '_ {
+        self.edges.keys().copied()
+    }
+
+    /// Outgoing edges of `node`, as the node they lead to and their weight.
+    pub fn edges(&self, node: T) -> &[(T, u64)] {
+        self.edges.get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    /// Finds the shortest path from `start` to every node it can reach, using Dijkstra's algorithm.
+    pub fn shortest_paths(&self, start: T) -> ShortestPaths<T> {
+        let mut shortest_paths = ShortestPaths {
+            start,
+            distances: FxHashMap::from_iter([(start, 0)]),
+            predecessors: FxHashMap::default(),
+        };
+        // Min-heap of nodes to visit, prioritizing the closest one. A node may be pushed several times if shorter
+        // paths to it are found before it's visited, in which case only its first visit counts.
+        let mut to_visit = BinaryHeap::from([Reverse(Candidate {
+            distance: 0,
+            node: start,
+        })]);
+
+        while let Some(Reverse(Candidate { distance, node })) = to_visit.pop() {
+            if shortest_paths.distances[&node] < distance {
+                continue;
+            }
+
+            for &(next, weight) in self.edges(node) {
+                let next_distance = distance + weight;
+                if shortest_paths
+                    .distances
+                    .get(&next)
+                    .is_some_and(|known_distance| *known_distance <= next_distance)
+                {
+                    continue;
+                }
+
+                shortest_paths.distances.insert(next, next_distance);
+                shortest_paths.predecessors.insert(next, node);
+                to_visit.push(Reverse(Candidate {
+                    distance: next_distance,
+                    node: next,
+                }));
+            }
+        }
+
+        shortest_paths
+    }
+}
+
+impl<T> Default for WeightedGraph<T> {
+    fn default() -> Self {
+        Self {
+            edges: FxHashMap::default(),
+        }
+    }
+}
+
+impl<T> FromIterator<(T, T, u64)> for WeightedGraph<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Builds the graph from a list of edges, each as the node it goes from, the node it goes to, and its weight.
+    fn from_iter<I: IntoIterator<Item = (T, T, u64)>>(iter: I) -> Self {
+        let mut graph = WeightedGraph::default();
+
+        for (from, to, weight) in iter {
+            graph.add_edge(from, to, weight);
+        }
+
+        graph
+    }
+}
+
+/// Shortest paths from a start node to all nodes it can reach. Nodes missing from `distances` can't be reached.
+#[derive(Clone, Debug)]
+pub struct ShortestPaths<T> {
+    start: T,
+    /// Length of the shortest path from the start to each reachable node, including the start itself.
+    pub distances: FxHashMap<T, u64>,
+    /// Node preceding each reachable node on one of its shortest paths. The start has none.
+    pub predecessors: FxHashMap<T, T>,
+}
+
+impl<T> ShortestPaths<T>
+where
+    T: Copy + Eq + Hash,
+{
+    /// Length of the shortest path to `node`, if it can be reached.
+    pub fn distance(&self, node: T) -> Option<u64> {
+        self.distances.get(&node).copied()
+    }
+
+    /// Nodes along a shortest path from the start to `node`, both included, if it can be reached. If several paths
+    /// share the shortest length, any of them may be returned.
+    pub fn path_to(&self, node: T) -> Option<Vec<T>> {
+        if !self.distances.contains_key(&node) {
+            return None;
+        }
+
+        let mut path = vec![node];
+        while *path.last().unwrap() != self.start {
+            path.push(self.predecessors[path.last().unwrap()]);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+}
+
+// Node waiting to be visited, ordered only by its distance so that nodes don't need to be ordered themselves.
+struct Candidate<T> {
+    distance: u64,
+    node: T,
+}
+
+impl<T> PartialEq for Candidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl<T> Eq for Candidate<T> {}
+
+impl<T> PartialOrd for Candidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Candidate<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.cmp(&other.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortest_paths_prefer_cheap_detours() {
+        let graph: WeightedGraph<_> = [
+            ('a', 'b', 7),
+            ('a', 'c', 2),
+            ('c', 'b', 3),
+            ('b', 'd', 1),
+            ('c', 'd', 10),
+            ('e', 'a', 1),
+        ]
+        .into_iter()
+        .collect();
+
+        let shortest_paths = graph.shortest_paths('a');
+        assert_eq!(shortest_paths.distance('a'), Some(0));
+        assert_eq!(shortest_paths.distance('b'), Some(5));
+        assert_eq!(shortest_paths.distance('d'), Some(6));
+        assert_eq!(shortest_paths.distance('e'), None);
+        assert_eq!(shortest_paths.path_to('d').unwrap(), ['a', 'c', 'b', 'd']);
+        assert_eq!(shortest_paths.path_to('a').unwrap(), ['a']);
+        assert_eq!(shortest_paths.path_to('e'), None);
+        assert_eq!(shortest_paths.predecessors.get(&'a'), None);
+    }
+
+    #[test]
+    fn cycles_and_zero_weights_are_handled() {
+        let graph: WeightedGraph<_> = [(0, 1, 0), (1, 2, 0), (2, 0, 0), (2, 3, 4), (0, 3, 5)]
+            .into_iter()
+            .collect();
+
+        let shortest_paths = graph.shortest_paths(0);
+        assert_eq!(shortest_paths.distance(3), Some(4));
+        assert_eq!(shortest_paths.path_to(3).unwrap(), [0, 1, 2, 3]);
+        assert_eq!(graph.nodes().count(), 4);
+        assert_eq!(graph.edges(3), []);
+    }
+}
@@ -0,0 +1,56 @@
+// Goes through every day, so only built along with all of them, e.g. not with `--features day16` alone.
+#![cfg(all_days)]
+
+use advent_of_code_2024::{Direction, Grid, Position, Trie, TrieElement};
+
+#[test]
+fn parts_are_solved_into_their_answer() {
+    use advent_of_code_2024::{Part, solve};
+
+    let input = std::fs::read_to_string("src/day1/example.txt").unwrap();
+
+    assert_eq!(solve(1, Part::Part1, &input).unwrap().text, "Total is 11");
+    assert_eq!(
+        solve(1, Part::Part2, &input).unwrap().to_string(),
+        "Total is 31"
+    );
+}
+
+#[test]
+fn solving_fails_on_unknown_days_and_malformed_inputs() {
+    use advent_of_code_2024::{AocError, Part, solve};
+
+    assert!(matches!(
+        solve(26, Part::Part1, ""),
+        Err(AocError::Unsupported(_))
+    ));
+    assert!(matches!(
+        solve(1, Part::Part1, "3   x\n"),
+        Err(AocError::Parse(_))
+    ));
+}
+
+#[derive(Clone, Copy)]
+struct Bit(bool);
+
+impl TrieElement for Bit {
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[test]
+fn utilities_are_usable_from_other_crates() {
+    let mut grid = Grid::from_rows([vec!['.', '#'], vec!['.', '.']]);
+    let position = Position { row: 0, col: 0 }.go(Direction::Right.turn_clockwise());
+    grid[position] = 'o';
+    assert_eq!(grid.rows().collect::<Vec<_>>(), [['.', '#'], ['o', '.']]);
+
+    let trie: Trie<Bit, 2> = [vec![Bit(true)], vec![Bit(true), Bit(false)]]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        trie.count_all_word_arrangements(&[Bit(true), Bit(false), Bit(true)], &mut Vec::new()),
+        1
+    );
+}
@@ -0,0 +1,555 @@
+// Goes through every day, so only built along with all of them, e.g. not with `--features day16` alone.
+#![cfg(all_days)]
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+fn aoc() -> Command {
+    let mut command = Command::cargo_bin("advent-of-code-2024").unwrap();
+    command.current_dir(env!("CARGO_MANIFEST_DIR"));
+    command
+}
+
+#[test]
+fn every_day_solves_its_example() {
+    for day in 1..=25 {
+        for part in ["part1", "part2"] {
+            aoc()
+                .args([&format!("day{day}"), part, "--example"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_empty().not());
+        }
+    }
+}
+
+#[test]
+fn input_selects_the_input_file() {
+    aoc()
+        .args(["day1", "part1", "--input", "example"])
+        .assert()
+        .success()
+        .stdout("Total is 11\n");
+    aoc()
+        .args(["day3", "-i", "example2", "part2"])
+        .assert()
+        .success()
+        .stdout("Sum of muls: 48\n");
+}
+
+#[test]
+fn input_reads_other_files_and_stdin() {
+    aoc()
+        .args(["day1", "part1", "--input", "src/day1/example.txt"])
+        .assert()
+        .success()
+        .stdout("Total is 11\n");
+    aoc()
+        .args(["day1", "part2", "--input", "-"])
+        .write_stdin("3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n")
+        .assert()
+        .success()
+        .stdout("Total is 31\n");
+    aoc()
+        .args(["day1", "part1", "--input", "missing/input.txt"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "could not access missing/input.txt",
+        ));
+}
+
+#[test]
+fn example_is_a_shorthand_for_the_example_input() {
+    aoc()
+        .args(["day1", "part2", "-e"])
+        .assert()
+        .success()
+        .stdout("Total is 31\n");
+}
+
+#[test]
+fn both_parts_are_solved_unless_one_is_picked() {
+    aoc()
+        .args(["day1", "--example"])
+        .assert()
+        .success()
+        .stdout("Total is 11\nTotal is 31\n");
+}
+
+#[test]
+fn example_conflicts_with_input() {
+    aoc()
+        .args(["day1", "part1", "--example", "--input", "example2"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn missing_input_fails_with_an_io_error() {
+    aoc()
+        .args(["day1", "part1", "--input", "missing"])
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(predicate::str::starts_with(
+            "Error: could not access src/day1/missing.txt",
+        ));
+}
+
+#[test]
+fn unsupported_commands_fail() {
+    aoc()
+        .args(["gen", "1"])
+        .assert()
+        .code(5)
+        .stderr("Error: unsupported: this day has no input generator\n");
+    aoc()
+        .args(["day1", "part1", "--example", "--visualize"])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("unsupported"));
+}
+
+#[test]
+fn solving_gives_up_after_the_timeout() {
+    aoc()
+        .args(["day6", "part2", "--timeout", "0.05"])
+        .assert()
+        .code(7)
+        .stdout("")
+        .stderr("Error: gave up solving after 50ms\n");
+    aoc()
+        .args(["day1", "part1", "--example", "--timeout", "60"])
+        .assert()
+        .success()
+        .stdout("Total is 11\n");
+}
+
+#[test]
+fn unknown_days_are_rejected() {
+    aoc().args(["day26", "part1"]).assert().code(2);
+    aoc()
+        .args(["scrub", "26"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("expected a day between 1 and 25"));
+}
+
+#[test]
+fn reports_are_written_as_json() {
+    let output = aoc()
+        .args([
+            "day22",
+            "--input",
+            "example2",
+            "best-sequence",
+            "--format",
+            "json",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!([{"changes": "-2,1,-1,3", "bananas": 23, "buyers": 3}])
+    );
+}
+
+#[test]
+fn reports_are_written_as_csv() {
+    aoc()
+        .args(["day12", "--example", "regions", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "plant,area,perimeter,sides,perimeter_based_price,sides_based_price\nR,12,18,10,216,120\n",
+        ));
+}
+
+#[test]
+fn batches_report_each_file_including_unparsable_ones() {
+    let malformed = std::env::temp_dir().join(format!("aoc-day13-{}.txt", std::process::id()));
+    std::fs::write(&malformed, "Button A: X+1\n").unwrap();
+    let output = aoc()
+        .args(["day13", "--example", "batch", "src/day13/example.txt"])
+        .arg(&malformed)
+        .args(["--format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    std::fs::remove_file(&malformed).unwrap();
+
+    let reports: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(reports[0]["num_claw_machines"], 4);
+    assert_eq!(reports[0]["part1_tokens"], 480);
+    assert_eq!(reports[0]["error"], serde_json::Value::Null);
+    assert_eq!(reports[1]["part1_tokens"], serde_json::Value::Null);
+    assert!(reports[1]["error"].is_string());
+}
+
+#[test]
+fn day_commands_reporting_a_part_run_with_their_options() {
+    aoc()
+        .args(["day4", "--example", "crossed", "XMASX"])
+        .assert()
+        .success()
+        .stdout("Crossed word appeared 1 times.\n");
+    aoc()
+        .args(["day4", "--input", "-", "crossed", "CAT"])
+        .write_stdin("CXT\nXAX\nCXT\n")
+        .assert()
+        .success()
+        .stdout("Crossed word appeared 1 times.\n");
+    aoc()
+        .args(["day7", "--example", "cheapest", "-c", "weighted"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("weighted operators at the least"));
+    aoc()
+        .args(["day17", "--example", "run", "--max-steps", "3"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "didn't halt within 3 instructions",
+        ));
+}
+
+#[test]
+fn batches_do_not_need_the_day_input() {
+    let example = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/day13/example.txt");
+    aoc()
+        .current_dir(std::env::temp_dir())
+        .args(["day13", "batch"])
+        .arg(&example)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("480"));
+}
+
+#[test]
+fn generated_inputs_are_reproducible_with_a_seed() {
+    let generate = || {
+        aoc()
+            .args(["gen", "day2", "--size", "20", "--seed", "7"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+    let generated = generate();
+
+    assert_eq!(generated, generate());
+    assert_eq!(String::from_utf8(generated).unwrap().lines().count(), 20);
+}
+
+#[test]
+fn implementations_agree_when_cross_checked() {
+    for (day, part) in [
+        ("day7", "part1"),
+        ("day7", "part2"),
+        ("day9", "part2"),
+        ("day11", "part1"),
+        ("day11", "part2"),
+        ("day12", "part2"),
+        ("day16", "part1"),
+        ("day16", "part2"),
+        ("day18", "part2"),
+    ] {
+        aoc()
+            .args([day, part, "--example", "--cross-check"])
+            .assert()
+            .success()
+            .stdout(predicate::str::ends_with("All 2 implementations agree.\n"));
+    }
+}
+
+#[test]
+fn cross_checking_a_single_implementation_fails() {
+    aoc()
+        .args(["day1", "part1", "--example", "--cross-check"])
+        .assert()
+        .code(5)
+        .stderr("Error: unsupported: this part has a single implementation\n");
+}
+
+#[test]
+fn sequential_solves_like_concurrently() {
+    for day in ["day6", "day7", "day11", "day14"] {
+        let solve = |sequential: bool| {
+            let mut command = aoc();
+            command.args([day, "part2", "--example"]);
+            if sequential {
+                command.arg("--sequential");
+            }
+            command.assert().success().get_output().stdout.clone()
+        };
+
+        assert_eq!(solve(true), solve(false));
+    }
+}
+
+#[test]
+fn memory_mapped_inputs_solve_like_read_ones() {
+    for day in ["day3", "day16", "day22"] {
+        let solve = |mmap: bool| {
+            let mut command = aoc();
+            command.args([day, "part1", "--example"]);
+            if mmap {
+                command.arg("--mmap");
+            }
+            command.assert().success().get_output().stdout.clone()
+        };
+
+        assert_eq!(solve(true), solve(false));
+    }
+}
+
+#[test]
+fn metrics_hold_every_part_solved_by_the_command() {
+    let metrics = std::env::temp_dir().join(format!("aoc-metrics-{}.prom", std::process::id()));
+    aoc()
+        .args(["day1", "--example", "--metrics-out"])
+        .arg(&metrics)
+        .assert()
+        .success();
+    let day_metrics = std::fs::read_to_string(&metrics).unwrap();
+
+    aoc()
+        .args(["all", "--input", "example", "--metrics-out"])
+        .arg(&metrics)
+        .assert()
+        .success();
+    let all_metrics = std::fs::read_to_string(&metrics).unwrap();
+    std::fs::remove_file(&metrics).unwrap();
+
+    let num_answers = |metrics: &str| {
+        metrics
+            .lines()
+            .filter(|line| line.starts_with("aoc_answer_info{"))
+            .count()
+    };
+    assert_eq!(num_answers(&day_metrics), 2);
+    assert!(day_metrics.contains(r#"answer="Total is 11""#));
+    assert!(day_metrics.contains(r#"answer="Total is 31""#));
+    assert_eq!(num_answers(&all_metrics), 50);
+}
+
+#[test]
+fn solved_parts_are_appended_to_the_history() {
+    let history = std::env::temp_dir().join(format!("aoc-history-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&history);
+    for part in ["part1", "part2"] {
+        aoc()
+            .args(["day1", part, "--example", "--history"])
+            .arg(&history)
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("Total is "));
+    }
+    // Both parts at once, each recorded with its own spans only.
+    aoc()
+        .args(["day1", "--example", "--history"])
+        .arg(&history)
+        .assert()
+        .success();
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&history)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    std::fs::remove_file(&history).unwrap();
+
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0]["part"], 1);
+    assert_eq!(records[0]["answer"], "Total is 11");
+    assert_eq!(records[1]["answer"], "Total is 31");
+    assert_eq!(records[0]["input"], "example");
+    assert_eq!(records[0]["input_hash"], records[1]["input_hash"]);
+    assert!(records[1]["durations_us"]["solve"].is_u64());
+    for record in &records[2..] {
+        let durations = &record["durations_us"];
+        assert!(durations["solve"].as_u64() <= durations["total"].as_u64());
+    }
+}
+
+#[test]
+fn inputs_are_compared_side_by_side() {
+    aoc()
+        .args([
+            "day3", "part2", "--inputs", "example", "example2", "missing",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Input     Part "))
+        .stdout(predicate::str::contains("Sum of muls: 48\n"))
+        .stdout(predicate::str::is_match("missing +part2 +error: could not access").unwrap());
+}
+
+#[test]
+fn all_days_are_solved_into_a_table() {
+    aoc()
+        .args(["all", "--input", "example"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Day  Part "))
+        .stdout(predicate::str::is_match(r"\n1 +part1 +\S+  Total is 11\n").unwrap())
+        .stdout(
+            predicate::str::contains("Safety factor: ")
+                .and(predicate::str::contains("Generation: ")),
+        )
+        .stdout(predicate::str::is_match(r"\n25 +part2 +\S+  There is no second part").unwrap())
+        .stdout(predicate::str::is_match(r"\nTotal: \S+\n$").unwrap());
+}
+
+#[test]
+fn examples_are_verified_against_their_expected_answers() {
+    aoc()
+        .args(["verify", "--input", "example"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^Day +Part +Result +Time +Answer\n").unwrap())
+        .stdout(predicate::str::is_match(r"\n1 +part1 +PASS +\S+  Total is 11\n").unwrap())
+        .stdout(predicate::str::contains("FAIL").not());
+}
+
+#[test]
+fn verifying_fails_when_parts_cannot_be_solved() {
+    aoc()
+        .args(["verify", "--input", "example2"])
+        .assert()
+        .code(8)
+        .stdout(predicate::str::is_match(r"\n3 +part2 +PASS +\S+  Sum of muls: 48\n").unwrap())
+        .stdout(predicate::str::is_match(r"\n1 +part1 +FAIL +error: could not access").unwrap())
+        .stderr(
+            predicate::str::is_match(
+                r"^Error: wrong answer: \d+ parts don't have the expected answer\n$",
+            )
+            .unwrap(),
+        );
+}
+
+#[test]
+fn parse_and_solve_times_are_written_as_csv_or_json() {
+    aoc()
+        .args(["day1", "--example", "--time", "csv"])
+        .assert()
+        .success()
+        .stdout("Total is 11\nTotal is 31\n")
+        .stderr(
+            predicate::str::is_match(
+                r"^day,part,input,parse_us,solve_us\n1,1,example,\d+,\d+\n1,2,example,\d+,\d+\n$",
+            )
+            .unwrap(),
+        );
+
+    let path = std::env::temp_dir().join(format!("aoc-timings-{}.json", std::process::id()));
+    aoc()
+        .args(["day1", "part2", "--example", "--time", "json", "--time-out"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stderr("");
+    let timings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(timings[0]["day"], 1);
+    assert_eq!(timings[0]["part"], 2);
+    assert!(timings[0]["solve_us"].is_u64());
+}
+
+#[test]
+fn timings_are_written_even_if_verifying_fails() {
+    aoc()
+        .args(["verify", "--input", "example2", "--time", "csv"])
+        .assert()
+        .code(8)
+        .stderr(predicate::str::starts_with(
+            "day,part,input,parse_us,solve_us\n",
+        ))
+        .stderr(predicate::str::is_match(r"\n3,2,example2,\d*,\d+\n").unwrap())
+        .stderr(
+            predicate::str::is_match(
+                r"\nError: wrong answer: \d+ parts don't have the expected answer\n$",
+            )
+            .unwrap(),
+        );
+}
+
+#[test]
+fn comparing_inputs_conflicts_with_input() {
+    aoc()
+        .args(["day1", "part1", "--inputs", "example", "--input", "input"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn allocations_are_reported_per_part() {
+    aoc()
+        .args(["day1", "--example", "--alloc-stats"])
+        .assert()
+        .success()
+        .stdout("Total is 11\nTotal is 31\n")
+        .stderr(
+            predicate::str::is_match(r"Day 1 part 1 took .+: [1-9]\d* allocations, \d+ bytes allocated, \d+ bytes live at peak\n")
+                .unwrap()
+                .and(predicate::str::contains("Day 1 part 2 took ")),
+        );
+}
+
+#[test]
+fn the_prompt_solves_commands_until_input_ends() {
+    aoc()
+        .arg("repl")
+        .write_stdin("1 1 --example\ntime day1 part2 -e\nday99\n3 1 -e\n")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Total is 11\n")
+                .and(predicate::str::contains("Total is 31\nTook "))
+                .and(predicate::str::contains("Sum of muls: 161\n")),
+        )
+        .stderr(predicate::str::contains("unrecognized subcommand 'day99'"));
+}
+
+#[test]
+fn the_prompt_rejects_flags_set_when_starting_it() {
+    aoc()
+        .arg("repl")
+        .write_stdin(
+            "1 1 -e --alloc-stats --trace\n1 1 -e --timeout 1\nreload\n1 2 -e --time json\n",
+        )
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Total is 11")
+                .not()
+                .and(predicate::str::contains("Total is 31\n")),
+        )
+        .stderr(
+            predicate::str::contains(
+                "--trace, --alloc-stats can only be given when starting the prompt",
+            )
+            .and(predicate::str::contains(
+                "solving can't time out from the prompt",
+            ))
+            .and(predicate::str::contains(r#""part": 2,"#)),
+        );
+    aoc()
+        .args(["repl", "--timeout", "1"])
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "solving can't time out from the prompt",
+        ));
+}
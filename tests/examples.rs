@@ -0,0 +1,119 @@
+// Goes through every day, so only built along with all of them, e.g. not with `--features day16` alone.
+#![cfg(all_days)]
+
+use std::path::Path;
+
+use advent_of_code_2024::args::Part;
+use advent_of_code_2024::solver;
+use advent_of_code_2024::verify::{ExpectedAnswers, Verdict, read_expected_answers};
+
+const PARTS: [Part; 2] = [Part::Part1, Part::Part2];
+
+fn read_answers(path: &Path) -> std::collections::BTreeMap<String, ExpectedAnswers> {
+    read_expected_answers(path).unwrap_or_else(|e| panic!("could not read {}: {e}", path.display()))
+}
+
+// Names of the example inputs of a day, i.e. its `example*.txt` files without their extension.
+fn example_names(day_dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+        .filter(|name| name.starts_with("example"))
+        .collect();
+    names.sort();
+    names
+}
+
+// Solves a part of a day on the given input, and checks that it finds the expected answer.
+fn check_answer(
+    root: &Path,
+    day: u8,
+    input: &str,
+    part: Part,
+    expected: &str,
+) -> Result<(), String> {
+    let input_path = root.join(format!("src/day{day}/{input}.txt"));
+    let file = std::fs::read_to_string(&input_path)
+        .map_err(|e| format!("could not read {}: {e}", input_path.display()))?;
+    let answer = solver::solve(day, part, &file).map_err(|e| format!("failed, {e}"))?;
+
+    match Verdict::of(&answer, Some(expected)) {
+        Verdict::Pass => Ok(()),
+        _ => Err(format!("expected {expected}, got {:?}", answer.text)),
+    }
+}
+
+#[test]
+fn examples() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut failures = Vec::new();
+
+    for day in 1..=25 {
+        let day_dir = root.join(format!("src/day{day}"));
+        // Answers of each part, keyed by the name of the example they're for.
+        let mut expected_answers = read_answers(&day_dir.join("expected.toml"));
+
+        for example in example_names(&day_dir) {
+            let Some(answers) = expected_answers.remove(&example) else {
+                failures.push(format!("day{day}/{example}: no expected answers"));
+                continue;
+            };
+
+            for (part, expected) in PARTS
+                .into_iter()
+                .filter_map(|part| Some((part, answers.get(part)?)))
+            {
+                if let Err(failure) = check_answer(root, day, &example, part, expected) {
+                    failures.push(format!("day{day}/{example} {part:?}: {failure}"));
+                }
+            }
+        }
+
+        // Answers to examples which don't exist would never be checked.
+        for example in expected_answers.keys() {
+            failures.push(format!("day{day}/{example}: no such example input"));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+// Must be set to run the `real_inputs` test, as a reminder that it needs the real inputs and their answers.
+const GOLDEN_ANSWERS_VAR: &str = "AOC_GOLDEN_ANSWERS";
+
+/// Solves every day on its real input, and compares with the answers found in `answers.toml`, at the root of the
+/// repository, as described in `verify`. Days and parts without answers are skipped. This is ignored unless asked
+/// for, as real inputs aren't committed, and some days take a while to solve without optimizations, e.g.
+/// `AOC_GOLDEN_ANSWERS=1 cargo test --release -- --ignored real_inputs`.
+#[test]
+#[ignore = "needs the real inputs and their answers"]
+fn real_inputs() {
+    assert!(
+        std::env::var_os(GOLDEN_ANSWERS_VAR).is_some(),
+        "set {GOLDEN_ANSWERS_VAR} to solve the real inputs"
+    );
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let answers = read_answers(&root.join("answers.toml"));
+    assert!(!answers.is_empty(), "answers.toml holds no answers");
+    let mut failures = Vec::new();
+
+    for day in 1..=25 {
+        let Some(answers) = answers.get(&format!("day{day}")) else {
+            continue;
+        };
+
+        for (part, expected) in PARTS
+            .into_iter()
+            .filter_map(|part| Some((part, answers.get(part)?)))
+        {
+            if let Err(failure) = check_answer(root, day, "input", part, expected) {
+                failures.push(format!("day{day} {part:?}: {failure}"));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,44 @@
+#![cfg(all(feature = "day15", feature = "day16", feature = "day18"))]
+
+use advent_of_code_2024::args::Part;
+use advent_of_code_2024::viz::Visualize;
+use advent_of_code_2024::{day15, day16, day18};
+
+// Last frame of the visualization of a part, with its caption, as shown on the terminal without colours.
+fn render_last_frame<V: Visualize>(part: Part, example: &str) -> String {
+    let file = std::fs::read_to_string(example).unwrap();
+    let frame = V::frames(part, &file).unwrap().unwrap().last().unwrap();
+    format!("{frame}{}\n", frame.caption)
+}
+
+#[test]
+fn day15_map_after_all_moves() {
+    insta::assert_snapshot!(
+        "day15_part1",
+        render_last_frame::<day15::SolverImpl>(Part::Part1, "src/day15/example.txt")
+    );
+    insta::assert_snapshot!(
+        "day15_part2",
+        render_last_frame::<day15::SolverImpl>(Part::Part2, "src/day15/example.txt")
+    );
+}
+
+#[test]
+fn day16_best_paths() {
+    insta::assert_snapshot!(
+        "day16_part2",
+        render_last_frame::<day16::SolverImpl>(Part::Part2, "src/day16/example.txt")
+    );
+}
+
+#[test]
+fn day18_paths() {
+    insta::assert_snapshot!(
+        "day18_part1",
+        render_last_frame::<day18::SolverImpl>(Part::Part1, "src/day18/example.txt")
+    );
+    insta::assert_snapshot!(
+        "day18_part2",
+        render_last_frame::<day18::SolverImpl>(Part::Part2, "src/day18/example.txt")
+    );
+}